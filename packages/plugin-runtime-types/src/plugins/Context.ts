@@ -13,6 +13,8 @@ import type {
   RenderGrpcRequestResponse,
   RenderHttpRequestRequest,
   RenderHttpRequestResponse,
+  SearchHttpResponsesRequest,
+  SearchHttpResponsesResponse,
   SendHttpRequestRequest,
   SendHttpRequestResponse,
   ShowToastRequest,
@@ -57,6 +59,9 @@ export interface Context {
   };
   httpResponse: {
     find(args: FindHttpResponsesRequest): Promise<FindHttpResponsesResponse['httpResponses']>;
+    search(
+      args: SearchHttpResponsesRequest,
+    ): Promise<SearchHttpResponsesResponse['httpResponses']>;
   };
   templates: {
     render<T extends JsonValue>(args: TemplateRenderRequest & { data: T }): Promise<T>;
@@ -17,6 +17,7 @@ import {
   PromptTextResponse,
   RenderGrpcRequestResponse,
   RenderHttpRequestResponse,
+  SearchHttpResponsesResponse,
   SendHttpRequestResponse,
   TemplateFunction,
   TemplateFunctionArg,
@@ -539,6 +540,17 @@ export class PluginInstance {
           );
           return httpResponses;
         },
+        search: async (args) => {
+          const payload = {
+            type: 'search_http_responses_request',
+            ...args,
+          } as const;
+          const { httpResponses } = await this.#sendAndWaitForReply<SearchHttpResponsesResponse>(
+            windowContext,
+            payload,
+          );
+          return httpResponses;
+        },
       },
       grpcRequest: {
         render: async (args) => {
@@ -4,6 +4,8 @@ export const plugin: PluginDefinition = {
   templateFunctions: [{
     name: 'prompt.text',
     description: 'Prompt the user for input when sending a request',
+    cache: true,
+    sequential: true,
     args: [
       { type: 'text', name: 'title', label: 'Title' },
       { type: 'text', name: 'label', label: 'Label', optional: true },
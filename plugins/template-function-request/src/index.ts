@@ -5,6 +5,7 @@ export const plugin: PluginDefinition = {
   templateFunctions: [
     {
       name: 'request.body',
+      cache: true,
       args: [
         {
           name: 'requestId',
@@ -26,6 +27,7 @@ export const plugin: PluginDefinition = {
     },
     {
       name: 'request.header',
+      cache: true,
       args: [
         {
           name: 'requestId',
@@ -56,6 +58,7 @@ export const plugin: PluginDefinition = {
     },
     {
       name: 'request.param',
+      cache: true,
       args: [
         {
           name: 'requestId',
@@ -98,6 +101,7 @@ export const plugin: PluginDefinition = {
     },
     {
       name: 'request.name',
+      cache: true,
       args: [
         {
           name: 'requestId',
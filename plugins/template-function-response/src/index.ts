@@ -52,6 +52,8 @@ export const plugin: PluginDefinition = {
     {
       name: 'response.header',
       description: 'Read the value of a response header, by name',
+      cache: true,
+      sequential: true,
       args: [
         requestArg,
         {
@@ -83,6 +85,8 @@ export const plugin: PluginDefinition = {
     {
       name: 'response.body.path',
       description: 'Access a field of the response body using JsonPath or XPath',
+      cache: true,
+      sequential: true,
       aliases: ['response'],
       args: [
         requestArg,
@@ -135,6 +139,8 @@ export const plugin: PluginDefinition = {
     {
       name: 'response.body.raw',
       description: 'Access the entire response body, as text',
+      cache: true,
+      sequential: true,
       aliases: ['response'],
       args: [requestArg, behaviorArg, ttlArg],
       async onRender(ctx: Context, args: CallTemplateFunctionArgs): Promise<string | null> {
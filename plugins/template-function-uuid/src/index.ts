@@ -14,6 +14,7 @@ export const plugin: PluginDefinition = {
     {
       name: 'uuid.v3',
       description: 'Generate a UUID V3',
+      cache: true,
       args: [
         { type: 'text', name: 'name', label: 'Name' },
         {
@@ -39,6 +40,7 @@ export const plugin: PluginDefinition = {
     {
       name: 'uuid.v5',
       description: 'Generate a UUID V5',
+      cache: true,
       args: [
         { type: 'text', name: 'name', label: 'Name' },
         { type: 'text', name: 'namespace', label: 'Namespace' },
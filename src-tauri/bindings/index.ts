@@ -1,4 +1,54 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LintDiagnostic } from "./lint";
+import type { Span } from "./parser";
+
+export type BodyDiff = { "type": "json", changes: Array<StructuralChange>, } | { "type": "xml", changes: Array<StructuralChange>, } | { "type": "text", lines: Array<LineChange>, };
+
+/**
+ * The workspace's `.env` file was created, changed, or removed
+ */
+export type DotenvChangeEvent = { workspaceId: string, path: string, kind: string, };
+
+export type DotenvWatchResult = { 
+/**
+ * Emit this event to stop watching
+ */
+unlistenEvent: string, };
+
+export type HeaderDiff = { "type": "added", name: string, value: string, } | { "type": "removed", name: string, value: string, } | { "type": "changed", name: string, before: string, after: string, };
+
+export type HttpResponseDiff = { status: StatusDiff | null, headers: Array<HeaderDiff>, body: BodyDiff, };
+
+export type LineChange = { tag: LineChangeTag, beforeLine: number | null, afterLine: number | null, value: string, };
+
+export type LineChangeTag = "equal" | "insert" | "delete";
+
+/**
+ * A model that was changed by renaming a variable, and how many uses were renamed
+ */
+export type RenamedModel = { model: string, modelId: string, modelName: string, count: number, 
+/**
+ * Fields that were left unchanged because their template couldn't be parsed, like
+ * `headers[0].value`
+ */
+skipped: Array<string>, };
+
+export type StatusDiff = { before: number, after: number, beforeReason: string | null, afterReason: string | null, };
+
+export type StructuralChange = { 
+/**
+ * JSONPath (for JSON) or XPath (for XML) of the changed node
+ */
+path: string, before: string | null, after: string | null, };
+
+/**
+ * Problems with the templates in one field of a workspace, folder, or request
+ */
+export type TemplateLintResult = { model: string, modelId: string, modelName: string, 
+/**
+ * Path to the field, like `url`, `headers[0].value`, or `body.text`
+ */
+field: string, diagnostics: Array<LintDiagnostic>, };
 
 export type UpdateInfo = { replyEventId: string, version: string, downloaded: boolean, };
 
@@ -6,6 +56,15 @@ export type UpdateResponse = { "type": "ack" } | { "type": "action", action: Upd
 
 export type UpdateResponseAction = "install" | "skip";
 
+/**
+ * Where a variable is used, in one field of an environment, workspace, folder, or request
+ */
+export type VariableUsage = { model: string, modelId: string, modelName: string, 
+/**
+ * Path to the field, like `url`, `headers[0].value`, or `variables[0].value`
+ */
+field: string, spans: Array<Span>, };
+
 export type YaakNotification = { timestamp: string, timeout: number | null, id: string, title: string | null, message: string, color: string | null, action: YaakNotificationAction | null, };
 
 export type YaakNotificationAction = { label: string, url: string, };
@@ -1,7 +1,19 @@
+use crate::dotenv_watch::{DotenvChangeEvent, DotenvWatchResult, watch_dotenv};
+use crate::error::Error::GenericError;
 use crate::error::Result;
+use crate::har::build_har;
+use crate::lint::{TemplateLintResult, lint_workspace};
+use crate::refactor::{
+    RenamedModel, VariableUsage, list_variable_usages, rename_workspace_variable,
+};
+use crate::response_diff::{HttpResponseDiff, diff_http_responses};
+use crate::secret_variables::encrypt_secret_variables;
+use tauri::ipc::Channel;
 use tauri::{command, AppHandle, Manager, Runtime, State, WebviewWindow};
 use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use yaak_crypto::manager::EncryptionManagerExt;
+use yaak_models::models::{EnvironmentVariable, HttpResponse};
+use yaak_models::query_manager::QueryManagerExt;
 use yaak_plugins::events::{GetThemesResponse, PluginWindowContext};
 use yaak_plugins::manager::PluginManager;
 use yaak_plugins::native_template_functions::{
@@ -42,6 +54,16 @@ pub(crate) async fn cmd_secure_template<R: Runtime>(
     Ok(encrypt_secure_template_function(&app_handle, window_context, template)?)
 }
 
+/// Encrypt the values of secret variables before they're saved
+#[command]
+pub(crate) async fn cmd_encrypt_secret_variables<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+    variables: Vec<EnvironmentVariable>,
+) -> Result<Vec<EnvironmentVariable>> {
+    encrypt_secret_variables(window.app_handle(), workspace_id, variables)
+}
+
 #[command]
 pub(crate) async fn cmd_get_themes<R: Runtime>(
     window: WebviewWindow<R>,
@@ -49,3 +71,115 @@ pub(crate) async fn cmd_get_themes<R: Runtime>(
 ) -> Result<Vec<GetThemesResponse>> {
     Ok(plugin_manager.get_themes(&window).await?)
 }
+
+#[command]
+pub(crate) async fn cmd_diff_http_responses<R: Runtime>(
+    window: WebviewWindow<R>,
+    before_id: &str,
+    after_id: &str,
+) -> Result<HttpResponseDiff> {
+    let before = window.db().get_http_response(before_id)?;
+    let after = window.db().get_http_response(after_id)?;
+    diff_http_responses(&before, &after).await
+}
+
+#[command]
+pub(crate) async fn cmd_search_http_responses<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+    query: &str,
+    request_id: Option<&str>,
+    limit: Option<u64>,
+) -> Result<Vec<HttpResponse>> {
+    Ok(window.db().search_http_responses(workspace_id, query, request_id, limit)?)
+}
+
+/// Export responses to a HAR file. Exactly one of the IDs selects what to export: a single
+/// response, or every stored response for a request, folder (recursively), or workspace.
+/// Credentials in the sent headers and request bodies are only exported with
+/// `include_request_data`.
+#[command]
+pub(crate) async fn cmd_export_har<R: Runtime>(
+    window: WebviewWindow<R>,
+    export_path: &str,
+    response_id: Option<&str>,
+    request_id: Option<&str>,
+    folder_id: Option<&str>,
+    workspace_id: Option<&str>,
+    include_request_data: Option<bool>,
+) -> Result<()> {
+    // Scoped so the DB connection isn't held while building the HAR
+    let responses = {
+        let db = window.db();
+        let responses = match (response_id, request_id, folder_id, workspace_id) {
+            (Some(id), None, None, None) => vec![db.get_http_response(id)?],
+            (None, Some(id), None, None) => db.list_http_responses_for_request(id, None)?,
+            (None, None, Some(id), None) => {
+                let mut responses = Vec::new();
+                for r in db.list_http_requests_for_folder_recursive(id)? {
+                    responses.extend(db.list_http_responses_for_request(&r.id, None)?);
+                }
+                responses
+            }
+            (None, None, None, Some(id)) => db.list_http_responses(id, None)?,
+            _ => {
+                return Err(GenericError(
+                    "Expected exactly one of responseId, requestId, folderId, or workspaceId"
+                        .to_string(),
+                ));
+            }
+        };
+        responses
+            .into_iter()
+            .map(|r| {
+                let request = db.get_http_request(&r.request_id).ok();
+                (r, request)
+            })
+            .collect()
+    };
+
+    let app_version = window.app_handle().package_info().version.to_string();
+    let har = build_har(&app_version, responses, include_request_data.unwrap_or(false)).await;
+    tokio::fs::write(export_path, serde_json::to_string_pretty(&har)?).await?;
+    Ok(())
+}
+
+/// Find templates in the workspace that reference undefined variables or functions, like after
+/// pulling changes from a teammate
+#[command]
+pub(crate) async fn cmd_lint_workspace<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+) -> Result<Vec<TemplateLintResult>> {
+    lint_workspace(&window, workspace_id).await
+}
+
+#[command]
+pub(crate) async fn cmd_list_variable_usages<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+    name: &str,
+) -> Result<Vec<VariableUsage>> {
+    list_variable_usages(&window, workspace_id, name)
+}
+
+/// Rename a variable in the workspace's environments and every template that uses it
+#[command]
+pub(crate) async fn cmd_rename_variable<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<Vec<RenamedModel>> {
+    rename_workspace_variable(&window, workspace_id, from, to)
+}
+
+/// Watch the workspace's `.env` file for changes
+#[command]
+pub(crate) async fn cmd_watch_dotenv<R: Runtime>(
+    window: WebviewWindow<R>,
+    workspace_id: &str,
+    channel: Channel<DotenvChangeEvent>,
+) -> Result<DotenvWatchResult> {
+    watch_dotenv(&window, workspace_id, channel).await
+}
@@ -0,0 +1,141 @@
+use crate::error::Error::GenericError;
+use crate::error::Result;
+use chrono::Utc;
+use log::{error, info, warn};
+use notify::Watcher;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tauri::ipc::Channel;
+use tauri::{Listener, Runtime, WebviewWindow};
+use tokio::select;
+use tokio::sync::watch;
+use ts_rs::TS;
+use yaak_models::external_variables::{
+    invalidate_dotenv_cache, unwatch_dotenv_cache, watch_dotenv_cache,
+};
+use yaak_models::query_manager::QueryManagerExt;
+
+/// The workspace's `.env` file was created, changed, or removed
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct DotenvChangeEvent {
+    pub workspace_id: String,
+    pub path: PathBuf,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct DotenvWatchResult {
+    /// Emit this event to stop watching
+    pub unlisten_event: String,
+}
+
+/// Watch the workspace's `.env` file, so anything rendered with its variables can be refreshed
+pub async fn watch_dotenv<R: Runtime>(
+    window: &WebviewWindow<R>,
+    workspace_id: &str,
+    channel: Channel<DotenvChangeEvent>,
+) -> Result<DotenvWatchResult> {
+    let workspace_meta = window.db().get_or_create_workspace_meta(workspace_id)?;
+    let path = match workspace_meta.dotenv_path() {
+        Some(path) => path,
+        None => return Err(GenericError(format!("No .env file set for {workspace_id}"))),
+    };
+
+    let (cancel_tx, cancel_rx) = watch::channel(());
+    watch_file(&path, workspace_id, channel, cancel_rx)?;
+
+    let window_inner = window.clone();
+    let unlisten_event =
+        format!("dotenv-unlisten-{}-{}", workspace_id, Utc::now().timestamp_millis());
+    window.listen_any(unlisten_event.clone(), move |event| {
+        window_inner.unlisten(event.id());
+        if let Err(e) = cancel_tx.send(()) {
+            warn!("Failed to send cancel signal to .env watcher {e:?}");
+        }
+    });
+
+    Ok(DotenvWatchResult { unlisten_event })
+}
+
+fn watch_file(
+    path: &Path,
+    workspace_id: &str,
+    channel: Channel<DotenvChangeEvent>,
+    mut cancel_rx: watch::Receiver<()>,
+) -> Result<()> {
+    // Watch the directory rather than the file, so we still see the file if it's deleted and
+    // recreated, which is how a lot of editors save
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path.file_name().map(|n| n.to_os_string());
+    let path = path.to_path_buf();
+    let workspace_id = workspace_id.to_string();
+
+    let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| GenericError(format!("{e:?}")))?;
+    watcher
+        .watch(&dir, notify::RecursiveMode::NonRecursive)
+        .map_err(|e| GenericError(format!("Failed to watch {dir:?}: {e:?}")))?;
+
+    // Spawn a blocking thread to handle the blocking `std::sync::mpsc::Receiver`
+    let (async_tx, mut async_rx) = tokio::sync::mpsc::channel::<notify::Result<notify::Event>>(100);
+    std::thread::spawn(move || {
+        for res in rx {
+            if async_tx.blocking_send(res).is_err() {
+                break; // Exit the thread if the async receiver is closed
+            }
+        }
+    });
+
+    // Renders use the cached file while it's watched, and it's read again after each change
+    watch_dotenv_cache(&path);
+    tauri::async_runtime::spawn(async move {
+        info!("Watching .env file {:?}", path);
+        loop {
+            select! {
+                Some(event_res) = async_rx.recv() => {
+                    match event_res {
+                        Ok(event) => {
+                            let is_dotenv = event
+                                .paths
+                                .iter()
+                                .any(|p| p.file_name() == file_name.as_deref());
+                            if !is_dotenv || event.kind.is_access() {
+                                continue;
+                            }
+
+                            invalidate_dotenv_cache(&path);
+
+                            let event = DotenvChangeEvent {
+                                workspace_id: workspace_id.clone(),
+                                path: path.clone(),
+                                kind: format!("{:?}", event.kind),
+                            };
+                            if let Err(e) = channel.send(event) {
+                                warn!("Failed to send .env change event {e:?}");
+                            }
+                        }
+                        Err(e) => error!(".env watch error: {:?}", e),
+                    }
+                }
+                _ = cancel_rx.changed() => {
+                    // Breaking drops the watcher, which stops watching
+                    info!("Cancelling watch for .env file {:?}", path);
+                    break;
+                }
+            }
+        }
+        drop(watcher);
+        unwatch_dotenv_cache(&path);
+    });
+
+    Ok(())
+}
@@ -0,0 +1,527 @@
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use reqwest::Url;
+use serde::Serialize;
+use tokio::fs;
+use yaak_models::models::{HttpRequest, HttpResponse, HttpResponseHeader};
+
+const HAR_VERSION: &str = "1.2";
+
+/// HAR 1.2 document (http://www.softwareishard.com/blog/har-12-spec/)
+#[derive(Debug, Clone, Serialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: String,
+    pub time: i32,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: HarCache,
+    pub timings: HarTimings,
+    #[serde(rename = "serverIPAddress", skip_serializing_if = "Option::is_none")]
+    pub server_ip_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<HarCookie>,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: i32,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<HarCookie>,
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarCookie {
+    pub name: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secure: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub params: Vec<HarNameValue>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarCache {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarTimings {
+    pub blocked: i32,
+    pub dns: i32,
+    pub connect: i32,
+    pub ssl: i32,
+    pub send: i32,
+    pub wait: i32,
+    pub receive: i32,
+}
+
+/// Build a HAR document from stored responses. Each response is paired with the request model
+/// it came from, which is only used as a fallback for responses recorded before the sent request
+/// was stored alongside them. Credentials in the sent headers (see [`CREDENTIAL_HEADERS`]) and
+/// request bodies are left out unless `include_request_data` is set.
+pub async fn build_har(
+    app_version: &str,
+    mut responses: Vec<(HttpResponse, Option<HttpRequest>)>,
+    include_request_data: bool,
+) -> Har {
+    responses.sort_by_key(|(r, _)| r.created_at);
+
+    let mut entries = Vec::new();
+    for (response, request) in responses {
+        entries.push(build_entry(&response, request.as_ref(), include_request_data).await);
+    }
+
+    Har {
+        log: HarLog {
+            version: HAR_VERSION.to_string(),
+            creator: HarCreator {
+                name: "Yaak".to_string(),
+                version: app_version.to_string(),
+            },
+            entries,
+        },
+    }
+}
+
+async fn build_entry(
+    response: &HttpResponse,
+    request: Option<&HttpRequest>,
+    include_request_data: bool,
+) -> HarEntry {
+    let http_version = response.version.clone().unwrap_or_else(|| "HTTP/1.1".to_string());
+    let receive = (response.elapsed - response.elapsed_headers).max(0);
+
+    HarEntry {
+        started_date_time: format!("{}Z", response.created_at.format("%Y-%m-%dT%H:%M:%S%.3f")),
+        time: response.elapsed,
+        request: build_request(response, request, &http_version, include_request_data).await,
+        response: build_response(response, &http_version).await,
+        cache: HarCache {},
+        timings: HarTimings {
+            blocked: -1,
+            dns: -1,
+            connect: -1,
+            ssl: -1,
+            send: 0,
+            wait: response.elapsed_headers,
+            receive,
+        },
+        server_ip_address: response
+            .remote_addr
+            .as_ref()
+            .map(|a| a.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(a).to_string()),
+        comment: response.error.clone(),
+    }
+}
+
+async fn build_request(
+    response: &HttpResponse,
+    request: Option<&HttpRequest>,
+    http_version: &str,
+    include_request_data: bool,
+) -> HarRequest {
+    let method = match (response.request_method.as_str(), request) {
+        ("", Some(r)) => r.method.to_uppercase(),
+        ("", None) => "GET".to_string(),
+        (m, _) => m.to_string(),
+    };
+    let url = match response.request_url.as_str() {
+        "" => response.url.clone(),
+        u => u.to_string(),
+    };
+
+    let query_string = match Url::parse(&url) {
+        Ok(u) => u
+            .query_pairs()
+            .map(|(name, value)| HarNameValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let headers: Vec<HarNameValue> = response
+        .request_headers
+        .iter()
+        .map(|h| HarNameValue {
+            name: h.name.clone(),
+            value: match include_request_data {
+                true => h.value.clone(),
+                false => without_credentials(&h.name, &h.value),
+            },
+        })
+        .collect();
+
+    let cookies = headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case("cookie"))
+        .flat_map(|h| cookie::Cookie::split_parse(h.value.clone()))
+        .filter_map(|c| c.ok())
+        .map(|c| HarCookie {
+            name: c.name().to_string(),
+            value: c.value().to_string(),
+            path: None,
+            domain: None,
+            expires: None,
+            http_only: None,
+            secure: None,
+        })
+        .collect();
+
+    let body = match &response.request_body_path {
+        Some(p) => fs::read(p).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let post_data = match body.is_empty() || !include_request_data {
+        true => None,
+        false => Some(HarPostData {
+            mime_type: find_header(&headers, "content-type").unwrap_or_default(),
+            params: Vec::new(),
+            text: String::from_utf8_lossy(&body).to_string(),
+        }),
+    };
+
+    HarRequest {
+        method,
+        url,
+        http_version: http_version.to_string(),
+        cookies,
+        headers,
+        query_string,
+        post_data,
+        headers_size: -1,
+        body_size: body.len() as i64,
+    }
+}
+
+async fn build_response(response: &HttpResponse, http_version: &str) -> HarResponse {
+    let body = match &response.body_path {
+        Some(p) => fs::read(p).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let (text, encoding) = match String::from_utf8(body.clone()) {
+        Ok(t) => (t, None),
+        Err(_) => (BASE64_STANDARD.encode(&body), Some("base64".to_string())),
+    };
+
+    let headers = to_har_headers(&response.headers);
+    let cookies = headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case("set-cookie"))
+        .filter_map(|h| cookie::Cookie::parse(h.value.clone()).ok())
+        .map(|c| HarCookie {
+            name: c.name().to_string(),
+            value: c.value().to_string(),
+            path: c.path().map(|p| p.to_string()),
+            domain: c.domain().map(|d| d.to_string()),
+            expires: c
+                .expires_datetime()
+                .and_then(|d| chrono::DateTime::from_timestamp(d.unix_timestamp(), 0))
+                .map(|d| d.to_rfc3339()),
+            http_only: c.http_only(),
+            secure: c.secure(),
+        })
+        .collect();
+
+    let mime_type = find_header(&headers, "content-type").unwrap_or_default();
+    let redirect_url = find_header(&headers, "location").unwrap_or_default();
+
+    HarResponse {
+        status: response.status,
+        status_text: response.status_reason.clone().unwrap_or_default(),
+        http_version: http_version.to_string(),
+        cookies,
+        headers,
+        content: HarContent {
+            size: body.len() as i64,
+            mime_type,
+            text: Some(text),
+            encoding,
+        },
+        redirect_url,
+        headers_size: -1,
+        body_size: response.content_length.map(|l| l as i64).unwrap_or(body.len() as i64),
+    }
+}
+
+fn to_har_headers(headers: &[HttpResponseHeader]) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .map(|h| HarNameValue {
+            name: h.name.clone(),
+            value: h.value.clone(),
+        })
+        .collect()
+}
+
+/// Request headers that carry credentials, matched by their whole name (case-insensitive)
+pub const CREDENTIAL_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "api-key",
+    "x-api-key",
+    "x-auth-token",
+    "x-access-token",
+];
+
+/// Blank the value of a header that carries credentials. Cookies keep their names, so it's still
+/// clear which ones were sent.
+fn without_credentials(name: &str, value: &str) -> String {
+    if !CREDENTIAL_HEADERS.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+        return value.to_string();
+    }
+    if !name.eq_ignore_ascii_case("cookie") {
+        return String::new();
+    }
+
+    cookie::Cookie::split_parse(value)
+        .filter_map(|c| c.ok())
+        .map(|c| format!("{}=", c.name()))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn find_header(headers: &[HarNameValue], name: &str) -> Option<String> {
+    headers.iter().find(|h| h.name.eq_ignore_ascii_case(name)).map(|h| h.value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::har::{build_request, build_response};
+    use yaak_models::models::{
+        HttpRequest, HttpResponse, HttpResponseHeader, HttpSentRequestHeader,
+    };
+
+    fn sent_header(name: &str, value: &str) -> HttpSentRequestHeader {
+        HttpSentRequestHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn response_header(name: &str, value: &str) -> HttpResponseHeader {
+        HttpResponseHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("yaak-har-test-{name}"));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn request_as_sent() {
+        let response = HttpResponse {
+            request_method: "POST".to_string(),
+            request_url: "https://example.com/path?a=1&b=two%20words".to_string(),
+            url: "https://example.com/redirected".to_string(),
+            request_headers: vec![
+                sent_header("content-type", "application/json"),
+                sent_header("cookie", "session=abc; theme=dark"),
+            ],
+            request_body_path: Some(temp_file("request-body", b"{\"a\":1}")),
+            ..Default::default()
+        };
+        let request = build_request(&response, None, "HTTP/2.0", true).await;
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://example.com/path?a=1&b=two%20words");
+        assert_eq!(
+            request
+                .query_string
+                .iter()
+                .map(|q| (q.name.as_str(), q.value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("a", "1"), ("b", "two words")]
+        );
+        assert_eq!(
+            request.cookies.iter().map(|c| (c.name.as_str(), c.value.as_str())).collect::<Vec<_>>(),
+            vec![("session", "abc"), ("theme", "dark")]
+        );
+        assert_eq!(request.headers.len(), 2);
+        let post_data = request.post_data.unwrap();
+        assert_eq!(post_data.mime_type, "application/json");
+        assert_eq!(post_data.text, "{\"a\":1}");
+        assert_eq!(request.body_size, 7);
+    }
+
+    #[tokio::test]
+    async fn request_fallbacks() {
+        let response = HttpResponse {
+            url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+        let model = HttpRequest {
+            method: "put".to_string(),
+            ..Default::default()
+        };
+
+        let request = build_request(&response, Some(&model), "HTTP/1.1", true).await;
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "https://example.com");
+        assert!(request.post_data.is_none());
+        assert_eq!(request.body_size, 0);
+
+        let request = build_request(&response, None, "HTTP/1.1", true).await;
+        assert_eq!(request.method, "GET");
+    }
+
+    #[tokio::test]
+    async fn request_data_left_out_by_default() {
+        let response = HttpResponse {
+            request_headers: vec![
+                sent_header("content-type", "application/json"),
+                sent_header("cookie", "session=abc; theme=dark"),
+                sent_header("Authorization", "Bearer abc"),
+                sent_header("proxy-authorization", "Basic abc"),
+                sent_header("api-key", "abc"),
+                sent_header("X-API-Key", "abc"),
+                sent_header("x-auth-token", "abc"),
+                sent_header("x-access-token", "abc"),
+                // Only the exact names are credentials
+                sent_header("x-csrf-token", "abc"),
+                sent_header("x-password-policy", "strict"),
+            ],
+            request_body_path: Some(temp_file("request-body-default", b"{\"a\":1}")),
+            ..Default::default()
+        };
+        let request = build_request(&response, None, "HTTP/1.1", false).await;
+
+        assert_eq!(
+            request.headers.iter().map(|h| (h.name.as_str(), h.value.as_str())).collect::<Vec<_>>(),
+            vec![
+                ("content-type", "application/json"),
+                ("cookie", "session=; theme="),
+                ("Authorization", ""),
+                ("proxy-authorization", ""),
+                ("api-key", ""),
+                ("X-API-Key", ""),
+                ("x-auth-token", ""),
+                ("x-access-token", ""),
+                ("x-csrf-token", "abc"),
+                ("x-password-policy", "strict"),
+            ]
+        );
+        assert_eq!(
+            request.cookies.iter().map(|c| (c.name.as_str(), c.value.as_str())).collect::<Vec<_>>(),
+            vec![("session", ""), ("theme", "")]
+        );
+        assert!(request.post_data.is_none());
+        assert_eq!(request.body_size, 7);
+
+        // Stored headers are exactly what was sent, so they can all be included
+        let request = build_request(&response, None, "HTTP/1.1", true).await;
+        assert_eq!(
+            request.headers.iter().map(|h| h.value.as_str()).collect::<Vec<_>>(),
+            response.request_headers.iter().map(|h| h.value.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(request.post_data.unwrap().text, "{\"a\":1}");
+    }
+
+    #[tokio::test]
+    async fn response() {
+        let response = HttpResponse {
+            status: 302,
+            status_reason: Some("Found".to_string()),
+            headers: vec![
+                response_header("Content-Type", "application/octet-stream"),
+                response_header("Location", "/next"),
+                response_header("Set-Cookie", "id=1; Path=/; HttpOnly"),
+            ],
+            body_path: Some(temp_file("response-body", &[0xff, 0xfe, 0x00])),
+            ..Default::default()
+        };
+        let har_response = build_response(&response, "HTTP/1.1").await;
+
+        assert_eq!(har_response.status, 302);
+        assert_eq!(har_response.status_text, "Found");
+        assert_eq!(har_response.redirect_url, "/next");
+        assert_eq!(har_response.content.mime_type, "application/octet-stream");
+        assert_eq!(har_response.content.text.as_deref(), Some("//4A"));
+        assert_eq!(har_response.content.encoding.as_deref(), Some("base64"));
+        assert_eq!(har_response.body_size, 3);
+
+        let cookie = &har_response.cookies[0];
+        assert_eq!((cookie.name.as_str(), cookie.value.as_str()), ("id", "1"));
+        assert_eq!(cookie.path.as_deref(), Some("/"));
+        assert_eq!(cookie.http_only, Some(true));
+    }
+}
@@ -22,8 +22,8 @@ use tokio::io::AsyncWriteExt;
 use tokio::sync::watch::Receiver;
 use tokio::sync::{Mutex, oneshot};
 use yaak_models::models::{
-    Cookie, CookieJar, Environment, HttpRequest, HttpResponse, HttpResponseHeader,
-    HttpResponseState, ProxySetting, ProxySettingAuth,
+    Cookie, CookieJar, HttpRequest, HttpResponse, HttpResponseHeader, HttpResponseState,
+    HttpSentRequestHeader, ProxySetting, ProxySettingAuth,
 };
 use yaak_models::query_manager::QueryManagerExt;
 use yaak_models::util::UpdateSource;
@@ -32,26 +32,26 @@ use yaak_plugins::events::{
 };
 use yaak_plugins::manager::PluginManager;
 use yaak_plugins::template_callback::PluginTemplateCallback;
-use yaak_templates::{RenderErrorBehavior, RenderOptions};
+use yaak_templates::validate_json::strip_json_comments;
+use yaak_templates::RenderOptions;
 use crate::dns::LocalhostResolver;
 
 pub async fn send_http_request<R: Runtime>(
     window: &WebviewWindow<R>,
     unrendered_request: &HttpRequest,
     og_response: &HttpResponse,
-    environment: Option<Environment>,
+    environment_ids: &[String],
     cookie_jar: Option<CookieJar>,
     cancelled_rx: &mut Receiver<bool>,
 ) -> Result<HttpResponse> {
     let app_handle = window.app_handle().clone();
     let plugin_manager = app_handle.state::<PluginManager>();
     let settings = window.db().get_settings();
-    let workspace = window.db().get_workspace(&unrendered_request.workspace_id)?;
-    let environment_id = environment.map(|e| e.id);
+    let request_settings = window.db().resolve_settings_for_http_request(unrendered_request)?;
     let environment_chain = window.db().resolve_environments(
         &unrendered_request.workspace_id,
         unrendered_request.folder_id.as_deref(),
-        environment_id.as_deref(),
+        environment_ids,
     )?;
 
     let response_id = og_response.id.clone();
@@ -78,9 +78,7 @@ pub async fn send_http_request<R: Runtime>(
         RenderPurpose::Send,
     );
 
-    let opt = RenderOptions {
-        error_behavior: RenderErrorBehavior::Throw,
-    };
+    let opt = RenderOptions::throw();
 
     let request = match render_http_request(&resolved_request, environment_chain, &cb, &opt).await {
         Ok(r) => r,
@@ -103,7 +101,7 @@ pub async fn send_http_request<R: Runtime>(
     debug!("Sending request to {} {url_string}", request.method);
 
     let mut client_builder = reqwest::Client::builder()
-        .redirect(match workspace.setting_follow_redirects {
+        .redirect(match request_settings.follow_redirects {
             true => Policy::limited(10), // TODO: Handle redirects natively
             false => Policy::none(),
         })
@@ -115,7 +113,7 @@ pub async fn send_http_request<R: Runtime>(
         .referer(false)
         .tls_info(true);
 
-    let tls_config = yaak_http::tls::get_config(workspace.setting_validate_certificates, true);
+    let tls_config = yaak_http::tls::get_config(request_settings.validate_certificates, true);
     client_builder = client_builder.use_preconfigured_tls(tls_config);
 
     match settings.proxy {
@@ -189,10 +187,9 @@ pub async fn send_http_request<R: Runtime>(
         None => None,
     };
 
-    if workspace.setting_request_timeout > 0 {
-        client_builder = client_builder.timeout(Duration::from_millis(
-            workspace.setting_request_timeout.unsigned_abs() as u64,
-        ));
+    if request_settings.request_timeout > 0 {
+        client_builder = client_builder
+            .timeout(Duration::from_millis(request_settings.request_timeout.unsigned_abs() as u64));
     }
 
     let client = client_builder.build()?;
@@ -416,7 +413,14 @@ pub async fn send_http_request<R: Runtime>(
             request_builder = request_builder.multipart(multipart_form);
         } else if request_body.contains_key("text") {
             let body = get_str_h(&request_body, "text");
-            request_builder = request_builder.body(body.to_owned());
+            let strip_comments = body_type == "application/json"
+                && get_bool_h(&request_body, "stripJsonComments", false);
+            if strip_comments {
+                // Lets JSON bodies have comments and trailing commas while editing
+                request_builder = request_builder.body(strip_json_comments(body));
+            } else {
+                request_builder = request_builder.body(body.to_owned());
+            }
         } else {
             warn!("Unsupported body type: {}", body_type);
         }
@@ -502,6 +506,37 @@ pub async fn send_http_request<R: Runtime>(
         }
     }
 
+    // Record the request exactly as it's going out, so it can be exported (eg. to HAR) later
+    {
+        let mut r = response.lock().await;
+        r.request_method = sendable_req.method().to_string();
+        r.request_url = sendable_req.url().to_string();
+        let jar_cookies = maybe_cookie_manager.as_ref().map(|(cookie_store, _)| {
+            cookie_store
+                .lock()
+                .unwrap()
+                .get_request_values(sendable_req.url())
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join("; ")
+        });
+        r.request_headers = sent_request_headers(sendable_req.headers(), jar_cookies);
+
+        // Multipart and streamed bodies aren't available as bytes, so they aren't recorded
+        let request_body = sendable_req.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+        if !response_id.is_empty() && !request_body.is_empty() {
+            let base_dir = app_handle.path().app_data_dir()?.join("responses");
+            create_dir_all(&base_dir).await?;
+            let request_body_path = base_dir.join(format!("{response_id}.request"));
+            match fs::write(&request_body_path, request_body).await {
+                Ok(_) => {
+                    r.request_body_path = Some(request_body_path.to_string_lossy().to_string())
+                }
+                Err(e) => warn!("Failed to write request body {e:?}"),
+            }
+        }
+    }
+
     let (resp_tx, resp_rx) = oneshot::channel::<std::result::Result<Response, reqwest::Error>>();
     let (done_tx, done_rx) = oneshot::channel::<HttpResponse>();
 
@@ -737,6 +772,32 @@ fn ensure_proto(url_str: &str) -> String {
     format!("http://{url_str}")
 }
 
+/// The headers of a request as they go out. Cookies from the jar are added by the client, which
+/// replaces any `Cookie` header set on the request, so one is never sent twice.
+fn sent_request_headers(
+    headers: &HeaderMap,
+    jar_cookies: Option<String>,
+) -> Vec<HttpSentRequestHeader> {
+    let jar_cookies = jar_cookies.filter(|c| !c.is_empty());
+    let mut sent_headers: Vec<HttpSentRequestHeader> = headers
+        .iter()
+        .filter(|(name, _)| jar_cookies.is_none() || *name != http::header::COOKIE)
+        .map(|(name, value)| HttpSentRequestHeader {
+            name: name.to_string(),
+            value: value.to_str().unwrap_or_default().to_string(),
+        })
+        .collect();
+
+    if let Some(value) = jar_cookies {
+        sent_headers.push(HttpSentRequestHeader {
+            name: "cookie".to_string(),
+            value,
+        });
+    }
+
+    sent_headers
+}
+
 fn get_bool(v: &Value, key: &str, fallback: bool) -> bool {
     match v.get(key) {
         None => fallback,
@@ -757,3 +818,76 @@ fn get_str_h<'a>(v: &'a BTreeMap<String, Value>, key: &str) -> &'a str {
         Some(v) => v.as_str().unwrap_or_default(),
     }
 }
+
+fn get_bool_h(v: &BTreeMap<String, Value>, key: &str, fallback: bool) -> bool {
+    match v.get(key) {
+        None => fallback,
+        Some(v) => v.as_bool().unwrap_or(fallback),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http_request::sent_request_headers;
+    use http::{HeaderMap, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn names_and_values(headers: &HeaderMap, jar: Option<&str>) -> Vec<(String, String)> {
+        sent_request_headers(headers, jar.map(|c| c.to_string()))
+            .into_iter()
+            .map(|h| (h.name, h.value))
+            .collect()
+    }
+
+    #[test]
+    fn without_jar() {
+        let headers = headers(&[("accept", "*/*"), ("cookie", "a=1")]);
+        assert_eq!(
+            names_and_values(&headers, None),
+            vec![
+                ("accept".to_string(), "*/*".to_string()),
+                ("cookie".to_string(), "a=1".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_jar_keeps_request_cookie() {
+        let headers = headers(&[("cookie", "a=1")]);
+        assert_eq!(
+            names_and_values(&headers, Some("")),
+            vec![("cookie".to_string(), "a=1".to_string())]
+        );
+    }
+
+    #[test]
+    fn jar_cookies_added() {
+        let headers = headers(&[("accept", "*/*")]);
+        assert_eq!(
+            names_and_values(&headers, Some("b=2; c=3")),
+            vec![
+                ("accept".to_string(), "*/*".to_string()),
+                ("cookie".to_string(), "b=2; c=3".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn jar_cookies_replace_request_cookie() {
+        let headers = headers(&[("cookie", "a=1"), ("accept", "*/*")]);
+        assert_eq!(
+            names_and_values(&headers, Some("b=2")),
+            vec![
+                ("accept".to_string(), "*/*".to_string()),
+                ("cookie".to_string(), "b=2".to_string())
+            ]
+        );
+    }
+}
@@ -1,13 +1,15 @@
 use crate::error::Result;
-use log::info;
+use crate::secret_variables::encrypt_secret_variables;
+use log::{info, warn};
 use std::collections::BTreeMap;
 use std::fs::read_to_string;
-use tauri::{Manager, Runtime, WebviewWindow};
+use tauri::{Emitter, Manager, Runtime, WebviewWindow};
 use yaak_models::models::{
     Environment, Folder, GrpcRequest, HttpRequest, WebsocketRequest, Workspace,
 };
 use yaak_models::query_manager::QueryManagerExt;
 use yaak_models::util::{BatchUpsertResult, UpdateSource, maybe_gen_id, maybe_gen_id_opt};
+use yaak_plugins::events::{Color, ShowToastRequest};
 use yaak_plugins::manager::PluginManager;
 
 pub(crate) async fn import_data<R: Runtime>(
@@ -24,6 +26,9 @@ pub(crate) async fn import_data<R: Runtime>(
 
     let resources = import_result.resources;
 
+    // Environments whose secrets couldn't be encrypted and will be stored as plain text
+    let mut unencrypted_environments: Vec<String> = Vec::new();
+
     let workspaces: Vec<Workspace> = resources
         .workspaces
         .into_iter()
@@ -53,6 +58,18 @@ pub(crate) async fn import_data<R: Runtime>(
                     v.parent_id = None;
                 }
             };
+
+            // Secrets are exported as plain text, so encrypt them with this workspace's key. If
+            // that fails (eg. encryption isn't enabled yet), they're kept as is and the user is
+            // told about it after the import.
+            let variables = v.variables.clone();
+            match encrypt_secret_variables(window.app_handle(), &v.workspace_id, variables) {
+                Ok(variables) => v.variables = variables,
+                Err(e) => {
+                    warn!("Failed to encrypt secrets of imported environment {}: {e}", v.id);
+                    unencrypted_environments.push(v.name.clone());
+                }
+            }
             v
         })
         .collect();
@@ -119,5 +136,22 @@ pub(crate) async fn import_data<R: Runtime>(
         )
     })?;
 
+    if !unencrypted_environments.is_empty() {
+        let message = format!(
+            "Secrets in {} were imported as plain text because they couldn't be encrypted. \
+            Enable encryption in the workspace settings to protect them.",
+            unencrypted_environments.join(", ")
+        );
+        let toast = ShowToastRequest {
+            message,
+            color: Some(Color::Warning),
+            timeout: Some(30000),
+            ..Default::default()
+        };
+        if let Err(e) = window.emit_to(window.label(), "show_toast", toast) {
+            warn!("Failed to show unencrypted secrets toast {e:?}");
+        }
+    }
+
     Ok(upserted)
 }
@@ -6,7 +6,10 @@ use crate::grpc::{build_metadata, metadata_to_map, resolve_grpc_request};
 use crate::http_request::{resolve_http_request, send_http_request};
 use crate::import::import_data;
 use crate::notifications::YaakNotifier;
-use crate::render::{render_grpc_request, render_template};
+use crate::render::{
+    RenderedTemplate, render_grpc_request, render_template, render_template_with_trace,
+};
+use crate::secret_variables::decrypt_secret_variables;
 use crate::updates::{UpdateMode, UpdateTrigger, YaakUpdater};
 use crate::uri_scheme::handle_deep_link;
 use error::Result as YaakResult;
@@ -37,26 +40,37 @@ use yaak_models::models::{
     WorkspaceMeta,
 };
 use yaak_models::query_manager::QueryManagerExt;
-use yaak_models::util::{BatchUpsertResult, UpdateSource, get_workspace_export_resources};
+use yaak_models::render::with_request_variables;
+use yaak_models::util::{
+    BatchUpsertResult, UpdateSource, active_environment_ids, get_workspace_export_resources,
+};
 use yaak_plugins::events::{CallGrpcRequestActionArgs, CallGrpcRequestActionRequest, CallHttpRequestActionArgs, CallHttpRequestActionRequest, Color, FilterResponse, GetGrpcRequestActionsResponse, GetHttpAuthenticationConfigResponse, GetHttpAuthenticationSummaryResponse, GetHttpRequestActionsResponse, GetTemplateFunctionSummaryResponse, GetTemplateFunctionConfigResponse, InternalEvent, InternalEventPayload, JsonPrimitive, PluginWindowContext, RenderPurpose, ShowToastRequest};
 use yaak_plugins::manager::PluginManager;
 use yaak_plugins::plugin_meta::PluginMetadata;
 use yaak_plugins::template_callback::PluginTemplateCallback;
 use yaak_sse::sse::ServerSentEvent;
-use yaak_templates::format::format_json;
-use yaak_templates::{RenderErrorBehavior, RenderOptions, Tokens, transform_args};
-use yaak_templates::format_xml::format_xml;
+use yaak_templates::filter::FilterType;
+use yaak_templates::format::{FormatMode, canonicalize_json, format_json, minify_json};
+use yaak_templates::{RenderOptions, Tokens, transform_args};
+use yaak_templates::format_xml::{canonicalize_xml, format_xml, minify_xml};
 
 mod commands;
+mod dotenv_watch;
 mod encoding;
 mod error;
 mod grpc;
+mod har;
 mod history;
 mod http_request;
 mod import;
+mod lint;
 mod notifications;
 mod plugin_events;
+mod refactor;
 mod render;
+mod response_diff;
+mod response_sweeper;
+mod secret_variables;
 mod updates;
 mod uri_scheme;
 mod window;
@@ -105,29 +119,35 @@ async fn cmd_template_tokens_to_string<R: Runtime>(
     Ok(new_tokens.to_string())
 }
 
+/// Render a template for preview. Pass the folder to include its environments, like when
+/// rendering a request in that folder, and `trace` to get a trace of where each tag's value came
+/// from instead of just the output.
 #[tauri::command]
 async fn cmd_render_template<R: Runtime>(
     window: WebviewWindow<R>,
     app_handle: AppHandle<R>,
     template: &str,
     workspace_id: &str,
+    folder_id: Option<&str>,
     environment_id: Option<&str>,
-) -> YaakResult<String> {
+    environment_ids: Option<Vec<String>>,
+    trace: Option<bool>,
+) -> YaakResult<RenderedTemplate> {
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let environment_chain =
-        app_handle.db().resolve_environments(workspace_id, None, environment_id)?;
-    let result = render_template(
-        template,
-        environment_chain,
-        &PluginTemplateCallback::new(
-            &app_handle,
-            &PluginWindowContext::new(&window),
-            RenderPurpose::Preview,
+        app_handle.db().resolve_environments(workspace_id, folder_id, &environment_ids)?;
+    let cb = PluginTemplateCallback::new(
+        &app_handle,
+        &PluginWindowContext::new(&window),
+        RenderPurpose::Preview,
+    );
+    let opt = &RenderOptions::throw();
+    let result = match trace {
+        Some(true) => RenderedTemplate::Trace(
+            render_template_with_trace(template, environment_chain, &cb, opt).await?,
         ),
-        &RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        },
-    )
-    .await?;
+        _ => RenderedTemplate::Output(render_template(template, environment_chain, &cb, opt).await?),
+    };
     Ok(result)
 }
 
@@ -144,6 +164,7 @@ async fn cmd_dismiss_notification<R: Runtime>(
 async fn cmd_grpc_reflect<R: Runtime>(
     request_id: &str,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     proto_files: Vec<String>,
     window: WebviewWindow<R>,
     app_handle: AppHandle<R>,
@@ -152,12 +173,14 @@ async fn cmd_grpc_reflect<R: Runtime>(
     let unrendered_request = app_handle.db().get_grpc_request(request_id)?;
     let (resolved_request, auth_context_id) = resolve_grpc_request(&window, &unrendered_request)?;
 
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let environment_chain = app_handle.db().resolve_environments(
         &unrendered_request.workspace_id,
         unrendered_request.folder_id.as_deref(),
-        environment_id,
+        &environment_ids,
     )?;
-    let workspace = app_handle.db().get_workspace(&unrendered_request.workspace_id)?;
+    let request_settings =
+        app_handle.db().resolve_settings_for_grpc_request(&unrendered_request)?;
 
     let req = render_grpc_request(
         &resolved_request,
@@ -167,9 +190,7 @@ async fn cmd_grpc_reflect<R: Runtime>(
             &PluginWindowContext::new(&window),
             RenderPurpose::Send,
         ),
-        &RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        },
+        &RenderOptions::throw(),
     )
     .await?;
 
@@ -184,7 +205,7 @@ async fn cmd_grpc_reflect<R: Runtime>(
             &uri,
             &proto_files.iter().map(|p| PathBuf::from_str(p).unwrap()).collect(),
             &metadata,
-            workspace.setting_validate_certificates,
+            request_settings.validate_certificates,
         )
         .await
         .map_err(|e| GenericError(e.to_string()))?)
@@ -194,6 +215,7 @@ async fn cmd_grpc_reflect<R: Runtime>(
 async fn cmd_grpc_go<R: Runtime>(
     request_id: &str,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     proto_files: Vec<String>,
     app_handle: AppHandle<R>,
     window: WebviewWindow<R>,
@@ -201,12 +223,14 @@ async fn cmd_grpc_go<R: Runtime>(
 ) -> YaakResult<String> {
     let unrendered_request = app_handle.db().get_grpc_request(request_id)?;
     let (resolved_request, auth_context_id) = resolve_grpc_request(&window, &unrendered_request)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let environment_chain = app_handle.db().resolve_environments(
         &unrendered_request.workspace_id,
         unrendered_request.folder_id.as_deref(),
-        environment_id,
+        &environment_ids,
     )?;
-    let workspace = app_handle.db().get_workspace(&unrendered_request.workspace_id)?;
+    let request_settings =
+        app_handle.db().resolve_settings_for_grpc_request(&unrendered_request)?;
 
     let request = render_grpc_request(
         &resolved_request,
@@ -216,12 +240,14 @@ async fn cmd_grpc_go<R: Runtime>(
             &PluginWindowContext::new(&window),
             RenderPurpose::Send,
         ),
-        &RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        },
+        &RenderOptions::throw(),
     )
     .await?;
 
+    // Messages are rendered separately, so they need the request's variables too
+    let environment_chain =
+        with_request_variables(environment_chain, &request.model, &request.id, &request.variables);
+
     let metadata = build_metadata(&window, &request, &auth_context_id).await?;
 
     let conn = app_handle.db().upsert_grpc_connection(
@@ -271,7 +297,7 @@ async fn cmd_grpc_go<R: Runtime>(
             uri.as_str(),
             &proto_files.iter().map(|p| PathBuf::from_str(p).unwrap()).collect(),
             &metadata,
-            workspace.setting_validate_certificates,
+            request_settings.validate_certificates,
         )
         .await;
 
@@ -341,9 +367,7 @@ async fn cmd_grpc_go<R: Runtime>(
                                     &PluginWindowContext::new(&window),
                                     RenderPurpose::Send,
                                 ),
-                                &RenderOptions {
-                                    error_behavior: RenderErrorBehavior::Throw,
-                                },
+                                &RenderOptions::throw(),
                             )
                             .await
                             .expect("Failed to render template")
@@ -413,9 +437,7 @@ async fn cmd_grpc_go<R: Runtime>(
                 &PluginWindowContext::new(&window),
                 RenderPurpose::Send,
             ),
-            &RenderOptions {
-                error_behavior: RenderErrorBehavior::Throw,
-            },
+            &RenderOptions::throw(),
         )
         .await?;
 
@@ -710,16 +732,14 @@ async fn cmd_restart<R: Runtime>(app_handle: AppHandle<R>) -> YaakResult<()> {
 async fn cmd_send_ephemeral_request<R: Runtime>(
     mut request: HttpRequest,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     cookie_jar_id: Option<&str>,
     window: WebviewWindow,
     app_handle: AppHandle<R>,
 ) -> YaakResult<HttpResponse> {
     let response = HttpResponse::default();
     request.id = "".to_string();
-    let environment = match environment_id {
-        Some(id) => Some(app_handle.db().get_environment(id)?),
-        None => None,
-    };
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let cookie_jar = match cookie_jar_id {
         Some(id) => Some(app_handle.db().get_cookie_jar(id)?),
         None => None,
@@ -732,17 +752,26 @@ async fn cmd_send_ephemeral_request<R: Runtime>(
         }
     });
 
-    send_http_request(&window, &request, &response, environment, cookie_jar, &mut cancel_rx).await
+    send_http_request(&window, &request, &response, &environment_ids, cookie_jar, &mut cancel_rx)
+        .await
 }
 
 #[tauri::command]
-async fn cmd_format_json(text: &str) -> YaakResult<String> {
-    Ok(format_json(text, "  "))
+async fn cmd_format_json(text: &str, mode: Option<FormatMode>) -> YaakResult<String> {
+    Ok(match mode.unwrap_or_default() {
+        FormatMode::Pretty => format_json(text, "  "),
+        FormatMode::Minify => minify_json(text),
+        FormatMode::Canonical => canonicalize_json(text, "  "),
+    })
 }
 
 #[tauri::command]
-async fn cmd_format_xml(text: &str) -> YaakResult<String> {
-    Ok(format_xml(text, "  "))
+async fn cmd_format_xml(text: &str, mode: Option<FormatMode>) -> YaakResult<String> {
+    Ok(match mode.unwrap_or_default() {
+        FormatMode::Pretty => format_xml(text, "  "),
+        FormatMode::Minify => minify_xml(text),
+        FormatMode::Canonical => canonicalize_xml(text, "  "),
+    })
 }
 
 #[tauri::command]
@@ -772,9 +801,27 @@ async fn cmd_http_response_body<R: Runtime>(
         .ok_or(GenericError("Failed to find response body".to_string()))?;
 
     match filter {
-        Some(filter) if !filter.is_empty() => {
-            Ok(plugin_manager.filter_data(&window, filter, &body, content_type).await?)
-        }
+        Some(filter) if !filter.is_empty() => match FilterType::from_content_type(content_type) {
+            Some(filter_type) => {
+                let result = filter_type.filter(&body, filter);
+                if filter_type.needs_plugin_fallback(filter, &result) {
+                    // Keep the native result if the plugin can't do any better
+                    match plugin_manager.filter_data(&window, filter, &body, content_type).await {
+                        Ok(r) if r.error.is_none() && r.content != "[]" => return Ok(r),
+                        _ => {}
+                    }
+                }
+                Ok(match result {
+                    Ok(content) => FilterResponse { content, error: None },
+                    Err(e) => FilterResponse {
+                        content: "".to_string(),
+                        error: Some(e.to_string()),
+                    },
+                })
+            }
+            // Fall back to plugins for content types without a native filter
+            None => Ok(plugin_manager.filter_data(&window, filter, &body, content_type).await?),
+        },
         _ => Ok(FilterResponse {
             content: body,
             error: None,
@@ -844,6 +891,7 @@ async fn cmd_template_function_config<R: Runtime>(
     values: HashMap<String, JsonPrimitive>,
     model: AnyModel,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
 ) -> YaakResult<GetTemplateFunctionConfigResponse> {
     let (workspace_id, folder_id) = match model.clone() {
         AnyModel::HttpRequest(m) => (m.workspace_id, m.folder_id),
@@ -855,8 +903,12 @@ async fn cmd_template_function_config<R: Runtime>(
             return Err(GenericError(format!("Unsupported model to call template functions {m:?}")));
         }
     };
-    let environment_chain =
-        window.db().resolve_environments(&workspace_id, folder_id.as_deref(), environment_id)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
+    let environment_chain = window.db().resolve_environments(
+        &workspace_id,
+        folder_id.as_deref(),
+        &environment_ids,
+    )?;
     Ok(plugin_manager.get_template_function_config(&window, function_name, environment_chain, values, model.id()).await?)
 }
 
@@ -877,6 +929,7 @@ async fn cmd_get_http_authentication_config<R: Runtime>(
     values: HashMap<String, JsonPrimitive>,
     model: AnyModel,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
 ) -> YaakResult<GetHttpAuthenticationConfigResponse> {
     let (workspace_id, folder_id) = match model.clone() {
         AnyModel::HttpRequest(m) => (m.workspace_id, m.folder_id),
@@ -889,8 +942,12 @@ async fn cmd_get_http_authentication_config<R: Runtime>(
         }
     };
 
-    let environment_chain =
-        window.db().resolve_environments(&workspace_id, folder_id.as_deref(), environment_id)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
+    let environment_chain = window.db().resolve_environments(
+        &workspace_id,
+        folder_id.as_deref(),
+        &environment_ids,
+    )?;
 
     Ok(plugin_manager
         .get_http_authentication_config(&window, environment_chain, auth_name, values, model.id())
@@ -946,6 +1003,7 @@ async fn cmd_call_http_authentication_action<R: Runtime>(
     values: HashMap<String, JsonPrimitive>,
     model: AnyModel,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
 ) -> YaakResult<()> {
     let (workspace_id, folder_id) = match model.clone() {
         AnyModel::HttpRequest(m) => (m.workspace_id, m.folder_id),
@@ -957,8 +1015,12 @@ async fn cmd_call_http_authentication_action<R: Runtime>(
             return Err(GenericError(format!("Unsupported model to call auth {m:?}")));
         }
     };
-    let environment_chain =
-        window.db().resolve_environments(&workspace_id, folder_id.as_deref(), environment_id)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
+    let environment_chain = window.db().resolve_environments(
+        &workspace_id,
+        folder_id.as_deref(),
+        &environment_ids,
+    )?;
     Ok(plugin_manager
         .call_http_authentication_action(
             &window,
@@ -999,9 +1061,24 @@ async fn cmd_export_data<R: Runtime>(
     export_path: &str,
     workspace_ids: Vec<&str>,
     include_private_environments: bool,
+    include_secrets: Option<bool>,
 ) -> YaakResult<()> {
-    let export_data =
-        get_workspace_export_resources(&app_handle, workspace_ids, include_private_environments)?;
+    let include_secrets = include_secrets.unwrap_or(false);
+    let mut export_data = get_workspace_export_resources(
+        &app_handle,
+        workspace_ids,
+        include_private_environments,
+        include_secrets,
+    )?;
+    if include_secrets {
+        // Secrets are exported as plain text, since the workspace key doesn't go with them
+        export_data.resources.environments = export_data
+            .resources
+            .environments
+            .into_iter()
+            .map(|e| decrypt_secret_variables(&app_handle, e))
+            .collect::<YaakResult<Vec<Environment>>>()?;
+    }
     let f = File::options()
         .create(true)
         .truncate(true)
@@ -1038,6 +1115,7 @@ async fn cmd_send_folder<R: Runtime>(
     app_handle: AppHandle<R>,
     window: WebviewWindow<R>,
     environment_id: Option<String>,
+    environment_ids: Option<Vec<String>>,
     cookie_jar_id: Option<String>,
     folder_id: &str,
 ) -> YaakResult<()> {
@@ -1046,12 +1124,14 @@ async fn cmd_send_folder<R: Runtime>(
         let app_handle = app_handle.clone();
         let window = window.clone();
         let environment_id = environment_id.clone();
+        let environment_ids = environment_ids.clone();
         let cookie_jar_id = cookie_jar_id.clone();
         tokio::spawn(async move {
             let _ = cmd_send_http_request(
                 app_handle,
                 window,
                 environment_id.as_deref(),
+                environment_ids,
                 cookie_jar_id.as_deref(),
                 request,
             )
@@ -1067,6 +1147,7 @@ async fn cmd_send_http_request<R: Runtime>(
     app_handle: AppHandle<R>,
     window: WebviewWindow<R>,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     cookie_jar_id: Option<&str>,
     // NOTE: We receive the entire request because to account for the race
     //   condition where the user may have just edited a field before sending
@@ -1089,16 +1170,7 @@ async fn cmd_send_http_request<R: Runtime>(
         }
     });
 
-    let environment = match environment_id {
-        Some(id) => match app_handle.db().get_environment(id) {
-            Ok(env) => Some(env),
-            Err(e) => {
-                warn!("Failed to find environment by id {id} {}", e);
-                None
-            }
-        },
-        None => None,
-    };
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
 
     let cookie_jar = match cookie_jar_id {
         Some(id) => Some(app_handle.db().get_cookie_jar(id)?),
@@ -1109,7 +1181,7 @@ async fn cmd_send_http_request<R: Runtime>(
         &window,
         &request,
         &response,
-        environment,
+        &environment_ids,
         cookie_jar,
         &mut cancel_rx,
     )
@@ -1451,9 +1523,17 @@ pub fn run() {
             //
             // Migrated commands
             crate::commands::cmd_decrypt_template,
+            crate::commands::cmd_diff_http_responses,
+            crate::commands::cmd_encrypt_secret_variables,
+            crate::commands::cmd_export_har,
             crate::commands::cmd_get_themes,
+            crate::commands::cmd_lint_workspace,
+            crate::commands::cmd_list_variable_usages,
+            crate::commands::cmd_rename_variable,
+            crate::commands::cmd_search_http_responses,
             crate::commands::cmd_secure_template,
             crate::commands::cmd_show_workspace_key,
+            crate::commands::cmd_watch_dotenv,
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application")
@@ -1475,6 +1555,9 @@ pub fn run() {
                         let _ = db.cancel_pending_grpc_connections();
                         let _ = db.cancel_pending_websocket_connections();
                     });
+
+                    // Prune old responses and orphaned body files in the background
+                    response_sweeper::start_response_sweeper(app_handle);
                 }
                 // RunEvent::WindowEvent {
                 //     event: WindowEvent::Focused(true),
@@ -1650,8 +1733,20 @@ fn workspace_from_window<R: Runtime>(window: &WebviewWindow<R>) -> Option<Worksp
     window.workspace_id().and_then(|id| window.db().get_workspace(&id).ok())
 }
 
-fn environment_from_window<R: Runtime>(window: &WebviewWindow<R>) -> Option<Environment> {
-    window.environment_id().and_then(|id| window.db().get_environment(&id).ok())
+/// The window's active environment, with the workspace's stacked sub-environments on top. Nothing
+/// is stacked without an active environment, matching what the environment menu shows.
+fn environment_ids_from_window<R: Runtime>(window: &WebviewWindow<R>) -> Vec<String> {
+    let mut ids: Vec<String> = match window.environment_id() {
+        Some(id) => vec![id],
+        None => return Vec::new(),
+    };
+    let workspace_meta = window.workspace_id().and_then(|id| window.db().get_workspace_meta(&id));
+    for id in workspace_meta.map(|m| m.stacked_environment_ids).unwrap_or_default() {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
 }
 
 fn cookie_jar_from_window<R: Runtime>(window: &WebviewWindow<R>) -> Option<CookieJar> {
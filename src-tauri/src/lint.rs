@@ -0,0 +1,306 @@
+use crate::error::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::{Manager, Runtime, WebviewWindow};
+use ts_rs::TS;
+use yaak_models::db_context::DbContext;
+use yaak_models::models::{Environment, EnvironmentVariable, HttpRequestHeader, HttpUrlParameter};
+use yaak_models::query_manager::QueryManagerExt;
+use yaak_models::render::make_vars_hashmap;
+use yaak_plugins::manager::PluginManager;
+use yaak_templates::lint::{LintContext, LintDiagnostic, LintDiagnosticKind, lint_template};
+use yaak_templates::{DiagnosticSeverity, Span};
+
+/// Problems with the templates in one field of a workspace, folder, or request
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct TemplateLintResult {
+    pub model: String,
+    pub model_id: String,
+    pub model_name: String,
+    /// Path to the field, like `url`, `headers[0].value`, or `body.text`
+    pub field: String,
+    pub diagnostics: Vec<LintDiagnostic>,
+}
+
+/// A model and its templated fields, by path
+pub(crate) struct TemplatedModel {
+    pub model: String,
+    pub model_id: String,
+    pub model_name: String,
+    pub folder_id: Option<String>,
+    pub fields: Vec<(String, String)>,
+    /// Names of the variables defined on the model itself
+    pub variables: Vec<String>,
+}
+
+/// Parse every templated field in the workspace, checking that the variables it uses are defined
+/// in each environment it could be rendered with, and that the functions it calls exist
+pub async fn lint_workspace<R: Runtime>(
+    window: &WebviewWindow<R>,
+    workspace_id: &str,
+) -> Result<Vec<TemplateLintResult>> {
+    let plugin_manager = window.state::<PluginManager>();
+    let functions = plugin_manager
+        .get_template_function_summaries(window)
+        .await?
+        .into_iter()
+        .flat_map(|s| s.functions)
+        .flat_map(|f| std::iter::once(f.name).chain(f.aliases.unwrap_or_default()))
+        .collect::<HashSet<String>>();
+
+    let db = window.db();
+    let targets = list_templated_models(&db, workspace_id)?;
+
+    // Every sub environment could be active, so variables are checked against each of them
+    let sub_environments = db
+        .list_environments_ensure_base(workspace_id)?
+        .into_iter()
+        .filter(|e| e.parent_model == "environment")
+        .collect::<Vec<Environment>>();
+
+    let mut contexts: HashMap<Option<String>, LintContext> = HashMap::new();
+    let mut results = Vec::new();
+    for target in targets {
+        if let Entry::Vacant(entry) = contexts.entry(target.folder_id.clone()) {
+            let folder_id = target.folder_id.as_deref();
+            let mut environments = Vec::new();
+            if sub_environments.is_empty() {
+                let chain = db.resolve_environments::<&str>(workspace_id, folder_id, &[])?;
+                let name = chain.last().map(|e| e.name.clone()).unwrap_or_default();
+                environments.push((name, variable_names(chain)));
+            }
+            for e in &sub_environments {
+                let chain = db.resolve_environments(workspace_id, folder_id, &[&e.id])?;
+                environments.push((e.name.clone(), variable_names(chain)));
+            }
+            let ctx = LintContext {
+                environments,
+                functions: functions.clone(),
+            };
+            entry.insert(ctx);
+        }
+        let ctx = with_variable_names(&contexts[&target.folder_id], &target.variables);
+
+        for (field, template) in target.fields {
+            if !template.contains("${") {
+                continue;
+            }
+            let diagnostics = lint_template(&template, &ctx)?;
+            if diagnostics.is_empty() {
+                continue;
+            }
+            results.push(TemplateLintResult {
+                model: target.model.clone(),
+                model_id: target.model_id.clone(),
+                model_name: target.model_name.clone(),
+                field,
+                diagnostics,
+            });
+        }
+    }
+
+    results.append(&mut lint_variable_types(&db, workspace_id)?);
+
+    Ok(results)
+}
+
+/// Check that environment variables with a declared type have a value of that type
+fn lint_variable_types(db: &DbContext, workspace_id: &str) -> Result<Vec<TemplateLintResult>> {
+    let mut results = Vec::new();
+    for e in db.list_environments_ensure_base(workspace_id)? {
+        for (i, v) in e.variables.iter().enumerate() {
+            let message = match v.type_error() {
+                Some(message) => message,
+                None => continue,
+            };
+            results.push(TemplateLintResult {
+                model: e.model.clone(),
+                model_id: e.id.clone(),
+                model_name: e.name.clone(),
+                field: format!("variables[{i}].value"),
+                diagnostics: vec![LintDiagnostic {
+                    kind: LintDiagnosticKind::InvalidValue,
+                    severity: DiagnosticSeverity::Error,
+                    message,
+                    name: Some(v.name.clone()),
+                    span: Span::new(0, v.value.encode_utf16().count()),
+                }],
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// List the workspace, its folders, and its requests, with the fields that can contain templates
+pub(crate) fn list_templated_models(
+    db: &DbContext,
+    workspace_id: &str,
+) -> Result<Vec<TemplatedModel>> {
+    let workspace = db.get_workspace(workspace_id)?;
+    let mut targets = vec![TemplatedModel {
+        model: workspace.model,
+        model_id: workspace.id,
+        model_name: workspace.name,
+        folder_id: None,
+        variables: Vec::new(),
+        fields: [
+            header_fields("headers", &workspace.headers),
+            map_fields("authentication", &workspace.authentication),
+        ]
+        .concat(),
+    }];
+
+    for f in db.list_folders(workspace_id)? {
+        targets.push(TemplatedModel {
+            fields: [
+                header_fields("headers", &f.headers),
+                map_fields("authentication", &f.authentication),
+            ]
+            .concat(),
+            model: f.model,
+            folder_id: Some(f.id.clone()),
+            model_id: f.id,
+            model_name: f.name,
+            variables: Vec::new(),
+        });
+    }
+
+    for r in db.list_http_requests(workspace_id)? {
+        targets.push(TemplatedModel {
+            fields: [
+                vec![("url".to_string(), r.url)],
+                param_fields("urlParameters", &r.url_parameters),
+                header_fields("headers", &r.headers),
+                map_fields("body", &r.body),
+                map_fields("authentication", &r.authentication),
+                variable_fields("variables", &r.variables),
+            ]
+            .concat(),
+            variables: defined_variable_names(r.variables),
+            model: r.model,
+            model_id: r.id,
+            model_name: r.name,
+            folder_id: r.folder_id,
+        });
+    }
+
+    for r in db.list_grpc_requests(workspace_id)? {
+        targets.push(TemplatedModel {
+            fields: [
+                vec![
+                    ("url".to_string(), r.url),
+                    ("message".to_string(), r.message),
+                ],
+                header_fields("metadata", &r.metadata),
+                map_fields("authentication", &r.authentication),
+                variable_fields("variables", &r.variables),
+            ]
+            .concat(),
+            variables: defined_variable_names(r.variables),
+            model: r.model,
+            model_id: r.id,
+            model_name: r.name,
+            folder_id: r.folder_id,
+        });
+    }
+
+    for r in db.list_websocket_requests(workspace_id)? {
+        targets.push(TemplatedModel {
+            fields: [
+                vec![
+                    ("url".to_string(), r.url),
+                    ("message".to_string(), r.message),
+                ],
+                param_fields("urlParameters", &r.url_parameters),
+                header_fields("headers", &r.headers),
+                map_fields("authentication", &r.authentication),
+                variable_fields("variables", &r.variables),
+            ]
+            .concat(),
+            variables: defined_variable_names(r.variables),
+            model: r.model,
+            model_id: r.id,
+            model_name: r.name,
+            folder_id: r.folder_id,
+        });
+    }
+
+    Ok(targets)
+}
+
+fn variable_names(environment_chain: Vec<Environment>) -> HashSet<String> {
+    make_vars_hashmap(environment_chain).into_keys().collect()
+}
+
+/// Names of the variables that are used when rendering, like [`make_vars_hashmap`] picks them
+fn defined_variable_names(variables: Vec<EnvironmentVariable>) -> Vec<String> {
+    variables.into_iter().filter(|v| v.enabled && !v.value.is_empty()).map(|v| v.name).collect()
+}
+
+/// Add variables defined on a model to every environment it could be rendered with
+fn with_variable_names<'a>(ctx: &'a LintContext, names: &[String]) -> Cow<'a, LintContext> {
+    if names.is_empty() {
+        return Cow::Borrowed(ctx);
+    }
+    let mut ctx = ctx.clone();
+    for (_, variables) in ctx.environments.iter_mut() {
+        variables.extend(names.iter().cloned());
+    }
+    Cow::Owned(ctx)
+}
+
+fn header_fields(prefix: &str, headers: &[HttpRequestHeader]) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for (i, h) in headers.iter().enumerate() {
+        fields.push((format!("{prefix}[{i}].name"), h.name.clone()));
+        fields.push((format!("{prefix}[{i}].value"), h.value.clone()));
+    }
+    fields
+}
+
+fn variable_fields(prefix: &str, variables: &[EnvironmentVariable]) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for (i, v) in variables.iter().enumerate() {
+        fields.push((format!("{prefix}[{i}].value"), v.value.clone()));
+    }
+    fields
+}
+
+fn param_fields(prefix: &str, params: &[HttpUrlParameter]) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for (i, p) in params.iter().enumerate() {
+        fields.push((format!("{prefix}[{i}].name"), p.name.clone()));
+        fields.push((format!("{prefix}[{i}].value"), p.value.clone()));
+    }
+    fields
+}
+
+fn map_fields(prefix: &str, map: &BTreeMap<String, Value>) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    for (k, v) in map {
+        json_fields(format!("{prefix}.{k}"), v, &mut fields);
+    }
+    fields
+}
+
+fn json_fields(path: String, v: &Value, fields: &mut Vec<(String, String)>) {
+    match v {
+        Value::String(s) => fields.push((path, s.clone())),
+        Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                json_fields(format!("{path}[{i}]"), v, fields);
+            }
+        }
+        Value::Object(o) => {
+            for (k, v) in o {
+                json_fields(format!("{path}.{k}"), v, fields);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,10 +1,12 @@
 use crate::error::Result;
 use crate::http_request::send_http_request;
-use crate::render::{render_grpc_request, render_http_request, render_json_value};
+use crate::render::{
+    render_grpc_request, render_http_request, render_json_value, render_json_value_with_trace,
+};
 use crate::window::{CreateWindowConfig, create_window};
 use crate::{
-    call_frontend, cookie_jar_from_window, environment_from_window, get_window_from_window_context,
-    workspace_from_window,
+    call_frontend, cookie_jar_from_window, environment_ids_from_window,
+    get_window_from_window_context, workspace_from_window,
 };
 use chrono::Utc;
 use cookie::Cookie;
@@ -20,12 +22,12 @@ use yaak_plugins::events::{
     Color, DeleteKeyValueResponse, EmptyPayload, ErrorResponse, FindHttpResponsesResponse,
     GetCookieValueResponse, GetHttpRequestByIdResponse, GetKeyValueResponse, Icon, InternalEvent,
     InternalEventPayload, ListCookieNamesResponse, PluginWindowContext, RenderGrpcRequestResponse,
-    RenderHttpRequestResponse, SendHttpRequestResponse, SetKeyValueResponse, ShowToastRequest,
-    TemplateRenderResponse, WindowNavigateEvent,
+    RenderHttpRequestResponse, SearchHttpResponsesResponse, SendHttpRequestResponse,
+    SetKeyValueResponse, ShowToastRequest, TemplateRenderResponse, WindowNavigateEvent,
 };
 use yaak_plugins::plugin_handle::PluginHandle;
 use yaak_plugins::template_callback::PluginTemplateCallback;
-use yaak_templates::{RenderErrorBehavior, RenderOptions};
+use yaak_templates::RenderOptions;
 
 pub(crate) async fn handle_plugin_event<R: Runtime>(
     app_handle: &AppHandle<R>,
@@ -61,6 +63,26 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
                 http_responses,
             })))
         }
+        InternalEventPayload::SearchHttpResponsesRequest(req) => {
+            let workspace_id = match &req.request_id {
+                Some(id) => app_handle.db().get_http_request(id)?.workspace_id,
+                None => {
+                    let window = get_window_from_window_context(app_handle, &window_context)?;
+                    workspace_from_window(&window)
+                        .expect("Failed to get workspace_id from window URL")
+                        .id
+                }
+            };
+            let http_responses = app_handle.db().search_http_responses(
+                &workspace_id,
+                &req.query,
+                req.request_id.as_deref(),
+                req.limit.map(|l| l as u64),
+            )?;
+            Ok(Some(InternalEventPayload::SearchHttpResponsesResponse(
+                SearchHttpResponsesResponse { http_responses },
+            )))
+        }
         InternalEventPayload::GetHttpRequestByIdRequest(req) => {
             let http_request = app_handle.db().get_http_request(&req.id).ok();
             Ok(Some(InternalEventPayload::GetHttpRequestByIdResponse(GetHttpRequestByIdResponse {
@@ -72,16 +94,14 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
 
             let workspace =
                 workspace_from_window(&window).expect("Failed to get workspace_id from window URL");
-            let environment_id = environment_from_window(&window).map(|e| e.id);
+            let environment_ids = environment_ids_from_window(&window);
             let environment_chain = window.db().resolve_environments(
                 &workspace.id,
                 req.grpc_request.folder_id.as_deref(),
-                environment_id.as_deref(),
+                &environment_ids,
             )?;
             let cb = PluginTemplateCallback::new(app_handle, &window_context, req.purpose);
-            let opt = RenderOptions {
-                error_behavior: RenderErrorBehavior::Throw,
-            };
+            let opt = RenderOptions::throw();
             let grpc_request =
                 render_grpc_request(&req.grpc_request, environment_chain, &cb, &opt).await?;
             Ok(Some(InternalEventPayload::RenderGrpcRequestResponse(RenderGrpcRequestResponse {
@@ -93,16 +113,14 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
 
             let workspace =
                 workspace_from_window(&window).expect("Failed to get workspace_id from window URL");
-            let environment_id = environment_from_window(&window).map(|e| e.id);
+            let environment_ids = environment_ids_from_window(&window);
             let environment_chain = window.db().resolve_environments(
                 &workspace.id,
                 req.http_request.folder_id.as_deref(),
-                environment_id.as_deref(),
+                &environment_ids,
             )?;
             let cb = PluginTemplateCallback::new(app_handle, &window_context, req.purpose);
-            let opt = &RenderOptions {
-                error_behavior: RenderErrorBehavior::Throw,
-            };
+            let opt = &RenderOptions::throw();
             let http_request =
                 render_http_request(&req.http_request, environment_chain, &cb, &opt).await?;
             Ok(Some(InternalEventPayload::RenderHttpRequestResponse(RenderHttpRequestResponse {
@@ -114,7 +132,7 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
 
             let workspace =
                 workspace_from_window(&window).expect("Failed to get workspace_id from window URL");
-            let environment_id = environment_from_window(&window).map(|e| e.id);
+            let environment_ids = environment_ids_from_window(&window);
             let folder_id = if let Some(id) = window.request_id() {
                 match window.db().get_any_request(&id) {
                     Ok(AnyRequest::HttpRequest(r)) => r.folder_id,
@@ -128,14 +146,23 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
             let environment_chain = window.db().resolve_environments(
                 &workspace.id,
                 folder_id.as_deref(),
-                environment_id.as_deref(),
+                &environment_ids,
             )?;
             let cb = PluginTemplateCallback::new(app_handle, &window_context, req.purpose);
-            let opt = RenderOptions {
-                error_behavior: RenderErrorBehavior::Throw,
+            let opt = RenderOptions::throw();
+            let (data, trace) = match req.trace {
+                Some(true) => {
+                    let (data, tags) =
+                        render_json_value_with_trace(req.data, environment_chain, &cb, &opt)
+                            .await?;
+                    (data, Some(tags))
+                }
+                _ => (render_json_value(req.data, environment_chain, &cb, &opt).await?, None),
             };
-            let data = render_json_value(req.data, environment_chain, &cb, &opt).await?;
-            Ok(Some(InternalEventPayload::TemplateRenderResponse(TemplateRenderResponse { data })))
+            Ok(Some(InternalEventPayload::TemplateRenderResponse(TemplateRenderResponse {
+                data,
+                trace,
+            })))
         }
         InternalEventPayload::ErrorResponse(resp) => {
             error!("Plugin error: {}: {:?}", resp.error, resp);
@@ -192,7 +219,7 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
             let workspace =
                 workspace_from_window(&window).expect("Failed to get workspace_id from window URL");
             let cookie_jar = cookie_jar_from_window(&window);
-            let environment = environment_from_window(&window);
+            let environment_ids = environment_ids_from_window(&window);
 
             if http_request.workspace_id.is_empty() {
                 http_request.workspace_id = workspace.id;
@@ -215,7 +242,7 @@ pub(crate) async fn handle_plugin_event<R: Runtime>(
                 &window,
                 &http_request,
                 &http_response,
-                environment,
+                &environment_ids,
                 cookie_jar,
                 &mut tokio::sync::watch::channel(false).1, // No-op cancel channel
             )
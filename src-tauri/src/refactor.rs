@@ -0,0 +1,323 @@
+use crate::error::Error::GenericError;
+use crate::error::Result;
+use crate::lint::list_templated_models;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::{Runtime, WebviewWindow};
+use ts_rs::TS;
+use yaak_models::models::{EnvironmentVariable, HttpRequestHeader, HttpUrlParameter};
+use yaak_models::query_manager::QueryManagerExt;
+use yaak_models::util::UpdateSource;
+use yaak_templates::Span;
+use yaak_templates::refactor::{find_variable_usages, is_valid_variable_name, rename_variable};
+
+/// A model that was changed by renaming a variable, and how many uses were renamed
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct RenamedModel {
+    pub model: String,
+    pub model_id: String,
+    pub model_name: String,
+    pub count: usize,
+    /// Fields that were left unchanged because their template couldn't be parsed, like
+    /// `headers[0].value`
+    pub skipped: Vec<String>,
+}
+
+/// Where a variable is used, in one field of an environment, workspace, folder, or request
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct VariableUsage {
+    pub model: String,
+    pub model_id: String,
+    pub model_name: String,
+    /// Path to the field, like `url`, `headers[0].value`, or `variables[0].value`
+    pub field: String,
+    pub spans: Vec<Span>,
+}
+
+pub fn list_variable_usages<R: Runtime>(
+    window: &WebviewWindow<R>,
+    workspace_id: &str,
+    name: &str,
+) -> Result<Vec<VariableUsage>> {
+    let db = window.db();
+    let mut usages = Vec::new();
+
+    for e in db.list_environments_ensure_base(workspace_id)? {
+        for (i, v) in e.variables.iter().enumerate() {
+            // Templates that can't be parsed are skipped, like when renaming
+            let spans = find_variable_usages(&v.value, name).unwrap_or_default();
+            if spans.is_empty() {
+                continue;
+            }
+            usages.push(VariableUsage {
+                model: e.model.clone(),
+                model_id: e.id.clone(),
+                model_name: e.name.clone(),
+                field: format!("variables[{i}].value"),
+                spans,
+            });
+        }
+    }
+
+    for m in list_templated_models(&db, workspace_id)? {
+        for (field, template) in m.fields {
+            let spans = find_variable_usages(&template, name).unwrap_or_default();
+            if spans.is_empty() {
+                continue;
+            }
+            usages.push(VariableUsage {
+                model: m.model.clone(),
+                model_id: m.model_id.clone(),
+                model_name: m.model_name.clone(),
+                field,
+                spans,
+            });
+        }
+    }
+
+    Ok(usages)
+}
+
+/// Rename a variable in every environment that defines it, and every template in the workspace
+/// that uses it. Everything is renamed in one transaction, so a failure leaves nothing changed.
+/// Templates that can't be parsed are left as they are, and reported as skipped.
+pub fn rename_workspace_variable<R: Runtime>(
+    window: &WebviewWindow<R>,
+    workspace_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<Vec<RenamedModel>> {
+    if !is_valid_variable_name(to) {
+        return Err(GenericError(format!("Invalid variable name {to}")));
+    }
+
+    let source = &UpdateSource::from_window(window);
+    window.db_manager().with_tx(|db| {
+        let mut renamed = Vec::new();
+
+        for mut e in db.list_environments_ensure_base(workspace_id)? {
+            let has_var = |name: &str| e.variables.iter().any(|v| v.name == name);
+            if from != to && has_var(from) && has_var(to) {
+                return Err(GenericError(format!("Variable {to} already exists in {}", e.name)));
+            }
+            let mut r = Renamer::new(from, to);
+            r.variables("variables", &mut e.variables);
+            if r.count > 0 {
+                db.upsert_environment(&e, source)?;
+            }
+            renamed.extend(r.finish(&e.model, &e.id, &e.name));
+        }
+
+        let mut w = db.get_workspace(workspace_id)?;
+        let mut r = Renamer::new(from, to);
+        r.headers("headers", &mut w.headers);
+        r.map("authentication", &mut w.authentication);
+        if r.count > 0 {
+            db.upsert_workspace(&w, source)?;
+        }
+        renamed.extend(r.finish(&w.model, &w.id, &w.name));
+
+        for mut f in db.list_folders(workspace_id)? {
+            let mut r = Renamer::new(from, to);
+            r.headers("headers", &mut f.headers);
+            r.map("authentication", &mut f.authentication);
+            if r.count > 0 {
+                db.upsert_folder(&f, source)?;
+            }
+            renamed.extend(r.finish(&f.model, &f.id, &f.name));
+        }
+
+        for mut req in db.list_http_requests(workspace_id)? {
+            let mut r = Renamer::new(from, to);
+            r.string("url", &mut req.url);
+            r.params("urlParameters", &mut req.url_parameters);
+            r.headers("headers", &mut req.headers);
+            r.map("body", &mut req.body);
+            r.map("authentication", &mut req.authentication);
+            r.variables("variables", &mut req.variables);
+            if r.count > 0 {
+                db.upsert_http_request(&req, source)?;
+            }
+            renamed.extend(r.finish(&req.model, &req.id, &req.name));
+        }
+
+        for mut req in db.list_grpc_requests(workspace_id)? {
+            let mut r = Renamer::new(from, to);
+            r.string("url", &mut req.url);
+            r.string("message", &mut req.message);
+            r.headers("metadata", &mut req.metadata);
+            r.map("authentication", &mut req.authentication);
+            r.variables("variables", &mut req.variables);
+            if r.count > 0 {
+                db.upsert_grpc_request(&req, source)?;
+            }
+            renamed.extend(r.finish(&req.model, &req.id, &req.name));
+        }
+
+        for mut req in db.list_websocket_requests(workspace_id)? {
+            let mut r = Renamer::new(from, to);
+            r.string("url", &mut req.url);
+            r.string("message", &mut req.message);
+            r.params("urlParameters", &mut req.url_parameters);
+            r.headers("headers", &mut req.headers);
+            r.map("authentication", &mut req.authentication);
+            r.variables("variables", &mut req.variables);
+            if r.count > 0 {
+                db.upsert_websocket_request(&req, source)?;
+            }
+            renamed.extend(r.finish(&req.model, &req.id, &req.name));
+        }
+
+        Ok(renamed)
+    })
+}
+
+/// Renames a variable in the fields of one model, counting the uses that were renamed and
+/// keeping track of fields that couldn't be parsed
+struct Renamer<'a> {
+    from: &'a str,
+    to: &'a str,
+    count: usize,
+    skipped: Vec<String>,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(from: &'a str, to: &'a str) -> Self {
+        Renamer {
+            from,
+            to,
+            count: 0,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// The model that was renamed in, if anything was renamed or skipped
+    fn finish(self, model: &str, id: &str, name: &str) -> Option<RenamedModel> {
+        if self.count == 0 && self.skipped.is_empty() {
+            return None;
+        }
+        Some(RenamedModel {
+            model: model.to_string(),
+            model_id: id.to_string(),
+            model_name: name.to_string(),
+            count: self.count,
+            skipped: self.skipped,
+        })
+    }
+
+    fn string(&mut self, field: &str, s: &mut String) {
+        if !s.contains("${") {
+            return;
+        }
+        match rename_variable(s, self.from, self.to) {
+            Ok((new_s, n)) if n > 0 => {
+                *s = new_s;
+                self.count += n;
+            }
+            Ok(_) => {}
+            Err(_) => self.skipped.push(field.to_string()),
+        }
+    }
+
+    /// Rename variables defined in the list, and usages in their values
+    fn variables(&mut self, prefix: &str, variables: &mut [EnvironmentVariable]) {
+        for (i, v) in variables.iter_mut().enumerate() {
+            if v.name == self.from {
+                v.name = self.to.to_string();
+                self.count += 1;
+            }
+            self.string(&format!("{prefix}[{i}].value"), &mut v.value);
+        }
+    }
+
+    fn headers(&mut self, prefix: &str, headers: &mut [HttpRequestHeader]) {
+        for (i, h) in headers.iter_mut().enumerate() {
+            self.string(&format!("{prefix}[{i}].name"), &mut h.name);
+            self.string(&format!("{prefix}[{i}].value"), &mut h.value);
+        }
+    }
+
+    fn params(&mut self, prefix: &str, params: &mut [HttpUrlParameter]) {
+        for (i, p) in params.iter_mut().enumerate() {
+            self.string(&format!("{prefix}[{i}].name"), &mut p.name);
+            self.string(&format!("{prefix}[{i}].value"), &mut p.value);
+        }
+    }
+
+    fn map(&mut self, prefix: &str, map: &mut BTreeMap<String, Value>) {
+        for (k, v) in map.iter_mut() {
+            self.json(&format!("{prefix}.{k}"), v);
+        }
+    }
+
+    fn json(&mut self, path: &str, v: &mut Value) {
+        match v {
+            Value::String(s) => self.string(path, s),
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    self.json(&format!("{path}[{i}]"), v);
+                }
+            }
+            Value::Object(o) => {
+                for (k, v) in o.iter_mut() {
+                    self.json(&format!("{path}.{k}"), v);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn header(name: &str, value: &str) -> HttpRequestHeader {
+        HttpRequestHeader {
+            enabled: true,
+            name: name.to_string(),
+            value: value.to_string(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn rename_skips_malformed() {
+        // The string isn't valid base64, so the template can't be parsed
+        let malformed = "${[ token ]} ${[ b64'%%' ]}";
+        let mut headers = vec![
+            header("Authorization", malformed),
+            header("X-Token", "${[ token ]}"),
+        ];
+        let mut body = BTreeMap::from([(
+            "form".to_string(),
+            json!([{ "value": "${[ token ]} ${[ token ]}" }, { "value": malformed }]),
+        )]);
+
+        let mut r = Renamer::new("token", "api_token");
+        r.headers("headers", &mut headers);
+        r.map("body", &mut body);
+        let renamed = r.finish("http_request", "rq_1", "Login").unwrap();
+
+        assert_eq!(renamed.count, 3);
+        assert_eq!(renamed.skipped, vec!["headers[0].value", "body.form[1].value"]);
+        assert_eq!(headers[0].value, malformed);
+        assert_eq!(headers[1].value, "${[ api_token ]}");
+        assert_eq!(body["form"][0]["value"], json!("${[ api_token ]} ${[ api_token ]}"));
+        assert_eq!(body["form"][1]["value"], json!(malformed));
+    }
+
+    #[test]
+    fn rename_nothing() {
+        let mut headers = vec![header("Accept", "${[ other ]}")];
+        let mut r = Renamer::new("token", "api_token");
+        r.headers("headers", &mut headers);
+        assert_eq!(r.finish("http_request", "rq_1", "Login"), None);
+    }
+}
@@ -1,11 +1,17 @@
+use futures_util::future::try_join_all;
+use serde::Serialize;
 use serde_json::Value;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use yaak_http::apply_path_placeholders;
 use yaak_models::models::{
     Environment, GrpcRequest, HttpRequest, HttpRequestHeader, HttpUrlParameter,
 };
-use yaak_models::render::make_vars_hashmap;
-use yaak_templates::{RenderOptions, TemplateCallback, parse_and_render, render_json_value_raw};
+use yaak_models::render::{make_vars_environments, make_vars_hashmap, with_request_variables};
+use yaak_templates::trace::{RenderTrace, TagTrace, TraceSource};
+use yaak_templates::{
+    RenderOptions, TemplateCallback, parse_and_render, parse_and_render_json_typed,
+    parse_and_render_with_trace, render_json_value_raw,
+};
 
 pub async fn render_template<T: TemplateCallback>(
     template: &str,
@@ -13,8 +19,9 @@ pub async fn render_template<T: TemplateCallback>(
     cb: &T,
     opt: &RenderOptions,
 ) -> yaak_templates::error::Result<String> {
+    let sources = make_vars_sources(&environment_chain);
     let vars = &make_vars_hashmap(environment_chain);
-    parse_and_render(template, vars, cb, &opt).await
+    parse_and_render(template, vars, cb, &opt).await.map_err(|e| e.with_variable_sources(&sources))
 }
 
 pub async fn render_json_value<T: TemplateCallback>(
@@ -23,34 +30,106 @@ pub async fn render_json_value<T: TemplateCallback>(
     cb: &T,
     opt: &RenderOptions,
 ) -> yaak_templates::error::Result<Value> {
+    let sources = make_vars_sources(&environment_chain);
     let vars = &make_vars_hashmap(environment_chain);
-    render_json_value_raw(value, vars, cb, opt).await
+    render_json_value_raw(value, vars, cb, opt).await.map_err(|e| e.with_variable_sources(&sources))
 }
 
-pub async fn render_grpc_request<T: TemplateCallback>(
-    r: &GrpcRequest,
+/// A rendered template, or the trace of rendering it when one was asked for
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum RenderedTemplate {
+    Output(String),
+    Trace(RenderTrace),
+}
+
+/// Functions that return secrets, which are redacted from traces
+const SECRET_FUNCTIONS: [&str; 3] = ["secure", "keychain", "keyring"];
+
+/// Render a template like [`render_template`], along with a trace of where each tag's value
+/// came from. Secret values are redacted from the trace, but not from the output.
+pub async fn render_template_with_trace<T: TemplateCallback>(
+    template: &str,
     environment_chain: Vec<Environment>,
     cb: &T,
     opt: &RenderOptions,
-) -> yaak_templates::error::Result<GrpcRequest> {
+) -> yaak_templates::error::Result<RenderTrace> {
+    let sources = make_vars_sources(&environment_chain);
+    let secrets = make_secret_vars(&environment_chain);
     let vars = &make_vars_hashmap(environment_chain);
+    let trace = parse_and_render_with_trace(template, vars, cb, opt)
+        .await
+        .map_err(|e| e.with_variable_sources(&sources))?;
+    Ok(RenderTrace {
+        tags: trace.tags.into_iter().map(|t| finish_trace(t, &sources, &secrets)).collect(),
+        ..trace
+    })
+}
 
-    let mut metadata = Vec::new();
-    for p in r.metadata.clone() {
-        metadata.push(HttpRequestHeader {
-            enabled: p.enabled,
-            name: parse_and_render(p.name.as_str(), vars, cb, &opt).await?,
-            value: parse_and_render(p.value.as_str(), vars, cb, &opt).await?,
-            id: p.id,
+pub async fn render_json_value_with_trace<T: TemplateCallback>(
+    value: Value,
+    environment_chain: Vec<Environment>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> yaak_templates::error::Result<(Value, Vec<TagTrace>)> {
+    let sources = make_vars_sources(&environment_chain);
+    let secrets = make_secret_vars(&environment_chain);
+    let vars = &make_vars_hashmap(environment_chain);
+    let (value, tags) = yaak_templates::render_json_value_with_trace(value, vars, cb, opt)
+        .await
+        .map_err(|e| e.with_variable_sources(&sources))?;
+    Ok((value, tags.into_iter().map(|t| finish_trace(t, &sources, &secrets)).collect()))
+}
+
+fn finish_trace(
+    tag: TagTrace,
+    sources: &HashMap<String, TraceSource>,
+    secrets: &HashSet<String>,
+) -> TagTrace {
+    tag.with_variable_sources(sources).redact_secrets(secrets, &SECRET_FUNCTIONS)
+}
+
+/// Names of the variables whose value comes from a secret variable
+fn make_secret_vars(environment_chain: &[Environment]) -> HashSet<String> {
+    make_vars_environments(environment_chain)
+        .into_iter()
+        .filter(|(name, e)| e.variables.iter().any(|v| v.enabled && v.secret && &v.name == name))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Map each variable to the environment it comes from, and the workspace or folder that owns it
+fn make_vars_sources(environment_chain: &[Environment]) -> HashMap<String, TraceSource> {
+    make_vars_environments(environment_chain)
+        .into_iter()
+        .map(|(name, e)| {
+            let source = TraceSource {
+                environment_id: e.id.clone(),
+                environment_name: e.name.clone(),
+                parent_model: e.parent_model.clone(),
+                parent_id: e.parent_id.clone(),
+            };
+            (name, source)
         })
-    }
+        .collect()
+}
 
-    let mut authentication = BTreeMap::new();
-    for (k, v) in r.authentication.clone() {
-        authentication.insert(k, render_json_value_raw(v, vars, cb, &opt).await?);
-    }
+pub async fn render_grpc_request<T: TemplateCallback>(
+    r: &GrpcRequest,
+    environment_chain: Vec<Environment>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> yaak_templates::error::Result<GrpcRequest> {
+    let environment_chain =
+        with_request_variables(environment_chain, &r.model, &r.id, &r.variables);
+    let sources = make_vars_sources(&environment_chain);
+    let vars = &make_vars_hashmap(environment_chain);
 
-    let url = parse_and_render(r.url.as_str(), vars, cb, &opt).await?;
+    let metadata = render_headers(&r.metadata, vars, cb, opt);
+    let authentication = render_json_map(&r.authentication, vars, cb, opt);
+    let url = parse_and_render(r.url.as_str(), vars, cb, opt);
+    let (metadata, authentication, url) = tokio::try_join!(metadata, authentication, url)
+        .map_err(|e| e.with_variable_sources(&sources))?;
 
     Ok(GrpcRequest {
         url,
@@ -66,39 +145,44 @@ pub async fn render_http_request<T: TemplateCallback>(
     cb: &T,
     opt: &RenderOptions,
 ) -> yaak_templates::error::Result<HttpRequest> {
+    let environment_chain =
+        with_request_variables(environment_chain, &r.model, &r.id, &r.variables);
+    let sources = make_vars_sources(&environment_chain);
     let vars = &make_vars_hashmap(environment_chain);
 
-    let mut url_parameters = Vec::new();
-    for p in r.url_parameters.clone() {
-        url_parameters.push(HttpUrlParameter {
+    let url_parameters = try_join_all(r.url_parameters.iter().map(|p| async move {
+        Ok(HttpUrlParameter {
             enabled: p.enabled,
-            name: parse_and_render(p.name.as_str(), vars, cb, &opt).await?,
-            value: parse_and_render(p.value.as_str(), vars, cb, &opt).await?,
-            id: p.id,
+            name: parse_and_render(p.name.as_str(), vars, cb, opt).await?,
+            value: parse_and_render(p.value.as_str(), vars, cb, opt).await?,
+            id: p.id.clone(),
         })
-    }
+    }));
 
-    let mut headers = Vec::new();
-    for p in r.headers.clone() {
-        headers.push(HttpRequestHeader {
-            enabled: p.enabled,
-            name: parse_and_render(p.name.as_str(), vars, cb, &opt).await?,
-            value: parse_and_render(p.value.as_str(), vars, cb, &opt).await?,
-            id: p.id,
-        })
-    }
+    let headers = render_headers(&r.headers, vars, cb, opt);
 
-    let mut body = BTreeMap::new();
-    for (k, v) in r.body.clone() {
-        body.insert(k, render_json_value_raw(v, vars, cb, &opt).await?);
-    }
+    // Typed mode renders JSON strings that are exactly one tag to the tag's JSON type
+    let typed = r.body.get("typedTemplates").and_then(|v| v.as_bool()).unwrap_or(false);
+    let body = try_join_all(r.body.clone().into_iter().map(|(k, v)| async move {
+        let v = match (r.body_type.as_deref(), k.as_str(), v) {
+            (Some("application/json"), "text", Value::String(text))
+            | (Some("graphql"), "variables", Value::String(text))
+                if typed =>
+            {
+                Value::String(parse_and_render_json_typed(&text, vars, cb, opt).await?)
+            }
+            (_, _, v) => render_json_value_raw(v, vars, cb, opt).await?,
+        };
+        Ok((k, v))
+    }));
 
-    let mut authentication = BTreeMap::new();
-    for (k, v) in r.authentication.clone() {
-        authentication.insert(k, render_json_value_raw(v, vars, cb, &opt).await?);
-    }
+    let authentication = render_json_map(&r.authentication, vars, cb, opt);
+    let url = parse_and_render(r.url.as_str(), vars, cb, opt);
 
-    let url = parse_and_render(r.url.clone().as_str(), vars, cb, &opt).await?;
+    // Each part is rendered concurrently, as are the fields within it
+    let (url_parameters, headers, body, authentication, url) =
+        tokio::try_join!(url_parameters, headers, body, authentication, url)
+            .map_err(|e| e.with_variable_sources(&sources))?;
 
     // This doesn't fit perfectly with the concept of "rendering" but it kind of does
     let (url, url_parameters) = apply_path_placeholders(&url, url_parameters);
@@ -107,8 +191,38 @@ pub async fn render_http_request<T: TemplateCallback>(
         url,
         url_parameters,
         headers,
-        body,
+        body: body.into_iter().collect(),
         authentication,
         ..r.to_owned()
     })
 }
+
+async fn render_headers<T: TemplateCallback>(
+    headers: &[HttpRequestHeader],
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> yaak_templates::error::Result<Vec<HttpRequestHeader>> {
+    try_join_all(headers.iter().map(|h| async move {
+        Ok(HttpRequestHeader {
+            enabled: h.enabled,
+            name: parse_and_render(h.name.as_str(), vars, cb, opt).await?,
+            value: parse_and_render(h.value.as_str(), vars, cb, opt).await?,
+            id: h.id.clone(),
+        })
+    }))
+    .await
+}
+
+async fn render_json_map<T: TemplateCallback>(
+    map: &BTreeMap<String, Value>,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> yaak_templates::error::Result<BTreeMap<String, Value>> {
+    let entries = try_join_all(map.iter().map(|(k, v)| async move {
+        Ok((k.clone(), render_json_value_raw(v.clone(), vars, cb, opt).await?))
+    }))
+    .await?;
+    Ok(entries.into_iter().collect())
+}
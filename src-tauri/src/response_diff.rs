@@ -0,0 +1,580 @@
+use crate::encoding::read_response_body;
+use crate::error::Error::GenericError;
+use crate::error::Result;
+use serde::Serialize;
+use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use ts_rs::TS;
+use yaak_models::models::{HttpResponse, HttpResponseHeader};
+
+// Lines of unchanged context to include around each changed section of a text diff
+const LINE_DIFF_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct HttpResponseDiff {
+    pub status: Option<StatusDiff>,
+    pub headers: Vec<HeaderDiff>,
+    pub body: BodyDiff,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct StatusDiff {
+    pub before: i32,
+    pub after: i32,
+    pub before_reason: Option<String>,
+    pub after_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase", tag = "type")]
+#[ts(export, export_to = "index.ts")]
+pub enum HeaderDiff {
+    Added {
+        name: String,
+        value: String,
+    },
+    Removed {
+        name: String,
+        value: String,
+    },
+    Changed {
+        name: String,
+        before: String,
+        after: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase", tag = "type")]
+#[ts(export, export_to = "index.ts")]
+pub enum BodyDiff {
+    Json { changes: Vec<StructuralChange> },
+    Xml { changes: Vec<StructuralChange> },
+    Text { lines: Vec<LineChange> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct StructuralChange {
+    /// JSONPath (for JSON) or XPath (for XML) of the changed node
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "index.ts")]
+pub struct LineChange {
+    pub tag: LineChangeTag,
+    pub before_line: Option<usize>,
+    pub after_line: Option<usize>,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "index.ts")]
+pub enum LineChangeTag {
+    Equal,
+    Insert,
+    Delete,
+}
+
+pub async fn diff_http_responses(
+    before: &HttpResponse,
+    after: &HttpResponse,
+) -> Result<HttpResponseDiff> {
+    let status = if before.status != after.status || before.status_reason != after.status_reason {
+        Some(StatusDiff {
+            before: before.status,
+            after: after.status,
+            before_reason: before.status_reason.clone(),
+            after_reason: after.status_reason.clone(),
+        })
+    } else {
+        None
+    };
+
+    let before_body = read_body(before).await?;
+    let after_body = read_body(after).await?;
+
+    Ok(HttpResponseDiff {
+        status,
+        headers: diff_headers(&before.headers, &after.headers),
+        body: diff_bodies(&before_body, content_type(before), &after_body, content_type(after)),
+    })
+}
+
+/// Read the body of a response. A response without a body file has an empty body, but one whose
+/// file can't be read fails, rather than showing the whole body as removed.
+async fn read_body(response: &HttpResponse) -> Result<String> {
+    match &response.body_path {
+        Some(p) => read_response_body(p, content_type(response)).await.ok_or_else(|| {
+            GenericError(format!("Failed to read body of response {}", response.id))
+        }),
+        None => Ok("".to_string()),
+    }
+}
+
+fn content_type(response: &HttpResponse) -> &str {
+    response
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("content-type"))
+        .map(|h| h.value.as_str())
+        .unwrap_or_default()
+}
+
+/// Diff headers by case-insensitive name. Repeated headers are compared as a single
+/// comma-separated value.
+pub fn diff_headers(
+    before: &[HttpResponseHeader],
+    after: &[HttpResponseHeader],
+) -> Vec<HeaderDiff> {
+    let before = group_headers(before);
+    let after = group_headers(after);
+
+    let mut diffs = Vec::new();
+    for (key, (name, value)) in &before {
+        match after.get(key) {
+            None => diffs.push(HeaderDiff::Removed {
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            Some((_, after_value)) if after_value != value => diffs.push(HeaderDiff::Changed {
+                name: name.clone(),
+                before: value.clone(),
+                after: after_value.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (key, (name, value)) in &after {
+        if !before.contains_key(key) {
+            diffs.push(HeaderDiff::Added {
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    diffs
+}
+
+fn group_headers(headers: &[HttpResponseHeader]) -> BTreeMap<String, (String, String)> {
+    let mut grouped: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for h in headers {
+        grouped
+            .entry(h.name.to_lowercase())
+            .and_modify(|(_, v)| {
+                v.push_str(", ");
+                v.push_str(&h.value);
+            })
+            .or_insert((h.name.clone(), h.value.clone()));
+    }
+    grouped
+}
+
+/// Diff two bodies structurally if both content types are JSON or both are XML and both sides
+/// parse, otherwise fall back to a line diff. A JSON API that starts returning an HTML error page
+/// gets a line diff, for example.
+pub fn diff_bodies(
+    before: &str,
+    before_content_type: &str,
+    after: &str,
+    after_content_type: &str,
+) -> BodyDiff {
+    let (before_content_type, after_content_type) =
+        (before_content_type.to_lowercase(), after_content_type.to_lowercase());
+    let both_contain = |s: &str| before_content_type.contains(s) && after_content_type.contains(s);
+
+    let structural = if both_contain("json") {
+        diff_json_bodies(before, after).map(|changes| BodyDiff::Json { changes })
+    } else if both_contain("xml") {
+        diff_xml_bodies(before, after).map(|changes| BodyDiff::Xml { changes })
+    } else {
+        None
+    };
+
+    structural.unwrap_or_else(|| BodyDiff::Text {
+        lines: diff_lines(before, after),
+    })
+}
+
+fn diff_json_bodies(before: &str, after: &str) -> Option<Vec<StructuralChange>> {
+    let a: Value = serde_json::from_str(before).ok()?;
+    let b: Value = serde_json::from_str(after).ok()?;
+    let mut changes = Vec::new();
+    diff_json("$", &a, &b, &mut changes);
+    Some(changes)
+}
+
+fn diff_xml_bodies(before: &str, after: &str) -> Option<Vec<StructuralChange>> {
+    let a = sxd_document::parser::parse(before).ok()?;
+    let b = sxd_document::parser::parse(after).ok()?;
+    let (a, b) = (a.as_document(), b.as_document());
+    let mut changes = Vec::new();
+    diff_xml_elements("", root_element_children(&a), root_element_children(&b), &mut changes);
+    Some(changes)
+}
+
+fn diff_json(path: &str, before: &Value, after: &Value, changes: &mut Vec<StructuralChange>) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            // Object keys are compared as sets, so key order doesn't matter
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = json_path_key(path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => diff_json(&path, a, b, changes),
+                    (a, b) => changes.push(StructuralChange {
+                        path,
+                        before: a.map(|v| v.to_string()),
+                        after: b.map(|v| v.to_string()),
+                    }),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let path = format!("{path}[{i}]");
+                match (a.get(i), b.get(i)) {
+                    (Some(a), Some(b)) => diff_json(&path, a, b, changes),
+                    (a, b) => changes.push(StructuralChange {
+                        path,
+                        before: a.map(|v| v.to_string()),
+                        after: b.map(|v| v.to_string()),
+                    }),
+                }
+            }
+        }
+        (a, b) if a != b => changes.push(StructuralChange {
+            path: path.to_string(),
+            before: Some(a.to_string()),
+            after: Some(b.to_string()),
+        }),
+        _ => {}
+    }
+}
+
+fn json_path_key(path: &str, key: &str) -> String {
+    let is_ident = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+fn root_element_children<'d>(doc: &sxd_document::dom::Document<'d>) -> Vec<Element<'d>> {
+    doc.root()
+        .children()
+        .into_iter()
+        .filter_map(|c| match c {
+            ChildOfRoot::Element(e) => Some(e),
+            _ => None,
+        })
+        .collect()
+}
+
+fn element_children<'d>(el: &Element<'d>) -> Vec<Element<'d>> {
+    el.children()
+        .into_iter()
+        .filter_map(|c| match c {
+            ChildOfElement::Element(e) => Some(e),
+            _ => None,
+        })
+        .collect()
+}
+
+fn element_name(el: &Element) -> String {
+    match el.preferred_prefix() {
+        Some(p) => format!("{p}:{}", el.name().local_part()),
+        None => el.name().local_part().to_string(),
+    }
+}
+
+fn element_text(el: &Element) -> String {
+    el.children()
+        .into_iter()
+        .filter_map(|c| match c {
+            ChildOfElement::Text(t) => Some(t.text().trim().to_string()),
+            _ => None,
+        })
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn element_to_string(el: &Element) -> String {
+    let text = element_text(el);
+    let name = element_name(el);
+    if text.is_empty() { format!("<{name}/>") } else { format!("<{name}>{text}</{name}>") }
+}
+
+/// Sibling elements are matched by name and position among same-named siblings, so each
+/// path is a valid XPath like `/root[1]/item[2]`
+fn diff_xml_elements(
+    path: &str,
+    before: Vec<Element>,
+    after: Vec<Element>,
+    changes: &mut Vec<StructuralChange>,
+) {
+    let mut names: Vec<String> = before.iter().chain(after.iter()).map(element_name).collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        let a: Vec<&Element> = before.iter().filter(|e| element_name(e) == name).collect();
+        let b: Vec<&Element> = after.iter().filter(|e| element_name(e) == name).collect();
+        for i in 0..a.len().max(b.len()) {
+            let path = format!("{path}/{name}[{}]", i + 1);
+            match (a.get(i), b.get(i)) {
+                (Some(a), Some(b)) => diff_xml_element(&path, a, b, changes),
+                (a, b) => changes.push(StructuralChange {
+                    path,
+                    before: a.map(|e| element_to_string(e)),
+                    after: b.map(|e| element_to_string(e)),
+                }),
+            }
+        }
+    }
+}
+
+fn diff_xml_element(
+    path: &str,
+    before: &Element,
+    after: &Element,
+    changes: &mut Vec<StructuralChange>,
+) {
+    // Attributes are compared as a map, so attribute order doesn't matter
+    let attrs = |el: &Element| -> BTreeMap<String, String> {
+        el.attributes()
+            .into_iter()
+            .map(|a| {
+                let name = match a.preferred_prefix() {
+                    Some(p) => format!("{p}:{}", a.name().local_part()),
+                    None => a.name().local_part().to_string(),
+                };
+                (name, a.value().to_string())
+            })
+            .collect()
+    };
+    let (a_attrs, b_attrs) = (attrs(before), attrs(after));
+    let mut attr_names: Vec<&String> = a_attrs.keys().chain(b_attrs.keys()).collect();
+    attr_names.sort();
+    attr_names.dedup();
+    for name in attr_names {
+        let (a, b) = (a_attrs.get(name), b_attrs.get(name));
+        if a != b {
+            changes.push(StructuralChange {
+                path: format!("{path}/@{name}"),
+                before: a.cloned(),
+                after: b.cloned(),
+            });
+        }
+    }
+
+    let (a_text, b_text) = (element_text(before), element_text(after));
+    if a_text != b_text {
+        changes.push(StructuralChange {
+            path: format!("{path}/text()"),
+            before: Some(a_text).filter(|t| !t.is_empty()),
+            after: Some(b_text).filter(|t| !t.is_empty()),
+        });
+    }
+
+    diff_xml_elements(path, element_children(before), element_children(after), changes);
+}
+
+pub fn diff_lines(before: &str, after: &str) -> Vec<LineChange> {
+    let diff = TextDiff::from_lines(before, after);
+    let mut lines = Vec::new();
+    for group in diff.grouped_ops(LINE_DIFF_CONTEXT) {
+        for op in group {
+            for change in diff.iter_changes(&op) {
+                lines.push(LineChange {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => LineChangeTag::Equal,
+                        ChangeTag::Insert => LineChangeTag::Insert,
+                        ChangeTag::Delete => LineChangeTag::Delete,
+                    },
+                    before_line: change.old_index().map(|i| i + 1),
+                    after_line: change.new_index().map(|i| i + 1),
+                    value: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                });
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::response_diff::{
+        BodyDiff, HeaderDiff, LineChangeTag, StructuralChange, diff_bodies, diff_headers,
+        diff_http_responses,
+    };
+    use yaak_models::models::{HttpResponse, HttpResponseHeader};
+
+    fn header(name: &str, value: &str) -> HttpResponseHeader {
+        HttpResponseHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn change(path: &str, before: Option<&str>, after: Option<&str>) -> StructuralChange {
+        StructuralChange {
+            path: path.to_string(),
+            before: before.map(|s| s.to_string()),
+            after: after.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn headers() {
+        let before = [header("Content-Type", "text/plain"), header("X-Old", "1")];
+        let after = [
+            header("content-type", "text/html"),
+            header("X-New", "a"),
+            header("x-new", "b"),
+        ];
+        assert_eq!(
+            diff_headers(&before, &after),
+            vec![
+                HeaderDiff::Changed {
+                    name: "Content-Type".to_string(),
+                    before: "text/plain".to_string(),
+                    after: "text/html".to_string(),
+                },
+                HeaderDiff::Removed {
+                    name: "X-Old".to_string(),
+                    value: "1".to_string(),
+                },
+                HeaderDiff::Added {
+                    name: "X-New".to_string(),
+                    value: "a, b".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn json_ignores_key_order() {
+        let before = r#"{"a": 1, "b": {"c": [1, 2]}, "d e": true}"#;
+        let after = r#"{"d e": false, "b": {"c": [1]}, "a": 1, "f": null}"#;
+        assert_eq!(
+            diff_bodies(before, "application/json", after, "application/json"),
+            BodyDiff::Json {
+                changes: vec![
+                    change("$.b.c[1]", Some("2"), None),
+                    change("$['d e']", Some("true"), Some("false")),
+                    change("$.f", None, Some("null")),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn xml_paths() {
+        let before = r#"<root><item id="1">a</item><item>b</item></root>"#;
+        let after = r#"<root><item id="2">a</item><other/></root>"#;
+        assert_eq!(
+            diff_bodies(before, "text/xml", after, "application/xml"),
+            BodyDiff::Xml {
+                changes: vec![
+                    change("/root[1]/item[1]/@id", Some("1"), Some("2")),
+                    change("/root[1]/item[2]", Some("<item>b</item>"), None),
+                    change("/root[1]/other[1]", None, Some("<other/>")),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn mismatched_content_types_use_line_diff() {
+        let before = "{\"ok\": true}";
+        let after = "<html>Bad Gateway</html>";
+        let BodyDiff::Text { lines } = diff_bodies(before, "application/json", after, "text/html")
+        else {
+            panic!("Expected a text diff");
+        };
+        let tags = lines.iter().map(|l| l.tag).collect::<Vec<_>>();
+        assert_eq!(tags, vec![LineChangeTag::Delete, LineChangeTag::Insert]);
+    }
+
+    #[test]
+    fn invalid_json_uses_line_diff() {
+        let diff = diff_bodies("{", "application/json", "{}", "application/json");
+        assert!(matches!(diff, BodyDiff::Text { .. }));
+    }
+
+    #[test]
+    fn lines_with_context() {
+        let before = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let after = "1\n2\n3\n4\n5\nsix\n7\n8\n9\n";
+        let BodyDiff::Text { lines } = diff_bodies(before, "text/plain", after, "text/plain")
+        else {
+            panic!("Expected a text diff");
+        };
+        let lines = lines
+            .iter()
+            .map(|l| (l.tag, l.before_line, l.after_line, l.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (LineChangeTag::Equal, Some(3), Some(3), "3"),
+                (LineChangeTag::Equal, Some(4), Some(4), "4"),
+                (LineChangeTag::Equal, Some(5), Some(5), "5"),
+                (LineChangeTag::Delete, Some(6), None, "6"),
+                (LineChangeTag::Insert, None, Some(6), "six"),
+                (LineChangeTag::Equal, Some(7), Some(7), "7"),
+                (LineChangeTag::Equal, Some(8), Some(8), "8"),
+                (LineChangeTag::Equal, Some(9), Some(9), "9"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_body_file_fails() {
+        let before = HttpResponse {
+            id: "rs_1".to_string(),
+            body_path: Some("/nonexistent/yaak/body".to_string()),
+            ..Default::default()
+        };
+        let after = HttpResponse::default();
+        let err = diff_http_responses(&before, &after).await.unwrap_err();
+        assert_eq!(err.to_string(), "Generic error: Failed to read body of response rs_1");
+    }
+
+    #[tokio::test]
+    async fn responses_without_bodies() {
+        let before = HttpResponse {
+            status: 200,
+            ..Default::default()
+        };
+        let after = HttpResponse {
+            status: 500,
+            ..Default::default()
+        };
+        let diff = diff_http_responses(&before, &after).await.unwrap();
+        assert_eq!(diff.status.map(|s| (s.before, s.after)), Some((200, 500)));
+        assert!(diff.headers.is_empty());
+        assert_eq!(diff.body, BodyDiff::Text { lines: vec![] });
+    }
+}
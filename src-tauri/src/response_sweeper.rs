@@ -0,0 +1,77 @@
+use crate::error::Result;
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::fs;
+use yaak_models::models::HttpResponse;
+use yaak_models::query_manager::QueryManagerExt;
+use yaak_models::util::UpdateSource;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 30);
+
+// Body files are written before their response is saved, and ephemeral responses never are, so
+// only consider files orphaned once they've been untouched for a while
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically apply each workspace's response retention settings and delete body files
+/// in `app_data_dir/responses` that no response references anymore.
+pub fn start_response_sweeper<R: Runtime>(app_handle: &AppHandle<R>) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sweep_responses(&app_handle).await {
+                warn!("Failed to sweep responses: {e:?}");
+            }
+        }
+    });
+}
+
+async fn sweep_responses<R: Runtime>(app_handle: &AppHandle<R>) -> Result<()> {
+    for workspace in app_handle.db().list_workspaces()? {
+        let count = app_handle
+            .db()
+            .prune_http_responses_for_workspace(&workspace.id, &UpdateSource::Background)?;
+        if count > 0 {
+            info!("Pruned {count} responses from workspace {}", workspace.id);
+        }
+    }
+
+    let referenced: HashSet<PathBuf> = app_handle
+        .db()
+        .find_all::<HttpResponse>()?
+        .into_iter()
+        .flat_map(|r| [r.body_path, r.request_body_path])
+        .flatten()
+        .map(PathBuf::from)
+        .collect();
+
+    let dir = app_handle.path().app_data_dir()?.join("responses");
+    let mut entries = match fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()), // Nothing has been written yet
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if referenced.contains(&path) {
+            continue;
+        }
+
+        let metadata = entry.metadata().await?;
+        let age = metadata.modified()?.elapsed().unwrap_or_default();
+        if !metadata.is_file() || age < ORPHAN_GRACE_PERIOD {
+            continue;
+        }
+
+        debug!("Deleting orphaned response body {path:?}");
+        if let Err(e) = fs::remove_file(&path).await {
+            warn!("Failed to delete orphaned response body {path:?}: {e:?}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::error::Error::GenericError;
+use crate::error::Result;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use tauri::{AppHandle, Runtime};
+use yaak_crypto::manager::EncryptionManagerExt;
+use yaak_models::models::{ENCRYPTED_VALUE_PREFIX, Environment, EnvironmentVariable};
+
+/// Encrypt the values of secret variables that are still plain text, with the workspace key
+pub(crate) fn encrypt_secret_variables<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    workspace_id: &str,
+    variables: Vec<EnvironmentVariable>,
+) -> Result<Vec<EnvironmentVariable>> {
+    variables
+        .into_iter()
+        .map(|v| {
+            if !v.secret || v.value.is_empty() || v.is_encrypted() {
+                return Ok(v);
+            }
+            let encrypted = app_handle.crypto().encrypt(workspace_id, v.value.as_bytes())?;
+            let value = format!("{ENCRYPTED_VALUE_PREFIX}{}", BASE64_STANDARD.encode(encrypted));
+            Ok(EnvironmentVariable { value, ..v })
+        })
+        .collect()
+}
+
+/// Decrypt the values of secret variables, so they can be exported to somewhere without the
+/// workspace key
+pub(crate) fn decrypt_secret_variables<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    environment: Environment,
+) -> Result<Environment> {
+    let mut variables = Vec::new();
+    for v in environment.variables {
+        let encoded = match v.value.strip_prefix(ENCRYPTED_VALUE_PREFIX) {
+            Some(encoded) if v.secret => encoded,
+            _ => {
+                variables.push(v);
+                continue;
+            }
+        };
+        let encrypted = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|e| GenericError(format!("Failed to decode secret {}: {e}", v.name)))?;
+        let decrypted = app_handle.crypto().decrypt(&environment.workspace_id, &encrypted)?;
+        let value = String::from_utf8(decrypted)
+            .map_err(|e| GenericError(format!("Failed to decode secret {}: {e}", v.name)))?;
+        variables.push(EnvironmentVariable { value, ..v });
+    }
+
+    Ok(Environment {
+        variables,
+        ..environment
+    })
+}
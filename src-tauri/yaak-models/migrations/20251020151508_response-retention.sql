@@ -0,0 +1,9 @@
+ALTER TABLE http_responses
+    ADD COLUMN retain BOOLEAN DEFAULT FALSE NOT NULL;
+
+ALTER TABLE workspace_metas
+    ADD COLUMN setting_response_history_limit INTEGER DEFAULT NULL;
+ALTER TABLE workspace_metas
+    ADD COLUMN setting_response_history_days INTEGER DEFAULT NULL;
+ALTER TABLE workspace_metas
+    ADD COLUMN setting_response_disk_quota_mb INTEGER DEFAULT NULL;
@@ -0,0 +1,23 @@
+-- Full-text index over responses. Bodies live on disk, so rows are written by the app when a
+-- response closes rather than by triggers.
+CREATE VIRTUAL TABLE http_responses_fts USING fts5
+(
+    response_id UNINDEXED,
+    workspace_id UNINDEXED,
+    request_id UNINDEXED,
+    url,
+    headers,
+    body
+);
+
+CREATE TRIGGER http_responses_fts_delete
+    AFTER DELETE
+    ON http_responses
+BEGIN
+    DELETE FROM http_responses_fts WHERE response_id = old.id;
+END;
+
+-- Backfill URLs and headers of existing responses (bodies are indexed for new responses only)
+INSERT INTO http_responses_fts (response_id, workspace_id, request_id, url, headers, body)
+SELECT id, workspace_id, request_id, url, headers, ''
+FROM http_responses;
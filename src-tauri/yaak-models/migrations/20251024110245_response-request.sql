@@ -0,0 +1,9 @@
+-- The request as it was actually sent, after rendering and authentication
+ALTER TABLE http_responses
+    ADD COLUMN request_method TEXT DEFAULT '' NOT NULL;
+ALTER TABLE http_responses
+    ADD COLUMN request_url TEXT DEFAULT '' NOT NULL;
+ALTER TABLE http_responses
+    ADD COLUMN request_headers TEXT DEFAULT '[]' NOT NULL;
+ALTER TABLE http_responses
+    ADD COLUMN request_body_path TEXT;
@@ -0,0 +1,4 @@
+ALTER TABLE workspace_metas
+    ADD COLUMN setting_dotenv_path TEXT DEFAULT NULL;
+ALTER TABLE workspace_metas
+    ADD COLUMN setting_env_allowlist TEXT DEFAULT '[]' NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE workspace_metas
+    ADD COLUMN stacked_environment_ids TEXT DEFAULT '[]' NOT NULL;
@@ -0,0 +1,6 @@
+ALTER TABLE http_requests
+    ADD COLUMN variables TEXT DEFAULT '[]' NOT NULL;
+ALTER TABLE grpc_requests
+    ADD COLUMN variables TEXT DEFAULT '[]' NOT NULL;
+ALTER TABLE websocket_requests
+    ADD COLUMN variables TEXT DEFAULT '[]' NOT NULL;
@@ -200,3 +200,21 @@ impl<'a> DbContext<'a> {
         Ok(m.clone())
     }
 }
+
+#[cfg(test)]
+impl DbContext<'static> {
+    /// Connect to a fresh, migrated in-memory database
+    pub(crate) fn in_memory() -> Self {
+        // Every in-memory connection is its own database, so only ever open one
+        let pool = r2d2::Pool::builder()
+            .max_size(1)
+            .build(r2d2_sqlite::SqliteConnectionManager::memory())
+            .expect("Failed to create in-memory DB");
+        crate::migrate::migrate_db(&pool).expect("Failed to migrate in-memory DB");
+        let (events_tx, _) = mpsc::channel();
+        DbContext {
+            events_tx,
+            conn: ConnectionOrTx::Connection(pool.get().expect("Failed to connect to DB")),
+        }
+    }
+}
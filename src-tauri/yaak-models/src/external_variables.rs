@@ -0,0 +1,306 @@
+use crate::models::{Environment, EnvironmentVariable, WorkspaceMeta};
+use log::warn;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Prefix of variables from the process environment, like `${[ env.HOME ]}`
+pub const PROCESS_ENV_PREFIX: &str = "env.";
+
+impl WorkspaceMeta {
+    /// Path of the `.env` file to load variables from, if the workspace has one
+    pub fn dotenv_path(&self) -> Option<PathBuf> {
+        let path = self.setting_dotenv_path.as_deref().map(str::trim).unwrap_or_default();
+        if path.is_empty() {
+            return None;
+        }
+
+        let path = PathBuf::from(path);
+        match self.setting_sync_dir.as_deref() {
+            Some(dir) if path.is_relative() => Some(PathBuf::from(dir).join(path)),
+            _ => Some(path),
+        }
+    }
+}
+
+/// Environments for the variables a workspace has opted in to from outside of Yaak, from highest
+/// to lowest priority. These aren't stored, so they're rebuilt every time.
+pub fn external_environments(workspace_meta: &WorkspaceMeta) -> Vec<Environment> {
+    [
+        dotenv_environment(workspace_meta),
+        process_environment(workspace_meta),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Load the workspace's `.env` file. A file that can't be read is skipped, so rendering still works
+/// while it's missing.
+pub fn dotenv_environment(workspace_meta: &WorkspaceMeta) -> Option<Environment> {
+    let path = workspace_meta.dotenv_path()?;
+    let variables = cached_dotenv_variables(&path)?;
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+    Some(Environment {
+        model: "environment".to_string(),
+        id: format!("{}_dotenv", workspace_meta.workspace_id),
+        workspace_id: workspace_meta.workspace_id.clone(),
+        name: name.unwrap_or(".env".to_string()),
+        parent_model: "dotenv".to_string(),
+        parent_id: Some(path.to_string_lossy().to_string()),
+        variables,
+        ..Default::default()
+    })
+}
+
+/// Parsed `.env` files that are being watched for changes, so they don't have to be read again
+/// on every render. Files that aren't watched are read every time, since they could have changed.
+static DOTENV_CACHE: LazyLock<Mutex<HashMap<PathBuf, DotenvCacheEntry>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Default)]
+struct DotenvCacheEntry {
+    watchers: usize,
+    /// `None` until the file is read, and again after it changes
+    variables: Option<Option<Vec<EnvironmentVariable>>>,
+    /// Counts changes, so a read that started before a change isn't cached after it
+    generation: usize,
+}
+
+/// Start caching the `.env` file. Call this when starting to watch it, and
+/// [`invalidate_dotenv_cache`] whenever it changes.
+pub fn watch_dotenv_cache(path: &Path) {
+    let mut cache = DOTENV_CACHE.lock().unwrap();
+    cache.entry(path.to_path_buf()).or_default().watchers += 1;
+}
+
+/// Stop caching the `.env` file, once nothing is watching it anymore
+pub fn unwatch_dotenv_cache(path: &Path) {
+    let mut cache = DOTENV_CACHE.lock().unwrap();
+    if let Some(entry) = cache.get_mut(path) {
+        entry.watchers -= 1;
+        if entry.watchers == 0 {
+            cache.remove(path);
+        }
+    }
+}
+
+/// Forget the cached contents of the `.env` file, so it's read again the next time it's needed
+pub fn invalidate_dotenv_cache(path: &Path) {
+    if let Some(entry) = DOTENV_CACHE.lock().unwrap().get_mut(path) {
+        entry.variables = None;
+        entry.generation += 1;
+    }
+}
+
+fn cached_dotenv_variables(path: &Path) -> Option<Vec<EnvironmentVariable>> {
+    let generation = match DOTENV_CACHE.lock().unwrap().get(path) {
+        Some(DotenvCacheEntry {
+            variables: Some(variables),
+            ..
+        }) => return variables.clone(),
+        Some(entry) => Some(entry.generation),
+        None => None,
+    };
+
+    // Read without holding the lock, so a slow disk doesn't hold up other renders
+    let variables = match fs::read_to_string(path) {
+        Ok(contents) => Some(parse_dotenv(&contents)),
+        Err(e) => {
+            warn!("Failed to read .env file {path:?}: {e}");
+            None
+        }
+    };
+
+    if let Some(entry) = DOTENV_CACHE.lock().unwrap().get_mut(path) {
+        if Some(entry.generation) == generation {
+            entry.variables = Some(variables.clone());
+        }
+    }
+    variables
+}
+
+/// The allowlisted process environment variables, prefixed with [`PROCESS_ENV_PREFIX`]
+pub fn process_environment(workspace_meta: &WorkspaceMeta) -> Option<Environment> {
+    let allowlist = &workspace_meta.setting_env_allowlist;
+    if allowlist.is_empty() {
+        return None;
+    }
+
+    let mut variables = std::env::vars()
+        .filter(|(name, _)| is_allowed_env_var(name, allowlist))
+        .map(|(name, value)| EnvironmentVariable {
+            enabled: true,
+            name: format!("{PROCESS_ENV_PREFIX}{name}"),
+            value,
+            ..Default::default()
+        })
+        .collect::<Vec<EnvironmentVariable>>();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Some(Environment {
+        model: "environment".to_string(),
+        id: format!("{}_process", workspace_meta.workspace_id),
+        workspace_id: workspace_meta.workspace_id.clone(),
+        name: "Process Environment".to_string(),
+        parent_model: "process".to_string(),
+        variables,
+        ..Default::default()
+    })
+}
+
+fn is_allowed_env_var(name: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().map(|a| a.trim()).any(|a| match a.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == a,
+    })
+}
+
+/// Parse the contents of a `.env` file. Lines can start with `export`, values can be unquoted,
+/// single-quoted (literal), or double-quoted (with `\n`, `\t`, `\"`, and `\\` escapes), and `#`
+/// starts a comment outside of quotes. Later definitions of a name take priority.
+pub fn parse_dotenv(contents: &str) -> Vec<EnvironmentVariable> {
+    let mut variables = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => (name.trim(), value.trim()),
+            _ => {
+                warn!("Skipping invalid .env line {line:?}");
+                continue;
+            }
+        };
+
+        variables.push(EnvironmentVariable {
+            enabled: true,
+            name: name.to_string(),
+            value: parse_dotenv_value(value),
+            ..Default::default()
+        });
+    }
+
+    variables
+}
+
+fn parse_dotenv_value(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest.split_once('\'').map(|(v, _)| v).unwrap_or(rest).to_string();
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some('r') => parsed.push('\r'),
+                    Some('t') => parsed.push('\t'),
+                    Some(c) => parsed.push(c),
+                    None => parsed.push('\\'),
+                },
+                c => parsed.push(c),
+            }
+        }
+        return parsed;
+    }
+
+    // Unquoted values end at a comment
+    let value = match value.find(" #") {
+        Some(i) => &value[..i],
+        None => value,
+    };
+    value.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(contents: &str) -> Vec<(String, String)> {
+        parse_dotenv(contents).into_iter().map(|v| (v.name, v.value)).collect()
+    }
+
+    fn var(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn dotenv_lines() {
+        let contents = r#"
+# A comment
+FOO=bar
+export TOKEN = abc
+  SPACED  =  value with spaces
+EMPTY=
+=no_name
+not a variable
+"#;
+        assert_eq!(
+            vars(contents),
+            vec![
+                var("FOO", "bar"),
+                var("TOKEN", "abc"),
+                var("SPACED", "value with spaces"),
+                var("EMPTY", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn dotenv_values() {
+        assert_eq!(parse_dotenv_value("plain"), "plain");
+        assert_eq!(parse_dotenv_value("plain # comment"), "plain");
+        assert_eq!(parse_dotenv_value("a#b"), "a#b");
+        assert_eq!(parse_dotenv_value("'single # quoted' # comment"), "single # quoted");
+        assert_eq!(parse_dotenv_value(r#"'no \n escapes'"#), r#"no \n escapes"#);
+        assert_eq!(parse_dotenv_value(r#""a\nb\tc\"d\\e" # comment"#), "a\nb\tc\"d\\e");
+        assert_eq!(parse_dotenv_value(r#""unterminated"#), "unterminated");
+        assert_eq!(parse_dotenv_value("'unterminated"), "unterminated");
+    }
+
+    #[test]
+    fn allowed_env_vars() {
+        let allowlist = vec!["HOME".to_string(), " AWS_* ".to_string()];
+        assert!(is_allowed_env_var("HOME", &allowlist));
+        assert!(is_allowed_env_var("AWS_REGION", &allowlist));
+        assert!(is_allowed_env_var("AWS_", &allowlist));
+        assert!(!is_allowed_env_var("HOMEPATH", &allowlist));
+        assert!(!is_allowed_env_var("PATH", &allowlist));
+        assert!(!is_allowed_env_var("HOME", &[]));
+    }
+
+    #[test]
+    fn dotenv_cache() {
+        let path = std::env::temp_dir().join(format!("yaak-test-{}.env", std::process::id()));
+        fs::write(&path, "A=1").unwrap();
+        let read = || cached_dotenv_variables(&path).unwrap()[0].value.clone();
+
+        // Unwatched files are read every time
+        assert_eq!(read(), "1");
+        fs::write(&path, "A=2").unwrap();
+        assert_eq!(read(), "2");
+
+        // Watched files are read again only after they change
+        watch_dotenv_cache(&path);
+        assert_eq!(read(), "2");
+        fs::write(&path, "A=3").unwrap();
+        assert_eq!(read(), "2");
+        invalidate_dotenv_cache(&path);
+        assert_eq!(read(), "3");
+
+        unwatch_dotenv_cache(&path);
+        fs::write(&path, "A=4").unwrap();
+        assert_eq!(read(), "4");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cached_dotenv_variables(&path), None);
+    }
+}
@@ -18,6 +18,7 @@ mod commands;
 mod connection_or_tx;
 pub mod db_context;
 pub mod error;
+pub mod external_variables;
 mod migrate;
 pub mod models;
 pub mod queries;
@@ -1,7 +1,8 @@
 use crate::error::Result;
 use crate::models::HttpRequestIden::{
     Authentication, AuthenticationType, Body, BodyType, CreatedAt, Description, FolderId, Headers,
-    Method, Name, SortPriority, UpdatedAt, Url, UrlParameters, WorkspaceId,
+    Method, Name, SettingFollowRedirects, SettingRequestTimeout, SettingValidateCertificates,
+    SortPriority, UpdatedAt, Url, UrlParameters, Variables, WorkspaceId,
 };
 use crate::util::{UpdateSource, generate_prefixed_id};
 use chrono::{NaiveDateTime, Utc};
@@ -344,6 +345,30 @@ impl UpsertModelInfo for Workspace {
     }
 }
 
+/// Settings for sending a request, after applying the overrides of its folders and itself to the
+/// workspace settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestSettings {
+    pub validate_certificates: bool,
+    pub follow_redirects: bool,
+    pub request_timeout: i32,
+}
+
+impl RequestSettings {
+    pub fn with_overrides(
+        self,
+        validate_certificates: Option<bool>,
+        follow_redirects: Option<bool>,
+        request_timeout: Option<i32>,
+    ) -> Self {
+        Self {
+            validate_certificates: validate_certificates.unwrap_or(self.validate_certificates),
+            follow_redirects: follow_redirects.unwrap_or(self.follow_redirects),
+            request_timeout: request_timeout.unwrap_or(self.request_timeout),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, TS)]
 #[serde(default, rename_all = "camelCase")]
 #[ts(export, export_to = "gen_models.ts")]
@@ -364,6 +389,25 @@ pub struct WorkspaceMeta {
     pub updated_at: NaiveDateTime,
     pub encryption_key: Option<EncryptedKey>,
     pub setting_sync_dir: Option<String>,
+
+    // Response retention. Responses marked with `retain` are never pruned.
+    /// Max responses to keep per request (defaults to 20, 0 for unlimited)
+    pub setting_response_history_limit: Option<i32>,
+    /// Max age of responses to keep, in days
+    pub setting_response_history_days: Option<i32>,
+    /// Max total size of response bodies in the workspace, in megabytes
+    pub setting_response_disk_quota_mb: Option<i32>,
+
+    // Variables from outside of Yaak, which are lower priority than the base environment
+    /// Path to a `.env` file to load variables from. Relative paths are resolved against the sync
+    /// directory.
+    pub setting_dotenv_path: Option<String>,
+    /// Process environment variables that can be used as `env.NAME`. Entries ending in `*` match
+    /// by prefix.
+    pub setting_env_allowlist: Vec<String>,
+
+    /// Sub-environments stacked on top of the active one, in order of increasing priority
+    pub stacked_environment_ids: Vec<String>,
 }
 
 impl UpsertModelInfo for WorkspaceMeta {
@@ -398,6 +442,12 @@ impl UpsertModelInfo for WorkspaceMeta {
             (WorkspaceId, self.workspace_id.into()),
             (EncryptionKey, self.encryption_key.map(|e| serde_json::to_string(&e).unwrap()).into()),
             (SettingSyncDir, self.setting_sync_dir.into()),
+            (SettingResponseHistoryLimit, self.setting_response_history_limit.into()),
+            (SettingResponseHistoryDays, self.setting_response_history_days.into()),
+            (SettingResponseDiskQuotaMb, self.setting_response_disk_quota_mb.into()),
+            (SettingDotenvPath, self.setting_dotenv_path.into()),
+            (SettingEnvAllowlist, serde_json::to_string(&self.setting_env_allowlist)?.into()),
+            (StackedEnvironmentIds, serde_json::to_string(&self.stacked_environment_ids)?.into()),
         ])
     }
 
@@ -406,6 +456,12 @@ impl UpsertModelInfo for WorkspaceMeta {
             WorkspaceMetaIden::UpdatedAt,
             WorkspaceMetaIden::EncryptionKey,
             WorkspaceMetaIden::SettingSyncDir,
+            WorkspaceMetaIden::SettingResponseHistoryLimit,
+            WorkspaceMetaIden::SettingResponseHistoryDays,
+            WorkspaceMetaIden::SettingResponseDiskQuotaMb,
+            WorkspaceMetaIden::SettingDotenvPath,
+            WorkspaceMetaIden::SettingEnvAllowlist,
+            WorkspaceMetaIden::StackedEnvironmentIds,
         ]
     }
 
@@ -414,6 +470,8 @@ impl UpsertModelInfo for WorkspaceMeta {
         Self: Sized,
     {
         let encryption_key: Option<String> = row.get("encryption_key")?;
+        let setting_env_allowlist: String = row.get("setting_env_allowlist")?;
+        let stacked_environment_ids: String = row.get("stacked_environment_ids")?;
         Ok(Self {
             id: row.get("id")?,
             workspace_id: row.get("workspace_id")?,
@@ -422,6 +480,13 @@ impl UpsertModelInfo for WorkspaceMeta {
             updated_at: row.get("updated_at")?,
             encryption_key: encryption_key.map(|e| serde_json::from_str(&e).unwrap()),
             setting_sync_dir: row.get("setting_sync_dir")?,
+            setting_response_history_limit: row.get("setting_response_history_limit")?,
+            setting_response_history_days: row.get("setting_response_history_days")?,
+            setting_response_disk_quota_mb: row.get("setting_response_disk_quota_mb")?,
+            setting_dotenv_path: row.get("setting_dotenv_path")?,
+            setting_env_allowlist: serde_json::from_str(&setting_env_allowlist).unwrap_or_default(),
+            stacked_environment_ids: serde_json::from_str(&stacked_environment_ids)
+                .unwrap_or_default(),
         })
     }
 }
@@ -642,6 +707,77 @@ pub struct EnvironmentVariable {
     pub value: String,
     #[ts(optional, as = "Option<String>")]
     pub id: Option<String>,
+    /// Secret values are stored encrypted with the workspace key, and are left out of exports
+    /// unless they're explicitly included
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[ts(optional, as = "Option<bool>")]
+    pub secret: bool,
+    /// What the value is expected to be, which is checked when linting the workspace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional, as = "Option<EnvironmentVariableType>")]
+    pub value_type: Option<EnvironmentVariableType>,
+}
+
+/// Prefix of values encrypted with the workspace key, followed by the base64 ciphertext. This is
+/// the same format the `secure()` template function stores its value in.
+pub const ENCRYPTED_VALUE_PREFIX: &str = "YENC_";
+
+impl EnvironmentVariable {
+    /// Whether the value is a secret that's been encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.secret && self.value.starts_with(ENCRYPTED_VALUE_PREFIX)
+    }
+
+    /// Describe how the value doesn't match its type. Values that are encrypted or contain
+    /// template tags can't be checked until they're rendered, so they're skipped.
+    pub fn type_error(&self) -> Option<String> {
+        let value_type = self.value_type.as_ref()?;
+        if self.value.is_empty() || self.is_encrypted() || self.value.contains("${[") {
+            return None;
+        }
+
+        let value = self.value.trim();
+        let (valid, expected) = match value_type {
+            EnvironmentVariableType::String => (true, "string"),
+            EnvironmentVariableType::Number => {
+                (value.parse::<f64>().is_ok_and(|n| n.is_finite()), "a number")
+            }
+            EnvironmentVariableType::Bool => (value == "true" || value == "false", "true or false"),
+            EnvironmentVariableType::Json => (serde_json::from_str::<Value>(value).is_ok(), "JSON"),
+        };
+        match valid {
+            true => None,
+            false => Some(format!("Expected {} to be {expected}", self.name)),
+        }
+    }
+}
+
+impl Environment {
+    /// Copy of the environment with the values of secret variables removed, for exporting
+    pub fn without_secrets(self) -> Self {
+        let variables = self
+            .variables
+            .into_iter()
+            .map(|v| match v.secret {
+                true => EnvironmentVariable {
+                    value: String::new(),
+                    ..v
+                },
+                false => v,
+            })
+            .collect();
+        Environment { variables, ..self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "gen_models.ts")]
+pub enum EnvironmentVariableType {
+    String,
+    Number,
+    Bool,
+    Json,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, TS)]
@@ -680,6 +816,11 @@ pub struct Folder {
     pub headers: Vec<HttpRequestHeader>,
     pub name: String,
     pub sort_priority: f32,
+
+    // Overrides of the workspace settings, which are inherited when unset
+    pub setting_validate_certificates: Option<bool>,
+    pub setting_follow_redirects: Option<bool>,
+    pub setting_request_timeout: Option<i32>,
 }
 
 impl UpsertModelInfo for Folder {
@@ -719,6 +860,9 @@ impl UpsertModelInfo for Folder {
             (Description, self.description.into()),
             (Name, self.name.trim().into()),
             (SortPriority, self.sort_priority.into()),
+            (SettingValidateCertificates, self.setting_validate_certificates.into()),
+            (SettingFollowRedirects, self.setting_follow_redirects.into()),
+            (SettingRequestTimeout, self.setting_request_timeout.into()),
         ])
     }
 
@@ -732,6 +876,9 @@ impl UpsertModelInfo for Folder {
             FolderIden::Description,
             FolderIden::FolderId,
             FolderIden::SortPriority,
+            FolderIden::SettingValidateCertificates,
+            FolderIden::SettingFollowRedirects,
+            FolderIden::SettingRequestTimeout,
         ]
     }
 
@@ -754,6 +901,9 @@ impl UpsertModelInfo for Folder {
             headers: serde_json::from_str(&headers).unwrap_or_default(),
             authentication_type: row.get("authentication_type")?,
             authentication: serde_json::from_str(&authentication).unwrap_or_default(),
+            setting_validate_certificates: row.get("setting_validate_certificates")?,
+            setting_follow_redirects: row.get("setting_follow_redirects")?,
+            setting_request_timeout: row.get("setting_request_timeout")?,
         })
     }
 }
@@ -811,6 +961,13 @@ pub struct HttpRequest {
     pub sort_priority: f64,
     pub url: String,
     pub url_parameters: Vec<HttpUrlParameter>,
+    /// Variables scoped to this request, which take priority over every environment
+    pub variables: Vec<EnvironmentVariable>,
+
+    // Overrides of the workspace settings, which are inherited when unset
+    pub setting_validate_certificates: Option<bool>,
+    pub setting_follow_redirects: Option<bool>,
+    pub setting_request_timeout: Option<i32>,
 }
 
 impl UpsertModelInfo for HttpRequest {
@@ -854,6 +1011,10 @@ impl UpsertModelInfo for HttpRequest {
             (AuthenticationType, self.authentication_type.into()),
             (Headers, serde_json::to_string(&self.headers)?.into()),
             (SortPriority, self.sort_priority.into()),
+            (Variables, serde_json::to_string(&self.variables)?.into()),
+            (SettingValidateCertificates, self.setting_validate_certificates.into()),
+            (SettingFollowRedirects, self.setting_follow_redirects.into()),
+            (SettingRequestTimeout, self.setting_request_timeout.into()),
         ])
     }
 
@@ -873,6 +1034,10 @@ impl UpsertModelInfo for HttpRequest {
             Url,
             UrlParameters,
             SortPriority,
+            Variables,
+            SettingValidateCertificates,
+            SettingFollowRedirects,
+            SettingRequestTimeout,
         ]
     }
 
@@ -881,6 +1046,7 @@ impl UpsertModelInfo for HttpRequest {
         let body: String = row.get("body")?;
         let authentication: String = row.get("authentication")?;
         let headers: String = row.get("headers")?;
+        let variables: String = row.get("variables")?;
         Ok(Self {
             id: row.get("id")?,
             model: row.get("model")?,
@@ -899,6 +1065,10 @@ impl UpsertModelInfo for HttpRequest {
             sort_priority: row.get("sort_priority")?,
             url: row.get("url")?,
             url_parameters: serde_json::from_str(url_parameters.as_str()).unwrap_or_default(),
+            variables: serde_json::from_str(variables.as_str()).unwrap_or_default(),
+            setting_validate_certificates: row.get("setting_validate_certificates")?,
+            setting_follow_redirects: row.get("setting_follow_redirects")?,
+            setting_request_timeout: row.get("setting_request_timeout")?,
         })
     }
 }
@@ -1052,6 +1222,8 @@ pub struct WebsocketRequest {
     pub sort_priority: f32,
     pub url: String,
     pub url_parameters: Vec<HttpUrlParameter>,
+    /// Variables scoped to this request, which take priority over every environment
+    pub variables: Vec<EnvironmentVariable>,
 }
 
 impl UpsertModelInfo for WebsocketRequest {
@@ -1094,6 +1266,7 @@ impl UpsertModelInfo for WebsocketRequest {
             (SortPriority, self.sort_priority.into()),
             (Url, self.url.into()),
             (UrlParameters, serde_json::to_string(&self.url_parameters)?.into()),
+            (Variables, serde_json::to_string(&self.variables)?.into()),
         ])
     }
 
@@ -1111,6 +1284,7 @@ impl UpsertModelInfo for WebsocketRequest {
             WebsocketRequestIden::SortPriority,
             WebsocketRequestIden::Url,
             WebsocketRequestIden::UrlParameters,
+            WebsocketRequestIden::Variables,
         ]
     }
 
@@ -1121,6 +1295,7 @@ impl UpsertModelInfo for WebsocketRequest {
         let url_parameters: String = row.get("url_parameters")?;
         let authentication: String = row.get("authentication")?;
         let headers: String = row.get("headers")?;
+        let variables: String = row.get("variables")?;
         Ok(Self {
             id: row.get("id")?,
             model: row.get("model")?,
@@ -1137,6 +1312,7 @@ impl UpsertModelInfo for WebsocketRequest {
             headers: serde_json::from_str(headers.as_str()).unwrap_or_default(),
             folder_id: row.get("folder_id")?,
             name: row.get("name")?,
+            variables: serde_json::from_str(variables.as_str()).unwrap_or_default(),
         })
     }
 }
@@ -1254,6 +1430,15 @@ pub struct HttpResponseHeader {
     pub value: String,
 }
 
+/// A header of the request as it was sent, including ones added by the client like cookies
+#[derive(Debug, Clone, Serialize, Deserialize, Default, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[ts(export, export_to = "gen_models.ts")]
+pub struct HttpSentRequestHeader {
+    pub name: String,
+    pub value: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "snake_case")]
 #[ts(export, export_to = "gen_models.ts")]
@@ -1288,7 +1473,12 @@ pub struct HttpResponse {
     pub elapsed_headers: i32,
     pub error: Option<String>,
     pub headers: Vec<HttpResponseHeader>,
+    pub retain: bool,
     pub remote_addr: Option<String>,
+    pub request_body_path: Option<String>,
+    pub request_headers: Vec<HttpSentRequestHeader>,
+    pub request_method: String,
+    pub request_url: String,
     pub status: i32,
     pub status_reason: Option<String>,
     pub state: HttpResponseState,
@@ -1333,7 +1523,12 @@ impl UpsertModelInfo for HttpResponse {
             (ElapsedHeaders, self.elapsed_headers.into()),
             (Error, self.error.into()),
             (Headers, serde_json::to_string(&self.headers)?.into()),
+            (Retain, self.retain.into()),
             (RemoteAddr, self.remote_addr.into()),
+            (RequestBodyPath, self.request_body_path.into()),
+            (RequestHeaders, serde_json::to_string(&self.request_headers)?.into()),
+            (RequestMethod, self.request_method.into()),
+            (RequestUrl, self.request_url.into()),
             (State, serde_json::to_value(self.state)?.as_str().into()),
             (Status, self.status.into()),
             (StatusReason, self.status_reason.into()),
@@ -1351,7 +1546,12 @@ impl UpsertModelInfo for HttpResponse {
             HttpResponseIden::ElapsedHeaders,
             HttpResponseIden::Error,
             HttpResponseIden::Headers,
+            HttpResponseIden::Retain,
             HttpResponseIden::RemoteAddr,
+            HttpResponseIden::RequestBodyPath,
+            HttpResponseIden::RequestHeaders,
+            HttpResponseIden::RequestMethod,
+            HttpResponseIden::RequestUrl,
             HttpResponseIden::State,
             HttpResponseIden::Status,
             HttpResponseIden::StatusReason,
@@ -1365,6 +1565,7 @@ impl UpsertModelInfo for HttpResponse {
         Self: Sized,
     {
         let headers: String = r.get("headers")?;
+        let request_headers: String = r.get("request_headers")?;
         let state: String = r.get("state")?;
         Ok(Self {
             id: r.get("id")?,
@@ -1380,6 +1581,11 @@ impl UpsertModelInfo for HttpResponse {
             elapsed: r.get("elapsed")?,
             elapsed_headers: r.get("elapsed_headers")?,
             remote_addr: r.get("remote_addr")?,
+            retain: r.get("retain")?,
+            request_body_path: r.get("request_body_path")?,
+            request_headers: serde_json::from_str(request_headers.as_str()).unwrap_or_default(),
+            request_method: r.get("request_method")?,
+            request_url: r.get("request_url")?,
             status: r.get("status")?,
             status_reason: r.get("status_reason")?,
             state: serde_json::from_str(format!(r#""{state}""#).as_str()).unwrap(),
@@ -1486,6 +1692,8 @@ pub struct GrpcRequest {
     pub service: Option<String>,
     pub sort_priority: f32,
     pub url: String,
+    /// Variables scoped to this request, which take priority over every environment
+    pub variables: Vec<EnvironmentVariable>,
 }
 
 impl UpsertModelInfo for GrpcRequest {
@@ -1529,6 +1737,7 @@ impl UpsertModelInfo for GrpcRequest {
             (AuthenticationType, self.authentication_type.into()),
             (Authentication, serde_json::to_string(&self.authentication)?.into()),
             (Metadata, serde_json::to_string(&self.metadata)?.into()),
+            (Variables, serde_json::to_string(&self.variables)?.into()),
         ])
     }
 
@@ -1547,6 +1756,7 @@ impl UpsertModelInfo for GrpcRequest {
             GrpcRequestIden::AuthenticationType,
             GrpcRequestIden::Authentication,
             GrpcRequestIden::Metadata,
+            GrpcRequestIden::Variables,
         ]
     }
 
@@ -1556,6 +1766,7 @@ impl UpsertModelInfo for GrpcRequest {
     {
         let authentication: String = row.get("authentication")?;
         let metadata: String = row.get("metadata")?;
+        let variables: String = row.get("variables")?;
         Ok(Self {
             id: row.get("id")?,
             model: row.get("model")?,
@@ -1573,6 +1784,7 @@ impl UpsertModelInfo for GrpcRequest {
             url: row.get("url")?,
             sort_priority: row.get("sort_priority")?,
             metadata: serde_json::from_str(metadata.as_str()).unwrap_or_default(),
+            variables: serde_json::from_str(variables.as_str()).unwrap_or_default(),
         })
     }
 }
@@ -2252,3 +2464,120 @@ fn upsert_date(update_source: &UpdateSource, dt: NaiveDateTime) -> SimpleExpr {
         _ => Utc::now().naive_utc().into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, value: &str) -> EnvironmentVariable {
+        EnvironmentVariable {
+            enabled: true,
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn typed(value: &str, value_type: EnvironmentVariableType) -> EnvironmentVariable {
+        EnvironmentVariable {
+            value_type: Some(value_type),
+            ..variable("foo", value)
+        }
+    }
+
+    fn secret(name: &str, value: &str) -> EnvironmentVariable {
+        EnvironmentVariable {
+            secret: true,
+            ..variable(name, value)
+        }
+    }
+
+    #[test]
+    fn type_error_per_type() {
+        assert_eq!(variable("foo", "anything").type_error(), None);
+        assert_eq!(typed("anything", EnvironmentVariableType::String).type_error(), None);
+
+        assert_eq!(typed("42", EnvironmentVariableType::Number).type_error(), None);
+        assert_eq!(typed(" -1.5 ", EnvironmentVariableType::Number).type_error(), None);
+        assert_eq!(
+            typed("NaN", EnvironmentVariableType::Number).type_error(),
+            Some("Expected foo to be a number".to_string())
+        );
+        assert_eq!(
+            typed("abc", EnvironmentVariableType::Number).type_error(),
+            Some("Expected foo to be a number".to_string())
+        );
+
+        assert_eq!(typed("true", EnvironmentVariableType::Bool).type_error(), None);
+        assert_eq!(typed("false", EnvironmentVariableType::Bool).type_error(), None);
+        assert_eq!(
+            typed("yes", EnvironmentVariableType::Bool).type_error(),
+            Some("Expected foo to be true or false".to_string())
+        );
+
+        assert_eq!(typed(r#"{"a": [1, 2]}"#, EnvironmentVariableType::Json).type_error(), None);
+        assert_eq!(
+            typed("{a: 1}", EnvironmentVariableType::Json).type_error(),
+            Some("Expected foo to be JSON".to_string())
+        );
+    }
+
+    #[test]
+    fn type_error_skips_unchecked_values() {
+        assert_eq!(typed("", EnvironmentVariableType::Number).type_error(), None);
+        assert_eq!(typed("${[ port ]}", EnvironmentVariableType::Number).type_error(), None);
+
+        let encrypted = EnvironmentVariable {
+            secret: true,
+            ..typed("YENC_abc", EnvironmentVariableType::Number)
+        };
+        assert!(encrypted.is_encrypted());
+        assert_eq!(encrypted.type_error(), None);
+
+        // Only secrets are ever encrypted, so the prefix alone doesn't skip the check
+        let plain = typed("YENC_abc", EnvironmentVariableType::Number);
+        assert!(!plain.is_encrypted());
+        assert_eq!(plain.type_error(), Some("Expected foo to be a number".to_string()));
+    }
+
+    #[test]
+    fn without_secrets_masks_secret_values() {
+        let environment = Environment {
+            variables: vec![
+                variable("url", "https://example.com"),
+                secret("token", "plain-token"),
+                secret("key", "YENC_abc"),
+            ],
+            ..Default::default()
+        };
+
+        let exported = environment.without_secrets();
+        assert_eq!(
+            exported.variables,
+            vec![
+                variable("url", "https://example.com"),
+                secret("token", ""),
+                secret("key", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_with_overrides() {
+        let settings = RequestSettings {
+            validate_certificates: true,
+            follow_redirects: true,
+            request_timeout: 0,
+        };
+
+        assert_eq!(settings.clone().with_overrides(None, None, None), settings);
+        assert_eq!(
+            settings.with_overrides(Some(false), None, Some(5000)),
+            RequestSettings {
+                validate_certificates: false,
+                follow_redirects: true,
+                request_timeout: 5000,
+            }
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::db_context::DbContext;
 use crate::error::Error::{MissingBaseEnvironment, MultipleBaseEnvironments};
 use crate::error::Result;
+use crate::external_variables::external_environments;
 use crate::models::{Environment, EnvironmentIden, EnvironmentVariable};
 use crate::util::UpdateSource;
 use log::{info, warn};
@@ -152,11 +153,13 @@ impl<'a> DbContext<'a> {
         )
     }
 
-    pub fn resolve_environments(
+    /// Build the chain of environments to render with, from highest to lowest priority. The
+    /// active sub-environments are stacked in order, so later ones override earlier ones.
+    pub fn resolve_environments<S: AsRef<str>>(
         &self,
         workspace_id: &str,
         folder_id: Option<&str>,
-        active_environment_id: Option<&str>,
+        active_environment_ids: &[S],
     ) -> Result<Vec<Environment>> {
         let mut environments = Vec::new();
 
@@ -172,22 +175,130 @@ impl<'a> DbContext<'a> {
             let ancestors = self.resolve_environments(
                 workspace_id,
                 folder.folder_id.as_deref(),
-                active_environment_id,
+                active_environment_ids,
             )?;
             environments.extend(ancestors);
         } else {
-            // Add active and base environments
-            if let Some(id) = active_environment_id {
-                if let Ok(e) = self.get_environment(&id) {
-                    // Add active sub environment
-                    environments.push(e);
+            // Add active sub environments, last one first since it has the highest priority
+            for id in active_environment_ids.iter().rev() {
+                match self.get_environment(id.as_ref()) {
+                    Ok(e) => environments.push(e),
+                    Err(e) => warn!("Failed to find environment by id {} {e}", id.as_ref()),
                 };
-            };
+            }
 
             // Add the base environment
             environments.push(self.get_base_environment(workspace_id)?);
+
+            // Add variables from outside of Yaak, which the base environment can override
+            if let Some(workspace_meta) = self.get_workspace_meta(workspace_id) {
+                environments.extend(external_environments(&workspace_meta));
+            }
         }
 
         Ok(environments)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::db_context::DbContext;
+    use crate::models::{Environment, EnvironmentVariable, Folder, Workspace};
+    use crate::render::make_vars_hashmap;
+    use crate::util::UpdateSource;
+
+    fn environment(
+        db: &DbContext,
+        workspace_id: &str,
+        parent: (&str, Option<&str>),
+        variables: &[(&str, &str)],
+    ) -> Environment {
+        let variables = variables
+            .iter()
+            .map(|(name, value)| EnvironmentVariable {
+                enabled: true,
+                name: name.to_string(),
+                value: value.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let environment = Environment {
+            workspace_id: workspace_id.to_string(),
+            parent_model: parent.0.to_string(),
+            parent_id: parent.1.map(|id| id.to_string()),
+            variables,
+            ..Default::default()
+        };
+        db.upsert_environment(&environment, &UpdateSource::Background).unwrap()
+    }
+
+    #[test]
+    fn resolve_stacked_environments() {
+        let db = DbContext::in_memory();
+        let workspace = Workspace {
+            name: "Workspace".to_string(),
+            ..Default::default()
+        };
+        let workspace = db.upsert_workspace(&workspace, &UpdateSource::Background).unwrap();
+        let folder = Folder {
+            workspace_id: workspace.id.clone(),
+            name: "Folder".to_string(),
+            ..Default::default()
+        };
+        let folder = db.upsert_folder(&folder, &UpdateSource::Background).unwrap();
+
+        let base = environment(
+            &db,
+            &workspace.id,
+            ("workspace", None),
+            &[
+                ("host", "base"),
+                ("token", "base"),
+                ("user", "base"),
+                ("port", "base"),
+            ],
+        );
+        let staging = environment(
+            &db,
+            &workspace.id,
+            ("environment", None),
+            &[
+                ("host", "staging"),
+                ("token", "staging"),
+                ("user", "staging"),
+            ],
+        );
+        let local = environment(&db, &workspace.id, ("environment", None), &[("token", "local")]);
+        let folder_environment = environment(
+            &db,
+            &workspace.id,
+            ("folder", Some(folder.id.as_str())),
+            &[("user", "folder")],
+        );
+
+        // Stacked environments are in the order they're applied, so the last one comes first
+        let chain = db
+            .resolve_environments(
+                &workspace.id,
+                Some(folder.id.as_str()),
+                &[&staging.id, &local.id],
+            )
+            .unwrap();
+        let ids: Vec<&str> = chain.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                folder_environment.id.as_str(),
+                local.id.as_str(),
+                staging.id.as_str(),
+                base.id.as_str()
+            ]
+        );
+
+        let vars = make_vars_hashmap(chain);
+        assert_eq!(vars.get("port").map(String::as_str), Some("base"));
+        assert_eq!(vars.get("host").map(String::as_str), Some("staging"));
+        assert_eq!(vars.get("token").map(String::as_str), Some("local"));
+        assert_eq!(vars.get("user").map(String::as_str), Some("folder"));
+    }
+}
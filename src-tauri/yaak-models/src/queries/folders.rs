@@ -1,7 +1,7 @@
 use crate::connection_or_tx::ConnectionOrTx;
 use crate::db_context::DbContext;
 use crate::error::Result;
-use crate::models::{Environment, EnvironmentIden, Folder, FolderIden, GrpcRequest, GrpcRequestIden, HttpRequest, HttpRequestHeader, HttpRequestIden, WebsocketRequest, WebsocketRequestIden};
+use crate::models::{Environment, EnvironmentIden, Folder, FolderIden, GrpcRequest, GrpcRequestIden, HttpRequest, HttpRequestHeader, HttpRequestIden, RequestSettings, WebsocketRequest, WebsocketRequestIden};
 use crate::util::UpdateSource;
 use serde_json::Value;
 use std::collections::BTreeMap;
@@ -160,4 +160,79 @@ impl<'a> DbContext<'a> {
 
         Ok(headers)
     }
+
+    pub fn resolve_settings_for_folder(&self, folder: &Folder) -> Result<RequestSettings> {
+        let inherited = match folder.folder_id.clone() {
+            Some(folder_id) => {
+                let parent_folder = self.get_folder(&folder_id)?;
+                self.resolve_settings_for_folder(&parent_folder)?
+            }
+            None => {
+                let workspace = self.get_workspace(&folder.workspace_id)?;
+                self.resolve_settings_for_workspace(&workspace)
+            }
+        };
+
+        Ok(inherited.with_overrides(
+            folder.setting_validate_certificates,
+            folder.setting_follow_redirects,
+            folder.setting_request_timeout,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_context::DbContext;
+    use crate::models::{Folder, RequestSettings, Workspace};
+    use crate::util::UpdateSource;
+
+    #[test]
+    fn resolve_settings_for_nested_folders() {
+        let db = DbContext::in_memory();
+        let workspace = Workspace {
+            name: "Workspace".to_string(),
+            setting_validate_certificates: true,
+            setting_follow_redirects: true,
+            setting_request_timeout: 0,
+            ..Default::default()
+        };
+        let workspace = db.upsert_workspace(&workspace, &UpdateSource::Background).unwrap();
+
+        // Folders without overrides inherit the workspace settings
+        let parent = Folder {
+            workspace_id: workspace.id.clone(),
+            name: "Parent".to_string(),
+            ..Default::default()
+        };
+        let parent = db.upsert_folder(&parent, &UpdateSource::Background).unwrap();
+        assert_eq!(
+            db.resolve_settings_for_folder(&parent).unwrap(),
+            db.resolve_settings_for_workspace(&workspace)
+        );
+
+        let parent = Folder {
+            setting_follow_redirects: Some(false),
+            setting_request_timeout: Some(1000),
+            ..parent
+        };
+        let parent = db.upsert_folder(&parent, &UpdateSource::Background).unwrap();
+        let child = Folder {
+            workspace_id: workspace.id.clone(),
+            folder_id: Some(parent.id.clone()),
+            name: "Child".to_string(),
+            setting_request_timeout: Some(2000),
+            ..Default::default()
+        };
+        let child = db.upsert_folder(&child, &UpdateSource::Background).unwrap();
+
+        assert_eq!(
+            db.resolve_settings_for_folder(&child).unwrap(),
+            RequestSettings {
+                validate_certificates: true,
+                follow_redirects: false,
+                request_timeout: 2000,
+            }
+        );
+    }
 }
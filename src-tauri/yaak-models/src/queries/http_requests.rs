@@ -1,6 +1,8 @@
 use crate::db_context::DbContext;
 use crate::error::Result;
-use crate::models::{Folder, FolderIden, HttpRequest, HttpRequestHeader, HttpRequestIden};
+use crate::models::{
+    Folder, FolderIden, HttpRequest, HttpRequestHeader, HttpRequestIden, RequestSettings,
+};
 use crate::util::UpdateSource;
 use serde_json::Value;
 use std::collections::BTreeMap;
@@ -90,6 +92,28 @@ impl<'a> DbContext<'a> {
         Ok(headers)
     }
 
+    pub fn resolve_settings_for_http_request(
+        &self,
+        http_request: &HttpRequest,
+    ) -> Result<RequestSettings> {
+        let inherited = match http_request.folder_id.clone() {
+            Some(folder_id) => {
+                let folder = self.get_folder(&folder_id)?;
+                self.resolve_settings_for_folder(&folder)?
+            }
+            None => {
+                let workspace = self.get_workspace(&http_request.workspace_id)?;
+                self.resolve_settings_for_workspace(&workspace)
+            }
+        };
+
+        Ok(inherited.with_overrides(
+            http_request.setting_validate_certificates,
+            http_request.setting_follow_redirects,
+            http_request.setting_request_timeout,
+        ))
+    }
+
     pub fn list_http_requests_for_folder_recursive(
         &self,
         folder_id: &str,
@@ -104,3 +128,69 @@ impl<'a> DbContext<'a> {
         Ok(children)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::db_context::DbContext;
+    use crate::models::{Folder, HttpRequest, RequestSettings, Workspace};
+    use crate::util::UpdateSource;
+
+    #[test]
+    fn resolve_settings_for_request() {
+        let db = DbContext::in_memory();
+        let workspace = Workspace {
+            name: "Workspace".to_string(),
+            setting_validate_certificates: true,
+            setting_follow_redirects: true,
+            setting_request_timeout: 0,
+            ..Default::default()
+        };
+        let workspace = db.upsert_workspace(&workspace, &UpdateSource::Background).unwrap();
+        let folder = Folder {
+            workspace_id: workspace.id.clone(),
+            name: "Folder".to_string(),
+            setting_validate_certificates: Some(false),
+            setting_request_timeout: Some(1000),
+            ..Default::default()
+        };
+        let folder = db.upsert_folder(&folder, &UpdateSource::Background).unwrap();
+
+        // Requests without overrides inherit from their folder, or the workspace
+        let request = HttpRequest {
+            workspace_id: workspace.id.clone(),
+            name: "Request".to_string(),
+            ..Default::default()
+        };
+        let request = db.upsert_http_request(&request, &UpdateSource::Background).unwrap();
+        assert_eq!(
+            db.resolve_settings_for_http_request(&request).unwrap(),
+            db.resolve_settings_for_workspace(&workspace)
+        );
+
+        let request = HttpRequest {
+            folder_id: Some(folder.id.clone()),
+            ..request
+        };
+        let request = db.upsert_http_request(&request, &UpdateSource::Background).unwrap();
+        assert_eq!(
+            db.resolve_settings_for_http_request(&request).unwrap(),
+            db.resolve_settings_for_folder(&folder).unwrap()
+        );
+
+        // The request's own overrides win over its folder's
+        let request = HttpRequest {
+            setting_validate_certificates: Some(true),
+            setting_follow_redirects: Some(false),
+            ..request
+        };
+        let request = db.upsert_http_request(&request, &UpdateSource::Background).unwrap();
+        assert_eq!(
+            db.resolve_settings_for_http_request(&request).unwrap(),
+            RequestSettings {
+                validate_certificates: true,
+                follow_redirects: false,
+                request_timeout: 1000,
+            }
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::db_context::DbContext;
+use crate::error::Result;
+use crate::models::{HttpResponse, HttpResponseState, UpsertModelInfo};
+use log::warn;
+use rusqlite::params;
+use std::fs;
+
+// Larger bodies are left out of the index to keep it (and indexing) fast
+const MAX_INDEXED_BODY_SIZE: u64 = 1024 * 1024;
+
+impl<'a> DbContext<'a> {
+    /// Full-text search responses in a workspace by URL, headers, and body, newest first. Each
+    /// whitespace-separated term must match, and is matched as a phrase so IDs containing
+    /// punctuation (eg. `ord_123-abc`) work as expected.
+    pub fn search_http_responses(
+        &self,
+        workspace_id: &str,
+        query: &str,
+        request_id: Option<&str>,
+        limit: Option<u64>,
+    ) -> Result<Vec<HttpResponse>> {
+        let Some(match_query) = fts_match_query(query) else {
+            return Ok(Vec::new());
+        };
+
+        let mut stmt = self.conn.prepare(
+            r#"
+                SELECT r.*
+                FROM http_responses AS r
+                         JOIN http_responses_fts ON http_responses_fts.response_id = r.id
+                WHERE http_responses_fts MATCH ?1
+                  AND http_responses_fts.workspace_id = ?2
+                  AND (?3 IS NULL OR http_responses_fts.request_id = ?3)
+                ORDER BY r.created_at DESC
+                LIMIT ?4
+            "#,
+        )?;
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let items = stmt.query_map(
+            params![match_query, workspace_id, request_id, limit],
+            HttpResponse::from_row,
+        )?;
+        Ok(items.map(|v| v.unwrap()).collect())
+    }
+
+    /// Add a closed response to the search index, replacing any existing entry
+    pub(crate) fn index_http_response(&self, response: &HttpResponse) {
+        if !matches!(response.state, HttpResponseState::Closed) || response.id.is_empty() {
+            return;
+        }
+
+        let headers = response
+            .headers
+            .iter()
+            .map(|h| format!("{}: {}", h.name, h.value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let body = response.body_path.as_deref().and_then(read_indexable_body).unwrap_or_default();
+
+        let result = self
+            .conn
+            .execute("DELETE FROM http_responses_fts WHERE response_id = ?1", params![response.id])
+            .and_then(|_| {
+                self.conn.execute(
+                    r#"
+                        INSERT INTO http_responses_fts
+                            (response_id, workspace_id, request_id, url, headers, body)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                    "#,
+                    params![
+                        response.id,
+                        response.workspace_id,
+                        response.request_id,
+                        response.url,
+                        headers,
+                        body
+                    ],
+                )
+            });
+
+        if let Err(e) = result {
+            warn!("Failed to index HTTP response {}: {e:?}", response.id);
+        }
+    }
+}
+
+/// Build an FTS5 query where each whitespace-separated term is quoted as a phrase, or `None` if
+/// there are no terms
+fn fts_match_query(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(|term| format!(r#""{}""#, term.replace('"', r#""""#)))
+        .collect::<Vec<_>>();
+    if terms.is_empty() { None } else { Some(terms.join(" ")) }
+}
+
+/// Read a body for indexing, skipping ones that are too large or not valid UTF-8 text
+fn read_indexable_body(path: &str) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_INDEXED_BODY_SIZE {
+        return None;
+    }
+    String::from_utf8(fs::read(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queries::http_response_search::{
+        MAX_INDEXED_BODY_SIZE, fts_match_query, read_indexable_body,
+    };
+    use rusqlite::{Connection, params};
+    use std::fs;
+
+    const MIGRATION: &str = include_str!("../../migrations/20251022093012_response-search.sql");
+
+    /// Just enough of the responses table for the migration's backfill and trigger
+    const RESPONSES_TABLE: &str = r#"
+        CREATE TABLE http_responses (id TEXT, workspace_id TEXT, request_id TEXT, url TEXT, headers TEXT);
+    "#;
+
+    fn search(conn: &Connection, query: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                r#"
+                    SELECT response_id
+                    FROM http_responses_fts
+                    WHERE http_responses_fts MATCH ?1
+                    ORDER BY response_id
+                "#,
+            )
+            .unwrap();
+        let ids = stmt.query_map(params![fts_match_query(query).unwrap()], |r| r.get(0)).unwrap();
+        ids.map(|id| id.unwrap()).collect()
+    }
+
+    #[test]
+    fn match_query() {
+        assert_eq!(fts_match_query(""), None);
+        assert_eq!(fts_match_query(" \n "), None);
+        assert_eq!(fts_match_query("ord_123-abc"), Some(r#""ord_123-abc""#.to_string()));
+        assert_eq!(
+            fts_match_query(r#"  shipped  say"hi"  "#),
+            Some(r#""shipped" "say""hi""""#.to_string())
+        );
+    }
+
+    #[test]
+    fn search_terms() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(RESPONSES_TABLE).unwrap();
+        conn.execute_batch(MIGRATION).unwrap();
+        conn.execute_batch(
+            r#"
+                INSERT INTO http_responses_fts (response_id, url, headers, body)
+                VALUES ('rs_1', 'https://shop.test/orders', '', '{"id":"ord_123-abc"}'),
+                       ('rs_2', 'https://shop.test/orders/ord_123', 'x-trace: abc', '');
+            "#,
+        )
+        .unwrap();
+
+        // IDs with punctuation match as a phrase, not as separate terms
+        assert_eq!(search(&conn, "ord_123-abc"), ["rs_1"]);
+        assert_eq!(search(&conn, "ord_123"), ["rs_1", "rs_2"]);
+
+        // Every term has to match, in any column
+        assert_eq!(search(&conn, "orders abc"), ["rs_1", "rs_2"]);
+        assert_eq!(search(&conn, "x-trace orders"), ["rs_2"]);
+
+        // FTS syntax is treated as text
+        assert!(search(&conn, "ord* OR NOT").is_empty());
+    }
+
+    #[test]
+    fn backfill_and_delete() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(RESPONSES_TABLE).unwrap();
+        conn.execute_batch(
+            r#"
+                INSERT INTO http_responses (id, url, headers)
+                VALUES ('rs_1', 'https://a.test/one', '[]'),
+                       ('rs_2', 'https://a.test/two', '[]');
+            "#,
+        )
+        .unwrap();
+        conn.execute_batch(MIGRATION).unwrap();
+        assert_eq!(search(&conn, "a.test"), ["rs_1", "rs_2"]);
+
+        conn.execute("DELETE FROM http_responses WHERE id = 'rs_1'", []).unwrap();
+        assert_eq!(search(&conn, "a.test"), ["rs_2"]);
+    }
+
+    #[test]
+    fn indexable_body() {
+        let dir = std::env::temp_dir().join(format!("yaak-search-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let text = dir.join("text");
+        fs::write(&text, "hello").unwrap();
+        assert_eq!(read_indexable_body(text.to_str().unwrap()), Some("hello".to_string()));
+
+        let binary = dir.join("binary");
+        fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+        assert_eq!(read_indexable_body(binary.to_str().unwrap()), None);
+
+        let large = dir.join("large");
+        fs::write(&large, "a".repeat(MAX_INDEXED_BODY_SIZE as usize + 1)).unwrap();
+        assert_eq!(read_indexable_body(large.to_str().unwrap()), None);
+
+        assert_eq!(read_indexable_body(dir.join("missing").to_str().unwrap()), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
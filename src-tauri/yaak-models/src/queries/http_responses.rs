@@ -7,6 +7,15 @@ use sea_query_rusqlite::RusqliteBinder;
 use std::fs;
 use crate::db_context::DbContext;
 use crate::queries::MAX_HISTORY_ITEMS;
+use chrono::{Duration, NaiveDateTime, Utc};
+
+struct ResponseRetention {
+    /// Max responses per request
+    limit: Option<usize>,
+    max_age: Option<Duration>,
+    /// Max bytes of response and request bodies per workspace
+    disk_quota: Option<u64>,
+}
 
 impl<'a> DbContext<'a> {
     pub fn get_http_response(&self, id: &str) -> Result<HttpResponse> {
@@ -59,8 +68,8 @@ impl<'a> DbContext<'a> {
         http_response: &HttpResponse,
         source: &UpdateSource,
     ) -> Result<HttpResponse> {
-        // Delete the body file if it exists
-        if let Some(p) = http_response.body_path.clone() {
+        // Delete the body files if they exist
+        for p in [&http_response.body_path, &http_response.request_body_path].into_iter().flatten() {
             if let Err(e) = fs::remove_file(p) {
                 error!("Failed to delete body file: {}", e);
             };
@@ -74,16 +83,63 @@ impl<'a> DbContext<'a> {
         http_response: &HttpResponse,
         source: &UpdateSource,
     ) -> Result<HttpResponse> {
+        let retention = self.get_response_retention(&http_response.workspace_id);
         let responses = self.list_http_responses_for_request(&http_response.request_id, None)?;
 
-        for m in responses.iter().skip(MAX_HISTORY_ITEMS - 1) {
-            debug!("Deleting old HTTP response {}", http_response.id);
-            self.delete_http_response(&m, source)?;
+        let now = Utc::now().naive_utc();
+        for m in responses_over_limit(&responses, &http_response.id, &retention, now) {
+            debug!("Deleting old HTTP response {}", m.id);
+            self.delete_http_response(m, source)?;
         }
 
         self.upsert(http_response, source)
     }
 
+    /// Apply the workspace's age and disk quota retention to all of its responses. Unlike the
+    /// per-request limit, which is applied on upsert, these need a full pass over the workspace.
+    /// Returns the number of responses deleted.
+    pub fn prune_http_responses_for_workspace(
+        &self,
+        workspace_id: &str,
+        source: &UpdateSource,
+    ) -> Result<usize> {
+        let retention = self.get_response_retention(workspace_id);
+        if retention.max_age.is_none() && retention.disk_quota.is_none() {
+            return Ok(0);
+        }
+
+        let responses = self.list_http_responses(workspace_id, None)?;
+        let sized = responses.iter().map(|r| (r, body_files_size(r))).collect::<Vec<_>>();
+        let now = Utc::now().naive_utc();
+
+        let pruned = responses_to_prune(&sized, &retention, now);
+        for r in &pruned {
+            debug!("Pruning HTTP response {}", r.id);
+            self.delete_http_response(r, source)?;
+        }
+
+        Ok(pruned.len())
+    }
+
+    fn get_response_retention(&self, workspace_id: &str) -> ResponseRetention {
+        let meta = self.get_workspace_meta(workspace_id).unwrap_or_default();
+        ResponseRetention {
+            limit: match meta.setting_response_history_limit {
+                None => Some(MAX_HISTORY_ITEMS),
+                Some(n) if n <= 0 => None,
+                Some(n) => Some(n as usize),
+            },
+            max_age: meta
+                .setting_response_history_days
+                .filter(|d| *d > 0)
+                .map(|d| Duration::days(d as i64)),
+            disk_quota: meta
+                .setting_response_disk_quota_mb
+                .filter(|mb| *mb > 0)
+                .map(|mb| mb as u64 * 1024 * 1024),
+        }
+    }
+
     pub fn cancel_pending_http_responses(&self) -> Result<()> {
         let closed = serde_json::to_value(&HttpResponseState::Closed)?;
         let (sql, params) = Query::update()
@@ -104,7 +160,191 @@ impl<'a> DbContext<'a> {
         if response.id.is_empty() {
             Ok(response.clone())
         } else {
-            self.upsert(response, source)
+            let response = self.upsert(response, source)?;
+            self.index_http_response(&response);
+            Ok(response)
+        }
+    }
+}
+
+/// Pick which of a request's responses (newest first) to delete to make room for another one,
+/// which is `keep_id` if it's being updated. Retained responses don't count toward the limit.
+fn responses_over_limit<'r>(
+    responses: &'r [HttpResponse],
+    keep_id: &str,
+    retention: &ResponseRetention,
+    now: NaiveDateTime,
+) -> Vec<&'r HttpResponse> {
+    let prunable = responses.iter().filter(|r| !r.retain && r.id != keep_id);
+    prunable
+        .enumerate()
+        .filter(|(i, r)| {
+            // Leave room for the one being upserted
+            let over_limit = retention.limit.is_some_and(|limit| i + 1 >= limit);
+            let expired = retention.max_age.is_some_and(|max_age| r.created_at < now - max_age);
+            over_limit || expired
+        })
+        .map(|(_, r)| r)
+        .collect()
+}
+
+/// Pick which of a workspace's responses (newest first, with the size of their files) to delete
+/// so it fits its age limit and disk quota. Once the newest responses fill the quota, the one that
+/// goes over it and every older one is picked. Retained and unfinished responses are never
+/// picked, but still count toward the quota.
+fn responses_to_prune<'r>(
+    responses: &[(&'r HttpResponse, u64)],
+    retention: &ResponseRetention,
+    now: NaiveDateTime,
+) -> Vec<&'r HttpResponse> {
+    let mut total_size: u64 = 0;
+    let mut over_quota = false;
+    let mut pruned = Vec::new();
+    for (r, size) in responses {
+        let prunable = !r.retain && matches!(r.state, HttpResponseState::Closed);
+        let expired =
+            prunable && retention.max_age.is_some_and(|max_age| r.created_at < now - max_age);
+
+        // Expired responses are deleted anyway, so they don't take up any of the quota
+        if !over_quota && !expired {
+            total_size += size;
+            over_quota = retention.disk_quota.is_some_and(|quota| total_size > quota);
+        }
+
+        if prunable && (expired || over_quota) {
+            pruned.push(*r);
+        }
+    }
+    pruned
+}
+
+/// Size of the response and request body files on disk
+fn body_files_size(r: &HttpResponse) -> u64 {
+    [&r.body_path, &r.request_body_path]
+        .into_iter()
+        .flatten()
+        .filter_map(|p| fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{HttpResponse, HttpResponseState};
+    use crate::queries::http_responses::{
+        ResponseRetention, responses_over_limit, responses_to_prune,
+    };
+    use chrono::{Duration, NaiveDateTime};
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-10-20 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    /// A closed response created some days ago
+    fn response(id: &str, days_old: i64) -> HttpResponse {
+        HttpResponse {
+            id: id.to_string(),
+            created_at: now() - Duration::days(days_old),
+            state: HttpResponseState::Closed,
+            ..Default::default()
         }
     }
+
+    fn retained(r: HttpResponse) -> HttpResponse {
+        HttpResponse { retain: true, ..r }
+    }
+
+    fn retention(
+        limit: Option<usize>,
+        max_age_days: Option<i64>,
+        disk_quota: Option<u64>,
+    ) -> ResponseRetention {
+        ResponseRetention {
+            limit,
+            max_age: max_age_days.map(Duration::days),
+            disk_quota,
+        }
+    }
+
+    fn ids(responses: Vec<&HttpResponse>) -> Vec<&str> {
+        responses.into_iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn limit_leaves_room_for_new_response() {
+        let responses = [
+            response("a", 0),
+            response("b", 1),
+            response("c", 2),
+            response("d", 3),
+        ];
+        let retention = retention(Some(3), None, None);
+        assert_eq!(ids(responses_over_limit(&responses, "new", &retention, now())), ["c", "d"]);
+    }
+
+    #[test]
+    fn limit_skips_retained_and_updated_response() {
+        let responses = [
+            response("a", 0),
+            retained(response("b", 1)),
+            response("c", 2),
+            response("d", 3),
+        ];
+        let retention = retention(Some(2), None, None);
+        assert_eq!(ids(responses_over_limit(&responses, "a", &retention, now())), ["d"]);
+        assert_eq!(ids(responses_over_limit(&responses, "new", &retention, now())), ["c", "d"]);
+    }
+
+    #[test]
+    fn limit_with_max_age() {
+        let responses = [response("a", 0), response("b", 10), response("c", 40)];
+        let unlimited = retention(None, Some(30), None);
+        assert_eq!(ids(responses_over_limit(&responses, "new", &unlimited, now())), ["c"]);
+    }
+
+    #[test]
+    fn prune_oldest_over_quota() {
+        let (a, b, c, d) = (response("a", 0), response("b", 1), response("c", 2), response("d", 3));
+        let retention = retention(None, None, Some(100));
+
+        let responses = [(&a, 40), (&b, 40), (&c, 40), (&d, 30)];
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["c", "d"]);
+
+        // Older responses are pruned too, even if they're small enough to fit
+        let responses = [(&a, 40), (&b, 40), (&c, 40), (&d, 10)];
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["c", "d"]);
+
+        let responses = [(&a, 40), (&b, 40), (&c, 20), (&d, 10)];
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["d"]);
+    }
+
+    #[test]
+    fn prune_expired_does_not_count_toward_quota() {
+        let (a, b, c) = (response("a", 0), response("b", 40), response("c", 2));
+        let responses = [(&a, 60), (&b, 60), (&c, 30)];
+        let retention = retention(None, Some(30), Some(100));
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["b"]);
+    }
+
+    #[test]
+    fn prune_counts_retained_and_open_responses() {
+        let a = HttpResponse {
+            state: HttpResponseState::Connected,
+            ..response("a", 0)
+        };
+        let b = retained(response("b", 1));
+        let c = response("c", 2);
+        let responses = [(&a, 60), (&b, 60), (&c, 10)];
+
+        let retention = retention(None, None, Some(100));
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["c"]);
+    }
+
+    #[test]
+    fn prune_expired() {
+        let (a, b, c) = (response("a", 0), retained(response("b", 60)), response("c", 61));
+        let responses = [(&a, 0), (&b, 0), (&c, 0)];
+        let retention = retention(None, Some(30), None);
+        assert_eq!(ids(responses_to_prune(&responses, &retention, now())), ["c"]);
+    }
 }
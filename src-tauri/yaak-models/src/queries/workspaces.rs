@@ -2,7 +2,7 @@ use crate::db_context::DbContext;
 use crate::error::Result;
 use crate::models::{
     EnvironmentIden, FolderIden, GrpcRequestIden, HttpRequestHeader, HttpRequestIden,
-    WebsocketRequestIden, Workspace, WorkspaceIden,
+    RequestSettings, WebsocketRequestIden, Workspace, WorkspaceIden,
 };
 use crate::util::UpdateSource;
 use serde_json::Value;
@@ -82,4 +82,12 @@ impl<'a> DbContext<'a> {
     pub fn resolve_headers_for_workspace(&self, workspace: &Workspace) -> Vec<HttpRequestHeader> {
         workspace.headers.clone()
     }
+
+    pub fn resolve_settings_for_workspace(&self, workspace: &Workspace) -> RequestSettings {
+        RequestSettings {
+            validate_certificates: workspace.setting_validate_certificates,
+            follow_redirects: workspace.setting_follow_redirects,
+            request_timeout: workspace.setting_request_timeout,
+        }
+    }
 }
@@ -11,6 +11,46 @@ pub fn make_vars_hashmap(environment_chain: Vec<Environment>) -> HashMap<String,
     variables
 }
 
+/// Put the variables defined on a request at the front of the chain, so they're merged last and
+/// take priority over every environment
+pub fn with_request_variables(
+    environment_chain: Vec<Environment>,
+    model: &str,
+    request_id: &str,
+    variables: &[EnvironmentVariable],
+) -> Vec<Environment> {
+    if variables.is_empty() {
+        return environment_chain;
+    }
+
+    let request_environment = Environment {
+        id: request_id.to_string(),
+        name: "Request Variables".to_string(),
+        parent_model: model.to_string(),
+        parent_id: Some(request_id.to_string()),
+        variables: variables.to_vec(),
+        ..Default::default()
+    };
+    std::iter::once(request_environment).chain(environment_chain).collect()
+}
+
+/// Map each variable to the environment it comes from, using the same precedence as
+/// [`make_vars_hashmap`]
+pub fn make_vars_environments(environment_chain: &[Environment]) -> HashMap<String, &Environment> {
+    let mut environments = HashMap::new();
+
+    for e in environment_chain.iter().rev() {
+        for variable in &e.variables {
+            if !variable.enabled || variable.value.is_empty() {
+                continue;
+            }
+            environments.insert(variable.name.clone(), e);
+        }
+    }
+
+    environments
+}
+
 fn add_variable_to_map(
     m: HashMap<String, String>,
     variables: &Vec<EnvironmentVariable>,
@@ -21,9 +61,99 @@ fn add_variable_to_map(
             continue;
         }
         let name = variable.name.as_str();
-        let value = variable.value.as_str();
-        map.insert(name.into(), value.into());
+        let value = match variable.is_encrypted() {
+            // Rendering the secure() function decrypts the value with the workspace key
+            true => format!("${{[ secure(value='{}') ]}}", variable.value),
+            false => variable.value.clone(),
+        };
+        map.insert(name.into(), value);
     }
 
     map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, value: &str) -> EnvironmentVariable {
+        EnvironmentVariable {
+            enabled: true,
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn secret(name: &str, value: &str) -> EnvironmentVariable {
+        EnvironmentVariable {
+            secret: true,
+            ..variable(name, value)
+        }
+    }
+
+    fn environment(id: &str, variables: Vec<EnvironmentVariable>) -> Environment {
+        Environment {
+            id: id.to_string(),
+            variables,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn encrypted_secrets_become_secure_tags() {
+        let chain = vec![environment(
+            "ev_1",
+            vec![
+                variable("url", "https://example.com"),
+                secret("token", "plain-token"),
+                secret("key", "YENC_abc"),
+            ],
+        )];
+
+        let vars = make_vars_hashmap(chain);
+        assert_eq!(vars.get("url").map(String::as_str), Some("https://example.com"));
+        assert_eq!(vars.get("token").map(String::as_str), Some("plain-token"));
+        assert_eq!(vars.get("key").map(String::as_str), Some("${[ secure(value='YENC_abc') ]}"));
+    }
+
+    #[test]
+    fn skips_disabled_and_empty_variables() {
+        let disabled = EnvironmentVariable {
+            enabled: false,
+            ..variable("disabled", "value")
+        };
+        let chain = vec![environment("ev_1", vec![disabled, variable("empty", "")])];
+
+        assert!(make_vars_hashmap(chain).is_empty());
+    }
+
+    #[test]
+    fn request_variables_take_priority() {
+        let chain = vec![
+            environment("ev_sub", vec![variable("host", "sub"), variable("token", "sub")]),
+            environment("ev_base", vec![variable("host", "base"), variable("user", "base")]),
+        ];
+        let request_variables = vec![variable("host", "request")];
+        let chain = with_request_variables(chain, "http_request", "rq_1", &request_variables);
+
+        let vars = make_vars_hashmap(chain.clone());
+        assert_eq!(vars.get("host").map(String::as_str), Some("request"));
+        assert_eq!(vars.get("token").map(String::as_str), Some("sub"));
+        assert_eq!(vars.get("user").map(String::as_str), Some("base"));
+
+        let environments = make_vars_environments(&chain);
+        let request_environment = environments.get("host").unwrap();
+        assert_eq!(request_environment.id, "rq_1");
+        assert_eq!(request_environment.name, "Request Variables");
+        assert_eq!(request_environment.parent_model, "http_request");
+        assert_eq!(environments.get("token").unwrap().id, "ev_sub");
+        assert_eq!(environments.get("user").unwrap().id, "ev_base");
+    }
+
+    #[test]
+    fn no_request_variables_keeps_chain() {
+        let chain = vec![environment("ev_base", vec![variable("host", "base")])];
+        assert_eq!(with_request_variables(chain.clone(), "http_request", "rq_1", &[]), chain);
+    }
+}
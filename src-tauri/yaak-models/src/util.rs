@@ -125,6 +125,7 @@ pub fn get_workspace_export_resources<R: Runtime>(
     app_handle: &AppHandle<R>,
     workspace_ids: Vec<&str>,
     include_private_environments: bool,
+    include_secrets: bool,
 ) -> Result<WorkspaceExport> {
     let mut data = WorkspaceExport {
         yaak_version: app_handle.package_info().version.clone().to_string(),
@@ -148,6 +149,10 @@ pub fn get_workspace_export_resources<R: Runtime>(
                 .list_environments_ensure_base(workspace_id)?
                 .into_iter()
                 .filter(|e| include_private_environments || e.public)
+                .map(|e| match include_secrets {
+                    true => e,
+                    false => e.without_secrets(),
+                })
                 .collect(),
         );
         data.resources.folders.append(&mut db.list_folders(workspace_id)?);
@@ -159,6 +164,18 @@ pub fn get_workspace_export_resources<R: Runtime>(
     Ok(data)
 }
 
+/// The sub-environments to stack for a send, in order of increasing priority. A list of stacked
+/// environments takes the place of the single active environment when both are given.
+pub fn active_environment_ids(
+    environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
+) -> Vec<String> {
+    match environment_ids {
+        Some(ids) => ids,
+        None => environment_id.map(|id| id.to_string()).into_iter().collect(),
+    }
+}
+
 pub fn maybe_gen_id<M: UpsertModelInfo, R: Runtime>(
     window: &WebviewWindow<R>,
     id: &str,
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Location in the template source, as a half-open range of UTF-16 code unit offsets. That's how
+ * JavaScript indexes strings, so spans can be used directly by the editor.
+ */
+export type Span = { start: number, end: number, };
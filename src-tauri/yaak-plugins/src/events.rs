@@ -6,6 +6,7 @@ use yaak_common::window::WorkspaceWindowTrait;
 use yaak_models::models::{
     Environment, Folder, GrpcRequest, HttpRequest, HttpResponse, WebsocketRequest, Workspace,
 };
+use yaak_templates::trace::TagTrace;
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "camelCase")]
@@ -152,6 +153,9 @@ pub enum InternalEventPayload {
     FindHttpResponsesRequest(FindHttpResponsesRequest),
     FindHttpResponsesResponse(FindHttpResponsesResponse),
 
+    SearchHttpResponsesRequest(SearchHttpResponsesRequest),
+    SearchHttpResponsesResponse(SearchHttpResponsesResponse),
+
     GetThemesRequest(GetThemesRequest),
     GetThemesResponse(GetThemesResponse),
 
@@ -436,6 +440,9 @@ pub struct GetThemesResponse {
 pub struct TemplateRenderRequest {
     pub data: serde_json::Value,
     pub purpose: RenderPurpose,
+    /// Also return a trace of how each tag was rendered
+    #[ts(optional)]
+    pub trace: Option<bool>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
@@ -443,6 +450,8 @@ pub struct TemplateRenderRequest {
 #[ts(export, export_to = "gen_events.ts")]
 pub struct TemplateRenderResponse {
     pub data: serde_json::Value,
+    #[ts(optional)]
+    pub trace: Option<Vec<TagTrace>>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
@@ -711,6 +720,17 @@ pub struct TemplateFunction {
     #[ts(optional)]
     pub aliases: Option<Vec<String>>,
     pub args: Vec<TemplateFunctionArg>,
+
+    /// Reuse the result of an identical call (same name and args) within the same render,
+    /// instead of calling the function again. Use this for functions that always return the same
+    /// value for the same args, or that shouldn't repeat, like sending a chained request.
+    #[ts(optional)]
+    pub cache: Option<bool>,
+
+    /// Don't run alongside any other function in the same render. Use this for functions that
+    /// prompt the user or send requests, so only one happens at a time.
+    #[ts(optional)]
+    pub sequential: Option<bool>,
 }
 
 /// Similar to FormInput, but contains
@@ -1128,6 +1148,26 @@ pub struct FindHttpResponsesResponse {
     pub http_responses: Vec<HttpResponse>,
 }
 
+/// Full-text search over response URLs, headers, and bodies in the active workspace
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[ts(export, export_to = "gen_events.ts")]
+pub struct SearchHttpResponsesRequest {
+    pub query: String,
+    /// Only search responses for this request
+    #[ts(optional)]
+    pub request_id: Option<String>,
+    #[ts(optional)]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[ts(export, export_to = "gen_events.ts")]
+pub struct SearchHttpResponsesResponse {
+    pub http_responses: Vec<HttpResponse>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
 #[serde(default, rename_all = "camelCase")]
 #[ts(export, export_to = "gen_events.ts")]
@@ -15,14 +15,17 @@ use crate::events::{
     ImportResponse, InternalEvent, InternalEventPayload, JsonPrimitive, PluginWindowContext,
     RenderPurpose,
 };
-use crate::native_template_functions::{template_function_keyring, template_function_secure};
+use crate::native_template_functions::{
+    find_native_template_function, is_fallback_template_function, native_template_functions,
+};
 use crate::nodejs::start_nodejs_plugin_runtime;
 use crate::plugin_handle::PluginHandle;
 use crate::server_ws::PluginRuntimeServerWebsocket;
 use crate::template_callback::PluginTemplateCallback;
 use log::{error, info, warn};
+use md5::{Digest, Md5};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -40,7 +43,10 @@ use yaak_models::render::make_vars_hashmap;
 use yaak_models::util::generate_id;
 use yaak_templates::error::Error::RenderError;
 use yaak_templates::error::Result as TemplateResult;
-use yaak_templates::{RenderErrorBehavior, RenderOptions, render_json_value_raw};
+use yaak_templates::{RenderOptions, render_json_value_raw};
+
+/// Plugin ref of the template function summaries for the Rust-based functions
+pub(crate) const NATIVE_PLUGIN_REF_ID: &str = "__NATIVE__";
 
 #[derive(Clone)]
 pub struct PluginManager {
@@ -528,11 +534,9 @@ impl PluginManager {
             RenderPurpose::Preview,
         );
         // We don't want to fail for this op because the UI will not be able to list any auth types then
-        let render_opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::ReturnEmpty,
-        };
+        let render_opt = RenderOptions::return_empty();
         let rendered_values = render_json_value_raw(json!(values), vars, &cb, &render_opt).await?;
-        let context_id = format!("{:x}", md5::compute(model_id.to_string()));
+        let context_id = format!("{:x}", Md5::digest(model_id));
 
         let event = self
             .send_to_plugin_and_wait(
@@ -639,11 +643,9 @@ impl PluginManager {
             RenderPurpose::Preview,
         );
         // We don't want to fail for this op because the UI will not be able to list any auth types then
-        let render_opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::ReturnEmpty,
-        };
+        let render_opt = RenderOptions::return_empty();
         let rendered_values = render_json_value_raw(json!(values), vars, &cb, &render_opt).await?;
-        let context_id = format!("{:x}", md5::compute(model_id.to_string()));
+        let context_id = format!("{:x}", Md5::digest(model_id));
         let event = self
             .send_to_plugin_and_wait(
                 &PluginWindowContext::new(window),
@@ -684,9 +686,7 @@ impl PluginManager {
                 &PluginWindowContext::new(&window),
                 RenderPurpose::Preview,
             ),
-            &RenderOptions {
-                error_behavior: RenderErrorBehavior::Throw,
-            },
+            &RenderOptions::throw(),
         )
         .await?;
         let results = self.get_http_authentication_summaries(window).await?;
@@ -695,7 +695,7 @@ impl PluginManager {
             .find_map(|(p, r)| if r.name == auth_name { Some(p) } else { None })
             .ok_or(PluginNotFoundErr(auth_name.into()))?;
 
-        let context_id = format!("{:x}", md5::compute(model_id.to_string()));
+        let context_id = format!("{:x}", Md5::digest(model_id));
         self.send_to_plugin_and_wait(
             &PluginWindowContext::new(window),
             &plugin,
@@ -761,10 +761,16 @@ impl PluginManager {
         &self,
         window: &WebviewWindow<R>,
     ) -> Result<Vec<GetTemplateFunctionSummaryResponse>> {
-        let window_context = PluginWindowContext::new(window);
+        self.get_template_function_summaries_for_context(&PluginWindowContext::new(window)).await
+    }
+
+    pub async fn get_template_function_summaries_for_context(
+        &self,
+        window_context: &PluginWindowContext,
+    ) -> Result<Vec<GetTemplateFunctionSummaryResponse>> {
         let reply_events = self
             .send_and_wait(
-                &window_context,
+                window_context,
                 &InternalEventPayload::GetTemplateFunctionSummaryRequest(EmptyPayload {}),
             )
             .await?;
@@ -776,10 +782,31 @@ impl PluginManager {
             }
         }
 
-        // Add Rust-based functions
+        // Add Rust-based functions. Plugin functions of the same name win, except for the ones
+        // that only run natively, so only add the fallbacks that no plugin provides.
+        for r in results.iter_mut() {
+            r.functions.retain(|f| {
+                find_native_template_function(&f.name).is_none_or(is_fallback_template_function)
+            });
+        }
+        let plugin_function_names = results
+            .iter()
+            .flat_map(|r| r.functions.iter())
+            .flat_map(|f| std::iter::once(&f.name).chain(f.aliases.iter().flatten()))
+            .collect::<HashSet<_>>();
+        let native_functions = native_template_functions()
+            .iter()
+            .filter(|n| {
+                !is_fallback_template_function(n)
+                    || !std::iter::once(&n.name)
+                        .chain(n.aliases.iter().flatten())
+                        .any(|name| plugin_function_names.contains(name))
+            })
+            .cloned()
+            .collect();
         results.push(GetTemplateFunctionSummaryResponse {
-            plugin_ref_id: "__NATIVE__".to_string(), // Meh
-            functions: vec![template_function_secure(), template_function_keyring()],
+            plugin_ref_id: NATIVE_PLUGIN_REF_ID.to_string(),
+            functions: native_functions,
         });
 
         Ok(results)
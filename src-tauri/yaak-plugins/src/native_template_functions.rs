@@ -1,24 +1,34 @@
 use crate::events::{
-    FormInput, FormInputBase, FormInputText, PluginWindowContext, RenderPurpose, TemplateFunction,
-    TemplateFunctionArg,
+    FormInput, FormInputBase, FormInputSelect, FormInputSelectOption, FormInputText,
+    PluginWindowContext, RenderPurpose, TemplateFunction, TemplateFunctionArg,
 };
 use crate::template_callback::PluginTemplateCallback;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
+use chrono::{SecondsFormat, Utc};
+use fancy_regex::Regex;
+use hmac::{Hmac, Mac};
 use keyring::Error::NoEntry;
 use log::{debug, info};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use sha2::Digest;
 use std::collections::HashMap;
+use std::sync::LazyLock;
 use tauri::{AppHandle, Runtime};
 use yaak_crypto::manager::EncryptionManagerExt;
 use yaak_templates::error::Error::RenderError;
 use yaak_templates::error::Result;
-use yaak_templates::{FnArg, Parser, Token, Tokens, Val, transform_args};
+use yaak_templates::{FnArg, Parser, Span, Token, Tokens, Val, transform_args};
 
 pub(crate) fn template_function_secure() -> TemplateFunction {
     TemplateFunction {
         name: "secure".to_string(),
         description: Some("Securely store encrypted text".to_string()),
         aliases: None,
+        // Decrypting always gives the same value
+        cache: Some(true),
+        sequential: None,
         args: vec![TemplateFunctionArg::FormInput(FormInput::Text(
             FormInputText {
                 multi_line: Some(true),
@@ -39,6 +49,9 @@ pub(crate) fn template_function_keyring() -> TemplateFunction {
         name: "keychain".to_string(),
         description: Some("Get a password from the OS keychain or keyring".to_string()),
         aliases: Some(vec!["keyring".to_string()]),
+        // Don't ask the OS for the same password more than once, or for two at a time
+        cache: Some(true),
+        sequential: Some(true),
         args: vec![
             TemplateFunctionArg::FormInput(FormInput::Text(FormInputText {
                 base: FormInputBase {
@@ -62,6 +75,202 @@ pub(crate) fn template_function_keyring() -> TemplateFunction {
     }
 }
 
+/// Template functions that are implemented in Rust, so they work without the plugin runtime.
+static NATIVE_TEMPLATE_FUNCTIONS: LazyLock<Vec<TemplateFunction>> = LazyLock::new(|| {
+    let mut functions = vec![template_function_secure(), template_function_keyring()];
+    functions.extend(template_functions_core());
+    functions
+});
+
+/// Native template functions, keyed by name and alias
+static NATIVE_TEMPLATE_FUNCTIONS_BY_NAME: LazyLock<HashMap<&str, &TemplateFunction>> =
+    LazyLock::new(|| {
+        let mut functions = HashMap::new();
+        for f in NATIVE_TEMPLATE_FUNCTIONS.iter() {
+            functions.insert(f.name.as_str(), f);
+            for alias in f.aliases.iter().flatten() {
+                functions.insert(alias.as_str(), f);
+            }
+        }
+        functions
+    });
+
+pub(crate) fn native_template_functions() -> &'static [TemplateFunction] {
+    &NATIVE_TEMPLATE_FUNCTIONS
+}
+
+/// Find a native template function by name or alias
+pub(crate) fn find_native_template_function(name: &str) -> Option<&'static TemplateFunction> {
+    NATIVE_TEMPLATE_FUNCTIONS_BY_NAME.get(name).copied()
+}
+
+/// Whether the native function is only a fallback for when no plugin provides one of the same
+/// name. Plugin versions of the core functions can take different args, so they're kept to not
+/// change how existing templates render. Only `secure()` and `keychain()` always run natively.
+pub(crate) fn is_fallback_template_function(f: &TemplateFunction) -> bool {
+    f.name != "secure" && f.name != "keychain"
+}
+
+fn template_functions_core() -> Vec<TemplateFunction> {
+    let mut functions = vec![
+        native_fn("uuid.v4", "Generate a UUID V4", vec![]),
+        native_fn("uuid.v7", "Generate a UUID V7", vec![]),
+        native_fn("timestamp.unix", "Get the current timestamp in seconds", vec![]),
+        native_fn("timestamp.unixMillis", "Get the current timestamp in milliseconds", vec![]),
+        native_fn("timestamp.iso8601", "Get the current date in ISO8601 format", vec![]),
+        cached(native_fn(
+            "base64.encode",
+            "Encode a value to base64",
+            vec![text_arg("value", "Plain Text", true)],
+        )),
+        cached(native_fn(
+            "base64.decode",
+            "Decode a value from base64",
+            vec![text_arg("value", "Encoded Value", true)],
+        )),
+        cached(native_fn(
+            "url.encode",
+            "Encode a value for use in a URL (percent-encoding)",
+            vec![text_arg("value", "Plain Text", true)],
+        )),
+        cached(native_fn(
+            "url.decode",
+            "Decode a percent-encoded URL value",
+            vec![text_arg("value", "Encoded Value", true)],
+        )),
+        native_fn(
+            "random.int",
+            "Generate a random integer between min and max (inclusive)",
+            vec![
+                with_default(text_arg("min", "Minimum", false), "0"),
+                with_default(text_arg("max", "Maximum", false), "100"),
+            ],
+        ),
+        native_fn(
+            "random.string",
+            "Generate a random alphanumeric string",
+            vec![with_default(text_arg("length", "Length", false), "16")],
+        ),
+        cached(native_fn(
+            "regex.match",
+            "Extract text using a regular expression",
+            vec![text_arg("input", "Input Text", true), regex_arg()],
+        )),
+        cached(native_fn(
+            "regex.replace",
+            "Replace text using a regular expression",
+            vec![
+                text_arg("input", "Input Text", true),
+                regex_arg(),
+                text_arg("replacement", "Replacement Text", false),
+                optional(with_default(text_arg("flags", "Flags", false), "g")),
+            ],
+        )),
+    ];
+
+    for algorithm in HASH_ALGORITHMS {
+        functions.push(cached(native_fn(
+            &format!("hash.{algorithm}"),
+            "Hash a value, encoded as base64 or hex",
+            vec![text_arg("input", "Input", true), encoding_arg()],
+        )));
+    }
+
+    for algorithm in HASH_ALGORITHMS {
+        let mut key = text_arg("key", "Key", false);
+        if let FormInput::Text(t) = &mut key {
+            t.password = Some(true);
+        }
+        functions.push(cached(native_fn(
+            &format!("hmac.{algorithm}"),
+            "Compute the HMAC of a value",
+            vec![text_arg("input", "Input", true), key, encoding_arg()],
+        )));
+    }
+
+    functions
+}
+
+const HASH_ALGORITHMS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+fn native_fn(name: &str, description: &str, args: Vec<FormInput>) -> TemplateFunction {
+    TemplateFunction {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        aliases: None,
+        cache: None,
+        sequential: None,
+        args: args.into_iter().map(TemplateFunctionArg::FormInput).collect(),
+    }
+}
+
+/// For functions that always return the same value for the same args
+fn cached(f: TemplateFunction) -> TemplateFunction {
+    TemplateFunction {
+        cache: Some(true),
+        ..f
+    }
+}
+
+fn text_arg(name: &str, label: &str, multi_line: bool) -> FormInput {
+    FormInput::Text(FormInputText {
+        multi_line: multi_line.then_some(true),
+        base: FormInputBase {
+            name: name.to_string(),
+            label: Some(label.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+fn with_default(mut input: FormInput, default_value: &str) -> FormInput {
+    if let FormInput::Text(t) = &mut input {
+        t.base.default_value = Some(default_value.to_string());
+    }
+    input
+}
+
+fn optional(mut input: FormInput) -> FormInput {
+    if let FormInput::Text(t) = &mut input {
+        t.base.optional = Some(true);
+    }
+    input
+}
+
+fn regex_arg() -> FormInput {
+    let mut input = with_default(text_arg("regex", "Regular Expression", false), ".*");
+    if let FormInput::Text(t) = &mut input {
+        t.placeholder = Some("\\w+".to_string());
+        t.base.description = Some(
+            "A regular expression. Use a capture group to reference parts of the match in the replacement."
+                .to_string(),
+        );
+    }
+    input
+}
+
+fn encoding_arg() -> FormInput {
+    FormInput::Select(FormInputSelect {
+        base: FormInputBase {
+            name: "encoding".to_string(),
+            label: Some("Encoding".to_string()),
+            default_value: Some("base64".to_string()),
+            ..Default::default()
+        },
+        options: vec![
+            FormInputSelectOption {
+                label: "Base64".to_string(),
+                value: "base64".to_string(),
+            },
+            FormInputSelectOption {
+                label: "Hex".to_string(),
+                value: "hex".to_string(),
+            },
+        ],
+    })
+}
+
 pub fn template_function_secure_run<R: Runtime>(
     app_handle: &AppHandle<R>,
     args: HashMap<String, serde_json::Value>,
@@ -147,12 +356,13 @@ pub fn decrypt_secure_template_function<R: Runtime>(
     for token in parsed.tokens.iter() {
         match token {
             Token::Tag {
-                val: Val::Fn { name, args },
+                val: Val::Fn { name, args, .. },
+                span,
             } if name == "secure" => {
                 let mut args_map = HashMap::new();
                 for a in args {
                     match a.clone().value {
-                        Val::Str { text } => {
+                        Val::Str { text, .. } => {
                             args_map.insert(a.name.to_string(), serde_json::Value::String(text));
                         }
                         _ => continue,
@@ -160,6 +370,7 @@ pub fn decrypt_secure_template_function<R: Runtime>(
                 }
                 new_tokens.push(Token::Raw {
                     text: template_function_secure_run(app_handle, args_map, window_context)?,
+                    span: *span,
                 });
             }
             t => {
@@ -185,9 +396,15 @@ pub fn encrypt_secure_template_function<R: Runtime>(
                 name: "secure".to_string(),
                 args: vec![FnArg {
                     name: "value".to_string(),
-                    value: Val::Str { text: decrypted },
+                    value: Val::Str {
+                        text: decrypted,
+                        span: Span::default(),
+                    },
+                    span: Span::default(),
                 }],
+                span: Span::default(),
             },
+            span: Span::default(),
         }],
     };
 
@@ -219,3 +436,357 @@ pub fn template_function_keychain_run(args: HashMap<String, serde_json::Value>)
         Err(e) => Err(RenderError(e.to_string())),
     }
 }
+
+/// Run one of the core native template functions, or return `None` if there isn't one by that
+/// name so the caller can fall back to the plugin runtime.
+pub fn template_function_native_run(
+    fn_name: &str,
+    args: &HashMap<String, serde_json::Value>,
+) -> Option<Result<String>> {
+    let result = match fn_name {
+        "uuid.v4" => Ok(uuid::Uuid::new_v4().to_string()),
+        "uuid.v7" => Ok(uuid::Uuid::now_v7().to_string()),
+        "timestamp.unix" => Ok(Utc::now().timestamp().to_string()),
+        "timestamp.unixMillis" => Ok(Utc::now().timestamp_millis().to_string()),
+        "timestamp.iso8601" => Ok(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        "base64.encode" => Ok(BASE64_STANDARD.encode(arg_str(args, "value"))),
+        "base64.decode" => BASE64_STANDARD
+            .decode(arg_str(args, "value").trim())
+            .map(|b| String::from_utf8_lossy(&b).to_string())
+            .map_err(|e| RenderError(format!("Invalid base64 value: {e}"))),
+        "url.encode" => Ok(url_encode_component(&arg_str(args, "value"))),
+        "url.decode" => Ok(urlencoding::decode(&arg_str(args, "value"))
+            .map(|v| v.to_string())
+            .unwrap_or_default()),
+        "random.int" => random_int(args),
+        "random.string" => random_string(args),
+        "regex.match" => regex_match(args),
+        "regex.replace" => regex_replace(args),
+        _ => match fn_name.split_once('.') {
+            Some(("hash", algorithm)) => hash(algorithm, args),
+            Some(("hmac", algorithm)) => hmac(algorithm, args),
+            _ => return None,
+        },
+    };
+    Some(result)
+}
+
+fn arg_str(args: &HashMap<String, serde_json::Value>, name: &str) -> String {
+    match args.get(name) {
+        None | Some(serde_json::Value::Null) => "".to_string(),
+        Some(serde_json::Value::String(s)) => s.to_owned(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn arg_i64(args: &HashMap<String, serde_json::Value>, name: &str, default: i64) -> Result<i64> {
+    let value = arg_str(args, name);
+    if value.trim().is_empty() {
+        return Ok(default);
+    }
+    value.trim().parse().map_err(|_| RenderError(format!("Argument {name} must be an integer")))
+}
+
+/// Percent-encode the same way as JavaScript's `encodeURIComponent`
+fn url_encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+fn encode_digest(digest: &[u8], args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    match arg_str(args, "encoding").as_str() {
+        "" | "base64" => Ok(BASE64_STANDARD.encode(digest)),
+        "hex" => Ok(hex::encode(digest)),
+        e => Err(RenderError(format!("Unsupported encoding {e}"))),
+    }
+}
+
+fn hash(algorithm: &str, args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let input = arg_str(args, "input");
+    let digest = match algorithm {
+        "md5" => md5::Md5::digest(&input).to_vec(),
+        "sha1" => sha1::Sha1::digest(&input).to_vec(),
+        "sha256" => sha2::Sha256::digest(&input).to_vec(),
+        "sha512" => sha2::Sha512::digest(&input).to_vec(),
+        a => return Err(RenderError(format!("Unsupported hash algorithm {a}"))),
+    };
+    encode_digest(&digest, args)
+}
+
+fn hmac(algorithm: &str, args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    fn compute<M: Mac + hmac::digest::KeyInit>(key: &str, input: &str) -> Vec<u8> {
+        let mut mac = <M as Mac>::new_from_slice(key.as_bytes()).expect("HMAC takes any key size");
+        mac.update(input.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    let input = arg_str(args, "input");
+    let key = arg_str(args, "key");
+    let digest = match algorithm {
+        "md5" => compute::<Hmac<md5::Md5>>(&key, &input),
+        "sha1" => compute::<Hmac<sha1::Sha1>>(&key, &input),
+        "sha256" => compute::<Hmac<sha2::Sha256>>(&key, &input),
+        "sha512" => compute::<Hmac<sha2::Sha512>>(&key, &input),
+        a => return Err(RenderError(format!("Unsupported HMAC algorithm {a}"))),
+    };
+    encode_digest(&digest, args)
+}
+
+fn random_int(args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let min = arg_i64(args, "min", 0)?;
+    let max = arg_i64(args, "max", 100)?;
+    if min > max {
+        return Err(RenderError(format!("Minimum {min} is greater than maximum {max}")));
+    }
+    Ok(rand::rng().random_range(min..=max).to_string())
+}
+
+fn random_string(args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let length = arg_i64(args, "length", 16)?;
+    let length = usize::try_from(length)
+        .map_err(|_| RenderError("Argument length must not be negative".to_string()))?;
+    Ok(rand::rng().sample_iter(&Alphanumeric).take(length).map(char::from).collect())
+}
+
+/// Build a regex that supports lookaround and backreferences, like JavaScript's does
+fn build_regex(args: &HashMap<String, serde_json::Value>, flags: &str) -> Result<Regex> {
+    let mut inline_flags = String::new();
+    for flag in flags.chars() {
+        match flag {
+            'i' | 'm' | 's' => inline_flags.push(flag),
+            'g' | 'u' => {}
+            f => return Err(RenderError(format!("Unsupported regex flag {f}"))),
+        };
+    }
+    let pattern = match inline_flags.as_str() {
+        "" => arg_str(args, "regex"),
+        f => format!("(?{f}){}", arg_str(args, "regex")),
+    };
+    Regex::new(&pattern).map_err(|e| RenderError(format!("Invalid regex: {e}")))
+}
+
+fn regex_match(args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let input = arg_str(args, "input");
+    let regex = build_regex(args, "")?;
+    let captures = match regex.captures(&input).map_err(|e| RenderError(e.to_string()))? {
+        None => return Ok("".to_string()),
+        Some(c) => c,
+    };
+
+    // Prefer the first named group, then the first group, then the whole match
+    let named = regex.capture_names().flatten().find_map(|n| captures.name(n));
+    let m = named.or_else(|| captures.get(1)).or_else(|| captures.get(0));
+    Ok(m.map(|m| m.as_str().to_string()).unwrap_or_default())
+}
+
+fn regex_replace(args: &HashMap<String, serde_json::Value>) -> Result<String> {
+    let input = arg_str(args, "input");
+    if arg_str(args, "regex").is_empty() {
+        return Ok("".to_string());
+    }
+
+    let flags = arg_str(args, "flags");
+    let regex = build_regex(args, &flags)?;
+
+    // Translate JavaScript replacement patterns ($&, $1) to the regex crate's syntax
+    let replacement = arg_str(args, "replacement").replace("$&", "${0}");
+    let replacement = regex::Regex::new(r"\$(\d+)")
+        .expect("valid regex")
+        .replace_all(&replacement, "$${$1}")
+        .to_string();
+
+    // A limit of 0 replaces every match
+    let limit = match flags.contains('g') {
+        true => 0,
+        false => 1,
+    };
+    let result = regex
+        .try_replacen(&input, limit, replacement.as_str())
+        .map_err(|e| RenderError(e.to_string()))?;
+    Ok(result.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::native_template_functions::{
+        find_native_template_function, is_fallback_template_function, native_template_functions,
+        template_function_native_run,
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+    use yaak_templates::error::Error::RenderError;
+    use yaak_templates::error::Result;
+
+    fn run(fn_name: &str, args: &[(&str, serde_json::Value)]) -> Result<String> {
+        let args = args.iter().map(|(k, v)| (k.to_string(), v.clone())).collect::<HashMap<_, _>>();
+        template_function_native_run(fn_name, &args).expect("native function exists")
+    }
+
+    #[test]
+    fn unknown_function() {
+        assert!(template_function_native_run("response.body.path", &HashMap::new()).is_none());
+        assert!(template_function_native_run("hash", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn find_by_name_or_alias() {
+        assert_eq!(find_native_template_function("keychain").unwrap().name, "keychain");
+        assert_eq!(find_native_template_function("keyring").unwrap().name, "keychain");
+        assert_eq!(find_native_template_function("hash.sha256").unwrap().name, "hash.sha256");
+        assert!(find_native_template_function("response.body.path").is_none());
+    }
+
+    #[test]
+    fn only_secure_and_keychain_replace_plugins() {
+        let replaced = native_template_functions()
+            .iter()
+            .filter(|f| !is_fallback_template_function(f))
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(replaced, vec!["secure", "keychain"]);
+    }
+
+    #[test]
+    fn core_functions_run_natively() {
+        for f in native_template_functions().iter().filter(|f| is_fallback_template_function(f)) {
+            assert!(template_function_native_run(&f.name, &HashMap::new()).is_some(), "{}", f.name);
+        }
+    }
+
+    #[test]
+    fn encoding() -> Result<()> {
+        assert_eq!(run("base64.encode", &[("value", json!("hello world"))])?, "aGVsbG8gd29ybGQ=");
+        assert_eq!(run("base64.decode", &[("value", json!(" aGVsbG8gd29ybGQ= "))])?, "hello world");
+        assert!(run("base64.decode", &[("value", json!("not base64!"))]).is_err());
+        assert_eq!(
+            run("url.encode", &[("value", json!("a b/c?d=é!*"))])?,
+            "a%20b%2Fc%3Fd%3D%C3%A9!*"
+        );
+        assert_eq!(run("url.decode", &[("value", json!("a%20b%2Fc%3Fd%3D%C3%A9"))])?, "a b/c?d=é");
+        Ok(())
+    }
+
+    #[test]
+    fn hashes() -> Result<()> {
+        let hex = |input: &str| [("input", json!(input)), ("encoding", json!("hex"))];
+        assert_eq!(run("hash.md5", &hex("hello"))?, "5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(run("hash.sha1", &hex("hello"))?, "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+        assert_eq!(
+            run("hash.sha256", &hex("hello"))?,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        // Base64 is the default encoding
+        assert_eq!(run("hash.md5", &[("input", json!("hello"))])?, "XUFAKrxLKna5cZ2REBfFkg==");
+        assert_eq!(
+            run("hash.md5", &[("input", json!("hello")), ("encoding", json!("binary"))]),
+            Err(RenderError("Unsupported encoding binary".to_string()))
+        );
+        assert_eq!(
+            run("hash.md4", &[("input", json!("hello"))]),
+            Err(RenderError("Unsupported hash algorithm md4".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hmac() -> Result<()> {
+        let args = [
+            ("input", json!("The quick brown fox jumps over the lazy dog")),
+            ("key", json!("key")),
+            ("encoding", json!("hex")),
+        ];
+        assert_eq!(
+            run("hmac.sha256", &args)?,
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn random() -> Result<()> {
+        for _ in 0..20 {
+            let n: i64 =
+                run("random.int", &[("min", json!("-2")), ("max", json!(2))])?.parse().unwrap();
+            assert!((-2..=2).contains(&n));
+        }
+        assert_eq!(run("random.int", &[("min", json!(5)), ("max", json!(5))])?, "5");
+        assert_eq!(
+            run("random.int", &[("min", json!(3)), ("max", json!(1))]),
+            Err(RenderError("Minimum 3 is greater than maximum 1".to_string()))
+        );
+        assert_eq!(
+            run("random.int", &[("min", json!("x"))]),
+            Err(RenderError("Argument min must be an integer".to_string()))
+        );
+
+        let s = run("random.string", &[("length", json!("24"))])?;
+        assert_eq!(s.len(), 24);
+        assert!(s.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(run("random.string", &[])?.len(), 16);
+        assert!(run("random.string", &[("length", json!(-1))]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn regex_match() -> Result<()> {
+        let matched = |input: &str, regex: &str| {
+            run("regex.match", &[("input", json!(input)), ("regex", json!(regex))])
+        };
+        assert_eq!(matched("id=42;", r"id=\d+")?, "id=42");
+        assert_eq!(matched("id=42;", r"id=(\d+)")?, "42");
+        assert_eq!(matched("id=42; v=7", r"(id)=(?<value>\d+)")?, "42");
+        assert_eq!(matched("id=42;", r"nope")?, "");
+        // Lookaround and backreferences work like they do in JavaScript
+        assert_eq!(matched("price: $42", r"(?<=\$)\d+")?, "42");
+        assert_eq!(matched("a1 b2", r"\w(?=2)")?, "b");
+        assert_eq!(matched("abccd", r"(\w)\1")?, "c");
+        assert!(matched("x", "(").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn regex_replace() -> Result<()> {
+        let replace = |input: &str, regex: &str, replacement: &str, flags: &str| {
+            run(
+                "regex.replace",
+                &[
+                    ("input", json!(input)),
+                    ("regex", json!(regex)),
+                    ("replacement", json!(replacement)),
+                    ("flags", json!(flags)),
+                ],
+            )
+        };
+        assert_eq!(replace("a-b-c", "-", "+", "g")?, "a+b+c");
+        assert_eq!(replace("a-b-c", "-", "+", "")?, "a+b-c");
+        assert_eq!(replace("Hello", "hello", "bye", "i")?, "bye");
+        assert_eq!(replace("a1b22", r"(\d+)", "<$1>", "g")?, "a<1>b<22>");
+        assert_eq!(replace("ab", "b", "[$&]", "")?, "a[b]");
+        assert_eq!(replace("one\ntwo", "^t", "T", "m")?, "one\nTwo");
+        assert_eq!(replace("aa bb cd", r"(\w)\1", "_", "g")?, "_ _ cd");
+        assert_eq!(replace("$5 and 5", r"(?<!\$)\b5", "five", "g")?, "$5 and five");
+        assert_eq!(replace("abc", "", "x", "g")?, "");
+        assert_eq!(
+            replace("abc", "b", "x", "y"),
+            Err(RenderError("Unsupported regex flag y".to_string()))
+        );
+        Ok(())
+    }
+}
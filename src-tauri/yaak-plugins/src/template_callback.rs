@@ -1,19 +1,30 @@
-use crate::events::{PluginWindowContext, RenderPurpose};
-use crate::manager::PluginManager;
+use crate::events::{
+    FormInput, GetTemplateFunctionSummaryResponse, PluginWindowContext, RenderPurpose,
+    TemplateFunction, TemplateFunctionArg,
+};
+use crate::manager::{NATIVE_PLUGIN_REF_ID, PluginManager};
 use crate::native_template_functions::{
-    template_function_keychain_run, template_function_secure_run,
-    template_function_secure_transform_arg,
+    find_native_template_function, template_function_keychain_run, template_function_native_run,
+    template_function_secure_run, template_function_secure_transform_arg,
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::OnceCell;
 use yaak_templates::TemplateCallback;
+use yaak_templates::cache::RenderCache;
+use yaak_templates::error::Error::RenderError;
 use yaak_templates::error::Result;
 
+/// Calls native and plugin template functions. Results are cached for the lifetime of the
+/// callback (and its clones), so create a new one for each render.
 #[derive(Clone)]
 pub struct PluginTemplateCallback<R: Runtime> {
     app_handle: AppHandle<R>,
     render_purpose: RenderPurpose,
     window_context: PluginWindowContext,
+    cache: RenderCache,
+    summaries: Arc<OnceCell<Vec<GetTemplateFunctionSummaryResponse>>>,
 }
 
 impl<R: Runtime> PluginTemplateCallback<R> {
@@ -26,22 +37,69 @@ impl<R: Runtime> PluginTemplateCallback<R> {
             render_purpose,
             app_handle: app_handle.to_owned(),
             window_context: window_context.to_owned(),
+            cache: RenderCache::new(),
+            summaries: Default::default(),
         }
     }
-}
 
-impl<R: Runtime> TemplateCallback for PluginTemplateCallback<R> {
-    async fn run(&self, fn_name: &str, args: HashMap<String, serde_json::Value>) -> Result<String> {
-        // The beta named the function `Response` but was changed in stable.
-        // Keep this here for a while because there's no easy way to migrate
-        let fn_name = if fn_name == "Response" { "response" } else { fn_name };
+    /// Find a function by name or alias, and whether it runs natively. The list of functions is
+    /// only fetched once, and native functions are still found without the plugin runtime.
+    async fn find_function(&self, fn_name: &str) -> Result<Option<(&TemplateFunction, bool)>> {
+        let summaries = self
+            .summaries
+            .get_or_try_init(|| async {
+                let plugin_manager = &*self.app_handle.state::<PluginManager>();
+                plugin_manager
+                    .get_template_function_summaries_for_context(&self.window_context)
+                    .await
+                    .map_err(|e| RenderError(format!("Failed to get template functions {e:}")))
+            })
+            .await;
+
+        let summaries = match summaries {
+            Ok(summaries) => summaries,
+            Err(e) => {
+                return match find_native_template_function(fn_name) {
+                    Some(f) => Ok(Some((f, true))),
+                    None => Err(e),
+                };
+            }
+        };
+
+        Ok(summaries.iter().find_map(|s| {
+            let f = s.functions.iter().find(|f| {
+                f.name == fn_name
+                    || f.aliases.as_ref().is_some_and(|a| a.iter().any(|a| a == fn_name))
+            })?;
+            Some((f, s.plugin_ref_id == NATIVE_PLUGIN_REF_ID))
+        }))
+    }
+
+    /// Functions are only cached if they opt in, since most can return something new each call
+    async fn is_cacheable(&self, fn_name: &str) -> bool {
+        self.find_function(fn_name).await.ok().flatten().and_then(|(f, _)| f.cache).unwrap_or(false)
+    }
 
+    async fn run_uncached(
+        &self,
+        fn_name: &str,
+        args: HashMap<String, serde_json::Value>,
+    ) -> Result<String> {
         if fn_name == "secure" {
             return template_function_secure_run(&self.app_handle, args, &self.window_context);
         } else if fn_name == "keychain" || fn_name == "keyring" {
             return template_function_keychain_run(args);
         }
 
+        // Core functions only run natively when no plugin provides them
+        let native_result = match self.find_function(fn_name).await {
+            Ok(Some((f, true))) => template_function_native_run(&f.name, &args),
+            _ => None,
+        };
+        if let Some(result) = native_result {
+            return result;
+        }
+
         let plugin_manager = &*self.app_handle.state::<PluginManager>();
         let resp = plugin_manager
             .call_template_function(
@@ -53,6 +111,20 @@ impl<R: Runtime> TemplateCallback for PluginTemplateCallback<R> {
             .await?;
         Ok(resp)
     }
+}
+
+impl<R: Runtime> TemplateCallback for PluginTemplateCallback<R> {
+    async fn run(&self, fn_name: &str, args: HashMap<String, serde_json::Value>) -> Result<String> {
+        // The beta named the function `Response` but was changed in stable.
+        // Keep this here for a while because there's no easy way to migrate
+        let fn_name = if fn_name == "Response" { "response" } else { fn_name };
+
+        if !self.is_cacheable(fn_name).await {
+            return self.run_uncached(fn_name, args).await;
+        }
+
+        self.cache.get_or_run(fn_name, &args.clone(), || self.run_uncached(fn_name, args)).await
+    }
 
     fn transform_arg(&self, fn_name: &str, arg_name: &str, arg_value: &str) -> Result<String> {
         if fn_name == "secure" {
@@ -66,4 +138,45 @@ impl<R: Runtime> TemplateCallback for PluginTemplateCallback<R> {
 
         Ok(arg_value.to_string())
     }
+
+    async fn first_arg_name(&self, fn_name: &str) -> Result<String> {
+        let fn_name = if fn_name == "Response" { "response" } else { fn_name };
+
+        let (function, _) = self
+            .find_function(fn_name)
+            .await?
+            .ok_or_else(|| RenderError(format!("Template function {fn_name} not found")))?;
+
+        function
+            .args
+            .iter()
+            .find_map(|TemplateFunctionArg::FormInput(input)| first_input_name(input))
+            .ok_or_else(|| RenderError(format!("Template function {fn_name} takes no arguments")))
+    }
+
+    async fn is_sequential(&self, fn_name: &str) -> bool {
+        let fn_name = if fn_name == "Response" { "response" } else { fn_name };
+        self.find_function(fn_name)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|(f, _)| f.sequential)
+            .unwrap_or(false)
+    }
+}
+
+/// Find the name of the first value-holding input, descending into layout inputs
+fn first_input_name(input: &FormInput) -> Option<String> {
+    let inputs = match input {
+        FormInput::Text(i) => return Some(i.base.name.clone()),
+        FormInput::Editor(i) => return Some(i.base.name.clone()),
+        FormInput::Select(i) => return Some(i.base.name.clone()),
+        FormInput::Checkbox(i) => return Some(i.base.name.clone()),
+        FormInput::File(i) => return Some(i.base.name.clone()),
+        FormInput::HttpRequest(i) => return Some(i.base.name.clone()),
+        FormInput::Accordion(i) => i.inputs.as_ref(),
+        FormInput::Banner(i) => i.inputs.as_ref(),
+        FormInput::Markdown(_) => None,
+    };
+    inputs.and_then(|inputs| inputs.iter().find_map(first_input_name))
 }
@@ -317,12 +317,15 @@ fn workspace_models<R: Runtime>(
     workspace_id: &str,
 ) -> Result<Vec<SyncModel>> {
     // We want to include private environments here so that we can take them into account during
-    // the sync process. Otherwise, they would be treated as deleted.
+    // the sync process. Otherwise, they would be treated as deleted. Secrets are kept too, since
+    // they're stored (and so written to the sync directory) encrypted.
     let include_private_environments = true;
+    let include_secrets = true;
     let resources = get_workspace_export_resources(
         app_handle,
         vec![workspace_id],
         include_private_environments,
+        include_secrets,
     )?
     .resources;
     let workspace = resources.workspaces.iter().find(|w| w.id == workspace_id);
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How to format a JSON or XML body
+ */
+export type FormatMode = "pretty" | "minify" | "canonical";
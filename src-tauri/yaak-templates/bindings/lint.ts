@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DiagnosticSeverity, Span } from "./parser";
+
+/**
+ * A tag that won't render: it couldn't be parsed, or it references a variable or function that
+ * doesn't exist. Variables that are only defined in some environments are warnings.
+ */
+export type LintDiagnostic = { kind: LintDiagnosticKind, severity: DiagnosticSeverity, message: string, 
+/**
+ * Name of the variable or function
+ */
+name?: string, span: Span, };
+
+export type LintDiagnosticKind = "invalid_tag" | "undefined_variable" | "undefined_function" | "invalid_value";
@@ -1,9 +1,43 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type FnArg = { name: string, value: Val, };
+export type BinaryOp = "or" | "and" | "eq" | "ne" | "lt" | "le" | "gt" | "ge" | "add" | "sub" | "mul" | "div" | "rem";
 
-export type Token = { "type": "raw", text: string, } | { "type": "tag", val: Val, } | { "type": "eof" };
+export type DiagnosticSeverity = "error" | "warning";
+
+export type FnArg = { name: string, value: Val, span?: Span, };
+
+/**
+ * A `key: value` pair in an object literal
+ */
+export type ObjectEntry = { key: string, value: Val, span?: Span, };
+
+/**
+ * A problem found while parsing. Errors are for tags that couldn't be parsed (and so were kept
+ * as raw text), and warnings are for tags that parsed but probably don't do what was intended.
+ */
+export type ParseDiagnostic = { kind: ParseDiagnosticKind, severity: DiagnosticSeverity, message: string, span: Span, };
+
+export type ParseDiagnosticKind = "unterminated_tag" | "invalid_tag" | "invalid_args" | "unterminated_string" | "unknown_escape";
+
+/**
+ * A function in a pipe, which is called with the previous value as its first argument
+ */
+export type PipeStage = { name: string, args: Array<FnArg>, span?: Span, };
+
+/**
+ * Location in the template source, as a half-open range of UTF-16 code unit offsets. That's how
+ * JavaScript indexes strings, so spans can be used directly by the editor.
+ */
+export type Span = { start: number, end: number, };
+
+export type Token = { "type": "raw", text: string, span?: Span, } | { "type": "tag", val: Val, span?: Span, } | { "type": "eof" };
 
 export type Tokens = { tokens: Array<Token>, };
 
-export type Val = { "type": "str", text: string, } | { "type": "var", name: string, } | { "type": "bool", value: boolean, } | { "type": "fn", name: string, args: Array<FnArg>, } | { "type": "null" };
+export type UnaryOp = "not" | "neg";
+
+export type Val = { "type": "str", text: string, span?: Span, } | { "type": "var", name: string, 
+/**
+ * Optional variables (`${[ name? ]}`) render as empty instead of failing when missing
+ */
+optional?: boolean, span?: Span, } | { "type": "bool", value: boolean, span?: Span, } | { "type": "num", value: number, span?: Span, } | { "type": "array", items: Array<Val>, span?: Span, } | { "type": "object", entries: Array<ObjectEntry>, span?: Span, } | { "type": "fn", name: string, args: Array<FnArg>, span?: Span, } | { "type": "coalesce", value: Val, fallback: Val, span?: Span, } | { "type": "pipe", value: Val, stages: Array<PipeStage>, span?: Span, } | { "type": "unary", op: UnaryOp, value: Val, span?: Span, } | { "type": "binary", op: BinaryOp, left: Val, right: Val, span?: Span, } | { "type": "ternary", condition: Val, then: Val, otherwise: Val, span?: Span, } | { "type": "null" };
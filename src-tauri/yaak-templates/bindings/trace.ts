@@ -0,0 +1,52 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Span } from "./parser";
+import type { JsonValue } from "./serde_json/JsonValue";
+
+/**
+ * A rendered template, along with a trace of how each of its tags was resolved
+ */
+export type RenderTrace = { output: string, tags: Array<TagTrace>, };
+
+/**
+ * How one tag was rendered: its value (or error), and the variables and functions that were
+ * resolved to get there
+ */
+export type TagTrace = { 
+/**
+ * Source of the tag, like `${[ token ]}`
+ */
+tag: string, span: Span, value?: string, error?: string, 
+/**
+ * The value was left out because it came from a secret
+ */
+redacted: boolean, steps: Array<TraceStep>, };
+
+/**
+ * The environment a variable was defined in, and the workspace or folder that owns it
+ */
+export type TraceSource = { environmentId: string, environmentName: string, parentModel: string, parentId?: string, };
+
+/**
+ * A variable or function call that was resolved while rendering a tag
+ */
+export type TraceStep = { kind: TraceStepKind, name: string, 
+/**
+ * Resolved args of a function call
+ */
+args?: { [key in string]?: JsonValue }, value?: string, error?: string, 
+/**
+ * Where a variable was defined. The renderer only sees the merged variables, so this is
+ * filled in afterward with [`TagTrace::with_variable_sources`].
+ */
+source?: TraceSource, 
+/**
+ * The value (and the args, if they were rendered from a secret) was left out because it
+ * came from a secret
+ */
+redacted: boolean, 
+/**
+ * Variables and functions resolved while rendering this one, in the order they started
+ */
+steps: Array<TraceStep>, };
+
+export type TraceStepKind = "var" | "fn";
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DiagnosticSeverity, Span } from "./parser";
+
+/**
+ * A problem with JSON that may contain template tags. Syntax and schema problems are errors.
+ * Comments and trailing commas are warnings, since they can be stripped before sending.
+ */
+export type JsonProblem = { kind: JsonProblemKind, severity: DiagnosticSeverity, message: string, 
+/**
+ * Line of the problem, starting at 1
+ */
+line: number, 
+/**
+ * Column of the problem in chars, starting at 1
+ */
+column: number, span: Span, };
+
+export type JsonProblemKind = "syntax" | "comment" | "trailing_comma" | "schema";
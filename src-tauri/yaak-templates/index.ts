@@ -1,11 +1,31 @@
 export * from './bindings/parser';
-import { Tokens } from './bindings/parser';
-import { escape_template, parse_template, unescape_template } from './pkg';
+export * from './bindings/trace';
+export * from './bindings/lint';
+export * from './bindings/validate_json';
+export * from './bindings/format';
+import { ParseDiagnostic, Tokens } from './bindings/parser';
+import { JsonProblem } from './bindings/validate_json';
+import {
+  escape_template,
+  parse_and_render,
+  parse_template,
+  parse_template_diagnostics,
+  strip_json_comments,
+  unescape_template,
+  validate_json,
+} from './pkg';
+import type { TemplateCallback } from './pkg';
+
+export type { TemplateCallback };
 
 export function parseTemplate(template: string) {
   return parse_template(template) as Tokens;
 }
 
+export function parseTemplateDiagnostics(template: string) {
+  return parse_template_diagnostics(template) as ParseDiagnostic[];
+}
+
 export function escapeTemplate(template: string) {
   return escape_template(template) as string;
 }
@@ -13,3 +33,19 @@ export function escapeTemplate(template: string) {
 export function unescapeTemplate(template: string) {
   return unescape_template(template) as string;
 }
+
+export function validateJson(text: string, schema?: string | null) {
+  return validate_json(text, schema ?? undefined) as JsonProblem[];
+}
+
+export function stripJsonComments(text: string) {
+  return strip_json_comments(text) as string;
+}
+
+export async function renderTemplate(
+  template: string,
+  vars: Record<string, string> | Map<string, string>,
+  callback: TemplateCallback,
+) {
+  return (await parse_and_render(template, vars, callback)) as string;
+}
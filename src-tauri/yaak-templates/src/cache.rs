@@ -0,0 +1,82 @@
+use crate::error::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Memoizes template function calls for the lifetime of a render (eg. sending one request), so
+/// a tag that's used in several places only calls its function once. Calls are keyed by the
+/// function name and its resolved args. Concurrent calls with the same key share one result,
+/// and failed calls aren't cached so they'll be tried again.
+#[derive(Clone, Default)]
+pub struct RenderCache {
+    calls: Arc<Mutex<HashMap<String, Arc<OnceCell<String>>>>>,
+}
+
+impl RenderCache {
+    pub fn new() -> RenderCache {
+        RenderCache::default()
+    }
+
+    pub async fn get_or_run<F, Fut>(
+        &self,
+        fn_name: &str,
+        args: &HashMap<String, serde_json::Value>,
+        run: F,
+    ) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let key = cache_key(fn_name, args);
+        let cell = self.calls.lock().unwrap().entry(key).or_default().clone();
+        cell.get_or_try_init(run).await.cloned()
+    }
+}
+
+fn cache_key(fn_name: &str, args: &HashMap<String, serde_json::Value>) -> String {
+    // Sort the args so the key doesn't depend on map order
+    let args = args.iter().collect::<BTreeMap<_, _>>();
+    format!("{fn_name}({})", serde_json::to_string(&args).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::RenderCache;
+    use crate::error::Error::RenderError;
+    use crate::error::Result;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn caches_by_name_and_args() -> Result<()> {
+        let cache = RenderCache::new();
+        let calls = AtomicUsize::new(0);
+        let run = || async { Ok(format!("call {}", calls.fetch_add(1, Ordering::SeqCst))) };
+
+        let a = HashMap::from([
+            ("x".to_string(), json!("1")),
+            ("y".to_string(), json!(true)),
+        ]);
+        let b = HashMap::from([("x".to_string(), json!("2"))]);
+
+        assert_eq!(cache.get_or_run("f", &a, run).await?, "call 0");
+        assert_eq!(cache.get_or_run("f", &a.clone(), run).await?, "call 0");
+        assert_eq!(cache.get_or_run("f", &b, run).await?, "call 1");
+        assert_eq!(cache.get_or_run("g", &a, run).await?, "call 2");
+        assert_eq!(cache.clone().get_or_run("f", &b, run).await?, "call 1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_errors() -> Result<()> {
+        let cache = RenderCache::new();
+        let args = HashMap::new();
+
+        let result = cache.get_or_run("f", &args, || async { Err(RenderError("x".into())) }).await;
+        assert_eq!(result, Err(RenderError("x".into())));
+        assert_eq!(cache.get_or_run("f", &args, || async { Ok("ok".into()) }).await?, "ok");
+        Ok(())
+    }
+}
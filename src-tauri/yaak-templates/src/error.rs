@@ -1,4 +1,7 @@
+use crate::trace::TraceSource;
 use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::Display;
 use thiserror::Error;
 use wasm_bindgen::JsValue;
 
@@ -12,6 +15,57 @@ pub enum Error {
 
     #[error("Render Error: Max recursion depth exceeded")]
     RenderStackExceededError,
+
+    #[error("Render Error: Variable references itself ({})", format_cycle(.0))]
+    VariableCycle(Vec<CycleStep>),
+
+    #[error("Invalid filter: {0}")]
+    FilterError(String),
+}
+
+/// A variable or function on the way through a cycle of variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleStep {
+    pub name: String,
+    pub is_fn: bool,
+    /// The environment the variable was defined in
+    pub source: Option<TraceSource>,
+}
+
+impl Display for CycleStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.is_fn, &self.source) {
+            (true, _) => write!(f, "{}()", self.name),
+            (false, Some(source)) => write!(f, "{} ({})", self.name, source.environment_name),
+            (false, None) => write!(f, "{}", self.name),
+        }
+    }
+}
+
+fn format_cycle(steps: &[CycleStep]) -> String {
+    steps.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" → ")
+}
+
+impl Error {
+    /// Fill in where each variable in a cycle was defined, from a map of variable names to
+    /// sources. Other errors are returned as-is.
+    pub fn with_variable_sources(self, sources: &HashMap<String, TraceSource>) -> Error {
+        match self {
+            Error::VariableCycle(steps) => Error::VariableCycle(
+                steps
+                    .into_iter()
+                    .map(|s| CycleStep {
+                        source: match s.is_fn {
+                            true => None,
+                            false => sources.get(&s.name).cloned(),
+                        },
+                        ..s
+                    })
+                    .collect(),
+            ),
+            e => e,
+        }
+    }
 }
 
 impl Serialize for Error {
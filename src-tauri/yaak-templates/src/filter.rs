@@ -0,0 +1,320 @@
+use crate::error::Error::FilterError;
+use crate::error::Result;
+use serde_json_path::JsonPath;
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use sxd_xpath::nodeset::Node;
+use sxd_xpath::{Context, Factory, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    JsonPath,
+    XPath,
+}
+
+impl FilterType {
+    /// Picks the native filter for a response content type, or `None` if the content type should
+    /// be handled by a filter plugin instead
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let content_type = content_type.to_lowercase();
+        if content_type.contains("json") {
+            Some(FilterType::JsonPath)
+        } else if content_type.contains("xml") {
+            Some(FilterType::XPath)
+        } else {
+            None
+        }
+    }
+
+    pub fn filter(&self, content: &str, filter: &str) -> Result<String> {
+        match self {
+            FilterType::JsonPath => filter_jsonpath(content, filter),
+            FilterType::XPath => filter_xpath(content, filter),
+        }
+    }
+
+    /// Whether a native result should be retried with the filter plugin. The JSONPath plugin uses
+    /// jsonpath-plus, which accepts extensions that RFC 9535 doesn't. Most of them (script
+    /// expressions like `[(@.length-1)]`, `~` for property names, `^` for parents) fail to parse
+    /// natively, but `.length` on arrays parses as a member name and matches nothing. Other
+    /// empty matches are final, as are both XPath implementations, which are XPath 1.0.
+    pub fn needs_plugin_fallback(&self, filter: &str, result: &Result<String>) -> bool {
+        match (self, result) {
+            (FilterType::JsonPath, Err(_)) => JsonPath::parse(filter).is_err(),
+            (FilterType::JsonPath, Ok(content)) => content == "[]" && filter.contains(".length"),
+            (FilterType::XPath, _) => false,
+        }
+    }
+}
+
+/// Filters JSON using an RFC 9535 JSONPath query. Matches are returned as a pretty-printed JSON
+/// array, the same shape the JSONPath filter plugin returns. See
+/// [`FilterType::needs_plugin_fallback`] for where this differs from the plugin.
+pub fn filter_jsonpath(content: &str, filter: &str) -> Result<String> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| FilterError(format!("invalid JSON: {e}")))?;
+    let path = JsonPath::parse(filter).map_err(|e| FilterError(e.to_string()))?;
+    let matches = path.query(&value).all();
+    serde_json::to_string_pretty(&matches).map_err(|e| FilterError(e.to_string()))
+}
+
+/// Filters XML using an XPath 1.0 expression. Node-set results are serialized one node per line,
+/// and scalar results (counts, strings, booleans) are returned as their string value.
+pub fn filter_xpath(content: &str, filter: &str) -> Result<String> {
+    let package = sxd_document::parser::parse(content)
+        .map_err(|e| FilterError(format!("invalid XML: {e}")))?;
+    let doc = package.as_document();
+
+    let xpath = Factory::new()
+        .build(filter)
+        .map_err(|e| FilterError(e.to_string()))?
+        .ok_or(FilterError("empty expression".to_string()))?;
+
+    let value =
+        xpath.evaluate(&Context::new(), doc.root()).map_err(|e| FilterError(e.to_string()))?;
+
+    match value {
+        Value::Nodeset(nodes) => {
+            let mut lines = Vec::new();
+            for node in nodes.document_order() {
+                let mut out = String::new();
+                write_node(&mut out, node);
+                lines.push(out);
+            }
+            Ok(lines.join("\n"))
+        }
+        v => Ok(v.string()),
+    }
+}
+
+fn write_node(out: &mut String, node: Node) {
+    match node {
+        Node::Root(r) => {
+            for child in r.children() {
+                match child {
+                    ChildOfRoot::Element(e) => write_element(out, e),
+                    ChildOfRoot::Comment(c) => write_comment(out, c.text()),
+                    ChildOfRoot::ProcessingInstruction(p) => write_pi(out, p.target(), p.value()),
+                }
+            }
+        }
+        Node::Element(e) => write_element(out, e),
+        Node::Attribute(a) => {
+            out.push_str(&qualified_name(a.preferred_prefix(), a.name().local_part()));
+            out.push_str("=\"");
+            out.push_str(&escape_xml(a.value(), true));
+            out.push('"');
+        }
+        Node::Text(t) => out.push_str(&escape_xml(t.text(), false)),
+        Node::Comment(c) => write_comment(out, c.text()),
+        Node::Namespace(ns) => out.push_str(ns.uri()),
+        Node::ProcessingInstruction(p) => write_pi(out, p.target(), p.value()),
+    }
+}
+
+fn write_element(out: &mut String, el: Element) {
+    let name = qualified_name(el.preferred_prefix(), el.name().local_part());
+    out.push('<');
+    out.push_str(&name);
+    if let Some(ns) = el.default_namespace_uri() {
+        out.push_str(" xmlns=\"");
+        out.push_str(&escape_xml(ns, true));
+        out.push('"');
+    }
+    for attr in el.attributes() {
+        out.push(' ');
+        write_node(out, Node::Attribute(attr));
+    }
+
+    let children = el.children();
+    if children.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+
+    out.push('>');
+    for child in children {
+        match child {
+            ChildOfElement::Element(e) => write_element(out, e),
+            ChildOfElement::Text(t) => out.push_str(&escape_xml(t.text(), false)),
+            ChildOfElement::Comment(c) => write_comment(out, c.text()),
+            ChildOfElement::ProcessingInstruction(p) => write_pi(out, p.target(), p.value()),
+        }
+    }
+    out.push_str("</");
+    out.push_str(&name);
+    out.push('>');
+}
+
+fn write_comment(out: &mut String, text: &str) {
+    out.push_str("<!--");
+    out.push_str(text);
+    out.push_str("-->");
+}
+
+fn write_pi(out: &mut String, target: &str, value: Option<&str>) {
+    out.push_str("<?");
+    out.push_str(target);
+    if let Some(v) = value {
+        out.push(' ');
+        out.push_str(v);
+    }
+    out.push_str("?>");
+}
+
+fn qualified_name(prefix: Option<&str>, local: &str) -> String {
+    match prefix {
+        Some(p) => format!("{p}:{local}"),
+        None => local.to_string(),
+    }
+}
+
+fn escape_xml(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error::FilterError;
+    use crate::filter::{FilterType, filter_jsonpath, filter_xpath};
+
+    #[test]
+    fn content_type_detection() {
+        assert_eq!(FilterType::from_content_type("application/json"), Some(FilterType::JsonPath));
+        assert_eq!(
+            FilterType::from_content_type("application/vnd.api+JSON; charset=utf-8"),
+            Some(FilterType::JsonPath)
+        );
+        assert_eq!(FilterType::from_content_type("text/xml"), Some(FilterType::XPath));
+        assert_eq!(FilterType::from_content_type("application/soap+xml"), Some(FilterType::XPath));
+        assert_eq!(FilterType::from_content_type("text/html"), None);
+        assert_eq!(FilterType::from_content_type(""), None);
+    }
+
+    #[test]
+    fn jsonpath_simple() {
+        let json = r#"{"store":{"book":[{"title":"A","price":8},{"title":"B","price":22}]}}"#;
+        assert_eq!(
+            filter_jsonpath(json, "$.store.book[*].title").unwrap(),
+            r#"
+[
+  "A",
+  "B"
+]
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn jsonpath_filter_expression() {
+        let json = r#"{"store":{"book":[{"title":"A","price":8},{"title":"B","price":22}]}}"#;
+        assert_eq!(
+            filter_jsonpath(json, "$.store.book[?@.price < 10].title").unwrap(),
+            r#"
+[
+  "A"
+]
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn jsonpath_no_matches() {
+        assert_eq!(filter_jsonpath(r#"{"foo":1}"#, "$.bar").unwrap(), "[]");
+    }
+
+    #[test]
+    fn jsonpath_invalid() {
+        assert!(matches!(filter_jsonpath(r#"{"foo":1}"#, "$.["), Err(FilterError(_))));
+        assert!(matches!(filter_jsonpath("not json", "$.foo"), Err(FilterError(_))));
+    }
+
+    #[test]
+    fn jsonpath_plus_extensions() {
+        let json = r#"{"items":[{"id":1},{"id":2}]}"#;
+
+        // Script expressions and property names aren't RFC 9535
+        assert!(matches!(filter_jsonpath(json, "$.items[(@.length-1)]"), Err(FilterError(_))));
+        assert!(matches!(filter_jsonpath(json, "$.items[0].*~"), Err(FilterError(_))));
+
+        // `.length` is just a member name, which arrays don't have
+        assert_eq!(filter_jsonpath(json, "$.items.length").unwrap(), "[]");
+
+        // Parenthesized filters are valid in both
+        assert_eq!(
+            filter_jsonpath(json, "$.items[?(@.id > 1)].id").unwrap(),
+            r#"
+[
+  2
+]
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn plugin_fallback() {
+        let json = r#"{"items":[1]}"#;
+        let jsonpath = FilterType::JsonPath;
+        let fallback =
+            |filter: &str| jsonpath.needs_plugin_fallback(filter, &filter_jsonpath(json, filter));
+        assert!(!fallback("$.items[0]"));
+        assert!(fallback("$.items.length"));
+        assert!(fallback("$.items[(@.length-1)]"));
+        assert!(fallback("$..*~"));
+
+        // Empty matches and invalid JSON are final
+        assert!(!fallback("$.missing"));
+        assert!(!fallback("$.items[?(@ > 5)]"));
+        assert!(!jsonpath.needs_plugin_fallback("$.a", &filter_jsonpath("{", "$.a")));
+
+        let xpath = FilterType::XPath;
+        assert!(!xpath.needs_plugin_fallback("//missing", &filter_xpath("<root/>", "//missing")));
+        assert!(!xpath.needs_plugin_fallback("//[", &filter_xpath("<root/>", "//[")));
+    }
+
+    #[test]
+    fn xpath_text_nodes() {
+        let xml = r#"<root><item>one</item><item>two</item></root>"#;
+        assert_eq!(filter_xpath(xml, "/root/item/text()").unwrap(), "one\ntwo");
+    }
+
+    #[test]
+    fn xpath_elements() {
+        let xml = r#"<root><item id="1">a &amp; b</item><item id="2"/></root>"#;
+        assert_eq!(
+            filter_xpath(xml, "//item").unwrap(),
+            "<item id=\"1\">a &amp; b</item>\n<item id=\"2\"/>"
+        );
+    }
+
+    #[test]
+    fn xpath_attributes() {
+        let xml = r#"<root><item id="1"/><item id="2"/></root>"#;
+        assert_eq!(filter_xpath(xml, "//item/@id").unwrap(), "id=\"1\"\nid=\"2\"");
+    }
+
+    #[test]
+    fn xpath_scalars() {
+        let xml = r#"<root><item/><item/></root>"#;
+        assert_eq!(filter_xpath(xml, "count(//item)").unwrap(), "2");
+        assert_eq!(filter_xpath(xml, "count(//item) > 1").unwrap(), "true");
+    }
+
+    #[test]
+    fn xpath_invalid() {
+        assert!(matches!(filter_xpath("<root/>", "//["), Err(FilterError(_))));
+        assert!(matches!(filter_xpath("<root>", "//root"), Err(FilterError(_))));
+    }
+}
@@ -1,3 +1,22 @@
+use crate::validate_json::strip_json_comments;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// How to format a JSON or XML body
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "format.ts")]
+pub enum FormatMode {
+    /// Indent nested values, one per line
+    #[default]
+    Pretty,
+    /// Remove all insignificant whitespace
+    Minify,
+    /// Pretty-print in a stable order (sorted JSON keys, sorted XML attributes), so equivalent
+    /// documents produce the same text
+    Canonical,
+}
+
 enum FormatState {
     TemplateTag,
     String,
@@ -141,9 +160,209 @@ pub fn format_json(text: &str, tab: &str) -> String {
         .join("\n") // Join the lines back into a single string
 }
 
+/// Removes all whitespace outside of strings and template tags. Comments and trailing commas are
+/// removed too, since they'd otherwise swallow or break what follows them.
+pub fn minify_json(text: &str) -> String {
+    let chars = strip_json_comments(text).chars().collect::<Vec<char>>();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => i = push_json_string(&chars, i, &mut out),
+            '$' if starts_with_tag(&chars, i) => i = push_template_tag(&chars, i, &mut out),
+            c if c.is_whitespace() => i += 1,
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Pretty-prints JSON with the keys of every object sorted, treating template tags as opaque
+/// values. Falls back to [`format_json`] without sorting if the text isn't valid enough to parse.
+pub fn canonicalize_json(text: &str, tab: &str) -> String {
+    let chars = minify_json(text).chars().collect::<Vec<char>>();
+    let mut parser = CanonicalJsonParser {
+        chars: &chars,
+        pos: 0,
+    };
+    match parser.parse_value() {
+        Some(value) if parser.pos == chars.len() => {
+            let mut sorted = String::new();
+            value.write(&mut sorted);
+            format_json(&sorted, tab)
+        }
+        _ => format_json(text, tab),
+    }
+}
+
+fn starts_with_tag(chars: &[char], i: usize) -> bool {
+    chars.get(i..i + 3) == Some(&['$', '{', '['])
+}
+
+/// Push the string starting at `start` (which must be a quote), returning the position after it
+fn push_json_string(chars: &[char], start: usize, out: &mut String) -> usize {
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        out.push(chars[i]);
+        match chars[i] {
+            '"' => return i + 1,
+            '\\' if i + 1 < chars.len() => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+/// Push the template tag starting at `start`, returning the position after it
+fn push_template_tag(chars: &[char], start: usize, out: &mut String) -> usize {
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == ']' && chars.get(i + 1) == Some(&'}') {
+            out.push_str("]}");
+            return i + 2;
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    i
+}
+
+enum CanonicalJsonValue {
+    Object(Vec<(String, CanonicalJsonValue)>),
+    Array(Vec<CanonicalJsonValue>),
+    /// Strings, numbers, literals, and template tags, kept exactly as written
+    Scalar(String),
+}
+
+impl CanonicalJsonValue {
+    fn write(&self, out: &mut String) {
+        match self {
+            CanonicalJsonValue::Object(entries) => {
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(key);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+            CanonicalJsonValue::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+            CanonicalJsonValue::Scalar(raw) => out.push_str(raw),
+        }
+    }
+}
+
+/// Parses minified JSON just enough to reorder object keys
+struct CanonicalJsonParser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl CanonicalJsonParser<'_> {
+    fn parse_value(&mut self) -> Option<CanonicalJsonValue> {
+        match self.chars.get(self.pos)? {
+            '{' => self.parse_object(),
+            '[' => self.parse_array(),
+            _ => self.parse_scalar().map(CanonicalJsonValue::Scalar),
+        }
+    }
+
+    fn parse_object(&mut self) -> Option<CanonicalJsonValue> {
+        self.pos += 1; // Skip {
+        let mut entries = Vec::new();
+        if self.eat('}') {
+            return Some(CanonicalJsonValue::Object(entries));
+        }
+        loop {
+            let key = self.parse_scalar()?;
+            if !self.eat(':') {
+                return None;
+            }
+            entries.push((key, self.parse_value()?));
+            if self.eat('}') {
+                break;
+            }
+            if !self.eat(',') {
+                return None;
+            }
+        }
+
+        // Sort by the unescaped key, keeping duplicates in their original order. Template tags
+        // used as keys can't be unescaped, so they're compared as written.
+        entries.sort_by_cached_key(|(key, _)| {
+            serde_json::from_str::<String>(key).unwrap_or_else(|_| key.clone())
+        });
+        Some(CanonicalJsonValue::Object(entries))
+    }
+
+    fn parse_array(&mut self) -> Option<CanonicalJsonValue> {
+        self.pos += 1; // Skip [
+        let mut items = Vec::new();
+        if self.eat(']') {
+            return Some(CanonicalJsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            if self.eat(']') {
+                break;
+            }
+            if !self.eat(',') {
+                return None;
+            }
+        }
+        Some(CanonicalJsonValue::Array(items))
+    }
+
+    fn parse_scalar(&mut self) -> Option<String> {
+        let mut raw = String::new();
+        while let Some(c) = self.chars.get(self.pos) {
+            match c {
+                '"' => self.pos = push_json_string(self.chars, self.pos, &mut raw),
+                '$' if starts_with_tag(self.chars, self.pos) => {
+                    self.pos = push_template_tag(self.chars, self.pos, &mut raw)
+                }
+                ',' | ':' | '{' | '}' | '[' | ']' => break,
+                c => {
+                    raw.push(*c);
+                    self.pos += 1;
+                }
+            }
+        }
+        if raw.is_empty() { None } else { Some(raw) }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.chars.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::format::format_json;
+    use crate::format::{canonicalize_json, format_json, minify_json};
 
     #[test]
     fn test_simple_object() {
@@ -301,4 +520,68 @@ mod tests {
             .trim()
         );
     }
+
+    #[test]
+    fn test_minify() {
+        assert_eq!(
+            minify_json("{\n  \"foo\": \"a b\",\n  \"bar\": [ 1, ${[ fn( 'x y' ) ]} ]\n}"),
+            r#"{"foo":"a b","bar":[1,${[ fn( 'x y' ) ]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_minify_comments() {
+        assert_eq!(
+            minify_json("{\n  // note\n  \"a\": \"//x\", /* b */\n  \"b\": [1, 2,],\n}"),
+            r#"{"a":"//x","b":[1,2]}"#
+        );
+    }
+
+    #[test]
+    fn test_minify_escaped_quote() {
+        assert_eq!(minify_json(r#"{ "a": "say \" hi " }"#), r#"{"a":"say \" hi "}"#);
+    }
+
+    #[test]
+    fn test_canonical_sorts_keys() {
+        assert_eq!(
+            canonicalize_json(r#"{"b":{"z":1,"y":[{"d":2,"c":3}]},"a":${[ fn('x') ]}}"#, "  "),
+            r#"
+{
+  "a": ${[ fn('x') ]},
+  "b": {
+    "y": [
+      {
+        "c": 3,
+        "d": 2
+      }
+    ],
+    "z": 1
+  }
+}
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn test_canonical_template_key() {
+        assert_eq!(
+            canonicalize_json(r#"{"b":1,${[ key ]}:2,"a":3}"#, "  "),
+            "{\n  ${[ key ]}: 2,\n  \"a\": 3,\n  \"b\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn test_canonical_escaped_keys() {
+        assert_eq!(
+            canonicalize_json(r#"{"b":1,"\u0061c":2,"ab":3}"#, "  "),
+            "{\n  \"ab\": 3,\n  \"\\u0061c\": 2,\n  \"b\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn test_canonical_invalid_falls_back() {
+        assert_eq!(canonicalize_json(r#"{"b":1,"a"}"#, "  "), format_json(r#"{"b":1,"a"}"#, "  "));
+    }
 }
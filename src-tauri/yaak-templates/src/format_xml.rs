@@ -242,9 +242,162 @@ fn parse_close_name(raw: &str) -> &str {
     &s[..end]
 }
 
+/// Removes whitespace-only text between tags. Other text is kept as-is, since its whitespace can
+/// be significant (like the space in `Hello <b>world</b>`).
+pub fn minify_xml(input: &str) -> String {
+    use XmlTok::*;
+    let mut out = String::new();
+    for tok in tokenize_with_templates(input) {
+        match tok {
+            OpenTag { raw, .. } | CloseTag { raw, .. } => out.push_str(raw),
+            SelfCloseTag(raw) | Comment(raw) | ProcInst(raw) | Doctype(raw) | CData(raw)
+            | Template(raw) => out.push_str(raw),
+            Text(raw) if raw.trim().is_empty() => {}
+            Text(raw) => out.push_str(raw),
+        }
+    }
+    out
+}
+
+/// Pretty-prints XML with attributes in a stable, C14N-style order: namespace declarations first,
+/// then the rest sorted by name. Attributes are separated by single spaces and double-quoted
+/// where that doesn't need escaping. Template tags used as attributes are kept, after the others.
+pub fn canonicalize_xml(input: &str, indent: &str) -> String {
+    use XmlTok::*;
+    let mut canonical = String::new();
+    for tok in tokenize_with_templates(input) {
+        match tok {
+            OpenTag { raw, .. } | SelfCloseTag(raw) => {
+                canonical.push_str(&canonicalize_tag(raw).unwrap_or_else(|| raw.to_string()))
+            }
+            CloseTag { raw, .. } => canonical.push_str(raw),
+            Comment(raw) | ProcInst(raw) | Doctype(raw) | CData(raw) | Template(raw)
+            | Text(raw) => canonical.push_str(raw),
+        }
+    }
+    format_xml(&canonical, indent)
+}
+
+struct XmlAttr<'a> {
+    name: &'a str,
+    /// The value without quotes, and the quote it was written with
+    value: Option<(&'a str, Option<char>)>,
+}
+
+impl XmlAttr<'_> {
+    fn sort_key(&self) -> (u8, &str) {
+        let rank = if self.name.starts_with("${[") {
+            2
+        } else if self.name == "xmlns" || self.name.starts_with("xmlns:") {
+            0
+        } else {
+            1
+        };
+        (rank, self.name)
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str(self.name);
+        if let Some((value, quote)) = self.value {
+            let quote = match quote {
+                Some('\'') if value.contains('"') => '\'',
+                _ => '"',
+            };
+            out.push('=');
+            out.push(quote);
+            out.push_str(value);
+            out.push(quote);
+        }
+    }
+}
+
+/// Rewrite an open or self-closing tag with its attributes sorted, or `None` if it can't be parsed
+fn canonicalize_tag(raw: &str) -> Option<String> {
+    let (inner, close) = match raw.strip_suffix("/>") {
+        Some(inner) => (inner, "/>"),
+        None => (raw.strip_suffix('>')?, ">"),
+    };
+    let inner = inner.strip_prefix('<')?;
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let (name, mut rest) = inner.split_at(name_end);
+
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        let name_len = if rest.starts_with("${[") {
+            rest.find("]}")? + 2
+        } else {
+            rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len())
+        };
+        let (attr_name, after) = rest.split_at(name_len);
+        rest = after;
+
+        let after_eq = match rest.trim_start().strip_prefix('=') {
+            Some(after_eq) => after_eq.trim_start(),
+            None => {
+                attrs.push(XmlAttr {
+                    name: attr_name,
+                    value: None,
+                });
+                continue;
+            }
+        };
+        let (value, quote, len) = match after_eq.chars().next() {
+            Some(q) if q == '"' || q == '\'' => {
+                let end = find_quote_end(&after_eq[1..], q)?;
+                (&after_eq[1..end + 1], Some(q), end + 2)
+            }
+            Some(_) if after_eq.starts_with("${[") => {
+                let end = after_eq.find("]}")? + 2;
+                (&after_eq[..end], None, end)
+            }
+            Some(_) => {
+                let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                (&after_eq[..end], None, end)
+            }
+            None => return None,
+        };
+        attrs.push(XmlAttr {
+            name: attr_name,
+            value: Some((value, quote)),
+        });
+        rest = &after_eq[len..];
+    }
+
+    attrs.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+
+    let mut out = format!("<{name}");
+    for attr in attrs {
+        out.push(' ');
+        attr.write(&mut out);
+    }
+    out.push_str(close);
+    Some(out)
+}
+
+/// Find the closing quote in `s`, skipping over any template tags
+fn find_quote_end(s: &str, quote: char) -> Option<usize> {
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with("${[") {
+            i += rest.find("]}")? + 2;
+        } else if rest.starts_with(quote) {
+            return Some(i);
+        } else {
+            i += rest.chars().next()?.len_utf8();
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
-    use super::format_xml;
+    use super::{canonicalize_xml, format_xml, minify_xml};
 
     #[test]
     fn inline_text_child() {
@@ -342,4 +495,33 @@ mod tests {
 </root>"#;
         assert_eq!(format_xml(src, "  "), want);
     }
+
+    #[test]
+    fn minify() {
+        let src = "<?xml version=\"1.0\"?>\n<root>\n  <foo a=\"1\">  hi  </foo>\n  ${[ x ]}\n  <b/>\n</root>";
+        let want = r#"<?xml version="1.0"?><root><foo a="1">  hi  </foo>${[ x ]}<b/></root>"#;
+        assert_eq!(minify_xml(src), want);
+    }
+
+    #[test]
+    fn minify_mixed_content() {
+        let src = "<p>\n  Hello <b>world</b> !\n</p>";
+        assert_eq!(minify_xml(src), "<p>\n  Hello <b>world</b> !\n</p>");
+    }
+
+    #[test]
+    fn canonical_sorts_attributes() {
+        let src = r#"<root b="2"   a='1' xmlns:x="urn:x" xmlns="urn:d"><x:el z='say "hi"' y=3 checked/></root>"#;
+        let want = r#"<root xmlns="urn:d" xmlns:x="urn:x" a="1" b="2">
+  <x:el checked y="3" z='say "hi"'/>
+</root>"#;
+        assert_eq!(canonicalize_xml(src, "  "), want);
+    }
+
+    #[test]
+    fn canonical_template_attributes() {
+        let src = r#"<root ${[ attrs ]} c="${[ fn('>') ]}" b=${[ v ]}>t</root>"#;
+        let want = r#"<root b="${[ v ]}" c="${[ fn('>') ]}" ${[ attrs ]}>t</root>"#;
+        assert_eq!(canonicalize_xml(src, "  "), want);
+    }
 }
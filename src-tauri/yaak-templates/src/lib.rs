@@ -1,10 +1,19 @@
+pub mod cache;
 pub mod error;
 pub mod escape;
+#[cfg(feature = "filter")]
+pub mod filter;
 pub mod format;
 pub mod parser;
+pub mod refactor;
 pub mod renderer;
+pub mod trace;
+pub mod validate_json;
 pub mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm_render;
 pub mod format_xml;
+pub mod lint;
 
 pub use parser::*;
 pub use renderer::*;
@@ -0,0 +1,351 @@
+use crate::error::Result;
+use crate::{DiagnosticSeverity, Parser, Span, Token, Tokens, Val};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use ts_rs::TS;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "lint.ts")]
+pub enum LintDiagnosticKind {
+    InvalidTag,
+    UndefinedVariable,
+    UndefinedFunction,
+    /// A value that doesn't match the type it's declared as
+    InvalidValue,
+}
+
+/// A tag that won't render: it couldn't be parsed, or it references a variable or function that
+/// doesn't exist. Variables that are only defined in some environments are warnings.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "lint.ts")]
+pub struct LintDiagnostic {
+    pub kind: LintDiagnosticKind,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// Name of the variable or function
+    #[ts(optional)]
+    pub name: Option<String>,
+    pub span: Span,
+}
+
+/// What templates can reference
+#[derive(Clone, Debug, Default)]
+pub struct LintContext {
+    /// Each environment a template could be rendered with, by name, and the variables it defines
+    pub environments: Vec<(String, HashSet<String>)>,
+    /// Names and aliases of the available template functions
+    pub functions: HashSet<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReferenceKind {
+    Var,
+    Fn,
+}
+
+/// A variable or function used by a template
+#[derive(Clone, PartialEq, Debug)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub name: String,
+    pub span: Span,
+    /// Whether the template still renders if a variable is missing (`name?` or `name ?? 'x'`)
+    pub optional: bool,
+}
+
+/// Find every variable and function the template uses, including those in function args, pipes,
+/// and strings. References inside a string have the string's span, since their own spans are
+/// relative to the string.
+pub fn collect_references(tokens: &Tokens) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    for t in &tokens.tokens {
+        if let Token::Tag { val, .. } = t {
+            collect_val_references(val, false, None, &mut refs);
+        }
+    }
+    refs
+}
+
+/// Whether a missing variable in the value of `??` renders the fallback, matching the renderer
+fn falls_back(val: &Val) -> bool {
+    match val {
+        Val::Var { .. } => true,
+        Val::Coalesce {
+            value, fallback, ..
+        } => falls_back(value) && falls_back(fallback),
+        _ => false,
+    }
+}
+
+fn collect_val_references(
+    val: &Val,
+    optional: bool,
+    outer_span: Option<Span>,
+    refs: &mut Vec<Reference>,
+) {
+    let mut push = |kind, name: &str, span: Span, optional| {
+        refs.push(Reference {
+            kind,
+            name: name.to_string(),
+            span: outer_span.unwrap_or(span),
+            optional,
+        })
+    };
+
+    match val {
+        Val::Str { text, span } => {
+            if let Ok(tokens) = Parser::new(text).parse() {
+                for t in tokens.tokens {
+                    if let Token::Tag { val, .. } = t {
+                        collect_val_references(
+                            &val,
+                            optional,
+                            Some(outer_span.unwrap_or(*span)),
+                            refs,
+                        );
+                    }
+                }
+            }
+        }
+        Val::Var {
+            name,
+            optional: o,
+            span,
+        } => push(ReferenceKind::Var, name, *span, optional || *o),
+        Val::Fn { name, args, span } => {
+            push(ReferenceKind::Fn, name, *span, optional);
+            for a in args {
+                collect_val_references(&a.value, optional, outer_span, refs);
+            }
+        }
+        Val::Coalesce {
+            value, fallback, ..
+        } => {
+            // Only a missing variable as the value itself renders the fallback instead
+            collect_val_references(value, optional || falls_back(value), outer_span, refs);
+            collect_val_references(fallback, optional, outer_span, refs);
+        }
+        Val::Pipe { value, stages, .. } => {
+            collect_val_references(value, optional, outer_span, refs);
+            for stage in stages {
+                refs.push(Reference {
+                    kind: ReferenceKind::Fn,
+                    name: stage.name.clone(),
+                    span: outer_span.unwrap_or(stage.span),
+                    optional,
+                });
+                for a in &stage.args {
+                    collect_val_references(&a.value, optional, outer_span, refs);
+                }
+            }
+        }
+        Val::Array { items, .. } => {
+            for v in items {
+                collect_val_references(v, optional, outer_span, refs);
+            }
+        }
+        Val::Object { entries, .. } => {
+            for e in entries {
+                collect_val_references(&e.value, optional, outer_span, refs);
+            }
+        }
+        Val::Unary { value, .. } => collect_val_references(value, optional, outer_span, refs),
+        Val::Binary { left, right, .. } => {
+            collect_val_references(left, optional, outer_span, refs);
+            collect_val_references(right, optional, outer_span, refs);
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            collect_val_references(condition, optional, outer_span, refs);
+            collect_val_references(then, optional, outer_span, refs);
+            collect_val_references(otherwise, optional, outer_span, refs);
+        }
+        Val::Bool { .. } | Val::Num { .. } | Val::Null => {}
+    }
+}
+
+/// Check that the template parses, and that the variables and functions it uses exist
+pub fn lint_template(template: &str, ctx: &LintContext) -> Result<Vec<LintDiagnostic>> {
+    let (tokens, parse_diagnostics) = Parser::new(template).parse_with_diagnostics()?;
+
+    let mut diagnostics = parse_diagnostics
+        .into_iter()
+        .map(|d| LintDiagnostic {
+            kind: LintDiagnosticKind::InvalidTag,
+            severity: d.severity,
+            message: d.message,
+            name: None,
+            span: d.span,
+        })
+        .collect::<Vec<LintDiagnostic>>();
+
+    for r in collect_references(&tokens) {
+        let problem = match r.kind {
+            ReferenceKind::Var if r.optional => None,
+            ReferenceKind::Var => {
+                let missing_from = ctx
+                    .environments
+                    .iter()
+                    .filter(|(_, vars)| !vars.contains(&r.name))
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<&str>>();
+                if missing_from.is_empty() {
+                    None
+                } else if missing_from.len() == ctx.environments.len() {
+                    let message = format!("Variable \"{}\" is not defined", r.name);
+                    Some((
+                        LintDiagnosticKind::UndefinedVariable,
+                        DiagnosticSeverity::Error,
+                        message,
+                    ))
+                } else {
+                    let message = format!(
+                        "Variable \"{}\" is not defined in {}",
+                        r.name,
+                        missing_from.join(", ")
+                    );
+                    Some((
+                        LintDiagnosticKind::UndefinedVariable,
+                        DiagnosticSeverity::Warning,
+                        message,
+                    ))
+                }
+            }
+            ReferenceKind::Fn if ctx.functions.contains(&r.name) => None,
+            ReferenceKind::Fn => {
+                let message = format!("Template function \"{}\" does not exist", r.name);
+                Some((LintDiagnosticKind::UndefinedFunction, DiagnosticSeverity::Error, message))
+            }
+        };
+
+        if let Some((kind, severity, message)) = problem {
+            diagnostics.push(LintDiagnostic {
+                kind,
+                severity,
+                message,
+                name: Some(r.name),
+                span: r.span,
+            });
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::lint::{
+        LintContext, LintDiagnosticKind, ReferenceKind, collect_references, lint_template,
+    };
+    use crate::{DiagnosticSeverity, Parser};
+    use std::collections::HashSet;
+
+    fn context() -> LintContext {
+        let vars = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<HashSet<_>>();
+        LintContext {
+            environments: vec![
+                ("Dev".to_string(), vars(&["host", "token"])),
+                ("Prod".to_string(), vars(&["host"])),
+            ],
+            functions: HashSet::from(["upper".to_string(), "response.body.path".to_string()]),
+        }
+    }
+
+    #[test]
+    fn collects_nested_references() -> Result<()> {
+        let template = "${[ upper(value=[a, {k: b}]) | trim(c=d) ]}${[ e? ]}${[ f ?? g ]}${[ h(x='${[ i ]}') ]}${[ upper(value=j) ?? k ]}";
+        let tokens = Parser::new(template).parse()?;
+        let refs = collect_references(&tokens)
+            .into_iter()
+            .map(|r| (r.kind, r.name, r.optional))
+            .collect::<Vec<_>>();
+        let var = |name: &str, optional| (ReferenceKind::Var, name.to_string(), optional);
+        let func = |name: &str| (ReferenceKind::Fn, name.to_string(), false);
+        assert_eq!(
+            refs,
+            vec![
+                func("upper"),
+                var("a", false),
+                var("b", false),
+                func("trim"),
+                var("d", false),
+                var("e", true),
+                var("f", true),
+                var("g", false),
+                func("h"),
+                var("i", false),
+                func("upper"),
+                var("j", false),
+                var("k", false),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn string_references_use_string_span() -> Result<()> {
+        let template = "${[ h(x='${[ i ]}') ]}";
+        let tokens = Parser::new(template).parse()?;
+        let refs = collect_references(&tokens);
+        assert_eq!((refs[1].span.start, refs[1].span.end), (8, 18));
+        Ok(())
+    }
+
+    #[test]
+    fn lint_defined() -> Result<()> {
+        let template = "${[ host ]}/${[ upper(value=host) ]}${[ missing? ]}${[ nope ?? host ]}";
+        assert_eq!(lint_template(template, &context())?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn lint_undefined_variable() -> Result<()> {
+        let diagnostics = lint_template("${[ token ]}${[ nope ]}", &context())?;
+        let found = diagnostics
+            .iter()
+            .map(|d| (d.kind, d.severity, d.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    LintDiagnosticKind::UndefinedVariable,
+                    DiagnosticSeverity::Warning,
+                    "Variable \"token\" is not defined in Prod"
+                ),
+                (
+                    LintDiagnosticKind::UndefinedVariable,
+                    DiagnosticSeverity::Error,
+                    "Variable \"nope\" is not defined"
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[1].name, Some("nope".to_string()));
+        assert_eq!((diagnostics[1].span.start, diagnostics[1].span.end), (16, 20));
+        Ok(())
+    }
+
+    #[test]
+    fn lint_undefined_function() -> Result<()> {
+        let diagnostics = lint_template("${[ host | lower ]}", &context())?;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, LintDiagnosticKind::UndefinedFunction);
+        assert_eq!(diagnostics[0].message, "Template function \"lower\" does not exist");
+        Ok(())
+    }
+
+    #[test]
+    fn lint_invalid_tag() -> Result<()> {
+        let diagnostics = lint_template("${[ host", &context())?;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, LintDiagnosticKind::InvalidTag);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        Ok(())
+    }
+}
@@ -5,8 +5,53 @@ use base64::Engine;
 use base64::prelude::BASE64_URL_SAFE_NO_PAD;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::ops::Range;
 use ts_rs::TS;
 
+/// Location in the template source, as a half-open range of UTF-16 code unit offsets. That's how
+/// JavaScript indexes strings, so spans can be used directly by the editor.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "parser.ts")]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Convert to a byte range, for slicing the template this span came from
+    pub fn byte_range(&self, template: &str) -> Range<usize> {
+        let byte_offset = |utf16_offset: usize| {
+            let mut units = 0;
+            for (i, c) in template.char_indices() {
+                if units >= utf16_offset {
+                    return i;
+                }
+                units += c.len_utf16();
+            }
+            template.len()
+        };
+        byte_offset(self.start)..byte_offset(self.end)
+    }
+}
+
+/// UTF-16 offset of each char, followed by the length of the whole text
+pub(crate) fn utf16_offsets(chars: &[char]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(chars.iter().scan(0, |offset, c| {
+            *offset += c.len_utf16();
+            Some(*offset)
+        }))
+        .collect()
+}
+
 #[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "parser.ts")]
 pub struct Tokens {
@@ -25,6 +70,8 @@ impl Display for Tokens {
 pub struct FnArg {
     pub name: String,
     pub value: Val,
+    #[serde(default, skip_serializing_if = "Span::is_empty")]
+    pub span: Span,
 }
 
 impl Display for FnArg {
@@ -34,41 +81,326 @@ impl Display for FnArg {
     }
 }
 
+fn args_to_string(args: &[FnArg]) -> String {
+    args.iter()
+        .filter_map(|a| match a.value.clone() {
+            Val::Null => None,
+            _ => Some(a.to_string()),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// A function in a pipe, which is called with the previous value as its first argument
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "parser.ts")]
+pub struct PipeStage {
+    pub name: String,
+    pub args: Vec<FnArg>,
+    #[serde(default, skip_serializing_if = "Span::is_empty")]
+    pub span: Span,
+}
+
+impl Display for PipeStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match args_to_string(&self.args).as_str() {
+            "" => write!(f, "{}", self.name),
+            args => write!(f, "{}({args})", self.name),
+        }
+    }
+}
+
+/// A `key: value` pair in an object literal
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "parser.ts")]
+pub struct ObjectEntry {
+    pub key: String,
+    pub value: Val,
+    #[serde(default, skip_serializing_if = "Span::is_empty")]
+    pub span: Span,
+}
+
+impl Display for ObjectEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut chars = self.key.chars();
+        let is_ident = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        let key = match is_ident {
+            true => self.key.clone(),
+            false => Val::Str {
+                text: self.key.clone(),
+                span: Span::default(),
+            }
+            .to_string(),
+        };
+        write!(f, "{key}: {}", self.value)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "parser.ts")]
+pub enum UnaryOp {
+    /// `!value`
+    Not,
+    /// `-value`
+    Neg,
+}
+
+impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Neg => "-",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "parser.ts")]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Adds numbers, or concatenates if either side isn't a number
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => PREC_OR,
+            BinaryOp::And => PREC_OR + 1,
+            BinaryOp::Eq | BinaryOp::Ne => PREC_OR + 2,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => PREC_OR + 3,
+            BinaryOp::Add | BinaryOp::Sub => PREC_OR + 4,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => PREC_OR + 5,
+        }
+    }
+}
+
+// How tightly each kind of value binds, from loosest to tightest, for adding parentheses back
+// when displaying
+const PREC_PIPE: u8 = 0;
+const PREC_TERNARY: u8 = 1;
+const PREC_COALESCE: u8 = 2;
+const PREC_OR: u8 = 3;
+const PREC_UNARY: u8 = 9;
+const PREC_VALUE: u8 = 10;
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
 #[serde(rename_all = "snake_case", tag = "type")]
 #[ts(export, export_to = "parser.ts")]
 pub enum Val {
-    Str { text: String },
-    Var { name: String },
-    Bool { value: bool },
-    Fn { name: String, args: Vec<FnArg> },
+    Str {
+        text: String,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    Var {
+        name: String,
+        /// Optional variables (`${[ name? ]}`) render as empty instead of failing when missing
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        optional: bool,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    Bool {
+        value: bool,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    Num {
+        value: f64,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// List of values (`${[ [1, 'two', three] ]}`)
+    Array {
+        items: Vec<Val>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// Map of keys to values, where keys are identifiers or strings (`${[ {a: 1, 'b c': d} ]}`)
+    Object {
+        entries: Vec<ObjectEntry>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    Fn {
+        name: String,
+        args: Vec<FnArg>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// Renders `value`, or `fallback` if `value` references a variable that isn't defined
+    /// (`${[ api_host ?? 'localhost:8080' ]}`)
+    Coalesce {
+        value: Box<Val>,
+        fallback: Box<Val>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// Passes `value` through each stage in turn (`${[ token | trim | base64.encode ]}`)
+    Pipe {
+        value: Box<Val>,
+        stages: Vec<PipeStage>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// `${[ !is_prod ]}` or `${[ -offset ]}`
+    Unary {
+        op: UnaryOp,
+        value: Box<Val>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// `${[ page + 1 ]}`, `${[ count >= 10 && enabled ]}`, etc.
+    Binary {
+        op: BinaryOp,
+        left: Box<Val>,
+        right: Box<Val>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    /// Renders `then` if `condition` is truthy, otherwise `otherwise`
+    /// (`${[ is_prod ? 'https' : 'http' ]}`)
+    Ternary {
+        condition: Box<Val>,
+        then: Box<Val>,
+        otherwise: Box<Val>,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
     Null,
 }
 
+impl Val {
+    /// Where the value came from in the template, or an empty span if it wasn't parsed
+    pub fn span(&self) -> Span {
+        match self {
+            Val::Str { span, .. }
+            | Val::Var { span, .. }
+            | Val::Bool { span, .. }
+            | Val::Num { span, .. }
+            | Val::Array { span, .. }
+            | Val::Object { span, .. }
+            | Val::Fn { span, .. }
+            | Val::Coalesce { span, .. }
+            | Val::Pipe { span, .. }
+            | Val::Unary { span, .. }
+            | Val::Binary { span, .. }
+            | Val::Ternary { span, .. } => *span,
+            Val::Null => Span::default(),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Val::Pipe { .. } => PREC_PIPE,
+            Val::Ternary { .. } => PREC_TERNARY,
+            Val::Coalesce { .. } => PREC_COALESCE,
+            Val::Binary { op, .. } => op.precedence(),
+            Val::Unary { .. } => PREC_UNARY,
+            _ => PREC_VALUE,
+        }
+    }
+
+    /// Display the value, wrapped in parentheses if it binds looser than `precedence`
+    fn display_operand(&self, precedence: u8) -> String {
+        match self.precedence() < precedence {
+            true => format!("({self})"),
+            false => self.to_string(),
+        }
+    }
+}
+
 impl Display for Val {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
-            Val::Str { text } => {
+            Val::Str { text, .. } => {
                 if text.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '_' || c == '_') {
                     format!("'{}'", text)
                 } else {
                     format!("b64'{}'", BASE64_URL_SAFE_NO_PAD.encode(text))
                 }
             }
-            Val::Var { name } => name.to_string(),
-            Val::Bool { value } => value.to_string(),
-            Val::Fn { name, args } => {
-                format!(
-                    "{name}({})",
-                    args.iter()
-                        .filter_map(|a| match a.value.clone() {
-                            Val::Null => None,
-                            _ => Some(a.to_string()),
-                        })
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                )
+            Val::Var { name, optional, .. } => match optional {
+                true => format!("{name}?"),
+                false => name.to_string(),
+            },
+            Val::Bool { value, .. } => value.to_string(),
+            Val::Num { value, .. } => value.to_string(),
+            Val::Array { items, .. } => {
+                let items = items.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                format!("[{}]", items.join(", "))
             }
+            Val::Object { entries, .. } => {
+                let entries = entries.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Val::Fn { name, args, .. } => format!("{name}({})", args_to_string(args)),
+            Val::Coalesce {
+                value, fallback, ..
+            } => format!(
+                "{} ?? {}",
+                value.display_operand(PREC_COALESCE + 1),
+                fallback.display_operand(PREC_COALESCE)
+            ),
+            Val::Pipe { value, stages, .. } => {
+                let stages = stages.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+                format!("{value} | {}", stages.join(" | "))
+            }
+            Val::Unary { op, value, .. } => {
+                format!("{}{}", op.symbol(), value.display_operand(PREC_UNARY))
+            }
+            Val::Binary {
+                op, left, right, ..
+            } => format!(
+                "{} {} {}",
+                left.display_operand(op.precedence()),
+                op.symbol(),
+                right.display_operand(op.precedence() + 1)
+            ),
+            Val::Ternary {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => format!(
+                "{} ? {} : {}",
+                condition.display_operand(PREC_TERNARY + 1),
+                then.display_operand(PREC_TERNARY),
+                otherwise.display_operand(PREC_TERNARY)
+            ),
             Val::Null => "null".to_string(),
         };
         write!(f, "{}", str)
@@ -79,61 +411,192 @@ impl Display for Val {
 #[serde(rename_all = "snake_case", tag = "type")]
 #[ts(export, export_to = "parser.ts")]
 pub enum Token {
-    Raw { text: String },
-    Tag { val: Val },
+    Raw {
+        text: String,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
+    Tag {
+        val: Val,
+        #[serde(default, skip_serializing_if = "Span::is_empty")]
+        span: Span,
+    },
     Eof,
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
-            Token::Raw { text } => text.to_string(),
-            Token::Tag { val } => format!("${{[ {} ]}}", val.to_string()),
+            Token::Raw { text, .. } => text.to_string(),
+            Token::Tag { val, .. } => format!("${{[ {} ]}}", val.to_string()),
             Token::Eof => "".to_string(),
         };
         write!(f, "{}", str)
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "parser.ts")]
+pub enum ParseDiagnosticKind {
+    UnterminatedTag,
+    InvalidTag,
+    InvalidArgs,
+    UnterminatedString,
+    UnknownEscape,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "parser.ts")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// A problem found while parsing. Errors are for tags that couldn't be parsed (and so were kept
+/// as raw text), and warnings are for tags that parsed but probably don't do what was intended.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "parser.ts")]
+pub struct ParseDiagnostic {
+    pub kind: ParseDiagnosticKind,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub span: Span,
+}
+
 fn transform_val<T: TemplateCallback>(val: &Val, cb: &T) -> Result<Val> {
     let val = match val {
         Val::Fn {
             name: fn_name,
             args,
+            span,
+        } => Val::Fn {
+            name: fn_name.clone(),
+            args: transform_fn_args(fn_name, args, cb)?,
+            span: *span,
+        },
+        Val::Pipe {
+            value,
+            stages,
+            span,
         } => {
-            let mut new_args: Vec<FnArg> = Vec::new();
-            for arg in args {
-                let value = match arg.clone().value {
-                    Val::Str { text } => {
-                        let text = cb.transform_arg(&fn_name, &arg.name, &text)?;
-                        Val::Str { text }
-                    }
-                    v => transform_val(&v, cb)?,
-                };
-
-                let arg_name = arg.name.clone();
-                new_args.push(FnArg {
-                    name: arg_name,
-                    value,
+            let mut new_stages = Vec::new();
+            for stage in stages {
+                new_stages.push(PipeStage {
+                    name: stage.name.clone(),
+                    args: transform_fn_args(&stage.name, &stage.args, cb)?,
+                    span: stage.span,
                 });
             }
-            Val::Fn {
-                name: fn_name.clone(),
-                args: new_args,
+            Val::Pipe {
+                value: Box::new(transform_val(value, cb)?),
+                stages: new_stages,
+                span: *span,
+            }
+        }
+        Val::Array { items, span } => Val::Array {
+            items: items.iter().map(|v| transform_val(v, cb)).collect::<Result<Vec<Val>>>()?,
+            span: *span,
+        },
+        Val::Object { entries, span } => {
+            let mut new_entries = Vec::new();
+            for entry in entries {
+                new_entries.push(ObjectEntry {
+                    key: entry.key.clone(),
+                    value: transform_val(&entry.value, cb)?,
+                    span: entry.span,
+                });
+            }
+            Val::Object {
+                entries: new_entries,
+                span: *span,
             }
         }
+        Val::Unary { op, value, span } => Val::Unary {
+            op: *op,
+            value: Box::new(transform_val(value, cb)?),
+            span: *span,
+        },
+        Val::Binary {
+            op,
+            left,
+            right,
+            span,
+        } => Val::Binary {
+            op: *op,
+            left: Box::new(transform_val(left, cb)?),
+            right: Box::new(transform_val(right, cb)?),
+            span: *span,
+        },
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            span,
+        } => Val::Ternary {
+            condition: Box::new(transform_val(condition, cb)?),
+            then: Box::new(transform_val(then, cb)?),
+            otherwise: Box::new(transform_val(otherwise, cb)?),
+            span: *span,
+        },
         _ => val.clone(),
     };
     Ok(val)
 }
 
+fn transform_fn_args<T: TemplateCallback>(
+    fn_name: &str,
+    args: &[FnArg],
+    cb: &T,
+) -> Result<Vec<FnArg>> {
+    let mut new_args: Vec<FnArg> = Vec::new();
+    for arg in args {
+        let value = transform_arg_val(fn_name, &arg.name, &arg.value, cb)?;
+
+        let arg_name = arg.name.clone();
+        new_args.push(FnArg {
+            name: arg_name,
+            value,
+            span: arg.span,
+        });
+    }
+    Ok(new_args)
+}
+
+fn transform_arg_val<T: TemplateCallback>(
+    fn_name: &str,
+    arg_name: &str,
+    val: &Val,
+    cb: &T,
+) -> Result<Val> {
+    let val = match val {
+        Val::Str { text, span } => Val::Str {
+            text: cb.transform_arg(fn_name, arg_name, text)?,
+            span: *span,
+        },
+        // Both sides of a fallback may end up being the arg's value
+        Val::Coalesce {
+            value,
+            fallback,
+            span,
+        } => Val::Coalesce {
+            value: Box::new(transform_arg_val(fn_name, arg_name, value, cb)?),
+            fallback: Box::new(transform_arg_val(fn_name, arg_name, fallback, cb)?),
+            span: *span,
+        },
+        v => transform_val(v, cb)?,
+    };
+    Ok(val)
+}
+
 pub fn transform_args<T: TemplateCallback>(tokens: Tokens, cb: &T) -> Result<Tokens> {
     let mut new_tokens = Tokens::default();
     for t in tokens.tokens.iter() {
         new_tokens.tokens.push(match t {
-            Token::Tag { val } => {
+            Token::Tag { val, span } => {
                 let val = transform_val(val, cb)?;
-                Token::Tag { val }
+                Token::Tag { val, span: *span }
             }
             _ => t.clone(),
         });
@@ -148,20 +611,37 @@ pub fn transform_args<T: TemplateCallback>(tokens: Tokens, cb: &T) -> Result<Tok
 //  ${[ my_fn() ]}
 //  ${[ my_fn(my_var) ]}
 //  ${[ my_fn(my_var, "A String") ]}
+//  ${[ my_var ?? 'fallback' ]}
+//  ${[ my_var? ]}
+//  ${[ my_var | my_fn | my_other_fn(a='b') ]}
+//  ${[ my_fn(count=-1.5e3, list=[1, 'two'], obj={a: my_var, 'b c': true}) ]}
+//  ${[ page + 1 ]}
+//  ${[ is_prod && !debug ? 'https' : 'http' ]}
 
 // default
 #[derive(Default)]
 pub struct Parser {
     tokens: Vec<Token>,
     chars: Vec<char>,
+    // UTF-16 offset of each char, plus the end of the template, for converting positions to spans
+    utf16_offsets: Vec<usize>,
     pos: usize,
     curr_text: String,
+    curr_text_start: usize,
+    diagnostics: Vec<ParseDiagnostic>,
+    // Parsing backtracks through alternatives, so the error that got furthest into the current
+    // tag is kept as the most specific one. Warnings are only kept if the tag ends up parsing.
+    tag_error: Option<ParseDiagnostic>,
+    tag_warnings: Vec<ParseDiagnostic>,
 }
 
 impl Parser {
     pub fn new(text: &str) -> Parser {
+        let chars: Vec<char> = text.chars().collect();
+        let utf16_offsets = utf16_offsets(&chars);
         Parser {
-            chars: text.chars().collect(),
+            chars,
+            utf16_offsets,
             ..Parser::default()
         }
     }
@@ -177,10 +657,16 @@ impl Parser {
                 // Unescaped template syntax so we treat it as a string
                 self.curr_text += "${[";
             } else if self.match_str("${[") {
+                let tag_start = self.pos - 3;
                 let start_curr = self.pos;
-                if let Some(t) = self.parse_tag()? {
-                    self.push_token(t);
+                self.tag_error = None;
+                self.tag_warnings.clear();
+                if let Some(t) = self.parse_tag(tag_start)? {
+                    self.diagnostics.append(&mut self.tag_warnings);
+                    self.push_token(t, tag_start);
                 } else {
+                    let diagnostic = self.invalid_tag_diagnostic(tag_start);
+                    self.diagnostics.push(diagnostic);
                     self.pos = start_curr;
                     self.curr_text += "${[";
                 }
@@ -194,30 +680,50 @@ impl Parser {
             }
         }
 
-        self.push_token(Token::Eof);
+        self.push_token(Token::Eof, self.pos);
         Ok(Tokens {
             tokens: self.tokens.clone(),
         })
     }
 
-    fn parse_tag(&mut self) -> Result<Option<Token>> {
+    /// Parse the template, also returning diagnostics for the problems found along the way.
+    /// Tags with errors are still returned as raw text, the same as [`Parser::parse`] does.
+    pub fn parse_with_diagnostics(&mut self) -> Result<(Tokens, Vec<ParseDiagnostic>)> {
+        let tokens = self.parse()?;
+        Ok((tokens, self.diagnostics.clone()))
+    }
+
+    fn parse_tag(&mut self, tag_start: usize) -> Result<Option<Token>> {
         // Parse up to first identifier
         //    ${[ my_var...
         self.skip_whitespace();
 
-        let val = match self.parse_value()? {
+        let val = match self.parse_pipe()? {
             Some(v) => v,
-            None => return Ok(None),
+            None => {
+                let span = self.point_span();
+                self.fail(
+                    ParseDiagnosticKind::InvalidTag,
+                    "Expected a variable, function, or value",
+                    span,
+                );
+                return Ok(None);
+            }
         };
 
         // Parse to closing tag
         //    ${[ my_var(a, b, c) ]}
         self.skip_whitespace();
         if !self.match_str("]}") {
+            let span = self.point_span();
+            self.fail(ParseDiagnosticKind::InvalidTag, "Expected ]} to close the tag", span);
             return Ok(None);
         }
 
-        Ok(Some(Token::Tag { val }))
+        Ok(Some(Token::Tag {
+            val,
+            span: self.span_from(tag_start),
+        }))
     }
 
     #[allow(dead_code)]
@@ -231,145 +737,656 @@ impl Parser {
         );
     }
 
-    fn parse_value(&mut self) -> Result<Option<Val>> {
-        let v = if let Some((name, args)) = self.parse_fn()? {
-            Some(Val::Fn { name, args })
-        } else if let Some(v) = self.parse_string()? {
-            Some(Val::Str { text: v })
-        } else if let Some(v) = self.parse_ident() {
-            if v == "null" {
-                Some(Val::Null)
-            } else if v == "true" {
-                Some(Val::Bool { value: true })
-            } else if v == "false" {
-                Some(Val::Bool { value: false })
-            } else {
-                Some(Val::Var { name: v })
-            }
-        } else {
-            None
+    /// Parse a value, along with any `| fn` stages after it
+    fn parse_pipe(&mut self) -> Result<Option<Val>> {
+        let start_pos = self.pos;
+        let value = match self.parse_expr()? {
+            Some(v) => v,
+            None => return Ok(None),
         };
 
-        Ok(v)
+        let mut stages = Vec::new();
+        loop {
+            let end_pos = self.pos;
+            self.skip_whitespace();
+            if self.peek_str("||") || !self.match_str("|") {
+                self.pos = end_pos;
+                break;
+            }
+
+            self.skip_whitespace();
+            match self.parse_pipe_stage()? {
+                Some(stage) => stages.push(stage),
+                None => {
+                    self.pos = end_pos;
+                    break;
+                }
+            }
+        }
+
+        if stages.is_empty() {
+            return Ok(Some(value));
+        }
+
+        Ok(Some(Val::Pipe {
+            value: Box::new(value),
+            stages,
+            span: self.span_from(start_pos),
+        }))
     }
 
-    fn parse_fn(&mut self) -> Result<Option<(String, Vec<FnArg>)>> {
+    fn parse_pipe_stage(&mut self) -> Result<Option<PipeStage>> {
         let start_pos = self.pos;
-
         let name = match self.parse_fn_name() {
-            Some(v) => v,
+            Some(name) => name,
             None => {
-                self.pos = start_pos;
+                let span = self.point_span();
+                self.fail(
+                    ParseDiagnosticKind::InvalidTag,
+                    "Expected a function name after |",
+                    span,
+                );
                 return Ok(None);
             }
         };
 
-        let args = match self.parse_fn_args()? {
-            Some(args) => args,
-            None => {
-                self.pos = start_pos;
-                return Ok(None);
+        // Args are optional for pipe stages, since the first one comes from the pipe
+        let args = if self.peek_str("(") {
+            match self.parse_fn_args()? {
+                Some(args) => args,
+                None => {
+                    self.pos = start_pos;
+                    return Ok(None);
+                }
             }
+        } else {
+            Vec::new()
         };
 
-        Ok(Some((name, args)))
+        Ok(Some(PipeStage {
+            name,
+            args,
+            span: self.span_from(start_pos),
+        }))
     }
 
-    fn parse_fn_args(&mut self) -> Result<Option<Vec<FnArg>>> {
-        if !self.match_str("(") {
-            return Ok(None);
-        }
-
+    /// Parse an expression, which is a ternary or anything that binds tighter than one
+    fn parse_expr(&mut self) -> Result<Option<Val>> {
         let start_pos = self.pos;
+        let condition = match self.parse_coalesce()? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
 
-        let mut args: Vec<FnArg> = Vec::new();
-
-        // Fn closed immediately
+        let end_pos = self.pos;
         self.skip_whitespace();
-        if self.match_str(")") {
-            return Ok(Some(args));
+        if self.peek_str("??") || !self.match_str("?") {
+            self.pos = end_pos;
+            return Ok(Some(condition));
         }
 
-        while self.pos < self.chars.len() {
-            self.skip_whitespace();
+        self.skip_whitespace();
+        let then = match self.parse_expr()? {
+            Some(v) => v,
+            None => {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected a value after ?", span);
+                self.pos = end_pos;
+                return Ok(Some(condition));
+            }
+        };
 
-            let name = self.parse_ident();
-            self.skip_whitespace();
-            self.match_str("=");
-            self.skip_whitespace();
-            let value = self.parse_value()?;
-            self.skip_whitespace();
+        self.skip_whitespace();
+        if !self.match_str(":") {
+            let span = self.point_span();
+            self.fail(ParseDiagnosticKind::InvalidTag, "Expected : after the value for ?", span);
+            self.pos = end_pos;
+            return Ok(Some(condition));
+        }
 
-            if let (Some(name), Some(value)) = (name.clone(), value.clone()) {
-                args.push(FnArg { name, value });
-            } else {
-                // Didn't find valid thing, so return
-                self.pos = start_pos;
-                return Ok(None);
+        self.skip_whitespace();
+        let otherwise = match self.parse_expr()? {
+            Some(v) => v,
+            None => {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected a value after :", span);
+                self.pos = end_pos;
+                return Ok(Some(condition));
             }
+        };
 
-            if self.match_str(")") {
-                break;
-            }
+        Ok(Some(Val::Ternary {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise: Box::new(otherwise),
+            span: self.span_from(start_pos),
+        }))
+    }
 
-            self.skip_whitespace();
+    /// Parse a value, along with any `??` fallbacks after it
+    fn parse_coalesce(&mut self) -> Result<Option<Val>> {
+        let start_pos = self.pos;
+        let value = match self.parse_binary(PREC_OR)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
 
-            // If we don't find a comma, that's bad
-            if !args.is_empty() && !self.match_str(",") {
-                self.pos = start_pos;
-                return Ok(None);
-            }
+        let end_pos = self.pos;
+        self.skip_whitespace();
+        if !self.match_str("??") {
+            self.pos = end_pos;
+            return Ok(Some(value));
+        }
 
-            if start_pos == self.pos {
-                panic!("Parser stuck!");
+        self.skip_whitespace();
+        let fallback = match self.parse_coalesce()? {
+            Some(v) => v,
+            None => {
+                let span = self.point_span();
+                self.fail(
+                    ParseDiagnosticKind::InvalidTag,
+                    "Expected a fallback value after ??",
+                    span,
+                );
+                self.pos = end_pos;
+                return Ok(Some(value));
             }
-        }
+        };
 
-        Ok(Some(args))
+        Ok(Some(Val::Coalesce {
+            value: Box::new(value),
+            fallback: Box::new(fallback),
+            span: self.span_from(start_pos),
+        }))
     }
 
-    fn parse_ident(&mut self) -> Option<String> {
+    /// Parse binary operators that bind at least as tightly as `min_precedence`. Operators of the
+    /// same precedence are left-associative (`a - b - c` is `(a - b) - c`).
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Option<Val>> {
         let start_pos = self.pos;
+        let mut left = match self.parse_unary()? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
 
-        let mut text = String::new();
-        while self.pos < self.chars.len() {
-            let ch = self.peek_char();
-            let is_valid = if start_pos == self.pos {
-                ch.is_alphabetic() || ch == '_' // First is more restrictive
-            } else {
-                ch.is_alphanumeric() || ch == '_' || ch == '-'
+        loop {
+            let end_pos = self.pos;
+            self.skip_whitespace();
+            let op = match self.parse_binary_op() {
+                Some(op) if op.precedence() >= min_precedence => op,
+                _ => {
+                    self.pos = end_pos;
+                    break;
+                }
             };
-            if is_valid {
-                text.push(ch);
-                self.pos += 1;
-            } else {
-                break;
-            }
 
-            if start_pos == self.pos {
-                panic!("Parser stuck!");
-            }
-        }
+            self.skip_whitespace();
+            let right = match self.parse_binary(op.precedence() + 1)? {
+                Some(v) => v,
+                None => {
+                    let span = self.point_span();
+                    let message = format!("Expected a value after {}", op.symbol());
+                    self.fail(ParseDiagnosticKind::InvalidTag, message, span);
+                    self.pos = end_pos;
+                    break;
+                }
+            };
 
-        if text.is_empty() {
-            self.pos = start_pos;
-            return None;
+            left = Val::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(start_pos),
+            };
         }
 
-        Some(text)
+        Ok(Some(left))
     }
 
-    fn parse_fn_name(&mut self) -> Option<String> {
+    fn parse_binary_op(&mut self) -> Option<BinaryOp> {
+        // Longer operators first, so `<=` isn't parsed as `<`. A single `|` is a pipe, and a single
+        // `?` is a ternary, so neither are here.
+        let ops = [
+            ("||", BinaryOp::Or),
+            ("&&", BinaryOp::And),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+            ("+", BinaryOp::Add),
+            ("-", BinaryOp::Sub),
+            ("*", BinaryOp::Mul),
+            ("/", BinaryOp::Div),
+            ("%", BinaryOp::Rem),
+        ];
+        ops.into_iter().find(|(symbol, _)| self.match_str(symbol)).map(|(_, op)| op)
+    }
+
+    /// Parse a value, along with any `!` or `-` in front of it
+    fn parse_unary(&mut self) -> Result<Option<Val>> {
         let start_pos = self.pos;
+        let next_is_digit = self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit());
+        let op = if self.match_str("!") {
+            UnaryOp::Not
+        } else if !next_is_digit && self.match_str("-") {
+            // A - followed by a digit is a negative number instead
+            UnaryOp::Neg
+        } else {
+            return match self.parse_group()? {
+                Some(v) => Ok(Some(v)),
+                None => self.parse_value(),
+            };
+        };
 
-        let mut text = String::new();
-        while self.pos < self.chars.len() {
-            let ch = self.peek_char();
-            if ch.is_alphanumeric() || ch == '_' || ch == '.' {
-                text.push(ch);
-                self.pos += 1;
-            } else {
-                break;
+        self.skip_whitespace();
+        let value = match self.parse_unary()? {
+            Some(v) => v,
+            None => {
+                let span = self.point_span();
+                let message = format!("Expected a value after {}", op.symbol());
+                self.fail(ParseDiagnosticKind::InvalidTag, message, span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(Val::Unary {
+            op,
+            value: Box::new(value),
+            span: self.span_from(start_pos),
+        }))
+    }
+
+    /// Parse an expression wrapped in parentheses, like `(a + b) * c`
+    fn parse_group(&mut self) -> Result<Option<Val>> {
+        let start_pos = self.pos;
+        if !self.match_str("(") {
+            return Ok(None);
+        }
+
+        self.skip_whitespace();
+        let value = match self.parse_pipe()? {
+            Some(v) => v,
+            None => {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected a value after (", span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+        };
+
+        self.skip_whitespace();
+        if !self.match_str(")") {
+            let span = self.point_span();
+            self.fail(ParseDiagnosticKind::InvalidTag, "Expected ) to close the group", span);
+            self.pos = start_pos;
+            return Ok(None);
+        }
+
+        Ok(Some(value))
+    }
+
+    fn parse_value(&mut self) -> Result<Option<Val>> {
+        let start_pos = self.pos;
+        let v = if let Some((name, args)) = self.parse_fn()? {
+            Some(Val::Fn {
+                name,
+                args,
+                span: self.span_from(start_pos),
+            })
+        } else if let Some(v) = self.parse_string()? {
+            Some(Val::Str {
+                text: v,
+                span: self.span_from(start_pos),
+            })
+        } else if let Some(v) = self.parse_number() {
+            Some(Val::Num {
+                value: v,
+                span: self.span_from(start_pos),
+            })
+        } else if let Some(items) = self.parse_array()? {
+            Some(Val::Array {
+                items,
+                span: self.span_from(start_pos),
+            })
+        } else if let Some(entries) = self.parse_object()? {
+            Some(Val::Object {
+                entries,
+                span: self.span_from(start_pos),
+            })
+        } else if let Some(v) = self.parse_ident() {
+            let span = self.span_from(start_pos);
+            if v == "null" {
+                Some(Val::Null)
+            } else if v == "true" {
+                Some(Val::Bool { value: true, span })
+            } else if v == "false" {
+                Some(Val::Bool { value: false, span })
+            } else {
+                let name = self.parse_var_namespace(v);
+                // A single trailing ? marks the variable optional, but ?? is a fallback
+                let optional = !self.peek_str("??") && self.match_str("?");
+                Some(Val::Var {
+                    name,
+                    optional,
+                    span: self.span_from(start_pos),
+                })
+            }
+        } else {
+            None
+        };
+
+        Ok(v)
+    }
+
+    /// Parse a JSON-style number (`-12.5e3`)
+    fn parse_number(&mut self) -> Option<f64> {
+        let start_pos = self.pos;
+        let mut text = String::new();
+        if self.match_str("-") {
+            text.push('-');
+        }
+        if !self.take_digits(&mut text) {
+            self.pos = start_pos;
+            return None;
+        }
+
+        let before_fraction = self.pos;
+        if self.match_str(".") {
+            text.push('.');
+            if !self.take_digits(&mut text) {
+                self.pos = before_fraction;
+                text.pop();
+            }
+        }
+
+        let before_exponent = self.pos;
+        if self.match_str("e") || self.match_str("E") {
+            let mut exponent = String::from("e");
+            if self.match_str("-") {
+                exponent.push('-');
+            } else {
+                self.match_str("+");
+            }
+            match self.take_digits(&mut exponent) {
+                true => text.push_str(&exponent),
+                false => self.pos = before_exponent,
+            }
+        }
+
+        match text.parse::<f64>() {
+            Ok(v) => Some(v),
+            Err(_) => {
+                self.pos = start_pos;
+                None
+            }
+        }
+    }
+
+    fn take_digits(&mut self, text: &mut String) -> bool {
+        let start_pos = self.pos;
+        while self.pos < self.chars.len() && self.peek_char().is_ascii_digit() {
+            text.push(self.next_char());
+        }
+        self.pos > start_pos
+    }
+
+    fn parse_array(&mut self) -> Result<Option<Vec<Val>>> {
+        let start_pos = self.pos;
+        if !self.match_str("[") {
+            return Ok(None);
+        }
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.match_str("]") {
+                return Ok(Some(items));
+            }
+
+            match self.parse_pipe()? {
+                Some(v) => items.push(v),
+                None => {
+                    let span = self.point_span();
+                    self.fail(ParseDiagnosticKind::InvalidTag, "Expected a value or ]", span);
+                    self.pos = start_pos;
+                    return Ok(None);
+                }
+            }
+
+            self.skip_whitespace();
+            if !self.match_str(",") && !self.peek_str("]") {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected , or ] after value", span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Option<Vec<ObjectEntry>>> {
+        let start_pos = self.pos;
+        if !self.match_str("{") {
+            return Ok(None);
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.match_str("}") {
+                return Ok(Some(entries));
+            }
+
+            let entry_start = self.pos;
+            let key = match self.parse_string()? {
+                Some(key) => key,
+                None => match self.parse_ident() {
+                    Some(key) => key,
+                    None => {
+                        let span = self.point_span();
+                        self.fail(ParseDiagnosticKind::InvalidTag, "Expected a key or }", span);
+                        self.pos = start_pos;
+                        return Ok(None);
+                    }
+                },
+            };
+
+            self.skip_whitespace();
+            if !self.match_str(":") {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected : after key", span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+
+            self.skip_whitespace();
+            let value = match self.parse_pipe()? {
+                Some(v) => v,
+                None => {
+                    let span = self.point_span();
+                    let message = format!("Expected a value for key {key}");
+                    self.fail(ParseDiagnosticKind::InvalidTag, message, span);
+                    self.pos = start_pos;
+                    return Ok(None);
+                }
+            };
+            entries.push(ObjectEntry {
+                key,
+                value,
+                span: self.span_from(entry_start),
+            });
+
+            self.skip_whitespace();
+            if !self.match_str(",") && !self.peek_str("}") {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidTag, "Expected , or } after value", span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+        }
+    }
+
+    fn parse_fn(&mut self) -> Result<Option<(String, Vec<FnArg>)>> {
+        let start_pos = self.pos;
+        let start_warnings = self.tag_warnings.len();
+
+        let name = match self.parse_fn_name() {
+            Some(v) => v,
+            None => {
+                self.pos = start_pos;
+                return Ok(None);
+            }
+        };
+
+        let args = match self.parse_fn_args()? {
+            Some(args) => args,
+            None => {
+                self.pos = start_pos;
+                self.tag_warnings.truncate(start_warnings);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some((name, args)))
+    }
+
+    fn parse_fn_args(&mut self) -> Result<Option<Vec<FnArg>>> {
+        if !self.match_str("(") {
+            return Ok(None);
+        }
+
+        let start_pos = self.pos;
+
+        let mut args: Vec<FnArg> = Vec::new();
+
+        // Fn closed immediately
+        self.skip_whitespace();
+        if self.match_str(")") {
+            return Ok(Some(args));
+        }
+
+        while self.pos < self.chars.len() {
+            self.skip_whitespace();
+
+            let arg_start = self.pos;
+            let name = self.parse_ident();
+            self.skip_whitespace();
+            self.match_str("=");
+            self.skip_whitespace();
+            let value_start = self.pos;
+            let value = self.parse_pipe()?;
+            let arg_span = self.span_from(arg_start);
+            self.skip_whitespace();
+
+            match (name, value) {
+                (Some(name), Some(value)) => {
+                    args.push(FnArg {
+                        name,
+                        value,
+                        span: arg_span,
+                    });
+                }
+                (None, _) => {
+                    let span = self.point_span_at(arg_start);
+                    self.fail(ParseDiagnosticKind::InvalidArgs, "Expected an argument name", span);
+                    self.pos = start_pos;
+                    return Ok(None);
+                }
+                (Some(name), None) => {
+                    let span = self.point_span_at(value_start);
+                    let message = format!("Expected a value for argument {name}");
+                    self.fail(ParseDiagnosticKind::InvalidArgs, message, span);
+                    self.pos = start_pos;
+                    return Ok(None);
+                }
+            }
+
+            if self.match_str(")") {
+                break;
+            }
+
+            self.skip_whitespace();
+
+            // If we don't find a comma, that's bad
+            if !args.is_empty() && !self.match_str(",") {
+                let span = self.point_span();
+                self.fail(ParseDiagnosticKind::InvalidArgs, "Expected , or ) after argument", span);
+                self.pos = start_pos;
+                return Ok(None);
+            }
+
+            if start_pos == self.pos {
+                panic!("Parser stuck!");
+            }
+        }
+
+        Ok(Some(args))
+    }
+
+    fn parse_ident(&mut self) -> Option<String> {
+        let start_pos = self.pos;
+
+        let mut text = String::new();
+        while self.pos < self.chars.len() {
+            let ch = self.peek_char();
+            let is_valid = if start_pos == self.pos {
+                ch.is_alphabetic() || ch == '_' // First is more restrictive
+            } else {
+                ch.is_alphanumeric() || ch == '_' || ch == '-'
+            };
+            if is_valid {
+                text.push(ch);
+                self.pos += 1;
+            } else {
+                break;
+            }
+
+            if start_pos == self.pos {
+                panic!("Parser stuck!");
+            }
+        }
+
+        if text.is_empty() {
+            self.pos = start_pos;
+            return None;
+        }
+
+        Some(text)
+    }
+
+    /// Continue a variable name with namespaced segments (`env.HOME`)
+    fn parse_var_namespace(&mut self, mut name: String) -> String {
+        loop {
+            let start_pos = self.pos;
+            if !self.match_str(".") {
+                return name;
+            }
+            match self.parse_ident() {
+                Some(segment) => {
+                    name.push('.');
+                    name.push_str(&segment);
+                }
+                None => {
+                    self.pos = start_pos;
+                    return name;
+                }
+            }
+        }
+    }
+
+    fn parse_fn_name(&mut self) -> Option<String> {
+        let start_pos = self.pos;
+
+        let mut text = String::new();
+        while self.pos < self.chars.len() {
+            let ch = self.peek_char();
+            if ch.is_alphanumeric() || ch == '_' || ch == '.' {
+                text.push(ch);
+                self.pos += 1;
+            } else {
+                break;
             }
 
             if start_pos == self.pos {
@@ -403,7 +1420,16 @@ impl Parser {
             let ch = self.next_char();
             match ch {
                 '\\' => {
-                    text.push(self.next_char());
+                    if self.pos >= self.chars.len() {
+                        break; // Trailing backslash, so the string was never closed
+                    }
+                    let escaped = self.next_char();
+                    if escaped != '\'' && escaped != '\\' {
+                        let span = self.span(self.pos - 2, self.pos);
+                        let message = format!(r#"Unknown escape sequence \{escaped}"#);
+                        self.warn(ParseDiagnosticKind::UnknownEscape, message, span);
+                    }
+                    text.push(escaped);
                 }
                 '\'' => {
                     found_closing = true;
@@ -420,6 +1446,8 @@ impl Parser {
         }
 
         if !found_closing {
+            let span = self.span(start_pos, self.chars.len());
+            self.fail(ParseDiagnosticKind::UnterminatedString, "Unterminated string", span);
             self.pos = start_pos;
             return Ok(None);
         }
@@ -460,17 +1488,19 @@ impl Parser {
         ch
     }
 
-    fn push_token(&mut self, token: Token) {
+    fn push_token(&mut self, token: Token, token_start: usize) {
         // Push any text we've accumulated
         if !self.curr_text.is_empty() {
             let text_token = Token::Raw {
                 text: self.curr_text.clone(),
+                span: self.span(self.curr_text_start, token_start),
             };
             self.tokens.push(text_token);
             self.curr_text.clear();
         }
 
         self.tokens.push(token);
+        self.curr_text_start = self.pos;
     }
 
     fn match_str(&mut self, value: &str) -> bool {
@@ -488,6 +1518,75 @@ impl Parser {
             false
         }
     }
+
+    fn peek_str(&self, value: &str) -> bool {
+        self.chars
+            .get(self.pos..self.pos + value.len())
+            .is_some_and(|c| c.iter().copied().eq(value.chars()))
+    }
+
+    /// Span between two char positions
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.utf16_offsets[start], self.utf16_offsets[end])
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        self.span(start, self.pos)
+    }
+
+    fn point_span(&self) -> Span {
+        self.point_span_at(self.pos)
+    }
+
+    /// Span of a single char, or an empty span at the end of the template
+    fn point_span_at(&self, pos: usize) -> Span {
+        self.span(pos, (pos + 1).min(self.chars.len()))
+    }
+
+    fn fail(&mut self, kind: ParseDiagnosticKind, message: impl Into<String>, span: Span) {
+        if self.tag_error.as_ref().is_some_and(|e| e.span.start >= span.start) {
+            return;
+        }
+
+        self.tag_error = Some(ParseDiagnostic {
+            kind,
+            severity: DiagnosticSeverity::Error,
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn warn(&mut self, kind: ParseDiagnosticKind, message: impl Into<String>, span: Span) {
+        self.tag_warnings.push(ParseDiagnostic {
+            kind,
+            severity: DiagnosticSeverity::Warning,
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn invalid_tag_diagnostic(&mut self, tag_start: usize) -> ParseDiagnostic {
+        // The tag is only closed by a ]} that comes before the next tag starts
+        let rest = &self.chars[tag_start + 3..];
+        let next_tag = rest.windows(3).position(|w| w == ['$', '{', '[']).unwrap_or(rest.len());
+        let closed = rest[..next_tag].windows(2).any(|w| w == [']', '}']);
+        let tag_error = self.tag_error.take();
+        match (closed, tag_error) {
+            (false, _) => ParseDiagnostic {
+                kind: ParseDiagnosticKind::UnterminatedTag,
+                severity: DiagnosticSeverity::Error,
+                message: "Unterminated tag, expected ]}".to_string(),
+                span: self.span(tag_start, tag_start + 3 + next_tag),
+            },
+            (true, Some(e)) => e,
+            (true, None) => ParseDiagnostic {
+                kind: ParseDiagnosticKind::InvalidTag,
+                severity: DiagnosticSeverity::Error,
+                message: "Invalid tag".to_string(),
+                span: self.span(tag_start, self.pos),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -503,7 +1602,8 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Raw {
-                    text: "${[ foo ]}".to_string()
+                    text: "${[ foo ]}".to_string(),
+                    span: Span::new(0, 11),
                 },
                 Token::Eof
             ]
@@ -518,10 +1618,16 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Raw {
-                    text: r#"\\"#.to_string()
+                    text: r#"\\"#.to_string(),
+                    span: Span::new(0, 2),
                 },
                 Token::Tag {
-                    val: Val::Var { name: "foo".into() }
+                    val: Val::Var {
+                        name: "foo".into(),
+                        optional: false,
+                        span: Span::new(6, 9),
+                    },
+                    span: Span::new(2, 12),
                 },
                 Token::Eof
             ]
@@ -536,8 +1642,13 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Tag {
-                    val: Val::Var { name: "foo".into() }
-                },
+                    val: Val::Var {
+                        name: "foo".into(),
+                        optional: false,
+                        span: Span::new(4, 7),
+                    },
+                    span: Span::new(0, 10),
+                },
                 Token::Eof
             ]
         );
@@ -551,7 +1662,12 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Tag {
-                    val: Val::Var { name: "a-b".into() }
+                    val: Val::Var {
+                        name: "a-b".into(),
+                        optional: false,
+                        span: Span::new(4, 7),
+                    },
+                    span: Span::new(0, 10),
                 },
                 Token::Eof
             ]
@@ -567,7 +1683,58 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Tag {
-                    val: Val::Var { name: "a_b".into() }
+                    val: Val::Var {
+                        name: "a_b".into(),
+                        optional: false,
+                        span: Span::new(4, 7),
+                    },
+                    span: Span::new(0, 10),
+                },
+                Token::Eof
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn var_namespaced() -> Result<()> {
+        let mut p = Parser::new("${[ env.HOME ]}${[ env.a.b? ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Var {
+                        name: "env.HOME".into(),
+                        optional: false,
+                        span: Span::new(4, 12),
+                    },
+                    span: Span::new(0, 15),
+                },
+                Token::Tag {
+                    val: Val::Var {
+                        name: "env.a.b".into(),
+                        optional: true,
+                        span: Span::new(19, 27),
+                    },
+                    span: Span::new(15, 30),
+                },
+                Token::Eof
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn var_namespace_incomplete() -> Result<()> {
+        let mut p = Parser::new("${[ env. ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Raw {
+                    text: "${[ env. ]}".into(),
+                    span: Span::new(0, 11),
                 },
                 Token::Eof
             ]
@@ -578,13 +1745,14 @@ mod tests {
 
     #[test]
     fn var_prefixes() -> Result<()> {
-        let mut p = Parser::new("${[ -a ]}${[ 0a ]}");
+        let mut p = Parser::new("${[ 0a ]}");
         assert_eq!(
             p.parse()?.tokens,
             vec![
                 Token::Raw {
-                    // Shouldn't be parsed, because they're invalid
-                    text: "${[ -a ]}${[ 0a ]}".into()
+                    // Shouldn't be parsed, because it's invalid
+                    text: "${[ 0a ]}".into(),
+                    span: Span::new(0, 9),
                 },
                 Token::Eof
             ]
@@ -600,7 +1768,12 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Tag {
-                    val: Val::Var { name: "_a".into() }
+                    val: Val::Var {
+                        name: "_a".into(),
+                        optional: false,
+                        span: Span::new(4, 6),
+                    },
+                    span: Span::new(0, 9),
                 },
                 Token::Eof
             ]
@@ -616,10 +1789,18 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Tag {
-                    val: Val::Bool { value: true },
+                    val: Val::Bool {
+                        value: true,
+                        span: Span::new(4, 8),
+                    },
+                    span: Span::new(0, 11),
                 },
                 Token::Tag {
-                    val: Val::Bool { value: false },
+                    val: Val::Bool {
+                        value: false,
+                        span: Span::new(15, 20),
+                    },
+                    span: Span::new(11, 23),
                 },
                 Token::Eof
             ]
@@ -635,7 +1816,8 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Raw {
-                    text: "${[ foo bar ]}".into()
+                    text: "${[ foo bar ]}".into(),
+                    span: Span::new(0, 14),
                 },
                 Token::Eof
             ]
@@ -652,8 +1834,10 @@ mod tests {
             vec![
                 Token::Tag {
                     val: Val::Str {
-                        text: r#"foo 'bar' baz"#.into()
-                    }
+                        text: r#"foo 'bar' baz"#.into(),
+                        span: Span::new(4, 21),
+                    },
+                    span: Span::new(0, 24),
                 },
                 Token::Eof
             ]
@@ -670,8 +1854,10 @@ mod tests {
             vec![
                 Token::Tag {
                     val: Val::Str {
-                        text: r#"foo 'bar' baz"#.into()
-                    }
+                        text: r#"foo 'bar' baz"#.into(),
+                        span: Span::new(4, 27),
+                    },
+                    span: Span::new(0, 30),
                 },
                 Token::Eof
             ]
@@ -687,13 +1873,20 @@ mod tests {
             p.parse()?.tokens,
             vec![
                 Token::Raw {
-                    text: "Hello ".to_string()
+                    text: "Hello ".to_string(),
+                    span: Span::new(0, 6),
                 },
                 Token::Tag {
-                    val: Val::Var { name: "foo".into() }
+                    val: Val::Var {
+                        name: "foo".into(),
+                        optional: false,
+                        span: Span::new(10, 13),
+                    },
+                    span: Span::new(6, 16),
                 },
                 Token::Raw {
-                    text: "!".to_string()
+                    text: "!".to_string(),
+                    span: Span::new(16, 17),
                 },
                 Token::Eof,
             ]
@@ -712,7 +1905,9 @@ mod tests {
                     val: Val::Fn {
                         name: "foo".into(),
                         args: Vec::new(),
-                    }
+                        span: Span::new(4, 9),
+                    },
+                    span: Span::new(0, 12),
                 },
                 Token::Eof
             ]
@@ -731,7 +1926,9 @@ mod tests {
                     val: Val::Fn {
                         name: "foo.bar.baz".into(),
                         args: Vec::new(),
-                    }
+                        span: Span::new(4, 17),
+                    },
+                    span: Span::new(0, 20),
                 },
                 Token::Eof
             ]
@@ -751,9 +1948,16 @@ mod tests {
                         name: "foo".into(),
                         args: vec![FnArg {
                             name: "a".into(),
-                            value: Val::Var { name: "bar".into() }
+                            value: Val::Var {
+                                name: "bar".into(),
+                                optional: false,
+                                span: Span::new(10, 13),
+                            },
+                            span: Span::new(8, 13),
                         }],
-                    }
+                        span: Span::new(4, 14),
+                    },
+                    span: Span::new(0, 17),
                 },
                 Token::Eof
             ]
@@ -774,18 +1978,35 @@ mod tests {
                         args: vec![
                             FnArg {
                                 name: "a".into(),
-                                value: Val::Var { name: "bar".into() }
+                                value: Val::Var {
+                                    name: "bar".into(),
+                                    optional: false,
+                                    span: Span::new(10, 13),
+                                },
+                                span: Span::new(8, 13),
                             },
                             FnArg {
                                 name: "b".into(),
-                                value: Val::Var { name: "baz".into() }
+                                value: Val::Var {
+                                    name: "baz".into(),
+                                    optional: false,
+                                    span: Span::new(18, 21),
+                                },
+                                span: Span::new(14, 21),
                             },
                             FnArg {
                                 name: "c".into(),
-                                value: Val::Var { name: "qux".into() }
+                                value: Val::Var {
+                                    name: "qux".into(),
+                                    optional: false,
+                                    span: Span::new(26, 29),
+                                },
+                                span: Span::new(23, 29),
                             },
                         ],
-                    }
+                        span: Span::new(4, 31),
+                    },
+                    span: Span::new(0, 34),
                 },
                 Token::Eof
             ]
@@ -806,24 +2027,42 @@ mod tests {
                         args: vec![
                             FnArg {
                                 name: "aaa".into(),
-                                value: Val::Var { name: "bar".into() }
+                                value: Val::Var {
+                                    name: "bar".into(),
+                                    optional: false,
+                                    span: Span::new(12, 15),
+                                },
+                                span: Span::new(8, 15),
                             },
                             FnArg {
                                 name: "bb".into(),
                                 value: Val::Str {
-                                    text: r#"baz 'hi'"#.into()
-                                }
+                                    text: r#"baz 'hi'"#.into(),
+                                    span: Span::new(19, 31),
+                                },
+                                span: Span::new(16, 31),
                             },
                             FnArg {
                                 name: "c".into(),
-                                value: Val::Var { name: "qux".into() }
+                                value: Val::Var {
+                                    name: "qux".into(),
+                                    optional: false,
+                                    span: Span::new(35, 38),
+                                },
+                                span: Span::new(33, 38),
                             },
                             FnArg {
                                 name: "z".into(),
-                                value: Val::Bool { value: true }
+                                value: Val::Bool {
+                                    value: true,
+                                    span: Span::new(42, 46),
+                                },
+                                span: Span::new(40, 46),
                             },
                         ],
-                    }
+                        span: Span::new(4, 48),
+                    },
+                    span: Span::new(0, 51),
                 },
                 Token::Eof
             ]
@@ -846,9 +2085,13 @@ mod tests {
                             value: Val::Fn {
                                 name: "bar".into(),
                                 args: vec![],
-                            }
+                                span: Span::new(10, 15),
+                            },
+                            span: Span::new(8, 15),
                         }],
-                    }
+                        span: Span::new(4, 16),
+                    },
+                    span: Span::new(0, 19),
                 },
                 Token::Eof
             ]
@@ -874,21 +2117,38 @@ mod tests {
                                     args: vec![
                                         FnArg {
                                             name: "a".into(),
-                                            value: Val::Var { name: "foo".into() }
+                                            value: Val::Var {
+                                                name: "foo".into(),
+                                                optional: false,
+                                                span: Span::new(20, 23),
+                                            },
+                                            span: Span::new(18, 23),
                                         },
                                         FnArg {
                                             name: "b".into(),
-                                            value: Val::Str { text: "i".into() },
+                                            value: Val::Str {
+                                                text: "i".into(),
+                                                span: Span::new(27, 30),
+                                            },
+                                            span: Span::new(25, 30),
                                         },
                                     ],
-                                }
+                                    span: Span::new(12, 31),
+                                },
+                                span: Span::new(10, 31),
                             },
                             FnArg {
                                 name: "c".into(),
-                                value: Val::Str { text: "o".into() }
+                                value: Val::Str {
+                                    text: "o".into(),
+                                    span: Span::new(35, 38),
+                                },
+                                span: Span::new(33, 38),
                             },
                         ],
-                    }
+                        span: Span::new(4, 39),
+                    },
+                    span: Span::new(0, 42),
                 },
                 Token::Eof
             ]
@@ -901,7 +2161,9 @@ mod tests {
     fn token_display_var() -> Result<()> {
         assert_eq!(
             Val::Var {
-                name: "foo".to_string()
+                name: "foo".to_string(),
+                optional: false,
+                span: Span::default(),
             }
             .to_string(),
             "foo"
@@ -914,7 +2176,8 @@ mod tests {
     fn token_display_str() -> Result<()> {
         assert_eq!(
             Val::Str {
-                text: "Hello You".to_string()
+                text: "Hello You".to_string(),
+                span: Span::default(),
             }
             .to_string(),
             "'Hello You'"
@@ -927,7 +2190,8 @@ mod tests {
     fn token_display_complex_str() -> Result<()> {
         assert_eq!(
             Val::Str {
-                text: "Hello 'You'".to_string()
+                text: "Hello 'You'".to_string(),
+                span: Span::default(),
             }
             .to_string(),
             "b64'SGVsbG8gJ1lvdSc'"
@@ -945,14 +2209,18 @@ mod tests {
                     FnArg {
                         name: "n".to_string(),
                         value: Null,
+                        span: Span::default(),
                     },
                     FnArg {
                         name: "a".to_string(),
                         value: Val::Str {
-                            text: "aaa".to_string()
-                        }
+                            text: "aaa".to_string(),
+                            span: Span::default(),
+                        },
+                        span: Span::default(),
                     }
-                ]
+                ],
+                span: Span::default(),
             }
             .to_string(),
             r#"fn(a='aaa')"#
@@ -971,17 +2239,24 @@ mod tests {
                         FnArg {
                             name: "arg".to_string(),
                             value: Val::Str {
-                                text: "v 'x'".to_string()
-                            }
+                                text: "v 'x'".to_string(),
+                                span: Span::default(),
+                            },
+                            span: Span::default(),
                         },
                         FnArg {
                             name: "arg2".to_string(),
                             value: Val::Var {
-                                name: "my_var".to_string()
-                            }
+                                name: "my_var".to_string(),
+                                optional: false,
+                                span: Span::default(),
+                            },
+                            span: Span::default(),
                         }
-                    ]
-                }
+                    ],
+                    span: Span::default(),
+                },
+                span: Span::default(),
             }
             .to_string(),
             r#"${[ foo(arg=b64'diAneCc', arg2=my_var) ]}"#
@@ -997,16 +2272,22 @@ mod tests {
                 tokens: vec![
                     Token::Tag {
                         val: Val::Var {
-                            name: "my_var".to_string()
-                        }
+                            name: "my_var".to_string(),
+                            optional: false,
+                            span: Span::default(),
+                        },
+                        span: Span::default(),
                     },
                     Token::Raw {
                         text: " Some cool text ".to_string(),
+                        span: Span::default(),
                     },
                     Token::Tag {
                         val: Val::Str {
-                            text: "Hello World".to_string()
-                        }
+                            text: "Hello World".to_string(),
+                            span: Span::default(),
+                        },
+                        span: Span::default(),
                     }
                 ]
             }
@@ -1016,4 +2297,625 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn spans() -> Result<()> {
+        let template = "Hi ${[ foo(a=bar, b='x') ]}!";
+        let tokens = Parser::new(template).parse()?.tokens;
+
+        let (raw_span, tag_span, val) = match (&tokens[0], &tokens[1]) {
+            (Token::Raw { span: raw_span, .. }, Token::Tag { span, val }) => (raw_span, span, val),
+            _ => panic!("Unexpected tokens {tokens:?}"),
+        };
+        assert_eq!((raw_span.start, raw_span.end), (0, 3));
+        assert_eq!((tag_span.start, tag_span.end), (3, 27));
+        assert_eq!((val.span().start, val.span().end), (7, 24));
+
+        let args = match val {
+            Val::Fn { args, .. } => args,
+            _ => panic!("Expected fn, got {val:?}"),
+        };
+        assert_eq!((args[0].span.start, args[0].span.end), (11, 16));
+        assert_eq!((args[0].value.span().start, args[0].value.span().end), (13, 16));
+        assert_eq!((args[1].span.start, args[1].span.end), (18, 23));
+        assert_eq!(&template[args[1].value.span().byte_range(template)], "'x'");
+
+        match &tokens[2] {
+            Token::Raw { span, .. } => assert_eq!((span.start, span.end), (27, 28)),
+            t => panic!("Expected raw, got {t:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn spans_multibyte() -> Result<()> {
+        let template = "é→${[ foo ]}";
+        let tokens = Parser::new(template).parse()?.tokens;
+        let span = match &tokens[1] {
+            Token::Tag { span, .. } => span,
+            t => panic!("Expected tag, got {t:?}"),
+        };
+        assert_eq!((span.start, span.end), (2, 12));
+        assert_eq!(&template[span.byte_range(template)], "${[ foo ]}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn spans_utf16() -> Result<()> {
+        // Emoji outside the BMP are two UTF-16 code units, like in the editor
+        let template = "😀 ${[ fn(a='😀😀') ]} ${[ b ]}";
+        let tokens = Parser::new(template).parse()?.tokens;
+        let spans = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Tag { span, .. } => Some(*span),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(3, 22), Span::new(23, 31)]);
+        assert_eq!(&template[spans[0].byte_range(template)], "${[ fn(a='😀😀') ]}");
+        assert_eq!(&template[spans[1].byte_range(template)], "${[ b ]}");
+
+        assert_eq!(diagnostics("😀 ${[ foo"), vec![(ParseDiagnosticKind::UnterminatedTag, 3, 10)]);
+
+        Ok(())
+    }
+
+    fn diagnostics(template: &str) -> Vec<(ParseDiagnosticKind, usize, usize)> {
+        let (_, diagnostics) = Parser::new(template).parse_with_diagnostics().unwrap();
+        diagnostics.iter().map(|d| (d.kind, d.span.start, d.span.end)).collect()
+    }
+
+    #[test]
+    fn diagnostics_valid() -> Result<()> {
+        assert_eq!(diagnostics(r#"${[ foo ]} ${[ bar(a='b', c=d()) ]} \${[ nope"#), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_unterminated_tag() -> Result<()> {
+        assert_eq!(diagnostics("Hi ${[ foo"), vec![(ParseDiagnosticKind::UnterminatedTag, 3, 10)]);
+        // A later tag's ]} doesn't close this one
+        assert_eq!(
+            diagnostics("${[ foo ${[ bar ]}"),
+            vec![(ParseDiagnosticKind::UnterminatedTag, 0, 8)]
+        );
+        assert_eq!(
+            diagnostics("${[ foo(a='b')"),
+            vec![(ParseDiagnosticKind::UnterminatedTag, 0, 14)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_invalid_tag() -> Result<()> {
+        assert_eq!(diagnostics("${[ foo bar ]}"), vec![(ParseDiagnosticKind::InvalidTag, 8, 9)]);
+        assert_eq!(diagnostics("${[ - ]}"), vec![(ParseDiagnosticKind::InvalidTag, 6, 7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_invalid_args() -> Result<()> {
+        let (tokens, diagnostics) = Parser::new("${[ foo( ]}").parse_with_diagnostics()?;
+        assert_eq!(
+            tokens.tokens,
+            vec![
+                Token::Raw {
+                    text: "${[ foo( ]}".into(),
+                    span: Span::new(0, 11),
+                },
+                Token::Eof
+            ]
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, ParseDiagnosticKind::InvalidArgs);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].message, "Expected an argument name");
+        assert_eq!((diagnostics[0].span.start, diagnostics[0].span.end), (9, 10));
+
+        assert_eq!(
+            self::diagnostics("${[ foo(a=) ]}"),
+            vec![(ParseDiagnosticKind::InvalidArgs, 10, 11)]
+        );
+        assert_eq!(
+            self::diagnostics("${[ foo(a=b c=d) ]}"),
+            vec![(ParseDiagnosticKind::InvalidArgs, 12, 13)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_unterminated_string() -> Result<()> {
+        assert_eq!(
+            diagnostics("${[ foo(a='b) ]}"),
+            vec![(ParseDiagnosticKind::UnterminatedString, 10, 16)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_unknown_escape() -> Result<()> {
+        let (tokens, diagnostics) = Parser::new(r#"${[ 'a\nb\'c' ]}"#).parse_with_diagnostics()?;
+        assert_eq!(
+            tokens.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Str {
+                        text: "anb'c".into(),
+                        span: Span::new(4, 13),
+                    },
+                    span: Span::new(0, 16),
+                },
+                Token::Eof
+            ]
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, ParseDiagnosticKind::UnknownEscape);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!((diagnostics[0].span.start, diagnostics[0].span.end), (6, 8));
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_backslash_in_string() -> Result<()> {
+        assert_eq!(diagnostics(r#"${[ 'abc\"#), vec![(ParseDiagnosticKind::UnterminatedTag, 0, 9)]);
+        Ok(())
+    }
+
+    #[test]
+    fn var_fallback() -> Result<()> {
+        let mut p = Parser::new("${[ api_host ?? 'localhost:8080' ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Coalesce {
+                        value: Box::new(Val::Var {
+                            name: "api_host".into(),
+                            optional: false,
+                            span: Span::new(4, 12),
+                        }),
+                        fallback: Box::new(Val::Str {
+                            text: "localhost:8080".into(),
+                            span: Span::new(16, 32),
+                        }),
+                        span: Span::new(4, 32),
+                    },
+                    span: Span::new(0, 35),
+                },
+                Token::Eof
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn var_fallback_chain() -> Result<()> {
+        let mut p = Parser::new("${[ a??b ?? fn(x=c ?? true) ]}");
+        let val = match &p.parse()?.tokens[0] {
+            Token::Tag { val, .. } => val.clone(),
+            t => panic!("Expected tag, got {t:?}"),
+        };
+        let var = |name: &str, start: usize| {
+            Box::new(Val::Var {
+                name: name.into(),
+                optional: false,
+                span: Span::new(start, start + 1),
+            })
+        };
+        assert_eq!(
+            val,
+            Val::Coalesce {
+                value: var("a", 4),
+                fallback: Box::new(Val::Coalesce {
+                    value: var("b", 7),
+                    fallback: Box::new(Val::Fn {
+                        name: "fn".into(),
+                        args: vec![FnArg {
+                            name: "x".into(),
+                            value: Val::Coalesce {
+                                value: var("c", 17),
+                                fallback: Box::new(Val::Bool {
+                                    value: true,
+                                    span: Span::new(22, 26),
+                                }),
+                                span: Span::new(17, 26),
+                            },
+                            span: Span::new(15, 26),
+                        }],
+                        span: Span::new(12, 27),
+                    }),
+                    span: Span::new(7, 27),
+                }),
+                span: Span::new(4, 27),
+            }
+        );
+        assert_eq!((val.span().start, val.span().end), (4, 27));
+        Ok(())
+    }
+
+    #[test]
+    fn var_fallback_missing() -> Result<()> {
+        let (tokens, diagnostics) = Parser::new("${[ a ?? ]}").parse_with_diagnostics()?;
+        assert_eq!(
+            tokens.tokens,
+            vec![
+                Token::Raw {
+                    text: "${[ a ?? ]}".into(),
+                    span: Span::new(0, 11),
+                },
+                Token::Eof
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "Expected a fallback value after ??");
+        assert_eq!((diagnostics[0].span.start, diagnostics[0].span.end), (9, 10));
+        Ok(())
+    }
+
+    #[test]
+    fn var_optional() -> Result<()> {
+        let mut p = Parser::new("${[ foo? ]}${[ a-b? ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Var {
+                        name: "foo".into(),
+                        optional: true,
+                        span: Span::new(4, 8),
+                    },
+                    span: Span::new(0, 11),
+                },
+                Token::Tag {
+                    val: Val::Var {
+                        name: "a-b".into(),
+                        optional: true,
+                        span: Span::new(15, 19),
+                    },
+                    span: Span::new(11, 22),
+                },
+                Token::Eof
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_display_round_trip() -> Result<()> {
+        for template in [
+            "${[ a ?? 'b' ]}",
+            "${[ a ?? b ?? 'c' ]}",
+            "${[ a? ]}",
+            "${[ fn(x=a ?? b?) ]}",
+        ] {
+            assert_eq!(Parser::new(template).parse()?.to_string(), template);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pipe() -> Result<()> {
+        let mut p = Parser::new("${[ token | trim|base64.encode | hash(algorithm='md5') ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Pipe {
+                        value: Box::new(Val::Var {
+                            name: "token".into(),
+                            optional: false,
+                            span: Span::new(4, 9),
+                        }),
+                        stages: vec![
+                            PipeStage {
+                                name: "trim".into(),
+                                args: vec![],
+                                span: Span::new(12, 16),
+                            },
+                            PipeStage {
+                                name: "base64.encode".into(),
+                                args: vec![],
+                                span: Span::new(17, 30),
+                            },
+                            PipeStage {
+                                name: "hash".into(),
+                                args: vec![FnArg {
+                                    name: "algorithm".into(),
+                                    value: Val::Str {
+                                        text: "md5".into(),
+                                        span: Span::new(48, 53),
+                                    },
+                                    span: Span::new(38, 53),
+                                }],
+                                span: Span::new(33, 54),
+                            },
+                        ],
+                        span: Span::new(4, 54),
+                    },
+                    span: Span::new(0, 57),
+                },
+                Token::Eof
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_spans() -> Result<()> {
+        let template = "${[ a ?? 'b' | trim ]}";
+        let val = match &Parser::new(template).parse()?.tokens[0] {
+            Token::Tag { val, .. } => val.clone(),
+            t => panic!("Expected tag, got {t:?}"),
+        };
+        let stages = match &val {
+            Val::Pipe { value, stages, .. } => {
+                assert!(matches!(**value, Val::Coalesce { .. }));
+                stages.clone()
+            }
+            v => panic!("Expected pipe, got {v:?}"),
+        };
+        assert_eq!(&template[val.span().byte_range(template)], "a ?? 'b' | trim");
+        assert_eq!(&template[stages[0].span.byte_range(template)], "trim");
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_invalid() -> Result<()> {
+        assert_eq!(diagnostics("${[ a | ]}"), vec![(ParseDiagnosticKind::InvalidTag, 8, 9)]);
+        assert_eq!(
+            diagnostics("${[ a | fn(x) ]}"),
+            vec![(ParseDiagnosticKind::InvalidArgs, 12, 13)]
+        );
+        assert_eq!(diagnostics("${[ a || ]}"), vec![(ParseDiagnosticKind::InvalidTag, 9, 10)]);
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_display_round_trip() -> Result<()> {
+        for template in [
+            "${[ token | trim | base64 | urlencode ]}",
+            "${[ a ?? 'b' | trim ]}",
+            "${[ fn(x=a | trim) | wrap(with='x') ]}",
+        ] {
+            let tokens = Parser::new(template).parse()?;
+            assert_eq!(tokens.to_string(), template);
+            assert_eq!(Parser::new(&tokens.to_string()).parse()?, tokens);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn number_literals() -> Result<()> {
+        for (template, value) in [
+            ("${[ 1 ]}", 1.0),
+            ("${[ -12 ]}", -12.0),
+            ("${[ 3.25 ]}", 3.25),
+            ("${[ 1.5e3 ]}", 1500.0),
+            ("${[ 2E-2 ]}", 0.02),
+        ] {
+            assert_eq!(
+                Parser::new(template).parse()?.tokens,
+                vec![
+                    Token::Tag {
+                        val: Val::Num {
+                            value,
+                            span: Span::new(4, template.len() - 3),
+                        },
+                        span: Span::new(0, template.len()),
+                    },
+                    Token::Eof
+                ],
+                "{template}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn array_and_object_literals() -> Result<()> {
+        let mut p = Parser::new("${[ foo(a=[1, 'b', [],], o={x: c, 'y z': {}}) ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Fn {
+                        name: "foo".into(),
+                        args: vec![
+                            FnArg {
+                                name: "a".into(),
+                                value: Val::Array {
+                                    items: vec![
+                                        Val::Num {
+                                            value: 1.0,
+                                            span: Span::new(11, 12),
+                                        },
+                                        Val::Str {
+                                            text: "b".into(),
+                                            span: Span::new(14, 17),
+                                        },
+                                        Val::Array {
+                                            items: vec![],
+                                            span: Span::new(19, 21),
+                                        },
+                                    ],
+                                    span: Span::new(10, 23),
+                                },
+                                span: Span::new(8, 23),
+                            },
+                            FnArg {
+                                name: "o".into(),
+                                value: Val::Object {
+                                    entries: vec![
+                                        ObjectEntry {
+                                            key: "x".into(),
+                                            value: Val::Var {
+                                                name: "c".into(),
+                                                optional: false,
+                                                span: Span::new(31, 32),
+                                            },
+                                            span: Span::new(28, 32),
+                                        },
+                                        ObjectEntry {
+                                            key: "y z".into(),
+                                            value: Val::Object {
+                                                entries: vec![],
+                                                span: Span::new(41, 43),
+                                            },
+                                            span: Span::new(34, 43),
+                                        },
+                                    ],
+                                    span: Span::new(27, 44),
+                                },
+                                span: Span::new(25, 44),
+                            },
+                        ],
+                        span: Span::new(4, 45),
+                    },
+                    span: Span::new(0, 48),
+                },
+                Token::Eof
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn literal_spans() -> Result<()> {
+        let template = "${[ [1, {a: -2.5}] ]}";
+        let val = match &Parser::new(template).parse()?.tokens[0] {
+            Token::Tag { val, .. } => val.clone(),
+            t => panic!("Expected tag, got {t:?}"),
+        };
+        assert_eq!(&template[val.span().byte_range(template)], "[1, {a: -2.5}]");
+        let entries = match &val {
+            Val::Array { items, .. } => match &items[1] {
+                Val::Object { entries, .. } => entries.clone(),
+                v => panic!("Expected object, got {v:?}"),
+            },
+            v => panic!("Expected array, got {v:?}"),
+        };
+        assert_eq!(&template[entries[0].span.byte_range(template)], "a: -2.5");
+        Ok(())
+    }
+
+    #[test]
+    fn literals_invalid() -> Result<()> {
+        assert_eq!(diagnostics("${[ [1 2] ]}"), vec![(ParseDiagnosticKind::InvalidTag, 7, 8)]);
+        assert_eq!(diagnostics("${[ {a 1} ]}"), vec![(ParseDiagnosticKind::InvalidTag, 7, 8)]);
+        assert_eq!(diagnostics("${[ {a: } ]}"), vec![(ParseDiagnosticKind::InvalidTag, 8, 9)]);
+        assert_eq!(diagnostics("${[ [1, ]}"), vec![(ParseDiagnosticKind::InvalidTag, 9, 10)]);
+        assert_eq!(diagnostics("${[ 1.5.2 ]}"), vec![(ParseDiagnosticKind::InvalidTag, 7, 8)]);
+        Ok(())
+    }
+
+    #[test]
+    fn literals_display_round_trip() -> Result<()> {
+        for template in [
+            "${[ 42 ]}",
+            "${[ -0.5 ]}",
+            "${[ [1, 'two', three, [], {}] ]}",
+            "${[ {a: 1, b-c: [true, null], 'd e': f()} ]}",
+            "${[ fn(x=[a | trim, 2], y={k: v ?? 3}) ]}",
+        ] {
+            let tokens = Parser::new(template).parse()?;
+            assert_eq!(tokens.to_string(), template);
+            assert_eq!(Parser::new(&tokens.to_string()).parse()?, tokens);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expr_simple() -> Result<()> {
+        let mut p = Parser::new("${[ page + 1 ]}");
+        assert_eq!(
+            p.parse()?.tokens,
+            vec![
+                Token::Tag {
+                    val: Val::Binary {
+                        op: BinaryOp::Add,
+                        left: Box::new(Val::Var {
+                            name: "page".into(),
+                            optional: false,
+                            span: Span::new(4, 8),
+                        }),
+                        right: Box::new(Val::Num {
+                            value: 1.0,
+                            span: Span::new(11, 12),
+                        }),
+                        span: Span::new(4, 12),
+                    },
+                    span: Span::new(0, 15),
+                },
+                Token::Eof
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn expr_precedence() -> Result<()> {
+        for (template, grouped) in [
+            ("${[ a + b * c ]}", "${[ a + (b * c) ]}"),
+            ("${[ a * b + c ]}", "${[ (a * b) + c ]}"),
+            ("${[ a - b - c ]}", "${[ (a - b) - c ]}"),
+            ("${[ a / b % c ]}", "${[ (a / b) % c ]}"),
+            ("${[ a < b == c > d ]}", "${[ (a < b) == (c > d) ]}"),
+            ("${[ a == b && c != d ]}", "${[ (a == b) && (c != d) ]}"),
+            ("${[ a || b && c ]}", "${[ a || (b && c) ]}"),
+            ("${[ !a == b ]}", "${[ (!a) == b ]}"),
+            ("${[ -a * b ]}", "${[ (-a) * b ]}"),
+            ("${[ a ?? b + 1 ]}", "${[ a ?? (b + 1) ]}"),
+            ("${[ a || b ?? c ]}", "${[ (a || b) ?? c ]}"),
+            ("${[ a ? b : c ? d : e ]}", "${[ a ? b : (c ? d : e) ]}"),
+            ("${[ a ?? b ? c : d ]}", "${[ (a ?? b) ? c : d ]}"),
+            ("${[ a + 1 | trim ]}", "${[ (a + 1) | trim ]}"),
+            ("${[ fn(x=a * 2) + 1 ]}", "${[ (fn(x=(a * 2))) + 1 ]}"),
+        ] {
+            // Spans differ because of the parentheses, so compare the printed structure instead
+            assert_eq!(
+                Parser::new(template).parse()?.to_string(),
+                Parser::new(grouped).parse()?.to_string(),
+                "{template} should parse like {grouped}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expr_display_round_trip() -> Result<()> {
+        for template in [
+            "${[ page + 1 ]}",
+            "${[ (a + b) * -c ]}",
+            "${[ a - (b - c) ]}",
+            "${[ !(a && b) || c ]}",
+            "${[ (a ?? 1) + 2 ]}",
+            "${[ is_prod ? 'https' : 'http' ]}",
+            "${[ (a ? b : c) ? d : e ]}",
+            "${[ fn(x=a >= 10, y=[a % 2 == 0]) ]}",
+        ] {
+            let tokens = Parser::new(template).parse()?;
+            assert_eq!(tokens.to_string(), template);
+            assert_eq!(Parser::new(&tokens.to_string()).parse()?, tokens);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expr_invalid() -> Result<()> {
+        let messages = |template: &str| {
+            let (_, diagnostics) = Parser::new(template).parse_with_diagnostics().unwrap();
+            diagnostics.into_iter().map(|d| (d.message, d.span.start)).collect::<Vec<_>>()
+        };
+        assert_eq!(messages("${[ a + ]}"), vec![("Expected a value after +".into(), 8)]);
+        assert_eq!(messages("${[ a && ]}"), vec![("Expected a value after &&".into(), 9)]);
+        assert_eq!(messages("${[ !]}"), vec![("Expected a value after !".into(), 5)]);
+        assert_eq!(messages("${[ (a + b ]}"), vec![("Expected ) to close the group".into(), 11)]);
+        assert_eq!(messages("${[ ( ]}"), vec![("Expected a value after (".into(), 6)]);
+        assert_eq!(messages("${[ a ? ]}"), vec![("Expected a value after ?".into(), 8)]);
+        assert_eq!(messages("${[ a ? b ]}"), vec![("Expected : after the value for ?".into(), 10)]);
+        assert_eq!(messages("${[ a ? b : ]}"), vec![("Expected a value after :".into(), 12)]);
+        Ok(())
+    }
 }
@@ -0,0 +1,292 @@
+use crate::error::Error::RenderError;
+use crate::error::Result;
+use crate::lint::{ReferenceKind, collect_references};
+use crate::{Parser, Span, Token, Val};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use std::ops::Range;
+
+/// Rename every use of a variable, including in function args, pipes, literals, and strings. Only
+/// the names of the uses are replaced, so the rest of the template stays exactly as written.
+/// Returns the new template and how many uses were renamed.
+pub fn rename_variable(template: &str, from: &str, to: &str) -> Result<(String, usize)> {
+    if !is_valid_variable_name(to) {
+        return Err(RenderError(format!("Invalid variable name {to}")));
+    }
+    rename_in_template(template, from, to)
+}
+
+/// Whether the name can be used as a variable in a tag (`${[ name ]}`)
+pub fn is_valid_variable_name(name: &str) -> bool {
+    match Parser::new(&format!("${{[ {name} ]}}")).parse() {
+        Ok(tokens) => match tokens.tokens.as_slice() {
+            [
+                Token::Tag {
+                    val: Val::Var { name: n, .. },
+                    ..
+                },
+                Token::Eof,
+            ] => n == name,
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Replacement of part of a template, by its byte range
+struct Edit {
+    range: Range<usize>,
+    text: String,
+    count: usize,
+}
+
+fn rename_in_template(template: &str, from: &str, to: &str) -> Result<(String, usize)> {
+    let mut edits = template_edits(template, from, to)?;
+    if edits.is_empty() {
+        return Ok((template.to_string(), 0));
+    }
+    edits.sort_by_key(|e| e.range.start);
+
+    let mut new_template = String::new();
+    let mut pos = 0;
+    let mut count = 0;
+    for e in edits {
+        new_template.push_str(&template[pos..e.range.start]);
+        new_template.push_str(&e.text);
+        pos = e.range.end;
+        count += e.count;
+    }
+    new_template.push_str(&template[pos..]);
+    Ok((new_template, count))
+}
+
+fn template_edits(template: &str, from: &str, to: &str) -> Result<Vec<Edit>> {
+    let tokens = Parser::new(template).parse()?;
+    let mut edits = Vec::new();
+    for t in &tokens.tokens {
+        if let Token::Tag { val, .. } = t {
+            collect_edits(template, val, from, to, &mut edits)?;
+        }
+    }
+    Ok(edits)
+}
+
+fn collect_edits(
+    template: &str,
+    val: &Val,
+    from: &str,
+    to: &str,
+    edits: &mut Vec<Edit>,
+) -> Result<()> {
+    match val {
+        Val::Var { name, span, .. } if name == from => {
+            // The span also covers the ? of optional variables, so only replace the name
+            let start = span.byte_range(template).start;
+            edits.push(Edit {
+                range: start..start + from.len(),
+                text: to.to_string(),
+                count: 1,
+            });
+        }
+        Val::Str { text, span } => {
+            let range = span.byte_range(template);
+            let literal = &template[range.clone()];
+            if literal.starts_with("b64'") {
+                // Encoded strings can't be edited in place, so encode the renamed text again
+                let (text, count) = rename_in_template(text, from, to)?;
+                if count > 0 {
+                    let text = format!("b64'{}'", BASE64_URL_SAFE_NO_PAD.encode(text));
+                    edits.push(Edit { range, text, count });
+                }
+            } else {
+                // Spans in the string are relative to its unescaped text, so map them back
+                let offsets = string_offsets(&literal[1..literal.len() - 1], range.start + 1);
+                for e in template_edits(text, from, to)? {
+                    edits.push(Edit {
+                        range: offsets[e.range.start]..offsets[e.range.end],
+                        ..e
+                    });
+                }
+            }
+        }
+        Val::Fn { args, .. } => {
+            for a in args {
+                collect_edits(template, &a.value, from, to, edits)?;
+            }
+        }
+        Val::Coalesce {
+            value, fallback, ..
+        } => {
+            collect_edits(template, value, from, to, edits)?;
+            collect_edits(template, fallback, from, to, edits)?;
+        }
+        Val::Pipe { value, stages, .. } => {
+            collect_edits(template, value, from, to, edits)?;
+            for stage in stages {
+                for a in &stage.args {
+                    collect_edits(template, &a.value, from, to, edits)?;
+                }
+            }
+        }
+        Val::Array { items, .. } => {
+            for v in items {
+                collect_edits(template, v, from, to, edits)?;
+            }
+        }
+        Val::Object { entries, .. } => {
+            for e in entries {
+                collect_edits(template, &e.value, from, to, edits)?;
+            }
+        }
+        Val::Unary { value, .. } => collect_edits(template, value, from, to, edits)?,
+        Val::Binary { left, right, .. } => {
+            collect_edits(template, left, from, to, edits)?;
+            collect_edits(template, right, from, to, edits)?;
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            collect_edits(template, condition, from, to, edits)?;
+            collect_edits(template, then, from, to, edits)?;
+            collect_edits(template, otherwise, from, to, edits)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Map each byte of a string's unescaped text to its offset in the template, given the string's
+/// contents between the quotes and where they start. Escaped chars map to their backslash.
+fn string_offsets(contents: &str, start: usize) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut chars = contents.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => escaped,
+                None => break,
+            },
+            c => c,
+        };
+        offsets.extend(std::iter::repeat_n(start + i, c.len_utf8()));
+    }
+    offsets.push(start + contents.len());
+    offsets
+}
+
+/// Find where the template uses a variable, with the same traversal as [`rename_variable`]
+pub fn find_variable_usages(template: &str, name: &str) -> Result<Vec<Span>> {
+    let tokens = Parser::new(template).parse()?;
+    Ok(collect_references(&tokens)
+        .into_iter()
+        .filter(|r| r.kind == ReferenceKind::Var && r.name == name)
+        .map(|r| r.span)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error::RenderError;
+    use crate::error::Result;
+    use crate::refactor::{find_variable_usages, rename_variable};
+
+    #[test]
+    fn rename_simple() -> Result<()> {
+        let (template, count) = rename_variable("${[host]}/api/${[ host ]}", "host", "base_url")?;
+        assert_eq!(template, "${[base_url]}/api/${[ base_url ]}");
+        assert_eq!(count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_nested() -> Result<()> {
+        let template = "${[ upper(value=host, other=[host, {k: host}]) | wrap(with=host?) ]}";
+        let (template, count) = rename_variable(template, "host", "h")?;
+        assert_eq!(template, "${[ upper(value=h, other=[h, {k: h}]) | wrap(with=h?) ]}");
+        assert_eq!(count, 4);
+
+        let (template, count) = rename_variable("${[ a ?? host ]}", "host", "h")?;
+        assert_eq!(template, "${[ a ?? h ]}");
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_in_string_arg() -> Result<()> {
+        let (template, count) = rename_variable("${[ f(x='${[ host ]}') ]}", "host", "h")?;
+        assert_eq!(template, "${[ f(x='${[ h ]}') ]}");
+        assert_eq!(count, 1);
+
+        let (template, count) =
+            rename_variable(r"${[ f(x='it\'s 😀 ${[ host ]} \\ ${[host]}') ]}", "host", "h")?;
+        assert_eq!(template, r"${[ f(x='it\'s 😀 ${[ h ]} \\ ${[h]}') ]}");
+        assert_eq!(count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_in_b64_string_arg() -> Result<()> {
+        // b64'${[ host ]}' and b64'${[ h ]}'
+        let (template, count) = rename_variable("${[ f(x=b64'JHtbIGhvc3QgXX0') ]}", "host", "h")?;
+        assert_eq!(template, "${[ f(x=b64'JHtbIGggXX0') ]}");
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_keeps_other_args() -> Result<()> {
+        let (template, _) = rename_variable(r"${[ f(a='it\'s', b=host) ]}", "host", "h")?;
+        assert_eq!(template, r"${[ f(a='it\'s', b=h) ]}");
+
+        let (template, _) = rename_variable(r"${[ host ?? 'a\\b' ]}", "host", "h")?;
+        assert_eq!(template, r"${[ h ?? 'a\\b' ]}");
+
+        let (template, _) =
+            rename_variable("${[ f(a=null, b=1.50, c=host?, d='${[ x ]}') ]}", "host", "h")?;
+        assert_eq!(template, "${[ f(a=null, b=1.50, c=h?, d='${[ x ]}') ]}");
+        Ok(())
+    }
+
+    #[test]
+    fn rename_keeps_other_tags() -> Result<()> {
+        let template = "${[ f(x='hi there!') ]} ${[hostname]} ${[ host ]}";
+        let (template, count) = rename_variable(template, "host", "h")?;
+        assert_eq!(template, "${[ f(x='hi there!') ]} ${[hostname]} ${[ h ]}");
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_after_multibyte_text() -> Result<()> {
+        let (template, count) = rename_variable("😀 é ${[host]} → ${[ host ]}", "host", "h")?;
+        assert_eq!(template, "😀 é ${[h]} → ${[ h ]}");
+        assert_eq!(count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_unused() -> Result<()> {
+        let template = "${[  other ]} host";
+        assert_eq!(rename_variable(template, "host", "h")?, (template.to_string(), 0));
+        Ok(())
+    }
+
+    #[test]
+    fn rename_invalid_name() {
+        assert_eq!(
+            rename_variable("${[ host ]}", "host", "not valid"),
+            Err(RenderError("Invalid variable name not valid".to_string()))
+        );
+    }
+
+    #[test]
+    fn find_usages() -> Result<()> {
+        let usages = find_variable_usages("${[ host ]} ${[ f(a=host) ]} ${[ hostname ]}", "host")?;
+        let usages = usages.iter().map(|s| (s.start, s.end)).collect::<Vec<_>>();
+        assert_eq!(usages, vec![(4, 8), (20, 24)]);
+        Ok(())
+    }
+}
@@ -1,13 +1,22 @@
-use crate::error::Error::{RenderStackExceededError, VariableNotFound};
-use crate::error::Result;
-use crate::{Parser, Token, Tokens, Val};
+use crate::error::Error::{RenderError, RenderStackExceededError, VariableCycle, VariableNotFound};
+use crate::error::{CycleStep, Result};
+use crate::trace::{PendingStep, RenderTrace, TagTrace, TraceCollector, TraceStepKind};
+use crate::{BinaryOp, FnArg, Parser, Token, Tokens, UnaryOp, Val};
+use futures_util::future::try_join_all;
 use log::warn;
 use serde_json::json;
 use std::collections::HashMap;
 use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
 const MAX_DEPTH: usize = 50;
 
+/// How many template functions are called at once, across the whole render. Functions may call
+/// out to plugins, so independent tags are rendered concurrently rather than waiting on each
+/// other.
+pub const MAX_CONCURRENT_RENDERS: usize = 8;
+
 pub trait TemplateCallback {
     fn run(
         &self,
@@ -16,6 +25,18 @@ pub trait TemplateCallback {
     ) -> impl Future<Output = Result<String>> + Send;
 
     fn transform_arg(&self, fn_name: &str, arg_name: &str, arg_value: &str) -> Result<String>;
+
+    /// Name of the function's first argument, which is what a piped value is passed as
+    /// (`${[ value | fn_name ]}`). Defaults to `value`, which most functions take as their input.
+    fn first_arg_name(&self, _fn_name: &str) -> impl Future<Output = Result<String>> + Send {
+        async { Ok("value".to_string()) }
+    }
+
+    /// Whether the function must not run alongside any other function, like one that prompts the
+    /// user or sends a request
+    fn is_sequential(&self, _fn_name: &str) -> impl Future<Output = bool> + Send {
+        async { false }
+    }
 }
 
 pub async fn render_json_value_raw<T: TemplateCallback>(
@@ -27,36 +48,178 @@ pub async fn render_json_value_raw<T: TemplateCallback>(
     let v = match v {
         serde_json::Value::String(s) => json!(parse_and_render(&s, vars, cb, opt).await?),
         serde_json::Value::Array(a) => {
-            let mut new_a = Vec::new();
-            for v in a {
-                new_a.push(Box::pin(render_json_value_raw(v, vars, cb, opt)).await?)
-            }
+            let new_a = try_join_all(
+                a.into_iter().map(|v| Box::pin(render_json_value_raw(v, vars, cb, opt))),
+            )
+            .await?;
             json!(new_a)
         }
         serde_json::Value::Object(o) => {
-            let mut new_o = serde_json::Map::new();
-            for (k, v) in o {
+            let entries = try_join_all(o.into_iter().map(|(k, v)| async move {
                 let key = Box::pin(parse_and_render(&k, vars, cb, opt)).await?;
                 let value = Box::pin(render_json_value_raw(v, vars, cb, opt)).await?;
-                new_o.insert(key, value);
-            }
-            json!(new_o)
+                Ok((key, value))
+            }))
+            .await?;
+            json!(entries.into_iter().collect::<serde_json::Map<_, _>>())
         }
         v => v,
     };
     Ok(v)
 }
 
-async fn parse_and_render_at_depth<T: TemplateCallback>(
+/// Render a JSON document (eg. a request body) in typed mode, where a string that is exactly
+/// one tag (`"${[ count ]}"`) is replaced by the tag's JSON value instead of being rendered
+/// inside the quotes. Everything else renders the same as [`parse_and_render`], so the
+/// formatting of the document is kept and it doesn't need to be valid JSON.
+pub async fn parse_and_render_json_typed<T: TemplateCallback>(
     template: &str,
     vars: &HashMap<String, String>,
     cb: &T,
     opt: &RenderOptions,
+) -> Result<String> {
+    let stack = &RenderStack {
+        depth: 2,
+        ..Default::default()
+    };
+    let tokens = Parser::new(template).parse()?.tokens;
+
+    let mut doc_str = String::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    // Whether we're right after the opening quote of a string
+    let mut string_empty = false;
+    let mut skip_quote = false;
+
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            Token::Raw { text, .. } => {
+                let mut chars = text.chars();
+                if skip_quote {
+                    // Drop the closing quote of the string the typed value replaced
+                    chars.next();
+                    skip_quote = false;
+                }
+                for ch in chars {
+                    if !in_string {
+                        in_string = ch == '"';
+                        string_empty = in_string;
+                    } else if escaped {
+                        escaped = false;
+                    } else if ch == '\\' {
+                        escaped = true;
+                    } else if ch == '"' {
+                        in_string = false;
+                    }
+                    if in_string && ch != '"' {
+                        string_empty = false;
+                    }
+                    doc_str.push(ch);
+                }
+            }
+            Token::Tag { val, .. } => {
+                let next_raw = match tokens.get(i + 1) {
+                    Some(Token::Raw { text, .. }) => Some(text.as_str()),
+                    _ => None,
+                };
+                let closes_string = next_raw.is_some_and(|t| t.starts_with('"'));
+                // Object keys have to be strings, so only values are typed
+                let is_key = next_raw
+                    .and_then(|t| t.strip_prefix('"'))
+                    .is_some_and(|t| t.trim_start().starts_with(':'));
+                if in_string && string_empty && closes_string && !is_key {
+                    let r = Box::pin(render_value_typed(val.clone(), vars, cb, opt, stack)).await;
+                    let v = match r {
+                        Ok(v) => v,
+                        Err(e) => json!(opt.error_behavior.handle(Err(e))?),
+                    };
+                    doc_str.pop(); // Opening quote
+                    doc_str.push_str(&v.to_string());
+                    in_string = false;
+                    skip_quote = true;
+                } else {
+                    let r = Box::pin(render_value(val.clone(), vars, cb, opt, stack)).await;
+                    doc_str.push_str(&opt.error_behavior.handle(r)?);
+                    string_empty = false;
+                }
+            }
+            Token::Eof => {}
+        }
+    }
+
+    Ok(doc_str)
+}
+
+/// The variables and functions being resolved, to stop infinite recursion, and where to record
+/// them when tracing
+#[derive(Clone, Default)]
+struct RenderStack {
     depth: usize,
+    path: Vec<CycleStep>,
+    trace: Option<TraceCollector>,
+}
+
+impl RenderStack {
+    fn nested(&self) -> RenderStack {
+        RenderStack {
+            depth: self.depth + 1,
+            ..self.clone()
+        }
+    }
+
+    /// Start a trace step, if tracing. Whatever is rendered with the returned stack is recorded
+    /// under the step.
+    fn traced(&self, kind: TraceStepKind, name: &str) -> (RenderStack, Option<PendingStep>) {
+        match &self.trace {
+            Some(trace) => {
+                let step = trace.start(kind, name);
+                let stack = RenderStack {
+                    trace: Some(step.children.clone()),
+                    ..self.clone()
+                };
+                (stack, Some(step))
+            }
+            None => (self.clone(), None),
+        }
+    }
+
+    /// Enter a variable, failing if it's already being resolved further up
+    fn with_var(&self, name: &str) -> Result<RenderStack> {
+        let step = CycleStep {
+            name: name.to_string(),
+            is_fn: false,
+            source: None,
+        };
+        let mut stack = self.clone();
+        let is_cycle = self.path.contains(&step);
+        stack.path.push(step);
+        match is_cycle {
+            true => Err(VariableCycle(stack.path)),
+            false => Ok(stack),
+        }
+    }
+
+    fn with_fn(&self, name: &str) -> RenderStack {
+        let mut stack = self.clone();
+        stack.path.push(CycleStep {
+            name: name.to_string(),
+            is_fn: true,
+            source: None,
+        });
+        stack
+    }
+}
+
+async fn parse_and_render_at_depth<T: TemplateCallback>(
+    template: &str,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
 ) -> Result<String> {
     let mut p = Parser::new(template);
     let tokens = p.parse()?;
-    render(tokens, vars, cb, opt, depth + 1).await
+    render_at(tokens, vars, cb, opt, &stack.nested()).await
 }
 
 pub async fn parse_and_render<T: TemplateCallback>(
@@ -65,7 +228,107 @@ pub async fn parse_and_render<T: TemplateCallback>(
     cb: &T,
     opt: &RenderOptions,
 ) -> Result<String> {
-    parse_and_render_at_depth(template, vars, cb, opt, 1).await
+    let stack = RenderStack {
+        depth: 1,
+        ..Default::default()
+    };
+    parse_and_render_at_depth(template, vars, cb, opt, &stack).await
+}
+
+/// Render the template like [`parse_and_render`], and also trace how each tag was resolved. A tag
+/// that fails is recorded with its error and renders empty, so the rest of the template is still
+/// traced. `opt` applies to tags nested in variables and function results.
+pub async fn parse_and_render_with_trace<T: TemplateCallback>(
+    template: &str,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> Result<RenderTrace> {
+    let stack = &RenderStack {
+        depth: 2,
+        ..Default::default()
+    };
+    let tokens = Parser::new(template).parse()?.tokens;
+
+    let rendered = try_join_all(tokens.into_iter().map(|t| async move {
+        match t {
+            Token::Raw { text, .. } => Ok((text, None)),
+            Token::Tag { val, span } => {
+                let tag = format!("${{[ {val} ]}}");
+                let trace = TraceCollector::default();
+                let stack = &RenderStack {
+                    trace: Some(trace.clone()),
+                    ..stack.clone()
+                };
+                let r = render_value(val, vars, cb, opt, stack).await;
+                let tag_trace = TagTrace {
+                    tag,
+                    span,
+                    value: r.as_ref().ok().cloned(),
+                    error: r.as_ref().err().map(|e| e.to_string()),
+                    redacted: false,
+                    steps: trace.take(),
+                };
+                Ok((r.unwrap_or_default(), Some(tag_trace)))
+            }
+            Token::Eof => Ok(("".to_string(), None)),
+        }
+    }))
+    .await?;
+
+    let (output, tags): (Vec<String>, Vec<Option<TagTrace>>) = rendered.into_iter().unzip();
+    Ok(RenderTrace {
+        output: output.join(""),
+        tags: tags.into_iter().flatten().collect(),
+    })
+}
+
+/// Render each string in a JSON value like [`render_json_value_raw`], and also trace each tag
+/// like [`parse_and_render_with_trace`]. Tag spans are relative to the string they're in.
+pub async fn render_json_value_with_trace<T: TemplateCallback>(
+    v: serde_json::Value,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+) -> Result<(serde_json::Value, Vec<TagTrace>)> {
+    let mut tags = Vec::new();
+    let v = trace_json_value(v, vars, cb, opt, &mut tags).await?;
+    Ok((v, tags))
+}
+
+async fn trace_json_value<T: TemplateCallback>(
+    v: serde_json::Value,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    tags: &mut Vec<TagTrace>,
+) -> Result<serde_json::Value> {
+    let v = match v {
+        serde_json::Value::String(s) => {
+            let trace = parse_and_render_with_trace(&s, vars, cb, opt).await?;
+            tags.extend(trace.tags);
+            json!(trace.output)
+        }
+        serde_json::Value::Array(a) => {
+            let mut new_a = Vec::new();
+            for v in a {
+                new_a.push(Box::pin(trace_json_value(v, vars, cb, opt, tags)).await?);
+            }
+            json!(new_a)
+        }
+        serde_json::Value::Object(o) => {
+            let mut new_o = serde_json::Map::new();
+            for (k, v) in o {
+                let key = parse_and_render_with_trace(&k, vars, cb, opt).await?;
+                tags.extend(key.tags);
+                let value = Box::pin(trace_json_value(v, vars, cb, opt, tags)).await?;
+                new_o.insert(key.output, value);
+            }
+            json!(new_o)
+        }
+        v => v,
+    };
+    Ok(v)
 }
 
 pub enum RenderErrorBehavior {
@@ -75,6 +338,51 @@ pub enum RenderErrorBehavior {
 
 pub struct RenderOptions {
     pub error_behavior: RenderErrorBehavior,
+    pub limiter: RenderLimiter,
+}
+
+impl RenderOptions {
+    /// Options with their own limiter, so everything rendered with them shares one limit
+    pub fn new(error_behavior: RenderErrorBehavior) -> Self {
+        Self {
+            error_behavior,
+            limiter: Default::default(),
+        }
+    }
+
+    pub fn throw() -> Self {
+        Self::new(RenderErrorBehavior::Throw)
+    }
+
+    pub fn return_empty() -> Self {
+        Self::new(RenderErrorBehavior::ReturnEmpty)
+    }
+}
+
+/// Limits how many template functions are called at once. Every render that shares the same
+/// options (or a clone of the limiter) counts towards the same limit.
+#[derive(Clone)]
+pub struct RenderLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl Default for RenderLimiter {
+    fn default() -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_RENDERS)),
+        }
+    }
+}
+
+impl RenderLimiter {
+    /// Wait for a turn to call a function. A sequential function waits for every other call to
+    /// finish, and holds off new ones until it's done.
+    async fn run<F: Future>(&self, sequential: bool, f: F) -> F::Output {
+        let permits = if sequential { MAX_CONCURRENT_RENDERS as u32 } else { 1 };
+        // The semaphore is never closed, so this can't fail
+        let _permit = self.permits.acquire_many(permits).await.ok();
+        f.await
+    }
 }
 
 impl RenderErrorBehavior {
@@ -88,94 +396,906 @@ impl RenderErrorBehavior {
             }
         }
     }
-}
+}
+
+pub async fn render<T: TemplateCallback>(
+    tokens: Tokens,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    depth: usize,
+) -> Result<String> {
+    let stack = RenderStack {
+        depth,
+        ..Default::default()
+    };
+    render_at(tokens, vars, cb, opt, &stack).await
+}
+
+async fn render_at<T: TemplateCallback>(
+    tokens: Tokens,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<String> {
+    let stack = &stack.nested();
+    if stack.depth > MAX_DEPTH {
+        return opt.error_behavior.handle(Err(RenderStackExceededError));
+    }
+
+    let doc_str = try_join_all(tokens.tokens.into_iter().map(|t| async move {
+        match t {
+            Token::Raw { text, .. } => Ok(text),
+            Token::Tag { val, .. } => {
+                let val = render_value(val, &vars, cb, opt, stack).await;
+                opt.error_behavior.handle(val)
+            }
+            Token::Eof => Ok("".to_string()),
+        }
+    }))
+    .await?;
+
+    Ok(doc_str.join(""))
+}
+
+/// Whether a value is itself a missing variable, which is when `??` falls back. Missing variables
+/// used inside it, like in a function argument or another variable's value, are still errors.
+fn is_missing_var(val: &Val, vars: &HashMap<String, String>) -> bool {
+    match val {
+        Val::Var { name, optional, .. } => !optional && !vars.contains_key(name),
+        Val::Coalesce {
+            value, fallback, ..
+        } => is_missing_var(value, vars) && is_missing_var(fallback, vars),
+        _ => false,
+    }
+}
+
+async fn render_value<T: TemplateCallback>(
+    val: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<String> {
+    let v = match val {
+        Val::Str { text, .. } => {
+            let r = Box::pin(parse_and_render_at_depth(&text, vars, cb, opt, stack)).await?;
+            r.to_string()
+        }
+        Val::Var { name, optional, .. } => match vars.get(name.as_str()) {
+            Some(v) => {
+                let (stack, step) = stack.with_var(&name)?.traced(TraceStepKind::Var, &name);
+                let r = Box::pin(parse_and_render_at_depth(v, vars, cb, opt, &stack)).await;
+                if let Some(step) = step {
+                    step.finish(&r);
+                }
+                r?
+            }
+            None if optional => "".to_string(),
+            None => {
+                let r = Err(VariableNotFound(name.clone()));
+                if let (_, Some(step)) = stack.traced(TraceStepKind::Var, &name) {
+                    step.finish(&r);
+                }
+                return r;
+            }
+        },
+        Val::Coalesce {
+            value, fallback, ..
+        } => {
+            let missing = is_missing_var(&value, vars);
+            match Box::pin(render_value(*value, vars, cb, opt, stack)).await {
+                Err(VariableNotFound(_)) if missing => {
+                    Box::pin(render_value(*fallback, vars, cb, opt, stack)).await?
+                }
+                r => r?,
+            }
+        }
+        Val::Fn { name, args, .. } => {
+            render_fn_call(&name, args, None, vars, cb, opt, stack).await?
+        }
+        Val::Pipe { value, stages, .. } => {
+            let mut result = Box::pin(render_value(*value, vars, cb, opt, stack)).await?;
+            for stage in stages {
+                let piped = Some(result);
+                result =
+                    render_fn_call(&stage.name, stage.args, piped, vars, cb, opt, stack).await?;
+            }
+            result
+        }
+        Val::Bool { value, .. } => value.to_string(),
+        Val::Num { value, .. } => json_number(value).to_string(),
+        v @ (Val::Array { .. } | Val::Object { .. }) => {
+            Box::pin(render_value_json(v, vars, cb, opt, stack)).await?.to_string()
+        }
+        v @ (Val::Unary { .. } | Val::Binary { .. }) => {
+            let v = Box::pin(render_operator(v, vars, cb, opt, stack)).await?;
+            Operand::Value(v).into_text()
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            let branch = render_ternary_branch(*condition, *then, *otherwise, vars, cb, opt, stack);
+            Box::pin(render_value(branch.await?, vars, cb, opt, stack)).await?
+        }
+        Val::Null => "".into(),
+    };
+
+    Ok(v)
+}
+
+/// Render a value to JSON, keeping the type of literals. Everything else renders to a string.
+async fn render_value_json<T: TemplateCallback>(
+    val: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<serde_json::Value> {
+    let v = match val {
+        Val::Bool { value, .. } => serde_json::Value::Bool(value),
+        Val::Num { value, .. } => json_number(value),
+        Val::Null => serde_json::Value::Null,
+        Val::Array { items, .. } => {
+            let mut new_items = Vec::new();
+            for v in items {
+                new_items.push(Box::pin(render_value_json(v, vars, cb, opt, stack)).await?);
+            }
+            serde_json::Value::Array(new_items)
+        }
+        Val::Object { entries, .. } => {
+            let mut new_entries = serde_json::Map::new();
+            for e in entries {
+                let value = Box::pin(render_value_json(e.value, vars, cb, opt, stack)).await?;
+                new_entries.insert(e.key, value);
+            }
+            serde_json::Value::Object(new_entries)
+        }
+        v @ (Val::Unary { .. } | Val::Binary { .. }) => {
+            Box::pin(render_operator(v, vars, cb, opt, stack)).await?
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            let branch = render_ternary_branch(*condition, *then, *otherwise, vars, cb, opt, stack);
+            Box::pin(render_value_json(branch.await?, vars, cb, opt, stack)).await?
+        }
+        v => serde_json::Value::String(Box::pin(render_value(v, vars, cb, opt, stack)).await?),
+    };
+    Ok(v)
+}
+
+/// Render a value for the typed JSON mode. Literals keep their type, and anything else is
+/// parsed as JSON if it renders to a number, bool, null, array, or object.
+async fn render_value_typed<T: TemplateCallback>(
+    val: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<serde_json::Value> {
+    match val {
+        v @ (Val::Var { .. } | Val::Fn { .. } | Val::Coalesce { .. } | Val::Pipe { .. }) => {
+            let rendered = render_value(v, vars, cb, opt, stack).await?;
+            match serde_json::from_str::<serde_json::Value>(&rendered) {
+                Ok(v) if !v.is_string() => Ok(v),
+                _ => Ok(serde_json::Value::String(rendered)),
+            }
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            let branch = render_ternary_branch(*condition, *then, *otherwise, vars, cb, opt, stack);
+            Box::pin(render_value_typed(branch.await?, vars, cb, opt, stack)).await
+        }
+        v => render_value_json(v, vars, cb, opt, stack).await,
+    }
+}
+
+/// A value being operated on in an expression. Text rendered from variables and functions is kept
+/// as written, but acts as a number, bool, or null when it looks like one, since variables are
+/// always strings.
+enum Operand {
+    Text(String),
+    Value(serde_json::Value),
+}
+
+impl Operand {
+    fn to_value(&self) -> serde_json::Value {
+        match self {
+            Operand::Text(text) => match serde_json::from_str::<serde_json::Value>(text) {
+                Ok(v) if !v.is_string() => v,
+                _ => serde_json::Value::String(text.clone()),
+            },
+            Operand::Value(v) => v.clone(),
+        }
+    }
+
+    fn into_text(self) -> String {
+        match self {
+            Operand::Text(text) => text,
+            Operand::Value(serde_json::Value::String(s)) => s,
+            Operand::Value(serde_json::Value::Null) => "".to_string(),
+            Operand::Value(v) => v.to_string(),
+        }
+    }
+}
+
+/// Render a value that's part of an expression
+async fn render_operand<T: TemplateCallback>(
+    val: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<Operand> {
+    let v = match val {
+        v @ (Val::Unary { .. } | Val::Binary { .. }) => {
+            Operand::Value(Box::pin(render_operator(v, vars, cb, opt, stack)).await?)
+        }
+        Val::Ternary {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => {
+            let branch = render_ternary_branch(*condition, *then, *otherwise, vars, cb, opt, stack);
+            Box::pin(render_operand(branch.await?, vars, cb, opt, stack)).await?
+        }
+        v @ (Val::Var { .. } | Val::Fn { .. } | Val::Coalesce { .. } | Val::Pipe { .. }) => {
+            Operand::Text(Box::pin(render_value(v, vars, cb, opt, stack)).await?)
+        }
+        v => Operand::Value(Box::pin(render_value_json(v, vars, cb, opt, stack)).await?),
+    };
+    Ok(v)
+}
+
+/// Render the condition of a ternary, returning the branch to render. The other branch isn't
+/// rendered, so it can reference variables that don't exist.
+async fn render_ternary_branch<T: TemplateCallback>(
+    condition: Val,
+    then: Val,
+    otherwise: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<Val> {
+    let condition = Box::pin(render_operand(condition, vars, cb, opt, stack)).await?;
+    match is_truthy(&condition.to_value()) {
+        true => Ok(then),
+        false => Ok(otherwise),
+    }
+}
+
+/// Render a unary or binary operator. `&&` and `||` only render their right side if needed.
+async fn render_operator<T: TemplateCallback>(
+    val: Val,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<serde_json::Value> {
+    let (op, left, right) = match val {
+        Val::Unary { op, value, .. } => {
+            let value = render_operand(*value, vars, cb, opt, stack).await?.to_value();
+            return apply_unary_op(op, &value);
+        }
+        Val::Binary {
+            op, left, right, ..
+        } => (op, *left, *right),
+        v => return Box::pin(render_value_json(v, vars, cb, opt, stack)).await,
+    };
+
+    let left = render_operand(left, vars, cb, opt, stack).await?;
+    match op {
+        BinaryOp::And if !is_truthy(&left.to_value()) => return Ok(json!(false)),
+        BinaryOp::Or if is_truthy(&left.to_value()) => return Ok(json!(true)),
+        BinaryOp::And | BinaryOp::Or => {
+            let right = render_operand(right, vars, cb, opt, stack).await?;
+            return Ok(json!(is_truthy(&right.to_value())));
+        }
+        _ => {}
+    }
+
+    let right = render_operand(right, vars, cb, opt, stack).await?;
+    apply_binary_op(op, left, right)
+}
+
+fn apply_unary_op(op: UnaryOp, value: &serde_json::Value) -> Result<serde_json::Value> {
+    match (op, value.as_f64()) {
+        (UnaryOp::Not, _) => Ok(json!(!is_truthy(value))),
+        (UnaryOp::Neg, Some(n)) => Ok(json_number(-n)),
+        (UnaryOp::Neg, None) => {
+            Err(RenderError(format!("Operator - expects a number, but got {}", type_name(value))))
+        }
+    }
+}
+
+fn apply_binary_op(op: BinaryOp, left: Operand, right: Operand) -> Result<serde_json::Value> {
+    let (l, r) = (left.to_value(), right.to_value());
+    let v = match (op, l.as_f64(), r.as_f64()) {
+        (BinaryOp::Eq, _, _) => json!(operands_equal(&left, &right)),
+        (BinaryOp::Ne, _, _) => json!(!operands_equal(&left, &right)),
+        (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, _, _) => {
+            let ordering = match (&l, &r) {
+                (serde_json::Value::Number(_), serde_json::Value::Number(_)) => {
+                    l.as_f64().partial_cmp(&r.as_f64())
+                }
+                (serde_json::Value::String(a), serde_json::Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            };
+            let ordering = ordering.ok_or_else(|| {
+                RenderError(format!(
+                    "Operator {} can't compare {} and {}",
+                    op.symbol(),
+                    type_name(&l),
+                    type_name(&r)
+                ))
+            })?;
+            json!(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+        (BinaryOp::Add, Some(a), Some(b)) => finite_number(op, a + b)?,
+        // Anything else is concatenated, keeping text as it was written
+        (BinaryOp::Add, _, _) => json!(left.into_text() + &right.into_text()),
+        (BinaryOp::Div | BinaryOp::Rem, Some(_), Some(0.0)) => {
+            return Err(RenderError(format!("Division by zero with {}", op.symbol())));
+        }
+        (BinaryOp::Sub, Some(a), Some(b)) => finite_number(op, a - b)?,
+        (BinaryOp::Mul, Some(a), Some(b)) => finite_number(op, a * b)?,
+        (BinaryOp::Div, Some(a), Some(b)) => finite_number(op, a / b)?,
+        (BinaryOp::Rem, Some(a), Some(b)) => finite_number(op, a % b)?,
+        (BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem, _, _) => {
+            return Err(RenderError(format!(
+                "Operator {} expects numbers, but got {} and {}",
+                op.symbol(),
+                type_name(&l),
+                type_name(&r)
+            )));
+        }
+        (BinaryOp::And | BinaryOp::Or, _, _) => json!(is_truthy(&l) && is_truthy(&r)),
+    };
+    Ok(v)
+}
+
+/// `false`, `null`, `0`, and empty strings are falsy, like in JavaScript
+fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => true,
+    }
+}
+
+/// Text compared to a string is compared as written, so `version == '1.10'` is true even though
+/// the text also looks like the number `1.1`
+fn operands_equal(left: &Operand, right: &Operand) -> bool {
+    match (left, right) {
+        (Operand::Text(text), Operand::Value(serde_json::Value::String(s)))
+        | (Operand::Value(serde_json::Value::String(s)), Operand::Text(text)) => text == s,
+        _ => values_equal(&left.to_value(), &right.to_value()),
+    }
+}
+
+/// Numbers are compared by value, so `1` and `1.0` are equal
+fn values_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a, b) {
+        (serde_json::Value::Number(a), serde_json::Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => a == b,
+    }
+}
+
+fn type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// JSON can't hold infinity or NaN (they'd become null and render as empty), so arithmetic that
+/// overflows fails like division by zero does
+fn finite_number(op: BinaryOp, value: f64) -> Result<serde_json::Value> {
+    match value.is_finite() {
+        true => Ok(json_number(value)),
+        false => Err(RenderError(format!("Result of {} isn't a finite number", op.symbol()))),
+    }
+}
+
+/// Integers are kept as integers so they don't render with a trailing `.0`
+fn json_number(value: f64) -> serde_json::Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        json!(value as i64)
+    } else {
+        json!(value)
+    }
+}
+
+async fn resolve_fn_args<T: TemplateCallback>(
+    args: Vec<FnArg>,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<HashMap<String, serde_json::Value>> {
+    let resolved_args = try_join_all(args.into_iter().map(|a| async move {
+        let v = Box::pin(render_value_json(a.value, vars, cb, opt, stack)).await?;
+        Ok((a.name, v))
+    }))
+    .await?;
+    Ok(resolved_args.into_iter().collect())
+}
+
+/// Resolve the args and call the function. A piped value is passed as the function's first arg.
+async fn render_fn_call<T: TemplateCallback>(
+    name: &str,
+    args: Vec<FnArg>,
+    piped: Option<String>,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<String> {
+    let (stack, mut step) = stack.traced(TraceStepKind::Fn, name);
+    let stack = &stack;
+    let r = async {
+        let mut args = resolve_fn_args(args, vars, cb, opt, stack).await?;
+        if let Some(value) = piped {
+            let arg_name = cb.first_arg_name(name).await?;
+            if args.contains_key(&arg_name) {
+                return Err(RenderError(format!(
+                    "Argument {arg_name} of {name} is already set by the pipe"
+                )));
+            }
+            args.insert(arg_name, serde_json::Value::String(value));
+        }
+        if let Some(step) = &mut step {
+            step.set_args(&args);
+        }
+        render_fn(name, args, vars, cb, opt, stack).await
+    }
+    .await;
+    if let Some(step) = step {
+        step.finish(&r);
+    }
+    r
+}
+
+async fn render_fn<T: TemplateCallback>(
+    name: &str,
+    args: HashMap<String, serde_json::Value>,
+    vars: &HashMap<String, String>,
+    cb: &T,
+    opt: &RenderOptions,
+    stack: &RenderStack,
+) -> Result<String> {
+    // Only hold the permit for the call itself. The args are already rendered, and the result is
+    // rendered after, so a call never waits on a permit while holding one.
+    let sequential = cb.is_sequential(name).await;
+    let result = opt.limiter.run(sequential, cb.run(name, args)).await?;
+    let stack = &stack.with_fn(name);
+    Box::pin(parse_and_render_at_depth(&result, vars, cb, opt, stack)).await
+}
+
+#[cfg(test)]
+mod parse_and_render_tests {
+    use crate::error::Error::{RenderError, VariableCycle, VariableNotFound};
+    use crate::error::{CycleStep, Result};
+    use crate::renderer::TemplateCallback;
+    use crate::trace::{TraceSource, TraceStep, TraceStepKind};
+    use crate::*;
+    use serde_json::json;
+    use std::collections::{HashMap, HashSet};
+
+    struct EmptyCB {}
+
+    impl TemplateCallback for EmptyCB {
+        async fn run(
+            &self,
+            _fn_name: &str,
+            _args: HashMap<String, serde_json::Value>,
+        ) -> Result<String> {
+            todo!()
+        }
+
+        fn transform_arg(
+            &self,
+            _fn_name: &str,
+            _arg_name: &str,
+            arg_value: &str,
+        ) -> Result<String> {
+            Ok(arg_value.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn render_empty() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "";
+        let vars = HashMap::new();
+        let result = "";
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_text_only() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "Hello World!";
+        let vars = HashMap::new();
+        let result = "Hello World!";
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_simple() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ foo ]}";
+        let vars = HashMap::from([("foo".to_string(), "bar".to_string())]);
+        let result = "bar";
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_recursive_var() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ foo ]}";
+        let mut vars = HashMap::new();
+        vars.insert("foo".to_string(), "foo: ${[ bar ]}".to_string());
+        vars.insert("bar".to_string(), "bar: ${[ baz ]}".to_string());
+        vars.insert("baz".to_string(), "baz".to_string());
+
+        let result = "foo: bar: baz";
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_missing_var() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ foo ]}";
+        let vars = HashMap::new();
+        let opt = RenderOptions::throw();
+        assert_eq!(
+            parse_and_render(template, &vars, &empty_cb, &opt).await,
+            Err(VariableNotFound("foo".to_string()))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_var_fallback() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ api_host ?? 'localhost:8080' ]}/${[ path ?? 'nope' ]}";
+        let vars = HashMap::from([("path".to_string(), "users".to_string())]);
+        let opt = RenderOptions::throw();
+        assert_eq!(
+            parse_and_render(template, &vars, &empty_cb, &opt).await?,
+            "localhost:8080/users"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_var_fallback_chain() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ a ?? b ?? 'c' ]} ${[ a ?? d ?? 'c' ]}";
+        let vars = HashMap::from([("d".to_string(), "${[ e ?? 'd' ]}".to_string())]);
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, "c d");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_var_fallback_missing() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "${[ a ?? b ]}";
+        let vars = HashMap::new();
+        let opt = RenderOptions::throw();
+        assert_eq!(
+            parse_and_render(template, &vars, &empty_cb, &opt).await,
+            Err(VariableNotFound("b".to_string()))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_var_fallback_only_for_operand() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let vars = HashMap::from([("a".to_string(), "${[ missing ]}".to_string())]);
+        let opt = RenderOptions::throw();
+        for template in ["${[ upper(x=missing) ?? 'd' ]}", "${[ a ?? 'd' ]}"] {
+            assert_eq!(
+                parse_and_render(template, &vars, &empty_cb, &opt).await,
+                Err(VariableNotFound("missing".to_string())),
+                "{template}"
+            );
+        }
+        assert_eq!(parse_and_render("${[ (b ?? c) ?? 'd' ]}", &vars, &empty_cb, &opt).await?, "d");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_optional_var() -> Result<()> {
+        let empty_cb = EmptyCB {};
+        let template = "[${[ foo? ]}] [${[ bar? ]}]";
+        let vars = HashMap::from([("bar".to_string(), "baz".to_string())]);
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, "[] [baz]");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_fn_arg_fallback() -> Result<()> {
+        let vars = HashMap::new();
+        let template = r#"${[ upper(foo=foo ?? 'bar') ]}"#;
+        let opt = RenderOptions::throw();
+        struct CB {}
+        impl TemplateCallback for CB {
+            async fn run(
+                &self,
+                _fn_name: &str,
+                args: HashMap<String, serde_json::Value>,
+            ) -> Result<String> {
+                Ok(args["foo"].as_str().unwrap_or_default().to_uppercase())
+            }
+
+            fn transform_arg(
+                &self,
+                _fn_name: &str,
+                _arg_name: &str,
+                arg_value: &str,
+            ) -> Result<String> {
+                Ok(arg_value.to_string())
+            }
+        }
+
+        assert_eq!(parse_and_render(template, &vars, &CB {}, &opt).await?, "BAR");
+        Ok(())
+    }
+
+    struct PipeCB {}
+    impl TemplateCallback for PipeCB {
+        async fn run(
+            &self,
+            fn_name: &str,
+            args: HashMap<String, serde_json::Value>,
+        ) -> Result<String> {
+            let arg = |name: &str| args.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+            Ok(match fn_name {
+                "trim" => arg("value").trim().to_string(),
+                "upper" => arg("value").to_uppercase(),
+                "wrap" => format!("{}{}{}", arg("with"), arg("text"), arg("with")),
+                _ => return Err(RenderError(format!("Unknown function {fn_name}"))),
+            })
+        }
+
+        fn transform_arg(
+            &self,
+            _fn_name: &str,
+            _arg_name: &str,
+            arg_value: &str,
+        ) -> Result<String> {
+            Ok(arg_value.to_string())
+        }
+
+        async fn first_arg_name(&self, fn_name: &str) -> Result<String> {
+            Ok(match fn_name {
+                "wrap" => "text".to_string(),
+                _ => "value".to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn render_pipe() -> Result<()> {
+        let template = "${[ foo | trim | upper | wrap(with='*') ]}";
+        let vars = HashMap::from([("foo".to_string(), "  bar ".to_string())]);
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &PipeCB {}, &opt).await?, "*BAR*");
+        Ok(())
+    }
 
-pub async fn render<T: TemplateCallback>(
-    tokens: Tokens,
-    vars: &HashMap<String, String>,
-    cb: &T,
-    opt: &RenderOptions,
-    mut depth: usize,
-) -> Result<String> {
-    depth += 1;
-    if depth > MAX_DEPTH {
-        return opt.error_behavior.handle(Err(RenderStackExceededError));
+    #[tokio::test]
+    async fn render_pipe_fallback_and_arg() -> Result<()> {
+        let template = "${[ wrap(with='_', text=foo ?? ' x ' | trim | upper) ]}";
+        let vars = HashMap::new();
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render(template, &vars, &PipeCB {}, &opt).await?, "_X_");
+        Ok(())
     }
 
-    let mut doc_str: Vec<String> = Vec::new();
+    #[tokio::test]
+    async fn render_pipe_arg_conflict() -> Result<()> {
+        let template = "${[ 'a' | wrap(text='b') ]}";
+        let vars = HashMap::new();
+        let opt = RenderOptions::throw();
+        assert_eq!(
+            parse_and_render(template, &vars, &PipeCB {}, &opt).await,
+            Err(RenderError("Argument text of wrap is already set by the pipe".to_string()))
+        );
+        Ok(())
+    }
 
-    for t in tokens.tokens {
-        match t {
-            Token::Raw { text } => doc_str.push(text),
-            Token::Tag { val } => {
-                let val = render_value(val, &vars, cb, opt, depth).await;
-                doc_str.push(opt.error_behavior.handle(val)?)
-            }
-            Token::Eof => {}
-        }
+    #[tokio::test]
+    async fn render_literals() -> Result<()> {
+        let vars = HashMap::from([("a".to_string(), "aaa".to_string())]);
+        let opt = RenderOptions::throw();
+        assert_eq!(parse_and_render("${[ 1.5e3 ]}", &vars, &EmptyCB {}, &opt).await?, "1500");
+        assert_eq!(parse_and_render("${[ -0.25 ]}", &vars, &EmptyCB {}, &opt).await?, "-0.25");
+        assert_eq!(
+            parse_and_render("${[ [1, a, {b: [true, null]}] ]}", &vars, &EmptyCB {}, &opt).await?,
+            r#"[1,"aaa",{"b":[true,null]}]"#
+        );
+        Ok(())
     }
 
-    Ok(doc_str.join(""))
-}
+    #[tokio::test]
+    async fn render_expressions() -> Result<()> {
+        let vars = HashMap::from([
+            ("page".to_string(), "2".to_string()),
+            ("is_prod".to_string(), "true".to_string()),
+            ("version".to_string(), "1.10".to_string()),
+            ("name".to_string(), "yaak".to_string()),
+            ("empty".to_string(), "".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+        for (template, expected) in [
+            ("${[ page + 1 ]}", "3"),
+            ("${[ page * 2 + 1 ]}", "5"),
+            ("${[ page * (2 + 1) ]}", "6"),
+            ("${[ 10 - page - 3 ]}", "5"),
+            ("${[ 7 / 2 ]}", "3.5"),
+            ("${[ 7 % 4 ]}", "3"),
+            ("${[ -page ]}", "-2"),
+            ("${[ is_prod ? 'https' : 'http' ]}://", "https://"),
+            ("${[ !is_prod ? 'https' : 'http' ]}://", "http://"),
+            ("${[ 'v' + version ]}", "v1.10"),
+            ("${[ name + '-' + page ]}", "yaak-2"),
+            ("${[ '1' + 2 ]}", "12"),
+            ("${[ page >= 2 && !empty ]}", "true"),
+            ("${[ name == 'yaak' ]}", "true"),
+            ("${[ page != 2.0 ]}", "false"),
+            ("${[ page == '2' ]}", "true"),
+            ("${[ version == '1.10' ]}", "true"),
+            ("${[ version == 1.1 ]}", "true"),
+            ("${[ '1.10' == 1.1 ]}", "false"),
+            ("${[ 'a' < 'b' ]}", "true"),
+            ("${[ (missing ?? 1) + 1 ]}", "2"),
+            // The right side of && and || and the other branch of a ternary aren't rendered
+            ("${[ is_prod || missing ]}", "true"),
+            ("${[ empty && missing ]}", "false"),
+            ("${[ empty ? missing : 'no' ]}", "no"),
+        ] {
+            let result = parse_and_render(template, &vars, &EmptyCB {}, &opt).await?;
+            assert_eq!(result, expected, "{template}");
+        }
+        Ok(())
+    }
 
-async fn render_value<T: TemplateCallback>(
-    val: Val,
-    vars: &HashMap<String, String>,
-    cb: &T,
-    opt: &RenderOptions,
-    depth: usize,
-) -> Result<String> {
-    let v = match val {
-        Val::Str { text } => {
-            let r = Box::pin(parse_and_render_at_depth(&text, vars, cb, opt, depth)).await?;
-            r.to_string()
+    #[tokio::test]
+    async fn render_expression_errors() -> Result<()> {
+        let vars = HashMap::from([
+            ("page".to_string(), "2".to_string()),
+            ("name".to_string(), "yaak".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+        for (template, expected) in [
+            ("${[ name - 1 ]}", "Operator - expects numbers, but got string and number"),
+            ("${[ page * true ]}", "Operator * expects numbers, but got number and bool"),
+            ("${[ page / 0 ]}", "Division by zero with /"),
+            ("${[ page % 0 ]}", "Division by zero with %"),
+            ("${[ page * 1e308 * 10 ]}", "Result of * isn't a finite number"),
+            ("${[ -1e308 - 1e308 ]}", "Result of - isn't a finite number"),
+            ("${[ name < 1 ]}", "Operator < can't compare string and number"),
+            ("${[ -name ]}", "Operator - expects a number, but got string"),
+        ] {
+            let result = parse_and_render(template, &vars, &EmptyCB {}, &opt).await;
+            assert_eq!(result, Err(RenderError(expected.to_string())), "{template}");
         }
-        Val::Var { name } => match vars.get(name.as_str()) {
-            Some(v) => {
-                let r = Box::pin(parse_and_render_at_depth(v, vars, cb, opt, depth)).await?;
-                r.to_string()
+        assert_eq!(
+            parse_and_render("${[ page + missing ]}", &vars, &EmptyCB {}, &opt).await,
+            Err(VariableNotFound("missing".to_string()))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_literal_fn_args() -> Result<()> {
+        struct CB {}
+        impl TemplateCallback for CB {
+            async fn run(
+                &self,
+                _fn_name: &str,
+                args: HashMap<String, serde_json::Value>,
+            ) -> Result<String> {
+                Ok(serde_json::to_string(&args["x"]).unwrap())
             }
-            None => return Err(VariableNotFound(name)),
-        },
-        Val::Fn { name, args } => {
-            let mut resolved_args: HashMap<String, serde_json::Value> = HashMap::new();
-            for a in args {
-                let v = match a.value.clone() {
-                    Val::Bool { value } => serde_json::Value::Bool(value),
-                    Val::Null => serde_json::Value::Null,
-                    _ => serde_json::Value::String(
-                        Box::pin(render_value(a.value, vars, cb, opt, depth)).await?,
-                    ),
-                };
-                resolved_args.insert(a.name, v);
+
+            fn transform_arg(
+                &self,
+                _fn_name: &str,
+                _arg_name: &str,
+                arg_value: &str,
+            ) -> Result<String> {
+                Ok(arg_value.to_string())
             }
-            let result = cb.run(name.as_str(), resolved_args.clone()).await?;
-            Box::pin(parse_and_render_at_depth(&result, vars, cb, opt, depth)).await?
         }
-        Val::Bool { value } => value.to_string(),
-        Val::Null => "".into(),
-    };
-
-    Ok(v)
-}
-
-#[cfg(test)]
-mod parse_and_render_tests {
-    use crate::error::Error::{RenderError, RenderStackExceededError, VariableNotFound};
-    use crate::error::Result;
-    use crate::renderer::TemplateCallback;
-    use crate::*;
-    use std::collections::HashMap;
 
-    struct EmptyCB {}
+        let vars = HashMap::from([("a".to_string(), "aaa".to_string())]);
+        let opt = RenderOptions::throw();
+        let template = "${[ f(x={n: 2, l: [a, 'b']}) ]} ${[ f(x=3) ]}";
+        assert_eq!(
+            parse_and_render(template, &vars, &CB {}, &opt).await?,
+            r#"{"l":["aaa","b"],"n":2} 3"#
+        );
+        Ok(())
+    }
 
-    impl TemplateCallback for EmptyCB {
+    #[derive(Default)]
+    struct ConcurrentCB {
+        in_flight: std::sync::atomic::AtomicUsize,
+        max_in_flight: std::sync::atomic::AtomicUsize,
+        sequential_running: std::sync::atomic::AtomicBool,
+        sequential_overlapped: std::sync::atomic::AtomicBool,
+    }
+    impl TemplateCallback for ConcurrentCB {
         async fn run(
             &self,
-            _fn_name: &str,
-            _args: HashMap<String, serde_json::Value>,
+            fn_name: &str,
+            args: HashMap<String, serde_json::Value>,
         ) -> Result<String> {
-            todo!()
+            use std::sync::atomic::Ordering::SeqCst;
+            match fn_name {
+                "fail" => return Err(RenderError("Failed".to_string())),
+                "hang" => std::future::pending::<()>().await,
+                _ => {}
+            }
+            let sequential = fn_name == "seq";
+            let n = self.in_flight.fetch_add(1, SeqCst) + 1;
+            self.max_in_flight.fetch_max(n, SeqCst);
+            if self.sequential_running.load(SeqCst) || (sequential && n > 1) {
+                self.sequential_overlapped.store(true, SeqCst);
+            }
+            self.sequential_running.fetch_or(sequential, SeqCst);
+            for _ in 0..5 {
+                tokio::task::yield_now().await;
+            }
+            if sequential {
+                self.sequential_running.store(false, SeqCst);
+            }
+            self.in_flight.fetch_sub(1, SeqCst);
+            Ok(args["i"].as_str().unwrap_or_default().to_string())
+        }
+
+        async fn is_sequential(&self, fn_name: &str) -> bool {
+            fn_name == "seq"
         }
 
         fn transform_arg(
@@ -189,101 +1309,206 @@ mod parse_and_render_tests {
     }
 
     #[tokio::test]
-    async fn render_empty() -> Result<()> {
-        let empty_cb = EmptyCB {};
-        let template = "";
+    async fn render_concurrently_in_order() -> Result<()> {
+        let template = (0..20).map(|i| format!("${{[ f(i='{i}') ]}},")).collect::<String>();
+        let expected = (0..20).map(|i| format!("{i},")).collect::<String>();
+        let cb = ConcurrentCB::default();
+        let opt = RenderOptions::throw();
+
         let vars = HashMap::new();
-        let result = "";
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
-        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        let result = parse_and_render(&template, &vars, &cb, &opt).await?;
+        assert_eq!(result, expected);
+
+        let max_in_flight = cb.max_in_flight.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(max_in_flight > 1);
+        assert!(max_in_flight <= MAX_CONCURRENT_RENDERS);
         Ok(())
     }
 
     #[tokio::test]
-    async fn render_text_only() -> Result<()> {
-        let empty_cb = EmptyCB {};
-        let template = "Hello World!";
+    async fn render_concurrency_limit_is_global() -> Result<()> {
+        // Fields, tags, and function args are all rendered concurrently at their own level, but
+        // the limit covers every call in the render
+        let field = (0..5).map(|i| format!("${{[ f(i=f(i='{i}')) ]}}")).collect::<String>();
+        let value = json!(vec![json!({ "a": field, "b": [field, field] }); 10]);
+        let cb = ConcurrentCB::default();
+        let opt = RenderOptions::throw();
+
         let vars = HashMap::new();
-        let result = "Hello World!";
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
-        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        let result = render_json_value_raw(value, &vars, &cb, &opt).await?;
+        assert_eq!(result[9]["b"][1], json!("01234"));
+
+        let max_in_flight = cb.max_in_flight.load(std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(max_in_flight, MAX_CONCURRENT_RENDERS);
         Ok(())
     }
 
     #[tokio::test]
-    async fn render_simple() -> Result<()> {
-        let empty_cb = EmptyCB {};
-        let template = "${[ foo ]}";
-        let vars = HashMap::from([("foo".to_string(), "bar".to_string())]);
-        let result = "bar";
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
-        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+    async fn render_sequential_fn_alone() -> Result<()> {
+        let template = (0..20)
+            .map(|i| match i % 4 {
+                0 => format!("${{[ seq(i='{i}') ]}},"),
+                _ => format!("${{[ f(i='{i}') ]}},"),
+            })
+            .collect::<String>();
+        let expected = (0..20).map(|i| format!("{i},")).collect::<String>();
+        let cb = ConcurrentCB::default();
+        let opt = RenderOptions::throw();
+
+        let vars = HashMap::new();
+        let result = parse_and_render(&template, &vars, &cb, &opt).await?;
+        assert_eq!(result, expected);
+        assert!(!cb.sequential_overlapped.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(cb.max_in_flight.load(std::sync::atomic::Ordering::SeqCst) > 1);
         Ok(())
     }
 
     #[tokio::test]
-    async fn render_recursive_var() -> Result<()> {
-        let empty_cb = EmptyCB {};
-        let template = "${[ foo ]}";
-        let mut vars = HashMap::new();
-        vars.insert("foo".to_string(), "foo: ${[ bar ]}".to_string());
-        vars.insert("bar".to_string(), "bar: ${[ baz ]}".to_string());
-        vars.insert("baz".to_string(), "baz".to_string());
+    async fn render_concurrently_fails_fast() -> Result<()> {
+        let cb = ConcurrentCB::default();
+        let opt = RenderOptions::throw();
 
-        let result = "foo: bar: baz";
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
-        assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
+        // The first tag never finishes, so this only returns if the error doesn't wait for it
+        let vars = HashMap::new();
+        let result = parse_and_render("${[ hang() ]} ${[ fail() ]}", &vars, &cb, &opt).await;
+        assert_eq!(result, Err(RenderError("Failed".to_string())));
         Ok(())
     }
 
+    #[test]
+    fn render_future_is_send() {
+        fn assert_send<T: Send>(_: T) {}
+        let vars = HashMap::new();
+        let opt = RenderOptions::throw();
+        assert_send(parse_and_render("${[ a ]}", &vars, &EmptyCB {}, &opt));
+        assert_send(render_json_value_raw(json!({"a": ["${[ a ]}"]}), &vars, &EmptyCB {}, &opt));
+    }
+
     #[tokio::test]
-    async fn render_missing_var() -> Result<()> {
+    async fn render_self_referencing_var() -> Result<()> {
         let empty_cb = EmptyCB {};
         let template = "${[ foo ]}";
-        let vars = HashMap::new();
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let mut vars = HashMap::new();
+        vars.insert("foo".to_string(), "${[ foo ]}".to_string());
+        let opt = RenderOptions::throw();
         assert_eq!(
             parse_and_render(template, &vars, &empty_cb, &opt).await,
-            Err(VariableNotFound("foo".to_string()))
+            Err(VariableCycle(vec![var_step("foo"), var_step("foo")]))
         );
         Ok(())
     }
 
+    fn var_step(name: &str) -> CycleStep {
+        CycleStep {
+            name: name.to_string(),
+            is_fn: false,
+            source: None,
+        }
+    }
+
     #[tokio::test]
-    async fn render_self_referencing_var() -> Result<()> {
-        let empty_cb = EmptyCB {};
-        let template = "${[ foo ]}";
-        let mut vars = HashMap::new();
-        vars.insert("foo".to_string(), "${[ foo ]}".to_string());
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
+    async fn render_var_cycle() -> Result<()> {
+        let vars = HashMap::from([
+            ("a".to_string(), "${[ b ]}".to_string()),
+            ("b".to_string(), "x ${[ c ]}".to_string()),
+            ("c".to_string(), "${[ a ]}".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+        let err = parse_and_render("${[ a ]}", &vars, &EmptyCB {}, &opt).await.unwrap_err();
+        assert_eq!(
+            err,
+            VariableCycle(vec![var_step("a"), var_step("b"), var_step("c"), var_step("a")])
+        );
+        assert_eq!(err.to_string(), "Render Error: Variable references itself (a → b → c → a)");
+
+        let global = TraceSource {
+            environment_id: "ev_1".to_string(),
+            environment_name: "Global".to_string(),
+            parent_model: "workspace".to_string(),
+            parent_id: None,
+        };
+        let production = TraceSource {
+            environment_id: "ev_2".to_string(),
+            environment_name: "Production".to_string(),
+            parent_model: "folder".to_string(),
+            parent_id: Some("fl_1".to_string()),
         };
+        let sources = HashMap::from([
+            ("a".to_string(), global.clone()),
+            ("b".to_string(), production.clone()),
+        ]);
+        let err = err.with_variable_sources(&sources);
         assert_eq!(
-            parse_and_render(template, &vars, &empty_cb, &opt).await,
-            Err(RenderStackExceededError)
+            err.to_string(),
+            "Render Error: Variable references itself (a (Global) → b (Production) → c → a (Global))"
+        );
+        let VariableCycle(steps) = err else {
+            panic!("Expected a cycle")
+        };
+        assert_eq!(steps[0].source, Some(global));
+        assert_eq!(steps[1].source, Some(production));
+        assert_eq!(steps[2].source, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_var_cycle_through_fn() -> Result<()> {
+        let vars = HashMap::from([
+            ("a".to_string(), "${[ wrap(text=b) ]}".to_string()),
+            ("b".to_string(), "b".to_string()),
+            ("c".to_string(), "${[ a ]}".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+
+        // The function returns a template that references the variable it was called from
+        struct CB {}
+        impl TemplateCallback for CB {
+            async fn run(
+                &self,
+                _fn_name: &str,
+                _args: HashMap<String, serde_json::Value>,
+            ) -> Result<String> {
+                Ok("${[ c ]}".to_string())
+            }
+
+            fn transform_arg(
+                &self,
+                _fn_name: &str,
+                _arg_name: &str,
+                arg_value: &str,
+            ) -> Result<String> {
+                Ok(arg_value.to_string())
+            }
+        }
+
+        let result = parse_and_render("${[ a ]}", &vars, &CB {}, &opt).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Render Error: Variable references itself (a → wrap() → c → a)"
         );
         Ok(())
     }
 
+    #[tokio::test]
+    async fn render_repeated_var_is_not_cycle() -> Result<()> {
+        let vars = HashMap::from([
+            ("a".to_string(), "${[ b ]}-${[ b ]}".to_string()),
+            ("b".to_string(), "${[ c ]}".to_string()),
+            ("c".to_string(), "c".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+        let result = parse_and_render("${[ a ]} ${[ b ]}", &vars, &EmptyCB {}, &opt).await?;
+        assert_eq!(result, "c-c c");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn render_surrounded() -> Result<()> {
         let empty_cb = EmptyCB {};
         let template = "hello ${[ word ]} world!";
         let vars = HashMap::from([("word".to_string(), "cruel".to_string())]);
         let result = "hello cruel world!";
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
         assert_eq!(parse_and_render(template, &vars, &empty_cb, &opt).await?, result.to_string());
         Ok(())
     }
@@ -293,9 +1518,7 @@ mod parse_and_render_tests {
         let vars = HashMap::new();
         let template = r#"${[ say_hello(a='John', b='Kate') ]}"#;
         let result = r#"say_hello: 2, Some(String("John")) Some(String("Kate"))"#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         struct CB {}
         impl TemplateCallback for CB {
@@ -325,9 +1548,7 @@ mod parse_and_render_tests {
         let vars = HashMap::new();
         let template = r#"${[ upper(foo='bar') ]}"#;
         let result = r#""BAR""#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
         struct CB {}
         impl TemplateCallback for CB {
             async fn run(
@@ -362,9 +1583,7 @@ mod parse_and_render_tests {
         vars.insert("foo".to_string(), "bar".to_string());
         let template = r#"${[ upper(foo=b64'Zm9vICdiYXInIGJheg') ]}"#;
         let result = r#""FOO 'BAR' BAZ""#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
         struct CB {}
         impl TemplateCallback for CB {
             async fn run(
@@ -398,9 +1617,7 @@ mod parse_and_render_tests {
         vars.insert("foo".to_string(), "bar".to_string());
         let template = r#"${[ upper(foo='${[ foo ]}') ]}"#;
         let result = r#""BAR""#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         struct CB {}
         impl TemplateCallback for CB {
@@ -436,9 +1653,7 @@ mod parse_and_render_tests {
         vars.insert("foo".to_string(), "bar".to_string());
         let template = r#"${[ no_op(inner='${[ foo ]}') ]}"#;
         let result = r#""bar""#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         struct CB {}
         impl TemplateCallback for CB {
@@ -473,9 +1688,7 @@ mod parse_and_render_tests {
         let template = r#"${[ upper(foo=secret()) ]}"#;
         let result = r#""ABC""#;
 
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
         struct CB {}
         impl TemplateCallback for CB {
             async fn run(
@@ -507,9 +1720,7 @@ mod parse_and_render_tests {
     async fn render_fn_err() -> Result<()> {
         let vars = HashMap::new();
         let template = r#"hello ${[ error() ]}"#;
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         struct CB {}
         impl TemplateCallback for CB {
@@ -537,13 +1748,165 @@ mod parse_and_render_tests {
         );
         Ok(())
     }
+
+    fn trace_step(
+        kind: TraceStepKind,
+        name: &str,
+        value: Option<&str>,
+        steps: Vec<TraceStep>,
+    ) -> TraceStep {
+        TraceStep {
+            kind,
+            name: name.to_string(),
+            args: None,
+            value: value.map(|v| v.to_string()),
+            error: None,
+            source: None,
+            redacted: false,
+            steps,
+        }
+    }
+
+    #[tokio::test]
+    async fn render_with_trace() -> Result<()> {
+        let template = "Hi ${[ greeting ]}${[ nope ]}";
+        let vars = HashMap::from([
+            ("greeting".to_string(), "hello ${[ name | upper ]}".to_string()),
+            ("name".to_string(), "bob".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+
+        let trace = parse_and_render_with_trace(template, &vars, &PipeCB {}, &opt).await?;
+        assert_eq!(trace.output, "Hi hello BOB");
+        assert_eq!(trace.tags.len(), 2);
+
+        let greeting = &trace.tags[0];
+        assert_eq!(greeting.tag, "${[ greeting ]}");
+        assert_eq!((greeting.span.start, greeting.span.end), (3, 18));
+        assert_eq!(greeting.value, Some("hello BOB".to_string()));
+        let upper = TraceStep {
+            args: Some(HashMap::from([("value".to_string(), json!("bob"))])),
+            ..trace_step(TraceStepKind::Fn, "upper", Some("BOB"), vec![])
+        };
+        assert_eq!(
+            greeting.steps,
+            vec![trace_step(
+                TraceStepKind::Var,
+                "greeting",
+                Some("hello BOB"),
+                vec![
+                    trace_step(TraceStepKind::Var, "name", Some("bob"), vec![]),
+                    upper
+                ],
+            )]
+        );
+
+        let nope = &trace.tags[1];
+        let error = VariableNotFound("nope".to_string()).to_string();
+        assert_eq!(nope.value, None);
+        assert_eq!(nope.error, Some(error.clone()));
+        assert_eq!(
+            nope.steps,
+            vec![TraceStep {
+                error: Some(error),
+                ..trace_step(TraceStepKind::Var, "nope", None, vec![])
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_with_trace_sources() -> Result<()> {
+        let template = "${[ a ?? b ]}";
+        let vars = HashMap::from([("b".to_string(), "${[ c ]}".to_string())]);
+        let opt = RenderOptions::return_empty();
+        let source = TraceSource {
+            environment_id: "ev_1".to_string(),
+            environment_name: "Global".to_string(),
+            parent_model: "workspace".to_string(),
+            parent_id: Some("wk_1".to_string()),
+        };
+        let sources = HashMap::from([("b".to_string(), source.clone())]);
+
+        let trace = parse_and_render_with_trace(template, &vars, &EmptyCB {}, &opt).await?;
+        let tag = trace.tags[0].clone().with_variable_sources(&sources);
+        let steps = tag.steps.iter().map(|s| (s.name.as_str(), s.source.clone()));
+        assert_eq!(steps.collect::<Vec<_>>(), vec![("a", None), ("b", Some(source))]);
+        // The missing var nested in b is traced even though it rendered empty
+        assert_eq!(tag.steps[1].steps[0].name, "c");
+        assert!(tag.steps[1].steps[0].error.is_some());
+        assert_eq!(tag.value, Some("".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_with_trace_redacted() -> Result<()> {
+        let template = "${[ token ]} ${[ upper(value=token) ]} ${[ name ]}";
+        let vars = HashMap::from([
+            ("token".to_string(), "${[ upper(value='secret') ]}".to_string()),
+            ("name".to_string(), "bob".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+
+        let trace = parse_and_render_with_trace(template, &vars, &PipeCB {}, &opt).await?;
+        let secret_vars = HashSet::from(["token".to_string()]);
+        let tags = trace
+            .tags
+            .into_iter()
+            .map(|t| t.redact_secrets(&secret_vars, &["secure"]))
+            .collect::<Vec<_>>();
+        assert!(!serde_json::to_string(&tags).unwrap().contains("SECRET"));
+
+        // The secret var, and what it rendered, are left out
+        assert!(tags[0].redacted);
+        assert_eq!(tags[0].value, None);
+        let token = &tags[0].steps[0];
+        assert_eq!((token.redacted, token.value.clone()), (true, None));
+        let inner = &token.steps[0];
+        assert_eq!((inner.redacted, inner.value.clone()), (true, None));
+        assert_eq!(inner.args, None);
+
+        // A function with a secret arg is left out, args and all
+        let upper = &tags[1].steps[0];
+        assert_eq!((upper.redacted, upper.value.clone(), upper.args.clone()), (true, None, None));
+
+        assert!(!tags[2].redacted);
+        assert_eq!(tags[2].value, Some("bob".to_string()));
+
+        // Secret functions are left out, but their own args aren't secret
+        let vars = HashMap::new();
+        let trace =
+            parse_and_render_with_trace("${[ upper(value='abc') ]}", &vars, &PipeCB {}, &opt)
+                .await?;
+        let tag = trace.tags[0].clone().redact_secrets(&HashSet::new(), &["upper"]);
+        assert_eq!((tag.redacted, tag.value), (true, None));
+        assert_eq!(tag.steps[0].args, Some(HashMap::from([("value".to_string(), json!("abc"))])));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_json_with_trace() -> Result<()> {
+        let v = json!({"${[ k ]}": ["${[ a ]}", 1, "${[ b ]}"]});
+        let vars = HashMap::from([
+            ("k".to_string(), "key".to_string()),
+            ("a".to_string(), "aaa".to_string()),
+            ("b".to_string(), "bbb".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+
+        let (v, tags) = render_json_value_with_trace(v, &vars, &EmptyCB {}, &opt).await?;
+        assert_eq!(v, json!({"key": ["aaa", 1, "bbb"]}));
+        let tags = tags.iter().map(|t| t.tag.as_str()).collect::<Vec<_>>();
+        assert_eq!(tags, vec!["${[ k ]}", "${[ a ]}", "${[ b ]}"]);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod render_json_value_raw_tests {
     use crate::error::Result;
     use crate::{
-        RenderErrorBehavior, RenderOptions, TemplateCallback, parse_and_render,
+        RenderOptions, TemplateCallback, parse_and_render, parse_and_render_json_typed,
         render_json_value_raw,
     };
     use serde_json::json;
@@ -575,9 +1938,7 @@ mod render_json_value_raw_tests {
         let v = json!("${[a]}");
         let mut vars = HashMap::new();
         vars.insert("a".to_string(), "aaa".to_string());
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         assert_eq!(render_json_value_raw(v, &vars, &EmptyCB {}, &opt).await?, json!("aaa"));
         Ok(())
@@ -588,9 +1949,7 @@ mod render_json_value_raw_tests {
         let v = json!(["${[a]}", "${[a]}"]);
         let mut vars = HashMap::new();
         vars.insert("a".to_string(), "aaa".to_string());
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         let result = render_json_value_raw(v, &vars, &EmptyCB {}, &opt).await?;
         assert_eq!(result, json!(["aaa", "aaa"]));
@@ -603,9 +1962,7 @@ mod render_json_value_raw_tests {
         let v = json!({"${[a]}": "${[a]}"});
         let mut vars = HashMap::new();
         vars.insert("a".to_string(), "aaa".to_string());
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         let result = render_json_value_raw(v, &vars, &EmptyCB {}, &opt).await?;
         assert_eq!(result, json!({"aaa": "aaa"}));
@@ -625,9 +1982,7 @@ mod render_json_value_raw_tests {
         ]);
         let mut vars = HashMap::new();
         vars.insert("a".to_string(), "aaa".to_string());
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        };
+        let opt = RenderOptions::throw();
 
         let result = render_json_value_raw(v, &vars, &EmptyCB {}, &opt).await?;
         assert_eq!(
@@ -645,12 +2000,72 @@ mod render_json_value_raw_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn render_json_typed() -> Result<()> {
+        let vars = HashMap::from([
+            ("n".to_string(), "42".to_string()),
+            ("o".to_string(), r#"{"x": [1]}"#.to_string()),
+            ("s".to_string(), "hello".to_string()),
+        ]);
+        let opt = RenderOptions::throw();
+
+        let template = r#"{
+  "n": "${[ n ]}",
+  "o": "${[ o ]}",
+  "s": "${[ s ]}",
+  "lit": ["${[ [1, {a: true}] ]}", "${[ 'str' ]}", "${[ null ]}"],
+  "mixed": "n=${[ n ]}",
+  "escaped": "\"${[ n ]}\"",
+  "${[ s ]}": 1
+}"#;
+        assert_eq!(
+            parse_and_render_json_typed(template, &vars, &EmptyCB {}, &opt).await?,
+            r#"{
+  "n": 42,
+  "o": {"x":[1]},
+  "s": "hello",
+  "lit": [[1,{"a":true}], "str", null],
+  "mixed": "n=42",
+  "escaped": "\"42\"",
+  "hello": 1
+}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_json_typed_keys() -> Result<()> {
+        let vars = HashMap::from([("n".to_string(), "42".to_string())]);
+        let opt = RenderOptions::throw();
+
+        // Keys stay strings, even when the tag's value is a number
+        let template = "{\"${[ n ]}\": \"${[ n ]}\", \"${[ n ]}\"\n  : [\"${[ n ]}\"]}";
+        assert_eq!(
+            parse_and_render_json_typed(template, &vars, &EmptyCB {}, &opt).await?,
+            "{\"42\": 42, \"42\"\n  : [42]}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn render_json_typed_error() -> Result<()> {
+        let vars = HashMap::new();
+        let template = r#"{"a": "${[ missing ]}"}"#;
+
+        let opt = RenderOptions::return_empty();
+        let result = parse_and_render_json_typed(template, &vars, &EmptyCB {}, &opt).await?;
+        assert_eq!(result, r#"{"a": ""}"#);
+
+        let opt = RenderOptions::throw();
+        let result = parse_and_render_json_typed(template, &vars, &EmptyCB {}, &opt).await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn render_opt_return_empty() -> Result<()> {
         let vars = HashMap::new();
-        let opt = RenderOptions {
-            error_behavior: RenderErrorBehavior::ReturnEmpty,
-        };
+        let opt = RenderOptions::return_empty();
 
         let result = parse_and_render("DNE: ${[hello]}", &vars, &EmptyCB {}, &opt).await?;
         assert_eq!(result, "DNE: ".to_string());
@@ -0,0 +1,216 @@
+use crate::Span;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use ts_rs::TS;
+
+/// A rendered template, along with a trace of how each of its tags was resolved
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "trace.ts")]
+pub struct RenderTrace {
+    pub output: String,
+    pub tags: Vec<TagTrace>,
+}
+
+/// How one tag was rendered: its value (or error), and the variables and functions that were
+/// resolved to get there
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "trace.ts")]
+pub struct TagTrace {
+    /// Source of the tag, like `${[ token ]}`
+    pub tag: String,
+    pub span: Span,
+    #[ts(optional)]
+    pub value: Option<String>,
+    #[ts(optional)]
+    pub error: Option<String>,
+    /// The value was left out because it came from a secret
+    #[serde(default)]
+    pub redacted: bool,
+    pub steps: Vec<TraceStep>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "trace.ts")]
+pub enum TraceStepKind {
+    Var,
+    Fn,
+}
+
+/// A variable or function call that was resolved while rendering a tag
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "trace.ts")]
+pub struct TraceStep {
+    pub kind: TraceStepKind,
+    pub name: String,
+    /// Resolved args of a function call
+    #[ts(optional)]
+    pub args: Option<HashMap<String, serde_json::Value>>,
+    #[ts(optional)]
+    pub value: Option<String>,
+    #[ts(optional)]
+    pub error: Option<String>,
+    /// Where a variable was defined. The renderer only sees the merged variables, so this is
+    /// filled in afterward with [`TagTrace::with_variable_sources`].
+    #[ts(optional)]
+    pub source: Option<TraceSource>,
+    /// The value (and the args, if they were rendered from a secret) was left out because it
+    /// came from a secret
+    #[serde(default)]
+    pub redacted: bool,
+    /// Variables and functions resolved while rendering this one, in the order they started
+    pub steps: Vec<TraceStep>,
+}
+
+/// The environment a variable was defined in, and the workspace or folder that owns it
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "trace.ts")]
+pub struct TraceSource {
+    pub environment_id: String,
+    pub environment_name: String,
+    pub parent_model: String,
+    #[ts(optional)]
+    pub parent_id: Option<String>,
+}
+
+impl TagTrace {
+    /// Fill in where each variable in the trace was defined, from a map of variable names to
+    /// sources
+    pub fn with_variable_sources(self, sources: &HashMap<String, TraceSource>) -> TagTrace {
+        TagTrace {
+            steps: steps_with_sources(self.steps, sources),
+            ..self
+        }
+    }
+
+    /// Leave out the values of secret variables and functions (like `secure`), of everything they
+    /// were rendered from, and of everything rendered from them, so the trace can be shown or
+    /// passed to plugins without leaking secrets
+    pub fn redact_secrets(self, secret_vars: &HashSet<String>, secret_fns: &[&str]) -> TagTrace {
+        let (steps, redacted) = redact_steps(self.steps, secret_vars, secret_fns, false);
+        TagTrace {
+            value: if redacted { None } else { self.value },
+            redacted,
+            steps,
+            ..self
+        }
+    }
+}
+
+/// Redact the steps, returning whether any of them were. Everything inside a secret step is
+/// redacted, since it's part of the secret.
+fn redact_steps(
+    steps: Vec<TraceStep>,
+    secret_vars: &HashSet<String>,
+    secret_fns: &[&str],
+    in_secret: bool,
+) -> (Vec<TraceStep>, bool) {
+    let mut any_redacted = false;
+    let steps = steps
+        .into_iter()
+        .map(|s| {
+            let secret = in_secret
+                || match s.kind {
+                    TraceStepKind::Var => secret_vars.contains(&s.name),
+                    TraceStepKind::Fn => secret_fns.contains(&s.name.as_str()),
+                };
+            let (steps, nested_secret) = redact_steps(s.steps, secret_vars, secret_fns, secret);
+            if !secret && !nested_secret {
+                return TraceStep { steps, ..s };
+            }
+            any_redacted = true;
+            TraceStep {
+                value: None,
+                // Args are rendered from the nested steps, so they'd contain the secret too. The
+                // args of a secret function itself (like the encrypted value) are fine to keep.
+                args: if nested_secret || in_secret { None } else { s.args },
+                redacted: true,
+                steps,
+                ..s
+            }
+        })
+        .collect();
+    (steps, any_redacted)
+}
+
+fn steps_with_sources(
+    steps: Vec<TraceStep>,
+    sources: &HashMap<String, TraceSource>,
+) -> Vec<TraceStep> {
+    steps
+        .into_iter()
+        .map(|s| TraceStep {
+            source: match s.kind {
+                TraceStepKind::Var => sources.get(&s.name).cloned(),
+                TraceStepKind::Fn => None,
+            },
+            steps: steps_with_sources(s.steps, sources),
+            ..s
+        })
+        .collect()
+}
+
+/// Collects the steps of a trace while rendering. Tags and args render concurrently, so steps are
+/// kept in the order they started rather than the order they finished.
+#[derive(Clone, Default)]
+pub(crate) struct TraceCollector {
+    steps: Arc<Mutex<Vec<(usize, TraceStep)>>>,
+    counter: Arc<AtomicUsize>,
+}
+
+impl TraceCollector {
+    /// Start a step. Steps recorded by its `children` collector are nested under it.
+    pub(crate) fn start(&self, kind: TraceStepKind, name: &str) -> PendingStep {
+        PendingStep {
+            parent: self.clone(),
+            children: TraceCollector {
+                steps: Default::default(),
+                counter: self.counter.clone(),
+            },
+            order: self.counter.fetch_add(1, Ordering::SeqCst),
+            kind,
+            name: name.to_string(),
+            args: None,
+        }
+    }
+
+    pub(crate) fn take(&self) -> Vec<TraceStep> {
+        let mut steps = std::mem::take(&mut *self.steps.lock().unwrap());
+        steps.sort_by_key(|(order, _)| *order);
+        steps.into_iter().map(|(_, s)| s).collect()
+    }
+}
+
+/// A step that's still being rendered
+pub(crate) struct PendingStep {
+    parent: TraceCollector,
+    pub(crate) children: TraceCollector,
+    order: usize,
+    kind: TraceStepKind,
+    name: String,
+    args: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl PendingStep {
+    pub(crate) fn set_args(&mut self, args: &HashMap<String, serde_json::Value>) {
+        self.args = Some(args.clone());
+    }
+
+    pub(crate) fn finish(self, r: &Result<String>) {
+        let step = TraceStep {
+            kind: self.kind,
+            name: self.name,
+            args: self.args,
+            value: r.as_ref().ok().cloned(),
+            error: r.as_ref().err().map(|e| e.to_string()),
+            source: None,
+            redacted: false,
+            steps: self.children.take(),
+        };
+        self.parent.steps.lock().unwrap().push((self.order, step));
+    }
+}
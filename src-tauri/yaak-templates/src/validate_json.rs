@@ -0,0 +1,867 @@
+use crate::parser::utf16_offsets;
+use crate::{DiagnosticSeverity, Parser, Span, Token};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+/// How deeply arrays and objects can be nested before validation gives up
+const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "validate_json.ts")]
+pub enum JsonProblemKind {
+    Syntax,
+    Comment,
+    TrailingComma,
+    Schema,
+}
+
+/// A problem with JSON that may contain template tags. Syntax and schema problems are errors.
+/// Comments and trailing commas are warnings, since they can be stripped before sending.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "validate_json.ts")]
+pub struct JsonProblem {
+    pub kind: JsonProblemKind,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// Line of the problem, starting at 1
+    pub line: usize,
+    /// Column of the problem in chars, starting at 1
+    pub column: usize,
+    pub span: Span,
+}
+
+/// Check that JSON will be valid once its template tags are rendered, and optionally that it
+/// matches a JSON Schema. Tags outside of strings are placeholders for any value, so they're never
+/// a problem themselves. Only the first syntax error is reported, since everything after it is
+/// ambiguous.
+///
+/// Schemas support `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+/// `items`, the length, size, and range keywords, `allOf`/`anyOf`/`oneOf`, and local `$ref`s.
+/// Other keywords are ignored.
+pub fn validate_json(text: &str, schema: Option<&Value>) -> Vec<JsonProblem> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut problems = Vec::new();
+    let mut push = |kind, message: String, span: Span| {
+        problems.push(JsonProblem {
+            kind,
+            severity: match kind {
+                JsonProblemKind::Comment | JsonProblemKind::TrailingComma => {
+                    DiagnosticSeverity::Warning
+                }
+                JsonProblemKind::Syntax | JsonProblemKind::Schema => DiagnosticSeverity::Error,
+            },
+            message,
+            line: 0,
+            column: 0,
+            span,
+        })
+    };
+
+    let (tokens, lex_error) = lex(text, &chars);
+    for t in &tokens {
+        if let LexemeKind::Comment = t.kind {
+            push(JsonProblemKind::Comment, "Comments aren't valid JSON".to_string(), t.span);
+        }
+    }
+
+    let tokens = tokens.into_iter().filter(|t| t.kind != LexemeKind::Comment).collect::<Vec<_>>();
+    let mut parser = JsonParser {
+        tokens: &tokens,
+        pos: 0,
+        lex_error: lex_error.clone(),
+        trailing_commas: Vec::new(),
+    };
+    let parsed = match tokens.is_empty() {
+        // Nothing to send is fine
+        true => Ok(None),
+        false => parser.parse_document().map(Some),
+    };
+    for span in parser.trailing_commas {
+        push(JsonProblemKind::TrailingComma, "Trailing commas aren't valid JSON".to_string(), span);
+    }
+
+    // The parser reports a lex error when it runs out of tokens, but a lex error can also come
+    // after a complete value
+    match (parsed, lex_error) {
+        (Err((message, span)), _) | (Ok(_), Some((message, span))) => {
+            push(JsonProblemKind::Syntax, message, span);
+        }
+        (Ok(Some(node)), None) => {
+            if let Some(schema) = schema {
+                let mut errors = Vec::new();
+                check_schema(&node.0, node.1, schema, schema, "", &mut errors);
+                for (message, span) in errors {
+                    push(JsonProblemKind::Schema, message, span);
+                }
+            }
+        }
+        (Ok(None), None) => {}
+    }
+
+    // Spans are in chars until here, but are returned in UTF-16 like the parser's
+    let offsets = utf16_offsets(&chars);
+    problems.sort_by_key(|p| p.span.start);
+    for p in problems.iter_mut() {
+        (p.line, p.column) = line_and_column(&chars, p.span.start);
+        p.span = Span::new(offsets[p.span.start], offsets[p.span.end]);
+    }
+    problems
+}
+
+/// Remove comments and trailing commas, so JSON that's easier to write is still valid to send.
+/// Strings and template tags are left as they are.
+pub fn strip_json_comments(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    let (tokens, _) = lex(text, &chars);
+
+    let mut removed = Vec::new();
+    let mut significant = tokens.iter().filter(|t| t.kind != LexemeKind::Comment).peekable();
+    while let Some(t) = significant.next() {
+        let next_closes = matches!(significant.peek(), Some(n) if n.kind == LexemeKind::Close);
+        if t.kind == LexemeKind::Comma && next_closes {
+            removed.push(t.span);
+        }
+    }
+    removed.extend(tokens.iter().filter(|t| t.kind == LexemeKind::Comment).map(|t| t.span));
+    removed.sort_by_key(|s| s.start);
+
+    let mut stripped = String::new();
+    let mut pos = 0;
+    for span in removed {
+        stripped.extend(&chars[pos..span.start]);
+        pos = span.end;
+    }
+    stripped.extend(&chars[pos..]);
+    stripped
+}
+
+fn line_and_column(chars: &[char], pos: usize) -> (usize, usize) {
+    let before = &chars[..pos.min(chars.len())];
+    let line = before.iter().filter(|c| **c == '\n').count() + 1;
+    let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+    (line, column)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LexemeKind {
+    Open(char),
+    Close,
+    Colon,
+    Comma,
+    Str,
+    /// Unquoted `true`, `false`, `null`, or a number
+    Literal,
+    /// One or more template tags, possibly joined with a literal (like `${[ n ]}00`)
+    Tag,
+    Comment,
+}
+
+#[derive(Clone, Debug)]
+struct Lexeme {
+    kind: LexemeKind,
+    span: Span,
+    /// Contents of a string (unescaped) or literal
+    text: String,
+    /// Whether a string contains a template tag, so its value isn't known until it's rendered
+    has_tag: bool,
+}
+
+type SyntaxError = (String, Span);
+
+/// Split JSON into tokens, stopping at the first character that can't start one
+fn lex(text: &str, chars: &[char]) -> (Vec<Lexeme>, Option<SyntaxError>) {
+    // Use the template parser to find tags, so tags containing quotes or brackets are skipped
+    // correctly. A tag that doesn't parse is left as text, which isn't valid JSON either.
+    let mut tag_ends = HashMap::new();
+    if let Ok(tokens) = Parser::new(text).parse() {
+        let char_positions: HashMap<usize, usize> =
+            utf16_offsets(chars).into_iter().enumerate().map(|(i, o)| (o, i)).collect();
+        for t in tokens.tokens {
+            if let Token::Tag { span, .. } = t {
+                tag_ends.insert(char_positions[&span.start], char_positions[&span.end]);
+            }
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let token = |kind, start: usize, end: usize, text: String, has_tag| Lexeme {
+        kind,
+        span: Span::new(start, end),
+        text,
+        has_tag,
+    };
+
+    while pos < chars.len() {
+        let start = pos;
+        let c = chars[pos];
+        match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                pos += 1;
+            }
+            '{' | '[' => {
+                tokens.push(token(LexemeKind::Open(c), start, pos + 1, c.to_string(), false));
+                pos += 1;
+            }
+            '}' | ']' => {
+                tokens.push(token(LexemeKind::Close, start, pos + 1, c.to_string(), false));
+                pos += 1;
+            }
+            ':' => {
+                tokens.push(token(LexemeKind::Colon, start, pos + 1, c.to_string(), false));
+                pos += 1;
+            }
+            ',' => {
+                tokens.push(token(LexemeKind::Comma, start, pos + 1, c.to_string(), false));
+                pos += 1;
+            }
+            '/' if chars.get(pos + 1) == Some(&'/') => {
+                while pos < chars.len() && chars[pos] != '\n' {
+                    pos += 1;
+                }
+                tokens.push(token(LexemeKind::Comment, start, pos, String::new(), false));
+            }
+            '/' if chars.get(pos + 1) == Some(&'*') => {
+                pos += 2;
+                while pos < chars.len() && !(chars[pos] == '*' && chars.get(pos + 1) == Some(&'/'))
+                {
+                    pos += 1;
+                }
+                if pos >= chars.len() {
+                    let span = Span::new(start, chars.len());
+                    return (tokens, Some(("Unterminated comment".to_string(), span)));
+                }
+                pos += 2;
+                tokens.push(token(LexemeKind::Comment, start, pos, String::new(), false));
+            }
+            '"' => match lex_string(chars, pos, &tag_ends) {
+                Ok((end, text, has_tag)) => {
+                    tokens.push(token(LexemeKind::Str, start, end, text, has_tag));
+                    pos = end;
+                }
+                Err(e) => return (tokens, Some(e)),
+            },
+            _ => {
+                let mut has_tag = false;
+                while pos < chars.len() {
+                    if let Some(end) = tag_ends.get(&pos) {
+                        has_tag = true;
+                        pos = *end;
+                    } else if is_literal_char(chars[pos]) {
+                        pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                if pos == start {
+                    let message = format!("Unexpected character '{c}'");
+                    return (tokens, Some((message, Span::new(start, start + 1))));
+                }
+
+                let text = chars[start..pos].iter().collect::<String>();
+                if has_tag {
+                    tokens.push(token(LexemeKind::Tag, start, pos, text, true));
+                } else if is_valid_literal(&text) {
+                    tokens.push(token(LexemeKind::Literal, start, pos, text, false));
+                } else {
+                    let message = format!("Unexpected token '{text}'");
+                    return (tokens, Some((message, Span::new(start, pos))));
+                }
+            }
+        }
+    }
+
+    (tokens, None)
+}
+
+/// Lex a string starting at its opening quote, returning where it ends, its unescaped contents,
+/// and whether it contains a template tag
+fn lex_string(
+    chars: &[char],
+    start: usize,
+    tag_ends: &HashMap<usize, usize>,
+) -> Result<(usize, String, bool), SyntaxError> {
+    let mut text = String::new();
+    let mut has_tag = false;
+    let mut pos = start + 1;
+    loop {
+        if let Some(end) = tag_ends.get(&pos) {
+            text.extend(&chars[pos..*end]);
+            has_tag = true;
+            pos = *end;
+            continue;
+        }
+
+        match chars.get(pos) {
+            None | Some('\n') => {
+                let span = Span::new(start, pos);
+                return Err(("Unterminated string".to_string(), span));
+            }
+            Some('"') => return Ok((pos + 1, text, has_tag)),
+            Some('\\') => {
+                let escaped = match chars.get(pos + 1) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let hex = chars.get(pos + 2..pos + 6).map(|h| h.iter().collect::<String>());
+                        match hex.and_then(|h| u32::from_str_radix(&h, 16).ok()) {
+                            Some(code) => {
+                                text.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                                pos += 6;
+                                continue;
+                            }
+                            None => {
+                                let span = Span::new(pos, (pos + 6).min(chars.len()));
+                                return Err(("Invalid unicode escape".to_string(), span));
+                            }
+                        }
+                    }
+                    _ => {
+                        let span = Span::new(pos, (pos + 2).min(chars.len()));
+                        return Err(("Invalid escape in string".to_string(), span));
+                    }
+                };
+                text.push(escaped);
+                pos += 2;
+            }
+            // Only U+0000 to U+001F must be escaped. Other control characters are allowed as-is.
+            Some(c) if *c < '\u{20}' => {
+                let span = Span::new(pos, pos + 1);
+                return Err(("Control characters must be escaped in strings".to_string(), span));
+            }
+            Some(c) => {
+                text.push(*c);
+                pos += 1;
+            }
+        }
+    }
+}
+
+fn is_literal_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.'
+}
+
+fn is_valid_literal(text: &str) -> bool {
+    matches!(text, "true" | "false" | "null") || is_valid_number(text)
+}
+
+/// Whether the text matches JSON's number grammar, which is stricter than Rust's
+fn is_valid_number(text: &str) -> bool {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let mut rest = text.strip_prefix('-').unwrap_or(text);
+    let int_len = digits(rest);
+    if int_len == 0 || (int_len > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    rest = &rest[int_len..];
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let fraction_len = digits(fraction);
+        if fraction_len == 0 {
+            return false;
+        }
+        rest = &fraction[fraction_len..];
+    }
+
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let exponent_len = digits(exponent);
+        if exponent_len == 0 {
+            return false;
+        }
+        rest = &exponent[exponent_len..];
+    }
+
+    rest.is_empty()
+}
+
+/// A parsed JSON value, where template tags are placeholders
+#[derive(Clone, Debug)]
+enum Node {
+    Tag,
+    Null,
+    Bool(bool),
+    Num(String),
+    Str {
+        text: String,
+        has_tag: bool,
+    },
+    Array(Vec<(Node, Span)>),
+    /// Entries with placeholder keys have `None` for their key
+    Object(Vec<(Option<String>, Node, Span)>),
+}
+
+struct JsonParser<'a> {
+    tokens: &'a [Lexeme],
+    pos: usize,
+    /// Why the tokens ended early, if they did
+    lex_error: Option<SyntaxError>,
+    trailing_commas: Vec<Span>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn parse_document(&mut self) -> Result<(Node, Span), SyntaxError> {
+        let value = self.parse_value(0)?;
+        match self.tokens.get(self.pos) {
+            None => Ok(value),
+            Some(t) => Err((format!("Unexpected '{}' after JSON value", t.text), t.span)),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a Lexeme> {
+        let t = self.tokens.get(self.pos);
+        self.pos += 1;
+        t
+    }
+
+    fn peek(&self) -> Option<&'a Lexeme> {
+        self.tokens.get(self.pos)
+    }
+
+    fn end_error(&self) -> SyntaxError {
+        if let Some(e) = &self.lex_error {
+            return e.clone();
+        }
+        let end = self.tokens.last().map(|t| t.span.end).unwrap_or_default();
+        ("Unexpected end of JSON".to_string(), Span::new(end, end))
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<(Node, Span), SyntaxError> {
+        let t = self.next().ok_or_else(|| self.end_error())?;
+        let node = match t.kind {
+            LexemeKind::Tag => Node::Tag,
+            LexemeKind::Str => Node::Str {
+                text: t.text.clone(),
+                has_tag: t.has_tag,
+            },
+            LexemeKind::Literal => match t.text.as_str() {
+                "true" => Node::Bool(true),
+                "false" => Node::Bool(false),
+                "null" => Node::Null,
+                n => Node::Num(n.to_string()),
+            },
+            LexemeKind::Open(_) if depth >= MAX_DEPTH => {
+                return Err(("JSON is nested too deeply".to_string(), t.span));
+            }
+            LexemeKind::Open('[') => return self.parse_array(t.span, depth + 1),
+            LexemeKind::Open(_) => return self.parse_object(t.span, depth + 1),
+            LexemeKind::Close | LexemeKind::Colon | LexemeKind::Comma | LexemeKind::Comment => {
+                return Err((format!("Unexpected '{}'", t.text), t.span));
+            }
+        };
+        Ok((node, t.span))
+    }
+
+    fn parse_array(&mut self, open: Span, depth: usize) -> Result<(Node, Span), SyntaxError> {
+        let mut items = Vec::new();
+        if let Some(close) = self.take_close(']') {
+            return Ok((Node::Array(items), Span::new(open.start, close.end)));
+        }
+
+        loop {
+            items.push(self.parse_value(depth)?);
+            let t = self.next().ok_or_else(|| self.end_error())?;
+            match (t.kind, t.text.as_str()) {
+                (LexemeKind::Comma, _) => {
+                    if let Some(close) = self.take_close(']') {
+                        self.trailing_commas.push(t.span);
+                        return Ok((Node::Array(items), Span::new(open.start, close.end)));
+                    }
+                }
+                (LexemeKind::Close, "]") => {
+                    return Ok((Node::Array(items), Span::new(open.start, t.span.end)));
+                }
+                _ => return Err((format!("Expected ',' or ']' but found '{}'", t.text), t.span)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self, open: Span, depth: usize) -> Result<(Node, Span), SyntaxError> {
+        let mut entries = Vec::new();
+        if let Some(close) = self.take_close('}') {
+            return Ok((Node::Object(entries), Span::new(open.start, close.end)));
+        }
+
+        loop {
+            let t = self.next().ok_or_else(|| self.end_error())?;
+            let key = match t.kind {
+                LexemeKind::Str if !t.has_tag => Some(t.text.clone()),
+                LexemeKind::Str | LexemeKind::Tag => None,
+                _ => return Err((format!("Expected a string key but found '{}'", t.text), t.span)),
+            };
+
+            match self.next() {
+                Some(c) if c.kind == LexemeKind::Colon => {}
+                Some(c) => return Err((format!("Expected ':' but found '{}'", c.text), c.span)),
+                None => return Err(self.end_error()),
+            }
+
+            let (value, span) = self.parse_value(depth)?;
+            entries.push((key, value, span));
+
+            let t = self.next().ok_or_else(|| self.end_error())?;
+            match (t.kind, t.text.as_str()) {
+                (LexemeKind::Comma, _) => {
+                    if let Some(close) = self.take_close('}') {
+                        self.trailing_commas.push(t.span);
+                        return Ok((Node::Object(entries), Span::new(open.start, close.end)));
+                    }
+                }
+                (LexemeKind::Close, "}") => {
+                    return Ok((Node::Object(entries), Span::new(open.start, t.span.end)));
+                }
+                _ => return Err((format!("Expected ',' or '}}' but found '{}'", t.text), t.span)),
+            }
+        }
+    }
+
+    fn take_close(&mut self, close: char) -> Option<Span> {
+        match self.peek() {
+            Some(t) if t.kind == LexemeKind::Close && t.text.starts_with(close) => {
+                self.pos += 1;
+                Some(t.span)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Node {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Node::Tag => "template tag",
+            Node::Null => "null",
+            Node::Bool(_) => "boolean",
+            Node::Num(n) if is_integer(n) => "integer",
+            Node::Num(_) => "number",
+            Node::Str { .. } => "string",
+            Node::Array(_) => "array",
+            Node::Object(_) => "object",
+        }
+    }
+
+    /// The value, if it doesn't depend on any template tags
+    fn to_value(&self) -> Option<Value> {
+        let v = match self {
+            Node::Tag | Node::Str { has_tag: true, .. } => return None,
+            Node::Null => Value::Null,
+            Node::Bool(b) => Value::Bool(*b),
+            Node::Num(n) => serde_json::from_str(n).ok()?,
+            Node::Str { text, .. } => Value::String(text.clone()),
+            Node::Array(items) => {
+                Value::Array(items.iter().map(|(n, _)| n.to_value()).collect::<Option<_>>()?)
+            }
+            Node::Object(entries) => {
+                let mut o = serde_json::Map::new();
+                for (k, n, _) in entries {
+                    o.insert(k.clone()?, n.to_value()?);
+                }
+                Value::Object(o)
+            }
+        };
+        Some(v)
+    }
+}
+
+fn is_integer(n: &str) -> bool {
+    !n.contains(['.', 'e', 'E']) || n.parse::<f64>().is_ok_and(|f| f.fract() == 0.0)
+}
+
+fn check_schema(
+    node: &Node,
+    span: Span,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    errors: &mut Vec<(String, Span)>,
+) {
+    let error = |message: &str| (format!("{message} at {}", describe_path(path)), span);
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(error("Unexpected value"));
+            return;
+        }
+        Value::Object(o) => o,
+        _ => return,
+    };
+
+    // The value of a tag isn't known until it's rendered
+    if let Node::Tag = node {
+        return;
+    }
+
+    if let Some(Value::String(r)) = schema.get("$ref") {
+        match resolve_ref(root, r) {
+            Some(s) => check_schema(node, span, s, root, path, errors),
+            None => errors.push(error(&format!("Unknown schema reference {r}"))),
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(a) => a.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let actual = node.type_name();
+        let matches = types.iter().any(|t| *t == actual || (*t == "number" && actual == "integer"));
+        if !types.is_empty() && !matches {
+            errors.push(error(&format!("Expected {} but found {actual}", types.join(" or "))));
+            return;
+        }
+    }
+
+    let value = node.to_value();
+    match (schema.get("enum"), &value) {
+        (Some(Value::Array(allowed)), Some(v)) if !allowed.contains(v) => {
+            let allowed = allowed.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+            errors.push(error(&format!("Expected one of {}", allowed.join(", "))));
+        }
+        _ => {}
+    }
+    match (schema.get("const"), &value) {
+        (Some(expected), Some(v)) if expected != v => {
+            errors.push(error(&format!("Expected {expected}")));
+        }
+        _ => {}
+    }
+
+    let limit = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+    match node {
+        Node::Str {
+            text,
+            has_tag: false,
+        } => {
+            let len = text.chars().count() as f64;
+            if let Some(min) = limit("minLength").filter(|min| len < *min) {
+                errors.push(error(&format!("Expected at least {min} characters")));
+            }
+            if let Some(max) = limit("maxLength").filter(|max| len > *max) {
+                errors.push(error(&format!("Expected at most {max} characters")));
+            }
+        }
+        Node::Num(n) => {
+            let n = n.parse::<f64>().unwrap_or_default();
+            if let Some(min) = limit("minimum").filter(|min| n < *min) {
+                errors.push(error(&format!("Expected at least {min}")));
+            }
+            if let Some(max) = limit("maximum").filter(|max| n > *max) {
+                errors.push(error(&format!("Expected at most {max}")));
+            }
+            if let Some(min) = limit("exclusiveMinimum").filter(|min| n <= *min) {
+                errors.push(error(&format!("Expected more than {min}")));
+            }
+            if let Some(max) = limit("exclusiveMaximum").filter(|max| n >= *max) {
+                errors.push(error(&format!("Expected less than {max}")));
+            }
+        }
+        Node::Array(items) => {
+            let len = items.len() as f64;
+            if let Some(min) = limit("minItems").filter(|min| len < *min) {
+                errors.push(error(&format!("Expected at least {min} items")));
+            }
+            if let Some(max) = limit("maxItems").filter(|max| len > *max) {
+                errors.push(error(&format!("Expected at most {max} items")));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, (item, item_span)) in items.iter().enumerate() {
+                    let item_path = format!("{path}/{i}");
+                    check_schema(item, *item_span, item_schema, root, &item_path, errors);
+                }
+            }
+        }
+        Node::Object(entries) => {
+            // A tag as a key could be any key, so we can't tell what's missing or extra
+            let has_tag_key = entries.iter().any(|(k, _, _)| k.is_none());
+            if let (Some(Value::Array(required)), false) = (schema.get("required"), has_tag_key) {
+                for name in required.iter().filter_map(|r| r.as_str()) {
+                    if !entries.iter().any(|(k, _, _)| k.as_deref() == Some(name)) {
+                        errors.push(error(&format!("Missing required property \"{name}\"")));
+                    }
+                }
+            }
+
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for (key, value, value_span) in entries {
+                let key = match key {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let item_path = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match properties.and_then(|p| p.get(key)) {
+                    Some(s) => check_schema(value, *value_span, s, root, &item_path, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            let (message, _) = error(&format!("Unexpected property \"{key}\""));
+                            errors.push((message, *value_span));
+                        }
+                        Some(s) => check_schema(value, *value_span, s, root, &item_path, errors),
+                        None => {}
+                    },
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let subschemas = |key: &str| -> &[Value] {
+        match schema.get(key) {
+            Some(Value::Array(a)) => a,
+            _ => &[],
+        }
+    };
+    for s in subschemas("allOf") {
+        check_schema(node, span, s, root, path, errors);
+    }
+
+    let matching = |schemas: &[Value]| {
+        let matches = |s: &Value| {
+            let mut errors = Vec::new();
+            check_schema(node, span, s, root, path, &mut errors);
+            errors.is_empty()
+        };
+        schemas.iter().filter(|s| matches(s)).count()
+    };
+    let any_of = subschemas("anyOf");
+    if !any_of.is_empty() && matching(any_of) == 0 {
+        errors.push(error("Expected a value matching anyOf"));
+    }
+    let one_of = subschemas("oneOf");
+    if !one_of.is_empty() && matching(one_of) != 1 {
+        errors.push(error("Expected a value matching exactly one of oneOf"));
+    }
+}
+
+/// Resolve a reference to another part of the same schema, like `#/$defs/user`
+fn resolve_ref<'a>(root: &'a Value, r: &str) -> Option<&'a Value> {
+    let pointer = r.strip_prefix('#')?;
+    root.pointer(pointer)
+}
+
+fn describe_path(path: &str) -> String {
+    match path {
+        "" => "root".to_string(),
+        p => p.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DiagnosticSeverity;
+    use crate::validate_json::{JsonProblemKind, strip_json_comments, validate_json};
+    use serde_json::json;
+
+    fn problems(text: &str) -> Vec<(JsonProblemKind, String, usize, usize)> {
+        validate_json(text, None)
+            .into_iter()
+            .map(|p| (p.kind, p.message, p.line, p.column))
+            .collect()
+    }
+
+    #[test]
+    fn valid_json() {
+        assert_eq!(problems(r#"{"a": [1, -2.5e3, true, null, "x\"yé"], "b": {}}"#), vec![]);
+        assert_eq!(problems("  "), vec![]);
+    }
+
+    #[test]
+    fn tags_are_placeholders() {
+        let text =
+            r#"{"a": ${[ count ]}, "b": "Bearer ${[ token ]}", ${[ key ]}: [${[ f(x=']}"') ]}]}"#;
+        assert_eq!(problems(text), vec![]);
+    }
+
+    #[test]
+    fn syntax_error_position() {
+        let text = "{\n  \"a\": 1\n  \"b\": 2\n}";
+        assert_eq!(
+            problems(text),
+            vec![(JsonProblemKind::Syntax, "Expected ',' or '}' but found 'b'".to_string(), 3, 3)]
+        );
+    }
+
+    #[test]
+    fn invalid_tokens() {
+        assert_eq!(problems("[01]")[0].1, "Unexpected token '01'");
+        assert_eq!(problems("{'a': 1}")[0].1, "Unexpected character '''");
+        assert_eq!(problems("[1, 2")[0].1, "Unexpected end of JSON");
+        assert_eq!(problems(r#"["a\q"]"#)[0].1, "Invalid escape in string");
+        assert_eq!(problems("[1] 2")[0].1, "Unexpected '2' after JSON value");
+        assert_eq!(problems("[\"a\tb\"]")[0].1, "Control characters must be escaped in strings");
+        assert_eq!(problems("[\"a\u{7f}\u{85}b\"]"), vec![]);
+    }
+
+    #[test]
+    fn comments_and_trailing_commas() {
+        let text = "{\n  // comment\n  \"a\": [1, 2,], /* block */\n}";
+        let found = validate_json(text, None)
+            .into_iter()
+            .map(|p| (p.kind, p.severity, p.line, p.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (JsonProblemKind::Comment, DiagnosticSeverity::Warning, 2, 3),
+                (JsonProblemKind::TrailingComma, DiagnosticSeverity::Warning, 3, 13),
+                (JsonProblemKind::TrailingComma, DiagnosticSeverity::Warning, 3, 15),
+                (JsonProblemKind::Comment, DiagnosticSeverity::Warning, 3, 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn strip_comments() {
+        let text = "{\n  // comment\n  \"a\": [1, \"//x\", ${[ f(x='/*') ]},], /* block */\n}";
+        let stripped = strip_json_comments(text);
+        assert_eq!(stripped, "{\n  \n  \"a\": [1, \"//x\", ${[ f(x='/*') ]}] \n}");
+        assert_eq!(validate_json(&stripped, None), vec![]);
+    }
+
+    #[test]
+    fn schema() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "additionalProperties": false,
+            "properties": {
+                "name": {"type": "string", "minLength": 2},
+                "age": {"type": "integer", "minimum": 0},
+                "role": {"enum": ["admin", "user"]},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+            },
+            "$defs": {"tag": {"type": "string"}}
+        });
+        let text = r#"{"name": "a", "age": -1.5, "role": "x", "tags": ["ok", 1], "extra": true}"#;
+        let messages =
+            validate_json(text, Some(&schema)).into_iter().map(|p| p.message).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Expected at least 2 characters at /name",
+                "Expected integer but found number at /age",
+                "Expected one of \"admin\", \"user\" at /role",
+                "Expected string but found integer at /tags/1",
+                "Unexpected property \"extra\" at root",
+            ]
+        );
+
+        let text = r#"{"name": ${[ name ]}, "age": ${[ age ]}, "role": "${[ role ]}"}"#;
+        assert_eq!(validate_json(text, Some(&schema)), vec![]);
+
+        let missing = validate_json(r#"{"name": "ab"}"#, Some(&schema));
+        assert_eq!(missing[0].message, "Missing required property \"age\" at root");
+    }
+}
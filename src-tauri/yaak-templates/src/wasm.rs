@@ -1,7 +1,8 @@
+use crate::error::Error::RenderError;
 use crate::error::Result;
-use crate::{escape, Parser};
-use wasm_bindgen::prelude::wasm_bindgen;
+use crate::{Parser, escape, validate_json};
 use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
 pub fn parse_template(template: &str) -> Result<JsValue> {
@@ -20,3 +21,29 @@ pub fn unescape_template(template: &str) -> Result<JsValue> {
     let escaped = escape::unescape_template(template);
     Ok(serde_wasm_bindgen::to_value(&escaped).unwrap())
 }
+
+#[wasm_bindgen]
+pub fn parse_template_diagnostics(template: &str) -> Result<JsValue> {
+    let (_, diagnostics) = Parser::new(template).parse_with_diagnostics()?;
+    Ok(serde_wasm_bindgen::to_value(&diagnostics).unwrap())
+}
+
+/// Validate JSON that may contain template tags, optionally against a JSON Schema (as text)
+#[wasm_bindgen]
+pub fn validate_json(text: &str, schema: Option<String>) -> Result<JsValue> {
+    let schema = match schema.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(s) => Some(
+            serde_json::from_str::<serde_json::Value>(s)
+                .map_err(|e| RenderError(format!("Invalid JSON Schema: {e}")))?,
+        ),
+    };
+    let problems = validate_json::validate_json(text, schema.as_ref());
+    Ok(serde_wasm_bindgen::to_value(&problems).unwrap())
+}
+
+#[wasm_bindgen]
+pub fn strip_json_comments(text: &str) -> Result<JsValue> {
+    let stripped = validate_json::strip_json_comments(text);
+    Ok(serde_wasm_bindgen::to_value(&stripped).unwrap())
+}
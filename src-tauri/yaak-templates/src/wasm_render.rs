@@ -0,0 +1,231 @@
+use crate::error::Error::RenderError;
+use crate::error::{Error, Result};
+use crate::renderer;
+use crate::renderer::{RenderOptions, TemplateCallback};
+use js_sys::{Array, Function, Promise};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TEMPLATE_CALLBACK: &'static str = r#"
+/** Runs template functions for `parse_and_render`, like `TemplateCallback` in Rust */
+export interface TemplateCallback {
+  run(fnName: string, args: Record<string, unknown>): string | Promise<string>;
+  /** Transform an arg before it's passed to `run`. Defaults to leaving it as-is. */
+  transformArg?(fnName: string, argName: string, argValue: string): string;
+  /** Name of the arg a piped value is passed as. Defaults to `value`. */
+  firstArgName?(fnName: string): string | Promise<string>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "TemplateCallback")]
+    pub type JsTemplateCallback;
+
+    #[wasm_bindgen(method, getter)]
+    fn run(this: &JsTemplateCallback) -> Function;
+
+    #[wasm_bindgen(method, getter, js_name = transformArg)]
+    fn transform_arg(this: &JsTemplateCallback) -> Option<Function>;
+
+    #[wasm_bindgen(method, getter, js_name = firstArgName)]
+    fn first_arg_name(this: &JsTemplateCallback) -> Option<Function>;
+}
+
+/// Render a template with the same renderer as the app, so it can be previewed without a round
+/// trip. Variables are a map (or object) of names to values, and template functions are run by
+/// the callback.
+#[wasm_bindgen]
+pub async fn parse_and_render(
+    template: &str,
+    vars: JsValue,
+    callback: JsTemplateCallback,
+) -> Result<JsValue> {
+    let vars: HashMap<String, String> = serde_wasm_bindgen::from_value(vars)
+        .map_err(|e| RenderError(format!("Invalid variables: {e}")))?;
+    let cb = WasmTemplateCallback { callback };
+    let opt = RenderOptions::throw();
+    let rendered = renderer::parse_and_render(template, &vars, &cb, &opt).await?;
+    Ok(JsValue::from_str(&rendered))
+}
+
+struct WasmTemplateCallback {
+    callback: JsTemplateCallback,
+}
+
+impl TemplateCallback for WasmTemplateCallback {
+    fn run(
+        &self,
+        fn_name: &str,
+        args: HashMap<String, serde_json::Value>,
+    ) -> impl Future<Output = Result<String>> + Send {
+        // Call into JS right away, so the future only holds the (possibly pending) result
+        let result = to_js_value(&args).and_then(|args| {
+            call_js(&self.callback, &self.callback.run(), &[JsValue::from_str(fn_name), args])
+        });
+        SingleThreaded(async move { resolve_js(result?).await })
+    }
+
+    fn transform_arg(&self, fn_name: &str, arg_name: &str, arg_value: &str) -> Result<String> {
+        let f = match self.callback.transform_arg() {
+            Some(f) => f,
+            None => return Ok(arg_value.to_string()),
+        };
+        let args = [fn_name, arg_name, arg_value].map(JsValue::from_str);
+        js_to_string(call_js(&self.callback, &f, &args)?)
+    }
+
+    fn first_arg_name(&self, fn_name: &str) -> impl Future<Output = Result<String>> + Send {
+        let result = self
+            .callback
+            .first_arg_name()
+            .map(|f| call_js(&self.callback, &f, &[JsValue::from_str(fn_name)]));
+        SingleThreaded(async move {
+            match result {
+                Some(v) => resolve_js(v?).await,
+                None => Ok("value".to_string()),
+            }
+        })
+    }
+}
+
+/// JS values and futures can't be sent between threads, but the renderer requires the callback's
+/// futures to be `Send` so it can run on the app's multi-threaded runtime. The wasm build is
+/// single-threaded, so nothing is ever actually sent, and this only exists there.
+struct SingleThreaded<T>(T);
+
+unsafe impl<T> Send for SingleThreaded<T> {}
+
+impl<F: Future> Future for SingleThreaded<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: the inner future is never moved out of the pinned wrapper
+        unsafe { self.map_unchecked_mut(|s| &mut s.0) }.poll(cx)
+    }
+}
+
+/// Call one of the callback's methods, with the callback as `this` so it can use its own state
+fn call_js(this: &JsValue, f: &Function, args: &[JsValue]) -> Result<JsValue> {
+    let args = args.iter().collect::<Array>();
+    f.apply(this, &args).map_err(js_error)
+}
+
+/// Wait for the value if it's a promise, then convert it to a string
+async fn resolve_js(v: JsValue) -> Result<String> {
+    let v = match v.dyn_into::<Promise>() {
+        Ok(promise) => JsFuture::from(promise).await.map_err(js_error)?,
+        Err(v) => v,
+    };
+    js_to_string(v)
+}
+
+fn js_to_string(v: JsValue) -> Result<String> {
+    if v.is_null() || v.is_undefined() {
+        return Ok("".to_string());
+    }
+    v.as_string().ok_or_else(|| RenderError(format!("Expected a string but got {v:?}")))
+}
+
+fn js_error(e: JsValue) -> Error {
+    let message = match e.dyn_ref::<js_sys::Error>() {
+        Some(e) => String::from(e.message()),
+        None => e.as_string().unwrap_or_else(|| format!("{e:?}")),
+    };
+    RenderError(message)
+}
+
+fn to_js_value<T: Serialize>(v: &T) -> Result<JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    v.serialize(&serializer).map_err(|e| RenderError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::wasm_render::{JsTemplateCallback, parse_and_render};
+    use std::collections::HashMap;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn callback(js: &str) -> JsTemplateCallback {
+        js_sys::eval(&format!("({js})")).unwrap().unchecked_into()
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> JsValue {
+        let vars = vars.iter().cloned().collect::<HashMap<_, _>>();
+        serde_wasm_bindgen::to_value(&vars).unwrap()
+    }
+
+    async fn render(template: &str, vars: JsValue, cb: &str) -> Result<Option<String>> {
+        Ok(parse_and_render(template, vars, callback(cb)).await?.as_string())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_vars() -> Result<()> {
+        let vars = vars(&[
+            ("name", "${[ first ]} ${[ last ]}"),
+            ("first", "Jane"),
+            ("last", "Doe"),
+        ]);
+        let result = render("Hi ${[ name ]}!", vars, "{ run() { throw 'unused'; } }").await?;
+        assert_eq!(result, Some("Hi Jane Doe!".to_string()));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_vars_from_object() -> Result<()> {
+        let vars = js_sys::eval("({ a: 'x' })").unwrap();
+        let result = render("${[ a ]}${[ a ]}", vars, "{ run() { throw 'unused'; } }").await?;
+        assert_eq!(result, Some("xx".to_string()));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_fn_with_this() -> Result<()> {
+        let cb = r#"{
+            greeting: 'Hello',
+            run(name, args) { return `${this.greeting} ${args.who} from ${name}`; },
+        }"#;
+        let result = render("${[ greet(who=name) ]}", vars(&[("name", "Jane")]), cb).await?;
+        assert_eq!(result, Some("Hello Jane from greet".to_string()));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_async_fn_and_pipe() -> Result<()> {
+        let cb = r#"{
+            argName: 'input',
+            async run(name, args) { return `${name}:${args.input}`; },
+            firstArgName() { return Promise.resolve(this.argName); },
+        }"#;
+        let result = render("${[ name | shout ]}", vars(&[("name", "Jane")]), cb).await?;
+        assert_eq!(result, Some("shout:Jane".to_string()));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_transform_arg() -> Result<()> {
+        let cb = r#"{
+            suffix: '!',
+            run(name, args) { return args.value; },
+            transformArg(name, argName, value) { return value + this.suffix; },
+        }"#;
+        let result = render("${[ echo(value='hi') ]}", vars(&[]), cb).await?;
+        assert_eq!(result, Some("hi!".to_string()));
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn render_fn_error() {
+        let cb = "{ async run() { throw new Error('No network'); } }";
+        let result = render("${[ fetch() ]}", vars(&[]), cb).await;
+        assert!(result.unwrap_err().to_string().contains("No network"));
+    }
+}
@@ -50,15 +50,18 @@ export function listWebsocketConnections({ workspaceId }: { workspaceId: string
 export function connectWebsocket({
   requestId,
   environmentId,
+  environmentIds,
   cookieJarId,
 }: {
   requestId: string;
   environmentId: string | null;
+  environmentIds?: string[];
   cookieJarId: string | null;
 }) {
   return invoke('plugin:yaak-ws|connect', {
     requestId,
     environmentId,
+    environmentIds,
     cookieJarId,
   }) as Promise<WebsocketConnection>;
 }
@@ -72,12 +75,15 @@ export function closeWebsocket({ connectionId }: { connectionId: string }) {
 export function sendWebsocket({
   connectionId,
   environmentId,
+  environmentIds,
 }: {
   connectionId: string;
   environmentId: string | null;
+  environmentIds?: string[];
 }) {
   return invoke('plugin:yaak-ws|send', {
     connectionId,
     environmentId,
+    environmentIds,
   });
 }
@@ -16,13 +16,13 @@ use yaak_models::models::{
     WebsocketEventType, WebsocketRequest,
 };
 use yaak_models::query_manager::QueryManagerExt;
-use yaak_models::util::UpdateSource;
+use yaak_models::util::{UpdateSource, active_environment_ids};
 use yaak_plugins::events::{
     CallHttpAuthenticationRequest, HttpHeader, PluginWindowContext, RenderPurpose,
 };
 use yaak_plugins::manager::PluginManager;
 use yaak_plugins::template_callback::PluginTemplateCallback;
-use yaak_templates::{RenderErrorBehavior, RenderOptions};
+use yaak_templates::RenderOptions;
 
 #[tauri::command]
 pub(crate) async fn upsert_request<R: Runtime>(
@@ -106,16 +106,18 @@ pub(crate) async fn list_connections<R: Runtime>(
 pub(crate) async fn send<R: Runtime>(
     connection_id: &str,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     app_handle: AppHandle<R>,
     window: WebviewWindow<R>,
     ws_manager: State<'_, Mutex<WebsocketManager>>,
 ) -> Result<WebsocketConnection> {
     let connection = app_handle.db().get_websocket_connection(connection_id)?;
     let unrendered_request = app_handle.db().get_websocket_request(&connection.request_id)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let environment_chain = app_handle.db().resolve_environments(
         &unrendered_request.workspace_id,
         unrendered_request.folder_id.as_deref(),
-        environment_id,
+        &environment_ids,
     )?;
     let (resolved_request, _auth_context_id) =
         resolve_websocket_request(&window, &unrendered_request)?;
@@ -127,9 +129,7 @@ pub(crate) async fn send<R: Runtime>(
             &PluginWindowContext::new(&window),
             RenderPurpose::Send,
         ),
-        &RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        },
+        &RenderOptions::throw(),
     )
     .await?;
 
@@ -183,6 +183,7 @@ pub(crate) async fn close<R: Runtime>(
 pub(crate) async fn connect<R: Runtime>(
     request_id: &str,
     environment_id: Option<&str>,
+    environment_ids: Option<Vec<String>>,
     cookie_jar_id: Option<&str>,
     app_handle: AppHandle<R>,
     window: WebviewWindow<R>,
@@ -190,12 +191,14 @@ pub(crate) async fn connect<R: Runtime>(
     ws_manager: State<'_, Mutex<WebsocketManager>>,
 ) -> Result<WebsocketConnection> {
     let unrendered_request = app_handle.db().get_websocket_request(request_id)?;
+    let environment_ids = active_environment_ids(environment_id, environment_ids);
     let environment_chain = app_handle.db().resolve_environments(
         &unrendered_request.workspace_id,
         unrendered_request.folder_id.as_deref(),
-        environment_id,
+        &environment_ids,
     )?;
-    let workspace = app_handle.db().get_workspace(&unrendered_request.workspace_id)?;
+    let request_settings =
+        app_handle.db().resolve_settings_for_websocket_request(&unrendered_request)?;
     let (resolved_request, auth_context_id) =
         resolve_websocket_request(&window, &unrendered_request)?;
     let request = render_websocket_request(
@@ -206,9 +209,7 @@ pub(crate) async fn connect<R: Runtime>(
             &PluginWindowContext::new(&window),
             RenderPurpose::Send,
         ),
-        &RenderOptions {
-            error_behavior: RenderErrorBehavior::Throw,
-        },
+        &RenderOptions::throw(),
     )
     .await?;
 
@@ -364,7 +365,7 @@ pub(crate) async fn connect<R: Runtime>(
             url.as_str(),
             headers,
             receive_tx,
-            workspace.setting_validate_certificates,
+            request_settings.validate_certificates,
         )
         .await
     {
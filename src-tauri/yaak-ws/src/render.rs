@@ -1,7 +1,7 @@
 use crate::error::Result;
 use std::collections::BTreeMap;
 use yaak_models::models::{Environment, HttpRequestHeader, WebsocketRequest};
-use yaak_models::render::make_vars_hashmap;
+use yaak_models::render::{make_vars_hashmap, with_request_variables};
 use yaak_templates::{parse_and_render, render_json_value_raw, RenderOptions, TemplateCallback};
 
 pub async fn render_websocket_request<T: TemplateCallback>(
@@ -10,6 +10,8 @@ pub async fn render_websocket_request<T: TemplateCallback>(
     cb: &T,
     opt: &RenderOptions,
 ) -> Result<WebsocketRequest> {
+    let environment_chain =
+        with_request_variables(environment_chain, &r.model, &r.id, &r.variables);
     let vars = &make_vars_hashmap(environment_chain);
 
     let mut headers = Vec::new();
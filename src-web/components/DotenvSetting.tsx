@@ -0,0 +1,46 @@
+import type { WorkspaceMeta } from '@yaakapp-internal/models';
+import { patchModel } from '@yaakapp-internal/models';
+import { PlainInput } from './core/PlainInput';
+import { VStack } from './core/Stacks';
+
+interface Props {
+  workspaceMeta: WorkspaceMeta;
+}
+
+export function DotenvSetting({ workspaceMeta }: Props) {
+  return (
+    <VStack space={3} className="w-full">
+      <PlainInput
+        size="sm"
+        name="settingDotenvPath"
+        label=".env File"
+        help="Use its variables as env.NAME. Relative to the sync directory"
+        labelClassName="w-[14rem]"
+        labelPosition="left"
+        placeholder=".env"
+        forceUpdateKey={workspaceMeta.id}
+        defaultValue={workspaceMeta.settingDotenvPath ?? ''}
+        onChange={(v) => patchModel(workspaceMeta, { settingDotenvPath: v.trim() || null })}
+      />
+      <PlainInput
+        size="sm"
+        name="settingEnvAllowlist"
+        label="Allowed Environment Variables"
+        help="Comma-separated process variables to expose. End with * to match a prefix"
+        labelClassName="w-[14rem]"
+        labelPosition="left"
+        placeholder="API_*, HOME"
+        forceUpdateKey={workspaceMeta.id}
+        defaultValue={workspaceMeta.settingEnvAllowlist.join(', ')}
+        onChange={(v) =>
+          patchModel(workspaceMeta, {
+            settingEnvAllowlist: v
+              .split(',')
+              .map((n) => n.trim())
+              .filter((n) => n !== ''),
+          })
+        }
+      />
+    </VStack>
+  );
+}
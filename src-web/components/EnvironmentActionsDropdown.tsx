@@ -1,6 +1,9 @@
+import { patchModel } from '@yaakapp-internal/models';
 import classNames from 'classnames';
+import { useAtomValue } from 'jotai';
 import { memo, useMemo } from 'react';
 import { useActiveEnvironment } from '../hooks/useActiveEnvironment';
+import { activeWorkspaceMetaAtom } from '../hooks/useActiveWorkspace';
 import { useEnvironmentsBreakdown } from '../hooks/useEnvironmentsBreakdown';
 import { editEnvironment } from '../lib/editEnvironment';
 import { setWorkspaceSearchParams } from '../lib/setWorkspaceSearchParams';
@@ -21,6 +24,11 @@ export const EnvironmentActionsDropdown = memo(function EnvironmentActionsDropdo
 }: Props) {
   const { subEnvironments, baseEnvironment } = useEnvironmentsBreakdown();
   const activeEnvironment = useActiveEnvironment();
+  const workspaceMeta = useAtomValue(activeWorkspaceMetaAtom);
+  const stackedIds = useMemo(
+    () => workspaceMeta?.stackedEnvironmentIds ?? [],
+    [workspaceMeta?.stackedEnvironmentIds],
+  );
 
   const items: DropdownItem[] = useMemo(
     () => [
@@ -40,6 +48,32 @@ export const EnvironmentActionsDropdown = memo(function EnvironmentActionsDropdo
         }),
         [activeEnvironment?.id],
       ),
+      // Other environments can be stacked on top of the active one, later ones taking priority
+      ...((activeEnvironment != null && subEnvironments.length > 1
+        ? [{ type: 'separator', label: 'Stack on Top' }]
+        : []) as DropdownItem[]),
+      ...(activeEnvironment == null ? [] : subEnvironments)
+        .filter((e) => e.id !== activeEnvironment?.id)
+        .map(
+          (e): DropdownItem => ({
+            key: `stack.${e.id}`,
+            label: e.name,
+            rightSlot: <EnvironmentColorIndicator environment={e} />,
+            leftSlot: stackedIds.includes(e.id) ? (
+              <Icon icon="check_square_checked" />
+            ) : (
+              <Icon icon="check_square_unchecked" />
+            ),
+            onSelect: async () => {
+              if (workspaceMeta == null) return;
+              await patchModel(workspaceMeta, {
+                stackedEnvironmentIds: stackedIds.includes(e.id)
+                  ? stackedIds.filter((id) => id !== e.id)
+                  : [...stackedIds, e.id],
+              });
+            },
+          }),
+        ),
       ...((subEnvironments.length > 0
         ? [{ type: 'separator', label: 'Environments' }]
         : []) as DropdownItem[]),
@@ -50,7 +84,7 @@ export const EnvironmentActionsDropdown = memo(function EnvironmentActionsDropdo
         onSelect: () => editEnvironment(activeEnvironment),
       },
     ],
-    [subEnvironments, activeEnvironment],
+    [subEnvironments, activeEnvironment, stackedIds, workspaceMeta],
   );
 
   const hasBaseVars =
@@ -1,6 +1,11 @@
-import type { Environment } from '@yaakapp-internal/models';
+import type {
+  Environment,
+  EnvironmentVariable,
+  EnvironmentVariableType,
+} from '@yaakapp-internal/models';
 import { patchModel } from '@yaakapp-internal/models';
 import type { GenericCompletionOption } from '@yaakapp-internal/plugins';
+import type { RenamedModel } from '@yaakapp-internal/tauri';
 import classNames from 'classnames';
 import React, { useCallback, useMemo } from 'react';
 import { useEnvironmentsBreakdown } from '../hooks/useEnvironmentsBreakdown';
@@ -9,14 +14,20 @@ import { useKeyValue } from '../hooks/useKeyValue';
 import { useRandomKey } from '../hooks/useRandomKey';
 import { analyzeTemplate, convertTemplateToSecure } from '../lib/encryption';
 import { isBaseEnvironment } from '../lib/model_util';
+import { showPrompt } from '../lib/prompt';
+import { invokeCmd } from '../lib/tauri';
 import {
   setupOrConfigureEncryption,
   withEncryptionEnabled,
 } from '../lib/setupOrConfigureEncryption';
+import { showErrorToast, showToast } from '../lib/toast';
 import { BadgeButton } from './core/BadgeButton';
 import { DismissibleBanner } from './core/DismissibleBanner';
+import type { DropdownItem } from './core/Dropdown';
 import type { GenericCompletionConfig } from './core/Editor/genericCompletion';
 import { Heading } from './core/Heading';
+import { Icon } from './core/Icon';
+import { InlineCode } from './core/InlineCode';
 import type { PairWithId } from './core/PairEditor';
 import { ensurePairId } from './core/PairEditor.util';
 import { PairOrBulkEditor } from './core/PairOrBulkEditor';
@@ -41,8 +52,23 @@ export function EnvironmentEditor({
   });
   const { allEnvironments } = useEnvironmentsBreakdown();
   const handleChange = useCallback(
-    (variables: PairWithId[]) => patchModel(environment, { variables }),
-    [environment],
+    async (pairs: PairWithId[]) => {
+      let variables: EnvironmentVariable[] = pairs;
+      // Secret values are encrypted before they're written to the database. Without a workspace
+      // key they're kept as plain text, like other values, until encryption is set up.
+      if (isEncryptionEnabled && variables.some((v) => v.secret)) {
+        try {
+          variables = await invokeCmd<EnvironmentVariable[]>('cmd_encrypt_secret_variables', {
+            workspaceId,
+            variables,
+          });
+        } catch (err) {
+          showErrorToast('encrypt-secret-variables', `Failed to encrypt secrets: ${err}`);
+        }
+      }
+      await patchModel(environment, { variables });
+    },
+    [environment, isEncryptionEnabled, workspaceId],
   );
   const [forceUpdateKey, regenerateForceUpdateKey] = useRandomKey();
 
@@ -72,6 +98,58 @@ export function EnvironmentEditor({
     return { options };
   }, [environment, allEnvironments]);
 
+  const updateVariable = useCallback(
+    async (id: string, update: (v: EnvironmentVariable) => EnvironmentVariable) => {
+      const variables = environment.variables.map((v) => ensurePairId(v.id === id ? update(v) : v));
+      await handleChange(variables);
+      regenerateForceUpdateKey();
+    },
+    [environment.variables, handleChange, regenerateForceUpdateKey],
+  );
+
+  const variableMenuItems = useCallback(
+    (pair: PairWithId): DropdownItem[] => {
+      const variable = environment.variables.find((v) => v.id === pair.id);
+      return [
+        {
+          label: 'Rename Everywhere',
+          hidden: pair.name === '',
+          onSelect: async () => {
+            if (await renameVariable(workspaceId, pair.name)) {
+              regenerateForceUpdateKey();
+            }
+          },
+        },
+        {
+          label: variable?.secret ? 'Unmark as Secret' : 'Mark as Secret',
+          hidden: variable == null,
+          onSelect: () => {
+            if (variable?.secret) {
+              // Keep an encrypted value encrypted, by using it the way secure() stores it
+              updateVariable(pair.id, (v) => ({
+                ...v,
+                secret: false,
+                value: isEncryptedSecret(v) ? `\${[ secure(value='${v.value}') ]}` : v.value,
+              }));
+            } else {
+              withEncryptionEnabled(() => updateVariable(pair.id, (v) => ({ ...v, secret: true })));
+            }
+          },
+        },
+        { type: 'separator', label: 'Type', hidden: variable == null },
+        ...variableTypes.map(
+          (t): DropdownItem => ({
+            label: t.label,
+            hidden: variable == null,
+            leftSlot: <Icon icon={variable?.valueType === t.value ? 'check' : 'empty'} />,
+            onSelect: () => updateVariable(pair.id, (v) => ({ ...v, valueType: t.value })),
+          }),
+        ),
+      ];
+    },
+    [environment.variables, regenerateForceUpdateKey, updateVariable, workspaceId],
+  );
+
   const validateName = useCallback((name: string) => {
     // Empty just means the variable doesn't have a name yet and is unusable
     if (name === '') return true;
@@ -156,6 +234,7 @@ export function EnvironmentEditor({
         valueAutocompleteVariables="environment"
         valueAutocompleteFunctions
         forceUpdateKey={`${environment.id}::${forceUpdateKey}`}
+        menuItems={variableMenuItems}
         pairs={environment.variables}
         onChange={handleChange}
         stateKey={`environment.${environment.id}`}
@@ -164,3 +243,60 @@ export function EnvironmentEditor({
     </div>
   );
 }
+
+const variableTypes: { label: string; value: EnvironmentVariableType | undefined }[] = [
+  { label: 'Any', value: undefined },
+  { label: 'String', value: 'string' },
+  { label: 'Number', value: 'number' },
+  { label: 'Boolean', value: 'bool' },
+  { label: 'JSON', value: 'json' },
+];
+
+/** Matches `EnvironmentVariable::is_encrypted` in the backend */
+function isEncryptedSecret(v: EnvironmentVariable) {
+  return v.secret === true && v.value.startsWith('YENC_');
+}
+
+/** Rename a variable everywhere it's defined or used. Returns whether it was renamed */
+async function renameVariable(workspaceId: string, from: string): Promise<boolean> {
+  const to = await showPrompt({
+    id: 'rename-variable',
+    title: 'Rename Variable',
+    description: (
+      <>
+        Rename <InlineCode>{from}</InlineCode> in every environment, and everywhere it&apos;s used
+      </>
+    ),
+    label: 'Name',
+    confirmText: 'Rename',
+    placeholder: 'VAR_NAME',
+    defaultValue: from,
+  });
+  if (to == null || to === from) return false;
+
+  let renamed: RenamedModel[];
+  try {
+    renamed = await invokeCmd<RenamedModel[]>('cmd_rename_variable', { workspaceId, from, to });
+  } catch (err) {
+    showErrorToast('rename-variable-error', String(err));
+    return false;
+  }
+
+  const count = renamed.reduce((n, m) => n + m.count, 0);
+  const skipped = renamed.flatMap((m) => m.skipped.map((field) => `${m.modelName} ${field}`));
+  const skippedText =
+    skipped.length > 0 ? `. Skipped fields that can't be parsed: ${skipped.join(', ')}` : '';
+  showToast({
+    id: 'rename-variable',
+    color: skipped.length > 0 ? 'notice' : 'success',
+    timeout: skipped.length > 0 ? null : undefined,
+    message: (
+      <>
+        Renamed {count} {count === 1 ? 'use' : 'uses'} of <InlineCode>{from}</InlineCode> to{' '}
+        <InlineCode>{to}</InlineCode>
+        {skippedText}
+      </>
+    ),
+  });
+  return true;
+}
@@ -43,6 +43,7 @@ function ExportDataDialogContent({
   activeWorkspace: Workspace;
 }) {
   const [includePrivateEnvironments, setIncludePrivateEnvironments] = useState<boolean>(false);
+  const [includeSecrets, setIncludeSecrets] = useState<boolean>(false);
   const [selectedWorkspaces, setSelectedWorkspaces] = useState<Record<string, boolean>>({
     [activeWorkspace.id]: true,
   });
@@ -75,10 +76,11 @@ function ExportDataDialogContent({
       workspaceIds: ids,
       exportPath,
       includePrivateEnvironments: includePrivateEnvironments,
+      includeSecrets,
     });
     onHide();
     onSuccess(exportPath);
-  }, [includePrivateEnvironments, onHide, onSuccess, selectedWorkspaces, workspaces]);
+  }, [includePrivateEnvironments, includeSecrets, onHide, onSuccess, selectedWorkspaces, workspaces]);
 
   const allSelected = workspaces.every((w) => selectedWorkspaces[w.id]);
   const numSelected = Object.values(selectedWorkspaces).filter(Boolean).length;
@@ -128,12 +130,20 @@ function ExportDataDialogContent({
           </tbody>
         </table>
         <DetailsBanner color="secondary" open summary="Extra Settings">
-          <Checkbox
-            checked={includePrivateEnvironments}
-            onChange={setIncludePrivateEnvironments}
-            title="Include private environments"
-            help='Environments marked as "sharable" will be exported by default'
-          />
+          <VStack space={2}>
+            <Checkbox
+              checked={includePrivateEnvironments}
+              onChange={setIncludePrivateEnvironments}
+              title="Include private environments"
+              help='Environments marked as "sharable" will be exported by default'
+            />
+            <Checkbox
+              checked={includeSecrets}
+              onChange={setIncludeSecrets}
+              title="Include secret values"
+              help="Secret variables are exported with empty values unless this is checked"
+            />
+          </VStack>
         </DetailsBanner>
       </VStack>
       <footer className="px-5 grid grid-cols-[1fr_auto] items-center bg-surface-highlight py-2 border-t border-border-subtle">
@@ -17,6 +17,7 @@ import { EnvironmentEditor } from './EnvironmentEditor';
 import { HeadersEditor } from './HeadersEditor';
 import { HttpAuthenticationEditor } from './HttpAuthenticationEditor';
 import { MarkdownEditor } from './MarkdownEditor';
+import { SettingOverridesEditor } from './SettingOverridesEditor';
 
 interface Props {
   folderId: string | null;
@@ -84,6 +85,7 @@ export function FolderSettingsDialog({ folderId, tab }: Props) {
             onChange={(name) => patchModel(folder, { name })}
             stateKey={`name.${folder.id}`}
           />
+          <SettingOverridesEditor model={folder} />
           <MarkdownEditor
             name="folder-description"
             placeholder="Folder description"
@@ -23,6 +23,7 @@ import { GrpcEditor } from './GrpcEditor';
 import { HeadersEditor } from './HeadersEditor';
 import { HttpAuthenticationEditor } from './HttpAuthenticationEditor';
 import { MarkdownEditor } from './MarkdownEditor';
+import { RequestVariablesEditor } from './RequestVariablesEditor';
 import { UrlBar } from './UrlBar';
 
 interface Props {
@@ -50,6 +51,7 @@ interface Props {
 const TAB_MESSAGE = 'message';
 const TAB_METADATA = 'metadata';
 const TAB_AUTH = 'auth';
+const TAB_VARIABLES = 'variables';
 const TAB_DESCRIPTION = 'description';
 
 export function GrpcRequestPane({
@@ -136,13 +138,18 @@ export function GrpcRequestPane({
       { value: TAB_MESSAGE, label: 'Message' },
       ...metadataTab,
       ...authTab,
+      {
+        value: TAB_VARIABLES,
+        rightSlot: <CountBadge count={activeRequest.variables.length} />,
+        label: 'Variables',
+      },
       {
         value: TAB_DESCRIPTION,
         label: 'Info',
         rightSlot: activeRequest.description && <CountBadge count={true} />,
       },
     ],
-    [activeRequest.description, authTab, metadataTab],
+    [activeRequest.description, activeRequest.variables.length, authTab, metadataTab],
   );
 
   const activeTab = activeTabs?.[activeRequest.id];
@@ -294,6 +301,9 @@ export function GrpcRequestPane({
             onChange={handleMetadataChange}
           />
         </TabContent>
+        <TabContent value={TAB_VARIABLES}>
+          <RequestVariablesEditor forceUpdateKey={forceUpdateKey} request={activeRequest} />
+        </TabContent>
         <TabContent value={TAB_DESCRIPTION}>
           <div className="grid grid-rows-[auto_minmax(0,1fr)] h-full">
             <PlainInput
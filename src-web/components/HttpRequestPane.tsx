@@ -4,7 +4,7 @@ import type { GenericCompletionOption } from '@yaakapp-internal/plugins';
 import classNames from 'classnames';
 import { atom, useAtomValue } from 'jotai';
 import type { CSSProperties } from 'react';
-import React, { lazy, Suspense, useCallback, useMemo, useState } from 'react';
+import React, { lazy, Suspense, useCallback, useMemo, useRef, useState } from 'react';
 import { activeRequestIdAtom } from '../hooks/useActiveRequestId';
 import { allRequestsAtom } from '../hooks/useAllRequests';
 import { useAuthTab } from '../hooks/useAuthTab';
@@ -39,6 +39,7 @@ import { ConfirmLargeRequestBody } from './ConfirmLargeRequestBody';
 import { CountBadge } from './core/CountBadge';
 import { Editor } from './core/Editor/LazyEditor';
 import type { GenericCompletionConfig } from './core/Editor/genericCompletion';
+import { jsonBodyLinter } from './core/Editor/json/linter';
 import { InlineCode } from './core/InlineCode';
 import type { Pair } from './core/PairEditor';
 import { PlainInput } from './core/PlainInput';
@@ -49,8 +50,12 @@ import { FormMultipartEditor } from './FormMultipartEditor';
 import { FormUrlencodedEditor } from './FormUrlencodedEditor';
 import { HeadersEditor } from './HeadersEditor';
 import { HttpAuthenticationEditor } from './HttpAuthenticationEditor';
+import { JsonBodyOptions } from './JsonBodyOptions';
 import { MarkdownEditor } from './MarkdownEditor';
 import { RequestMethodDropdown } from './RequestMethodDropdown';
+import { RequestVariablesEditor } from './RequestVariablesEditor';
+import { SettingOverridesEditor } from './SettingOverridesEditor';
+import { TypedTemplatesCheckbox } from './TypedTemplatesCheckbox';
 import { UrlBar } from './UrlBar';
 import { UrlParametersEditor } from './UrlParameterEditor';
 
@@ -69,6 +74,7 @@ const TAB_BODY = 'body';
 const TAB_PARAMS = 'params';
 const TAB_HEADERS = 'headers';
 const TAB_AUTH = 'auth';
+const TAB_VARIABLES = 'variables';
 const TAB_DESCRIPTION = 'description';
 
 const nonActiveRequestUrlsAtom = atom((get) => {
@@ -228,6 +234,11 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
       },
       ...headersTab,
       ...authTab,
+      {
+        value: TAB_VARIABLES,
+        rightSlot: <CountBadge count={activeRequest.variables.length} />,
+        label: 'Variables',
+      },
       {
         value: TAB_DESCRIPTION,
         label: 'Info',
@@ -256,7 +267,31 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
   );
 
   const handleBodyTextChange = useCallback(
-    (text: string) => patchModel(activeRequest, { body: { text } }),
+    (text: string) =>
+      patchModel(activeRequest, {
+        body: {
+          text,
+          typedTemplates: activeRequest.body.typedTemplates,
+          stripJsonComments: activeRequest.body.stripJsonComments,
+          jsonSchema: activeRequest.body.jsonSchema,
+        },
+      }),
+    [activeRequest],
+  );
+
+  // The editor only reads its extensions once, so the linter looks up the latest schema itself
+  const jsonSchema = useRef<string | null>(null);
+  jsonSchema.current =
+    typeof activeRequest.body.jsonSchema === 'string' && activeRequest.body.jsonSchema !== ''
+      ? activeRequest.body.jsonSchema
+      : null;
+  const jsonExtensions = useMemo(() => [jsonBodyLinter(() => jsonSchema.current)], []);
+
+  const handleGraphQLBodyChange = useCallback(
+    (body: HttpRequest['body']) =>
+      patchModel(activeRequest, {
+        body: { ...body, typedTemplates: activeRequest.body.typedTemplates },
+      }),
     [activeRequest],
   );
 
@@ -381,20 +416,27 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
                 onChange={(urlParameters) => patchModel(activeRequest, { urlParameters })}
               />
             </TabContent>
+            <TabContent value={TAB_VARIABLES}>
+              <RequestVariablesEditor forceUpdateKey={forceUpdateKey} request={activeRequest} />
+            </TabContent>
             <TabContent value={TAB_BODY}>
               <ConfirmLargeRequestBody request={activeRequest}>
                 {activeRequest.bodyType === BODY_TYPE_JSON ? (
-                  <Editor
-                    forceUpdateKey={forceUpdateKey}
-                    autocompleteFunctions
-                    autocompleteVariables
-                    placeholder="..."
-                    heightMode={fullHeight ? 'full' : 'auto'}
-                    defaultValue={`${activeRequest.body?.text ?? ''}`}
-                    language="json"
-                    onChange={handleBodyTextChange}
-                    stateKey={`json.${activeRequest.id}`}
-                  />
+                  <div className="h-full grid grid-rows-[minmax(0,1fr)_auto]">
+                    <Editor
+                      forceUpdateKey={forceUpdateKey}
+                      autocompleteFunctions
+                      autocompleteVariables
+                      placeholder="..."
+                      heightMode={fullHeight ? 'full' : 'auto'}
+                      defaultValue={`${activeRequest.body?.text ?? ''}`}
+                      language="json"
+                      onChange={handleBodyTextChange}
+                      stateKey={`json.${activeRequest.id}`}
+                      extraExtensions={jsonExtensions}
+                    />
+                    <JsonBodyOptions request={activeRequest} />
+                  </div>
                 ) : activeRequest.bodyType === BODY_TYPE_XML ? (
                   <Editor
                     forceUpdateKey={forceUpdateKey}
@@ -408,14 +450,17 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
                     stateKey={`xml.${activeRequest.id}`}
                   />
                 ) : activeRequest.bodyType === BODY_TYPE_GRAPHQL ? (
-                  <Suspense>
-                    <GraphQLEditor
-                      forceUpdateKey={forceUpdateKey}
-                      baseRequest={activeRequest}
-                      request={activeRequest}
-                      onChange={handleBodyChange}
-                    />
-                  </Suspense>
+                  <div className="h-full grid grid-rows-[minmax(0,1fr)_auto]">
+                    <Suspense>
+                      <GraphQLEditor
+                        forceUpdateKey={forceUpdateKey}
+                        baseRequest={activeRequest}
+                        request={activeRequest}
+                        onChange={handleGraphQLBodyChange}
+                      />
+                    </Suspense>
+                    <TypedTemplatesCheckbox request={activeRequest} />
+                  </div>
                 ) : activeRequest.bodyType === BODY_TYPE_FORM_URLENCODED ? (
                   <FormUrlencodedEditor
                     forceUpdateKey={forceUpdateKey}
@@ -454,7 +499,7 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
               </ConfirmLargeRequestBody>
             </TabContent>
             <TabContent value={TAB_DESCRIPTION}>
-              <div className="grid grid-rows-[auto_minmax(0,1fr)] h-full">
+              <div className="grid grid-rows-[auto_auto_minmax(0,1fr)] gap-y-3 h-full">
                 <PlainInput
                   label="Request Name"
                   hideLabel
@@ -465,6 +510,7 @@ export function HttpRequestPane({ style, fullHeight, className, activeRequest }:
                   placeholder={resolvedModelName(activeRequest)}
                   onChange={(name) => patchModel(activeRequest, { name })}
                 />
+                <SettingOverridesEditor model={activeRequest} />
                 <MarkdownEditor
                   name="request-description"
                   placeholder="Request description"
@@ -0,0 +1,65 @@
+import type { HttpRequest } from '@yaakapp-internal/models';
+import { patchModel, patchModelById } from '@yaakapp-internal/models';
+import { showDialog } from '../lib/dialog';
+import { Button } from './core/Button';
+import { Checkbox } from './core/Checkbox';
+import { Editor } from './core/Editor/LazyEditor';
+import { HStack } from './core/Stacks';
+import { TypedTemplatesCheckbox } from './TypedTemplatesCheckbox';
+
+interface Props {
+  request: HttpRequest;
+}
+
+export function JsonBodyOptions({ request }: Props) {
+  const hasSchema = typeof request.body.jsonSchema === 'string' && request.body.jsonSchema !== '';
+  return (
+    <HStack space={3}>
+      <TypedTemplatesCheckbox request={request} />
+      <Checkbox
+        className="mt-2"
+        checked={request.body.stripJsonComments === true}
+        title="Strip comments"
+        help="Remove comments and trailing commas before sending"
+        onChange={(stripJsonComments) =>
+          patchModel(request, { body: { ...request.body, stripJsonComments } })
+        }
+      />
+      <Button
+        className="mt-2 ml-auto"
+        variant="border"
+        size="xs"
+        onClick={() => showJsonSchemaDialog(request)}
+      >
+        {hasSchema ? 'Edit JSON Schema' : 'Add JSON Schema'}
+      </Button>
+    </HStack>
+  );
+}
+
+function showJsonSchemaDialog(request: HttpRequest) {
+  showDialog({
+    id: 'json-schema',
+    size: 'full',
+    title: 'JSON Schema',
+    description: 'The body is checked against this schema while editing',
+    className: '!max-w-[50rem] !max-h-[60rem]',
+    render() {
+      return (
+        <Editor
+          language="json"
+          placeholder='{ "type": "object" }'
+          defaultValue={`${request.body.jsonSchema ?? ''}`}
+          stateKey={`json_schema.${request.id}`}
+          onChange={(jsonSchema) =>
+            // Patch the latest body, since the request may have changed since the dialog opened
+            patchModelById(request.model, request.id, (r: HttpRequest) => ({
+              ...r,
+              body: { ...r.body, jsonSchema },
+            }))
+          }
+        />
+      );
+    },
+  });
+}
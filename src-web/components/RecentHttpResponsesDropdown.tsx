@@ -1,5 +1,5 @@
 import type { HttpResponse } from '@yaakapp-internal/models';
-import { deleteModel } from '@yaakapp-internal/models';
+import { deleteModel, patchModel } from '@yaakapp-internal/models';
 import { useCopyHttpResponse } from '../hooks/useCopyHttpResponse';
 import { useDeleteHttpResponses } from '../hooks/useDeleteHttpResponses';
 import { useSaveResponse } from '../hooks/useSaveResponse';
@@ -44,6 +44,11 @@ export const RecentHttpResponsesDropdown = function ResponsePane({
           hidden: responses.length === 0 || !!activeResponse.error,
           disabled: activeResponse.state !== 'closed' && activeResponse.status >= 100,
         },
+        {
+          label: activeResponse.retain ? 'Allow Auto-Delete' : 'Keep Forever',
+          leftSlot: <Icon icon={activeResponse.retain ? 'lock_open' : 'lock'} />,
+          onSelect: () => patchModel(activeResponse, { retain: !activeResponse.retain }),
+        },
         {
           label: 'Delete',
           leftSlot: <Icon icon="trash" />,
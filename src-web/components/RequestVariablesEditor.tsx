@@ -0,0 +1,29 @@
+import type { GrpcRequest, HttpRequest, WebsocketRequest } from '@yaakapp-internal/models';
+import { patchModel } from '@yaakapp-internal/models';
+import { PairOrBulkEditor } from './core/PairOrBulkEditor';
+import { VStack } from './core/Stacks';
+
+type Props = {
+  forceUpdateKey: string;
+  request: HttpRequest | GrpcRequest | WebsocketRequest;
+};
+
+// Variables defined here take priority over every environment, but only for this request
+export function RequestVariablesEditor({ forceUpdateKey, request }: Props) {
+  return (
+    <VStack className="h-full">
+      <PairOrBulkEditor
+        allowMultilineValues
+        forceUpdateKey={`${request.id}::${forceUpdateKey}`}
+        namePlaceholder="VAR_NAME"
+        onChange={(variables) => patchModel(request, { variables })}
+        pairs={request.variables}
+        preferenceName="request_variables"
+        stateKey={`variables.${request.id}`}
+        valueAutocompleteFunctions
+        valueAutocompleteVariables
+        valuePlaceholder="Value"
+      />
+    </VStack>
+  );
+}
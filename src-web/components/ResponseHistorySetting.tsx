@@ -0,0 +1,75 @@
+import type { WorkspaceMeta } from '@yaakapp-internal/models';
+import { patchModel } from '@yaakapp-internal/models';
+import { parseOptionalInt } from '../lib/parseOptionalInt';
+import { PlainInput } from './core/PlainInput';
+import { VStack } from './core/Stacks';
+
+interface Props {
+  workspaceMeta: WorkspaceMeta;
+}
+
+type RetentionSetting =
+  | 'settingResponseHistoryLimit'
+  | 'settingResponseHistoryDays'
+  | 'settingResponseDiskQuotaMb';
+
+export function ResponseHistorySetting({ workspaceMeta }: Props) {
+  return (
+    <VStack space={3} className="w-full">
+      <p className="text-text-subtle">
+        Older responses are deleted automatically. Responses marked <em>Keep Forever</em> are never
+        deleted.
+      </p>
+      <RetentionInput
+        workspaceMeta={workspaceMeta}
+        setting="settingResponseHistoryLimit"
+        label="Responses per Request"
+        placeholder="20"
+        help="Leave empty for the default of 20, or set to 0 to keep every response"
+      />
+      <RetentionInput
+        workspaceMeta={workspaceMeta}
+        setting="settingResponseHistoryDays"
+        label="Max Age (days)"
+        placeholder="No limit"
+      />
+      <RetentionInput
+        workspaceMeta={workspaceMeta}
+        setting="settingResponseDiskQuotaMb"
+        label="Disk Quota (MB)"
+        placeholder="No limit"
+        help="Total size of stored request and response bodies, across the whole workspace"
+      />
+    </VStack>
+  );
+}
+
+function RetentionInput({
+  workspaceMeta,
+  setting,
+  label,
+  placeholder,
+  help,
+}: Props & { setting: RetentionSetting; label: string; placeholder: string; help?: string }) {
+  const value = workspaceMeta[setting];
+  return (
+    <PlainInput
+      size="sm"
+      type="number"
+      name={setting}
+      label={label}
+      help={help}
+      labelClassName="w-[14rem]"
+      labelPosition="left"
+      placeholder={placeholder}
+      forceUpdateKey={workspaceMeta.id}
+      defaultValue={value == null ? '' : `${value}`}
+      validate={(v) => parseOptionalInt(v) !== undefined}
+      onChange={(v) => {
+        const parsed = parseOptionalInt(v);
+        if (parsed === undefined) return;
+        return patchModel(workspaceMeta, { [setting]: parsed });
+      }}
+    />
+  );
+}
@@ -0,0 +1,68 @@
+import type { Folder, HttpRequest } from '@yaakapp-internal/models';
+import { patchModel } from '@yaakapp-internal/models';
+import { PlainInput } from './core/PlainInput';
+import { Select } from './core/Select';
+import { VStack } from './core/Stacks';
+
+type Props = {
+  model: Folder | HttpRequest;
+};
+
+type OverrideValue = 'inherit' | 'on' | 'off';
+
+const options = [
+  { label: 'Inherit', value: 'inherit' as const },
+  { label: 'Enabled', value: 'on' as const },
+  { label: 'Disabled', value: 'off' as const },
+];
+
+function toOverrideValue(value: boolean | null): OverrideValue {
+  return value == null ? 'inherit' : value ? 'on' : 'off';
+}
+
+function fromOverrideValue(value: OverrideValue): boolean | null {
+  return value === 'inherit' ? null : value === 'on';
+}
+
+// Unset settings are inherited from the parent folder, or the workspace
+export function SettingOverridesEditor({ model }: Props) {
+  return (
+    <VStack space={3}>
+      <PlainInput
+        size="sm"
+        name="requestTimeout"
+        label="Request Timeout (ms)"
+        labelClassName="w-[14rem]"
+        placeholder="Inherit"
+        labelPosition="left"
+        forceUpdateKey={model.id}
+        defaultValue={model.settingRequestTimeout == null ? '' : `${model.settingRequestTimeout}`}
+        validate={(value) => value === '' || parseInt(value) >= 0}
+        onChange={(v) =>
+          patchModel(model, { settingRequestTimeout: v === '' ? null : parseInt(v) || 0 })
+        }
+        type="number"
+      />
+      <Select
+        name="validateCertificates"
+        label="Validate TLS Certificates"
+        labelClassName="w-[14rem]"
+        labelPosition="left"
+        size="sm"
+        value={toOverrideValue(model.settingValidateCertificates)}
+        options={options}
+        onChange={(v) => patchModel(model, { settingValidateCertificates: fromOverrideValue(v) })}
+      />
+      <Select
+        name="followRedirects"
+        label="Follow Redirects"
+        labelClassName="w-[14rem]"
+        labelPosition="left"
+        size="sm"
+        value={toOverrideValue(model.settingFollowRedirects)}
+        options={options}
+        onChange={(v) => patchModel(model, { settingFollowRedirects: fromOverrideValue(v) })}
+      />
+    </VStack>
+  );
+}
@@ -25,7 +25,7 @@ import React, { memo, useCallback, useEffect, useMemo, useRef } from 'react';
 import { moveToWorkspace } from '../commands/moveToWorkspace';
 import { openFolderSettings } from '../commands/openFolderSettings';
 import { activeCookieJarAtom } from '../hooks/useActiveCookieJar';
-import { activeEnvironmentAtom } from '../hooks/useActiveEnvironment';
+import { activeEnvironmentAtom, getActiveEnvironmentIds } from '../hooks/useActiveEnvironment';
 import { activeFolderIdAtom } from '../hooks/useActiveFolderId';
 import { activeRequestIdAtom } from '../hooks/useActiveRequestId';
 import { activeWorkspaceAtom, activeWorkspaceIdAtom } from '../hooks/useActiveWorkspace';
@@ -430,6 +430,7 @@ async function getContextMenu(tree: TreeHandle, items: SidebarModel[]): Promise<
         invokeCmd('cmd_send_folder', {
           folderId: child.id,
           environmentId: environment?.id,
+          environmentIds: getActiveEnvironmentIds(),
           cookieJarId: cookieJar?.id,
         });
       },
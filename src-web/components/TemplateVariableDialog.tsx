@@ -1,7 +1,7 @@
 import type { Tokens } from '@yaakapp-internal/templates';
 import { useCallback, useMemo, useState } from 'react';
 import { useActiveEnvironmentVariables } from '../hooks/useActiveEnvironmentVariables';
-import { useRenderTemplate } from '../hooks/useRenderTemplate';
+import { useRenderTemplateTrace } from '../hooks/useRenderTemplate';
 import { useTemplateTokensToString } from '../hooks/useTemplateTokensToString';
 import { Button } from './core/Button';
 import { InlineCode } from './core/InlineCode';
@@ -46,7 +46,8 @@ export function TemplateVariableDialog({ hide, onChange, initialTokens }: Props)
     hide();
   }, [hide, onChange, tagText.data]);
 
-  const rendered = useRenderTemplate(tagText.data ?? '');
+  const rendered = useRenderTemplateTrace(tagText.data ?? '');
+  const step = rendered.data?.tags[0]?.steps[0];
 
   return (
     <VStack className="pb-3" space={4}>
@@ -61,7 +62,12 @@ export function TemplateVariableDialog({ hide, onChange, initialTokens }: Props)
       </VStack>
       <VStack>
         <div className="text-sm text-text-subtle">Preview</div>
-        <InlineCode className="select-text cursor-text">{rendered.data}</InlineCode>
+        <InlineCode className="select-text cursor-text">{rendered.data?.output}</InlineCode>
+        {step?.source && (
+          <div className="text-sm text-text-subtle mt-1">
+            Defined in <InlineCode>{step.source.environmentName}</InlineCode>
+          </div>
+        )}
       </VStack>
       <Button color="primary" onClick={handleDone}>
         Done
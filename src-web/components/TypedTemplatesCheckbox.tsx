@@ -0,0 +1,27 @@
+import type { HttpRequest } from '@yaakapp-internal/models';
+import { patchModel } from '@yaakapp-internal/models';
+import { Checkbox } from './core/Checkbox';
+import { InlineCode } from './core/InlineCode';
+
+interface Props {
+  request: HttpRequest;
+}
+
+export function TypedTemplatesCheckbox({ request }: Props) {
+  return (
+    <Checkbox
+      className="mt-2"
+      checked={request.body.typedTemplates === true}
+      title="Typed templates"
+      help={
+        <>
+          Strings that are a single template tag, like <InlineCode>{'"${[ count ]}"'}</InlineCode>,
+          are sent as the tag&apos;s JSON value instead of a string
+        </>
+      }
+      onChange={(typedTemplates) =>
+        patchModel(request, { body: { ...request.body, typedTemplates } })
+      }
+    />
+  );
+}
@@ -7,7 +7,7 @@ import { atom, useAtomValue } from 'jotai';
 import type { CSSProperties } from 'react';
 import React, { useCallback, useMemo } from 'react';
 import { getActiveCookieJar } from '../hooks/useActiveCookieJar';
-import { getActiveEnvironment } from '../hooks/useActiveEnvironment';
+import { getActiveEnvironment, getActiveEnvironmentIds } from '../hooks/useActiveEnvironment';
 import { activeRequestIdAtom } from '../hooks/useActiveRequestId';
 import { allRequestsAtom } from '../hooks/useAllRequests';
 import { useAuthTab } from '../hooks/useAuthTab';
@@ -35,6 +35,7 @@ import { TabContent, Tabs } from './core/Tabs/Tabs';
 import { HeadersEditor } from './HeadersEditor';
 import { HttpAuthenticationEditor } from './HttpAuthenticationEditor';
 import { MarkdownEditor } from './MarkdownEditor';
+import { RequestVariablesEditor } from './RequestVariablesEditor';
 import { UrlBar } from './UrlBar';
 import { UrlParametersEditor } from './UrlParameterEditor';
 
@@ -49,6 +50,7 @@ const TAB_MESSAGE = 'message';
 const TAB_PARAMS = 'params';
 const TAB_HEADERS = 'headers';
 const TAB_AUTH = 'auth';
+const TAB_VARIABLES = 'variables';
 const TAB_DESCRIPTION = 'description';
 
 const nonActiveRequestUrlsAtom = atom((get) => {
@@ -104,12 +106,17 @@ export function WebsocketRequestPane({ style, fullHeight, className, activeReque
       },
       ...headersTab,
       ...authTab,
+      {
+        value: TAB_VARIABLES,
+        rightSlot: <CountBadge count={activeRequest.variables.length} />,
+        label: 'Variables',
+      },
       {
         value: TAB_DESCRIPTION,
         label: 'Info',
       },
     ];
-  }, [authTab, headersTab, urlParameterPairs.length]);
+  }, [activeRequest.variables.length, authTab, headersTab, urlParameterPairs.length]);
 
   const { activeResponse } = usePinnedHttpResponse(activeRequestId);
   const { mutate: cancelResponse } = useCancelHttpResponse(activeResponse?.id ?? null);
@@ -147,6 +154,7 @@ export function WebsocketRequestPane({ style, fullHeight, className, activeReque
     await connectWebsocket({
       requestId: activeRequest.id,
       environmentId: getActiveEnvironment()?.id ?? null,
+      environmentIds: getActiveEnvironmentIds(),
       cookieJarId: getActiveCookieJar()?.id ?? null,
     });
   }, [activeRequest.id]);
@@ -156,6 +164,7 @@ export function WebsocketRequestPane({ style, fullHeight, className, activeReque
     await sendWebsocket({
       connectionId: connection?.id,
       environmentId: getActiveEnvironment()?.id ?? null,
+      environmentIds: getActiveEnvironmentIds(),
     });
   }, [connection]);
 
@@ -256,6 +265,9 @@ export function WebsocketRequestPane({ style, fullHeight, className, activeReque
                 onChange={(urlParameters) => patchModel(activeRequest, { urlParameters })}
               />
             </TabContent>
+            <TabContent value={TAB_VARIABLES}>
+              <RequestVariablesEditor forceUpdateKey={forceUpdateKey} request={activeRequest} />
+            </TabContent>
             <TabContent value={TAB_MESSAGE}>
               <Editor
                 forceUpdateKey={forceUpdateKey}
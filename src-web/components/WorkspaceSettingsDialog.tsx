@@ -13,9 +13,11 @@ import { InlineCode } from './core/InlineCode';
 import { PlainInput } from './core/PlainInput';
 import { HStack, VStack } from './core/Stacks';
 import { TabContent, Tabs } from './core/Tabs/Tabs';
+import { DotenvSetting } from './DotenvSetting';
 import { HeadersEditor } from './HeadersEditor';
 import { HttpAuthenticationEditor } from './HttpAuthenticationEditor';
 import { MarkdownEditor } from './MarkdownEditor';
+import { ResponseHistorySetting } from './ResponseHistorySetting';
 import { SyncToFilesystemSetting } from './SyncToFilesystemSetting';
 import { WorkspaceEncryptionSetting } from './WorkspaceEncryptionSetting';
 
@@ -28,13 +30,15 @@ interface Props {
 const TAB_AUTH = 'auth';
 const TAB_DATA = 'data';
 const TAB_HEADERS = 'headers';
+const TAB_HISTORY = 'history';
 const TAB_GENERAL = 'general';
 
 export type WorkspaceSettingsTab =
   | typeof TAB_AUTH
   | typeof TAB_HEADERS
   | typeof TAB_GENERAL
-  | typeof TAB_DATA;
+  | typeof TAB_DATA
+  | typeof TAB_HISTORY;
 
 const DEFAULT_TAB: WorkspaceSettingsTab = TAB_GENERAL;
 
@@ -75,6 +79,7 @@ export function WorkspaceSettingsDialog({ workspaceId, hide, tab }: Props) {
           value: TAB_DATA,
           label: 'Directory Sync',
         },
+        { value: TAB_HISTORY, label: 'Response History' },
         ...headersTab,
         ...authTab,
       ]}
@@ -150,9 +155,13 @@ export function WorkspaceSettingsDialog({ workspaceId, hide, tab }: Props) {
             onCreateNewWorkspace={hide}
             onChange={({ filePath }) => patchModel(workspaceMeta, { settingSyncDir: filePath })}
           />
+          <DotenvSetting workspaceMeta={workspaceMeta} />
           <WorkspaceEncryptionSetting size="xs" />
         </VStack>
       </TabContent>
+      <TabContent value={TAB_HISTORY} className="overflow-y-auto h-full px-4">
+        <ResponseHistorySetting workspaceMeta={workspaceMeta} />
+      </TabContent>
     </Tabs>
   );
 }
@@ -10,6 +10,7 @@ import { vscodeKeymap } from '@replit/codemirror-vscode-keymap';
 import type { EditorKeymap } from '@yaakapp-internal/models';
 import { settingsAtom } from '@yaakapp-internal/models';
 import type { EditorLanguage, TemplateFunction } from '@yaakapp-internal/plugins';
+import type { FormatMode } from '@yaakapp-internal/templates';
 import { parseTemplate } from '@yaakapp-internal/templates';
 import classNames from 'classnames';
 import type { GraphQLSchema } from 'graphql';
@@ -41,6 +42,7 @@ import { jotaiStore } from '../../../lib/jotai';
 import { withEncryptionEnabled } from '../../../lib/setupOrConfigureEncryption';
 import { TemplateFunctionDialog } from '../../TemplateFunctionDialog';
 import { TemplateVariableDialog } from '../../TemplateVariableDialog';
+import { Dropdown } from '../Dropdown';
 import { IconButton } from '../IconButton';
 import { InlineCode } from '../InlineCode';
 import { HStack } from '../Stacks';
@@ -172,13 +174,16 @@ export const Editor = forwardRef<EditorView | undefined, EditorProps>(function E
     disableTabIndent = true;
   }
 
+  // The built-in formatters can also minify and canonicalize
+  let formatWithMode: ((v: string, mode: FormatMode) => Promise<string>) | undefined;
   if (format == null && !readOnly) {
-    format =
+    formatWithMode =
       language === 'json'
         ? tryFormatJson
         : language === 'xml' || language === 'html'
           ? tryFormatXml
           : undefined;
+    format = formatWithMode;
   }
 
   const cm = useRef<{ view: EditorView; languageCompartment: Compartment } | null>(null);
@@ -324,7 +329,7 @@ export const Editor = forwardRef<EditorView | undefined, EditorProps>(function E
   const onClickVariable = useCallback(
     // eslint-disable-next-line @typescript-eslint/no-unused-vars
     async (v: WrappedEnvironmentVariable, _tagValue: string, _startPos: number) => {
-      editEnvironment(v.environment);
+      if (v.environment != null) editEnvironment(v.environment);
     },
     [],
   );
@@ -501,6 +506,19 @@ export const Editor = forwardRef<EditorView | undefined, EditorProps>(function E
       'bg-surface transition-opacity transform-gpu opacity-0 group-hover:opacity-100 hover:!opacity-100 shadow',
     );
 
+    const reformat = async (f: (v: string) => Promise<string>) => {
+      if (cm.current === null) return;
+      const { doc } = cm.current.view.state;
+      const formatted = await f(doc.toString());
+      // Update editor and blur because the cursor will reset anyway
+      cm.current.view.dispatch({
+        changes: { from: 0, to: doc.length, insert: formatted },
+      });
+      cm.current.view.contentDOM.blur();
+      // Fire change event
+      onChange?.(formatted);
+    };
+
     if (format) {
       results.push(
         <IconButton
@@ -511,21 +529,36 @@ export const Editor = forwardRef<EditorView | undefined, EditorProps>(function E
           icon="magic_wand"
           variant="border"
           className={classNames(actionClassName)}
-          onClick={async () => {
-            if (cm.current === null) return;
-            const { doc } = cm.current.view.state;
-            const formatted = await format(doc.toString());
-            // Update editor and blur because the cursor will reset anyway
-            cm.current.view.dispatch({
-              changes: { from: 0, to: doc.length, insert: formatted },
-            });
-            cm.current.view.contentDOM.blur();
-            // Fire change event
-            onChange?.(formatted);
-          }}
+          onClick={() => reformat(format)}
         />,
       );
     }
+    if (formatWithMode) {
+      results.push(
+        <Dropdown
+          key="format_mode"
+          items={[
+            {
+              label: 'Minify',
+              onSelect: () => reformat((v) => formatWithMode(v, 'minify')),
+            },
+            {
+              label:
+                language === 'json' ? 'Canonicalize (sort keys)' : 'Canonicalize (sort attributes)',
+              onSelect: () => reformat((v) => formatWithMode(v, 'canonical')),
+            },
+          ]}
+        >
+          <IconButton
+            size="sm"
+            title="More formatting options"
+            icon="chevron_down"
+            variant="border"
+            className={classNames(actionClassName)}
+          />
+        </Dropdown>,
+      );
+    }
     results.push(
       Children.map(actions, (existingChild) => {
         if (!isValidElement<{ className?: string }>(existingChild)) return null;
@@ -538,7 +571,7 @@ export const Editor = forwardRef<EditorView | undefined, EditorProps>(function E
       }),
     );
     return results;
-  }, [actions, format, onChange]);
+  }, [actions, format, formatWithMode, language, onChange]);
 
   const cmContainer = (
     <div
@@ -0,0 +1,36 @@
+import type { Diagnostic } from '@codemirror/lint';
+import { linter } from '@codemirror/lint';
+import type { JsonProblem } from '@yaakapp-internal/templates';
+import { validateJson } from '@yaakapp-internal/templates';
+
+/** Lint JSON that may contain template tags, checking it against a JSON Schema if one is given */
+export function jsonBodyLinter(getSchema: () => string | null) {
+  return linter(
+    (view) => {
+      const text = view.state.doc.toString();
+      const schema = getSchema();
+      const diagnostics: Diagnostic[] = [];
+
+      let problems: JsonProblem[];
+      try {
+        problems = validateJson(text, schema);
+      } catch (err) {
+        // Still check the syntax when the schema itself is broken
+        problems = validateJson(text, null);
+        diagnostics.push({ from: 0, to: 0, severity: 'warning', message: String(err) });
+      }
+
+      for (const p of problems) {
+        diagnostics.push({
+          from: p.span.start,
+          to: p.span.end,
+          severity: p.severity,
+          message: p.message,
+        });
+      }
+
+      return diagnostics;
+    },
+    { delay: 200 },
+  );
+}
@@ -3,6 +3,7 @@ import type { Range } from '@codemirror/state';
 import type { DecorationSet, ViewUpdate } from '@codemirror/view';
 import { Decoration, ViewPlugin, WidgetType, EditorView } from '@codemirror/view';
 import type { SyntaxNodeRef } from '@lezer/common';
+import { parseTemplate } from '@yaakapp-internal/templates';
 import type { TwigCompletionOption } from './completion';
 
 class TemplateTagWidget extends WidgetType {
@@ -84,6 +85,11 @@ function templateTags(
             return;
           }
 
+          // Expressions like `page + 1` aren't a single variable or function, so leave them as text
+          if (isExpression(rawTag)) {
+            return;
+          }
+
           // The beta named the function `Response` but was changed in stable.
           // Keep this here for a while because there's no easy way to migrate
           if (name === 'Response') {
@@ -121,6 +127,18 @@ function templateTags(
   return Decoration.set(widgets);
 }
 
+function isExpression(rawTag: string) {
+  try {
+    const token = parseTemplate(rawTag).tokens[0];
+    return (
+      token?.type === 'tag' &&
+      (token.val.type === 'unary' || token.val.type === 'binary' || token.val.type === 'ternary')
+    );
+  } catch {
+    return false;
+  }
+}
+
 export function templateTagsPlugin(
   options: TwigCompletionOption[],
   onClickMissingVariable: (name: string, tagValue: string, startPos: number) => void,
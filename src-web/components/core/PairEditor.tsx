@@ -56,6 +56,8 @@ export type PairEditorProps = {
   className?: string;
   forcedEnvironmentId?: string;
   forceUpdateKey?: string;
+  /** Extra items for the dropdown menu of each pair */
+  menuItems?: (pair: PairWithId) => DropdownItem[];
   nameAutocomplete?: GenericCompletionConfig;
   nameAutocompleteFunctions?: boolean;
   nameAutocompleteVariables?: boolean;
@@ -97,6 +99,7 @@ export const PairEditor = forwardRef<PairEditorRef, PairEditorProps>(function Pa
     className,
     forcedEnvironmentId,
     forceUpdateKey,
+    menuItems,
     nameAutocomplete,
     nameAutocompleteFunctions,
     nameAutocompleteVariables,
@@ -312,6 +315,7 @@ export const PairEditor = forwardRef<PairEditorRef, PairEditorProps>(function Pa
                 index={i}
                 isLast={isLast}
                 isDraggingGlobal={!!isDragging}
+                menuItems={menuItems}
                 nameAutocomplete={nameAutocomplete}
                 nameAutocompleteFunctions={nameAutocompleteFunctions}
                 nameAutocompleteVariables={nameAutocompleteVariables}
@@ -376,6 +380,7 @@ type PairEditorRowProps = {
   | 'allowMultilineValues'
   | 'forcedEnvironmentId'
   | 'forceUpdateKey'
+  | 'menuItems'
   | 'nameAutocomplete'
   | 'nameAutocompleteVariables'
   | 'namePlaceholder'
@@ -402,6 +407,7 @@ export function PairEditorRow({
   forcedEnvironmentId,
   index,
   isLast,
+  menuItems,
   nameAutocomplete,
   nameAutocompleteFunctions,
   nameAutocompleteVariables,
@@ -492,13 +498,14 @@ export function PairEditorRow({
         onSelect: handleEditMultiLineValue,
         hidden: !allowMultilineValues,
       },
+      ...(menuItems?.(pair) ?? []),
       {
         label: 'Delete',
         onSelect: handleDelete,
         color: 'danger',
       },
     ],
-    [allowMultilineValues, handleDelete, handleEditMultiLineValue],
+    [allowMultilineValues, handleDelete, handleEditMultiLineValue, menuItems, pair],
   );
 
   const { attributes, listeners, setNodeRef: setDraggableRef } = useDraggable({ id: pair.id });
@@ -4,6 +4,7 @@ import { environmentsAtom } from '@yaakapp-internal/models';
 import { useAtomValue , atom } from 'jotai';
 import { useEffect } from 'react';
 import { jotaiStore } from '../lib/jotai';
+import { activeWorkspaceMetaAtom } from './useActiveWorkspace';
 
 export const activeEnvironmentIdAtom = atom<string>();
 
@@ -12,6 +13,15 @@ export const activeEnvironmentAtom = atom<Environment | null>((get) => {
   return get(environmentsAtom).find((e) => e.id === activeEnvironmentId) ?? null;
 });
 
+// The active environment, with the workspace's stacked sub-environments on top of it. Nothing is
+// stacked when no environment is active, since the stack can't be seen or changed then.
+export const activeEnvironmentIdsAtom = atom<string[]>((get) => {
+  const activeEnvironment = get(activeEnvironmentAtom);
+  if (activeEnvironment == null) return [];
+  const stackedIds = get(activeWorkspaceMetaAtom)?.stackedEnvironmentIds ?? [];
+  return [activeEnvironment.id, ...stackedIds.filter((id) => id !== activeEnvironment.id)];
+});
+
 export function useActiveEnvironment() {
   return useAtomValue(activeEnvironmentAtom);
 }
@@ -20,6 +30,10 @@ export function getActiveEnvironment() {
   return jotaiStore.get(activeEnvironmentAtom);
 }
 
+export function getActiveEnvironmentIds() {
+  return jotaiStore.get(activeEnvironmentIdsAtom);
+}
+
 export function useSubscribeActiveEnvironmentId() {
   const { environment_id } = useSearch({ strict: false });
   useEffect(
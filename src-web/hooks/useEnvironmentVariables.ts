@@ -4,7 +4,7 @@ import { useAtomValue } from 'jotai';
 import { useMemo } from 'react';
 import { jotaiStore } from '../lib/jotai';
 import { isBaseEnvironment, isFolderEnvironment } from '../lib/model_util';
-import { useActiveEnvironment } from './useActiveEnvironment';
+import { activeEnvironmentIdsAtom, useActiveEnvironment } from './useActiveEnvironment';
 import { useActiveRequest } from './useActiveRequest';
 import { useEnvironmentsBreakdown } from './useEnvironmentsBreakdown';
 import { useParentFolders } from './useParentFolders';
@@ -12,6 +12,7 @@ import { useParentFolders } from './useParentFolders';
 export function useEnvironmentVariables(targetEnvironmentId: string | null) {
   const { baseEnvironment, folderEnvironments, allEnvironments } = useEnvironmentsBreakdown();
   const activeEnvironment = useActiveEnvironment();
+  const activeEnvironmentIds = useAtomValue(activeEnvironmentIdsAtom);
   const targetEnvironment = allEnvironments.find((e) => e.id === targetEnvironmentId) ?? null;
   const activeRequest = useActiveRequest();
   const folders = useAtomValue(foldersAtom);
@@ -24,15 +25,30 @@ export function useEnvironmentVariables(targetEnvironmentId: string | null) {
       wrapVariables(folderEnvironments.find((fe) => fe.parentId === f.id) ?? null),
     );
 
-    // Add active environment variables to everything except sub environments
+    // Add active (and stacked) environment variables to everything except other sub
+    // environments. Stacked ones come first, since the last one has the highest priority.
     const activeEnvironmentVariables =
       targetEnvironment == null || // Editing request
+      targetEnvironment.id === activeEnvironment?.id || // Rendering with the active environment
       isFolderEnvironment(targetEnvironment) || // Editing folder variables
       isBaseEnvironment(targetEnvironment) // Editing global variables
-        ? wrapVariables(activeEnvironment)
+        ? [...activeEnvironmentIds]
+            .reverse()
+            .flatMap((id) => wrapVariables(allEnvironments.find((e) => e.id === id) ?? null))
         : wrapVariables(targetEnvironment); // Add own variables for sub environments
 
+    // Variables defined on the request itself take priority over every environment
+    const requestVariables: WrappedEnvironmentVariable[] =
+      targetEnvironment == null && activeRequest != null
+        ? activeRequest.variables.map((v) => ({
+            variable: v,
+            environment: null,
+            source: 'Request',
+          }))
+        : [];
+
     const allVariables = [
+      ...requestVariables,
       ...folderVariables,
       ...activeEnvironmentVariables,
       ...wrapVariables(baseEnvironment),
@@ -46,12 +62,22 @@ export function useEnvironmentVariables(targetEnvironmentId: string | null) {
     }
 
     return Object.values(varMap);
-  }, [activeEnvironment, baseEnvironment, folderEnvironments, parentFolders, targetEnvironment]);
+  }, [
+    activeEnvironment,
+    activeEnvironmentIds,
+    activeRequest,
+    allEnvironments,
+    baseEnvironment,
+    folderEnvironments,
+    parentFolders,
+    targetEnvironment,
+  ]);
 }
 
 export interface WrappedEnvironmentVariable {
   variable: EnvironmentVariable;
-  environment: Environment;
+  /** The environment the variable is defined in, or null if it's defined on the request */
+  environment: Environment | null;
   source: string;
 }
 
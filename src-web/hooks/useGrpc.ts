@@ -4,7 +4,11 @@ import type { GrpcConnection, GrpcRequest } from '@yaakapp-internal/models';
 import {jotaiStore} from "../lib/jotai";
 import { minPromiseMillis } from '../lib/minPromiseMillis';
 import { invokeCmd } from '../lib/tauri';
-import {activeEnvironmentIdAtom, useActiveEnvironment} from './useActiveEnvironment';
+import {
+  activeEnvironmentIdAtom,
+  getActiveEnvironmentIds,
+  useActiveEnvironment,
+} from './useActiveEnvironment';
 import { useDebouncedValue } from './useDebouncedValue';
 
 export interface ReflectResponseService {
@@ -23,7 +27,12 @@ export function useGrpc(
   const go = useMutation<void, string>({
     mutationKey: ['grpc_go', conn?.id],
     mutationFn: () =>
-      invokeCmd<void>('cmd_grpc_go', { requestId, environmentId: environment?.id, protoFiles }),
+      invokeCmd<void>('cmd_grpc_go', {
+        requestId,
+        environmentId: environment?.id,
+        environmentIds: getActiveEnvironmentIds(),
+        protoFiles,
+      }),
   });
 
   const send = useMutation({
@@ -49,8 +58,9 @@ export function useGrpc(
     queryKey: ['grpc_reflect', req?.id ?? 'n/a', debouncedUrl, protoFiles],
     queryFn: () => {
       const environmentId = jotaiStore.get(activeEnvironmentIdAtom);
+      const environmentIds = getActiveEnvironmentIds();
       return minPromiseMillis<ReflectResponseService[]>(
-        invokeCmd('cmd_grpc_reflect', { requestId, protoFiles, environmentId }),
+        invokeCmd('cmd_grpc_reflect', { requestId, protoFiles, environmentId, environmentIds }),
         300,
       );
     },
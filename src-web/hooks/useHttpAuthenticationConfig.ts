@@ -12,7 +12,7 @@ import { useAtomValue } from 'jotai';
 import { md5 } from 'js-md5';
 import { useState } from 'react';
 import { invokeCmd } from '../lib/tauri';
-import { activeEnvironmentIdAtom } from './useActiveEnvironment';
+import { activeEnvironmentIdAtom, activeEnvironmentIdsAtom } from './useActiveEnvironment';
 import { activeWorkspaceIdAtom } from './useActiveWorkspace';
 
 export function useHttpAuthenticationConfig(
@@ -22,6 +22,7 @@ export function useHttpAuthenticationConfig(
 ) {
   const workspaceId = useAtomValue(activeWorkspaceIdAtom);
   const environmentId = useAtomValue(activeEnvironmentIdAtom);
+  const environmentIds = useAtomValue(activeEnvironmentIdsAtom);
   const responses = useAtomValue(httpResponsesAtom);
   const [forceRefreshCounter, setForceRefreshCounter] = useState<number>(0);
 
@@ -44,6 +45,7 @@ export function useHttpAuthenticationConfig(
       forceRefreshCounter,
       workspaceId,
       environmentId,
+      environmentIds,
     ],
     placeholderData: (prev) => prev, // Keep previous data on refetch
     queryFn: async () => {
@@ -55,6 +57,7 @@ export function useHttpAuthenticationConfig(
           values,
           model,
           environmentId,
+          environmentIds,
         },
       );
 
@@ -72,6 +75,7 @@ export function useHttpAuthenticationConfig(
               values,
               model,
               environmentId,
+              environmentIds,
             });
 
             // Ensure the config is refreshed after the action is done
@@ -1,17 +1,52 @@
 import { useQuery } from '@tanstack/react-query';
+import type { EnvironmentVariable } from '@yaakapp-internal/models';
+import type { RenderTrace } from '@yaakapp-internal/templates';
+import { renderTemplate as renderTemplateWasm } from '@yaakapp-internal/templates';
 import { useAtomValue } from 'jotai';
 import { minPromiseMillis } from '../lib/minPromiseMillis';
 import { invokeCmd } from '../lib/tauri';
-import { useActiveEnvironment } from './useActiveEnvironment';
+import { activeEnvironmentIdsAtom, useActiveEnvironment } from './useActiveEnvironment';
+import { useActiveEnvironmentVariables } from './useActiveEnvironmentVariables';
 import { activeWorkspaceIdAtom } from './useActiveWorkspace';
 
 export function useRenderTemplate(template: string) {
   const workspaceId = useAtomValue(activeWorkspaceIdAtom) ?? 'n/a';
   const environmentId = useActiveEnvironment()?.id ?? null;
+  const environmentIds = useAtomValue(activeEnvironmentIdsAtom);
+  const variables = useActiveEnvironmentVariables();
+
+  // Show the local render while typing, until the app's render (which can also run template
+  // functions and see secrets and .env values) comes back
+  const local = useQuery<string | null>({
+    refetchOnWindowFocus: false,
+    queryKey: ['render_template_local', template, variables],
+    queryFn: () => renderTemplateLocally(template, variables),
+  });
+
   return useQuery<string>({
     refetchOnWindowFocus: false,
-    queryKey: ['render_template', template, workspaceId, environmentId],
-    queryFn: () => minPromiseMillis(renderTemplate({ template, workspaceId, environmentId }), 200),
+    queryKey: ['render_template', template, workspaceId, environmentId, environmentIds],
+    queryFn: () =>
+      minPromiseMillis(
+        renderTemplate({ template, workspaceId, environmentId, environmentIds }),
+        200,
+      ),
+    placeholderData: local.data ?? undefined,
+  });
+}
+
+export function useRenderTemplateTrace(template: string) {
+  const workspaceId = useAtomValue(activeWorkspaceIdAtom) ?? 'n/a';
+  const environmentId = useActiveEnvironment()?.id ?? null;
+  const environmentIds = useAtomValue(activeEnvironmentIdsAtom);
+  return useQuery<RenderTrace>({
+    refetchOnWindowFocus: false,
+    queryKey: ['render_template_trace', template, workspaceId, environmentId, environmentIds],
+    queryFn: () =>
+      minPromiseMillis(
+        renderTemplateTrace({ template, workspaceId, environmentId, environmentIds }),
+        200,
+      ),
   });
 }
 
@@ -19,12 +54,55 @@ export async function renderTemplate({
   template,
   workspaceId,
   environmentId,
+  environmentIds,
 }: {
   template: string;
   workspaceId: string;
   environmentId: string | null;
+  environmentIds?: string[];
 }): Promise<string> {
-  return invokeCmd('cmd_render_template', { template, workspaceId, environmentId });
+  return invokeCmd('cmd_render_template', { template, workspaceId, environmentId, environmentIds });
+}
+
+/** Render with only the given variables, or return null if the template needs anything else */
+export async function renderTemplateLocally(
+  template: string,
+  variables: EnvironmentVariable[],
+): Promise<string | null> {
+  const vars: Record<string, string> = {};
+  for (const v of variables) {
+    if (v.secret) continue; // Encrypted, so leave these to the app
+    vars[v.name] = v.value;
+  }
+  try {
+    return await renderTemplateWasm(template, vars, {
+      run: (fnName) => {
+        throw new Error(`${fnName}() can only run in the app`);
+      },
+    });
+  } catch {
+    return null;
+  }
+}
+
+export async function renderTemplateTrace({
+  template,
+  workspaceId,
+  environmentId,
+  environmentIds,
+}: {
+  template: string;
+  workspaceId: string;
+  environmentId: string | null;
+  environmentIds?: string[];
+}): Promise<RenderTrace> {
+  return invokeCmd('cmd_render_template', {
+    template,
+    workspaceId,
+    environmentId,
+    environmentIds,
+    trace: true,
+  });
 }
 
 export async function decryptTemplate({
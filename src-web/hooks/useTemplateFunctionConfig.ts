@@ -11,7 +11,7 @@ import type { GetTemplateFunctionConfigResponse, JsonPrimitive } from '@yaakapp-
 import { useAtomValue } from 'jotai';
 import { md5 } from 'js-md5';
 import { invokeCmd } from '../lib/tauri';
-import { activeEnvironmentIdAtom } from './useActiveEnvironment';
+import { activeEnvironmentIdAtom, activeEnvironmentIdsAtom } from './useActiveEnvironment';
 import { activeWorkspaceIdAtom } from './useActiveWorkspace';
 
 export function useTemplateFunctionConfig(
@@ -21,6 +21,7 @@ export function useTemplateFunctionConfig(
 ) {
   const workspaceId = useAtomValue(activeWorkspaceIdAtom);
   const environmentId = useAtomValue(activeEnvironmentIdAtom);
+  const environmentIds = useAtomValue(activeEnvironmentIdsAtom);
   const responses = useAtomValue(httpResponsesAtom);
 
   // Some auth handlers like OAuth 2.0 show the current token after a successful request. To
@@ -41,6 +42,7 @@ export function useTemplateFunctionConfig(
       responseKey,
       workspaceId,
       environmentId,
+      environmentIds,
     ],
     placeholderData: (prev) => prev, // Keep previous data on refetch
     queryFn: async () => {
@@ -52,6 +54,7 @@ export function useTemplateFunctionConfig(
           values,
           model,
           environmentId,
+          environmentIds,
         },
       );
       return config.function;
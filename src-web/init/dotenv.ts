@@ -0,0 +1,49 @@
+import { Channel } from '@tauri-apps/api/core';
+import { emit } from '@tauri-apps/api/event';
+import { debounce } from '@yaakapp-internal/lib';
+import type { WorkspaceMeta } from '@yaakapp-internal/models';
+import type { DotenvChangeEvent, DotenvWatchResult } from '@yaakapp-internal/tauri';
+import { activeWorkspaceMetaAtom } from '../hooks/useActiveWorkspace';
+import { jotaiStore } from '../lib/jotai';
+import { queryClient } from '../lib/queryClient';
+import { invokeCmd } from '../lib/tauri';
+
+const refreshRenderedTemplates = debounce(() => {
+  queryClient.invalidateQueries({ queryKey: ['render_template'] }).catch(console.error);
+}, 300);
+
+/**
+ * Watch the active workspace's `.env` file, and re-render templates when it changes. This
+ * re-subscribes when the workspace, or its `.env` path, changes.
+ */
+export function initDotenv() {
+  let unsub: null | (() => Promise<void>) = null;
+  let watchedKey: string | null = null;
+  jotaiStore.sub(activeWorkspaceMetaAtom, async () => {
+    const workspaceMeta = jotaiStore.get(activeWorkspaceMetaAtom);
+    const key = dotenvWatchKey(workspaceMeta);
+    if (key === watchedKey) return;
+
+    watchedKey = key;
+    await unsub?.(); // Unsub to previous
+    unsub = null;
+    if (workspaceMeta == null || key == null) return;
+
+    refreshRenderedTemplates();
+    unsub = watchDotenv(workspaceMeta.workspaceId, refreshRenderedTemplates);
+  });
+}
+
+function dotenvWatchKey(workspaceMeta: WorkspaceMeta | null) {
+  if (workspaceMeta?.settingDotenvPath == null) return null;
+  // Relative paths are resolved against the sync dir, so it's part of the key too
+  const { workspaceId, settingSyncDir, settingDotenvPath } = workspaceMeta;
+  return [workspaceId, settingSyncDir, settingDotenvPath].join('::');
+}
+
+function watchDotenv(workspaceId: string, callback: (e: DotenvChangeEvent) => void) {
+  const channel = new Channel<DotenvChangeEvent>();
+  channel.onmessage = callback;
+  const result = invokeCmd<DotenvWatchResult>('cmd_watch_dotenv', { workspaceId, channel });
+  return () => result.then(({ unlistenEvent }) => emit(unlistenEvent)).catch(console.error);
+}
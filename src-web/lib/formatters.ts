@@ -1,9 +1,20 @@
+import type { FormatMode } from '@yaakapp-internal/templates';
 import { jsonrepair } from "jsonrepair";
 import { invokeCmd } from './tauri';
 
-export async function tryFormatJson(text: string): Promise<string> {
+export async function tryFormatJson(text: string, mode: FormatMode = 'pretty'): Promise<string> {
   if (text === '') return text;
 
+  // Minified and canonical output is done by the template-aware formatter in the backend
+  if (mode !== 'pretty') {
+    try {
+      return await invokeCmd<string>('cmd_format_json', { text, mode });
+    } catch (err) {
+      console.warn('Failed to format JSON', err);
+      return text;
+    }
+  }
+
   try {
     const jsonObject = jsonrepair(
       text.replace(/(^|\n)\s*[\u3000\u2000-\u200F\u2028-\u202F]+/g, '$1    ')
@@ -15,11 +26,11 @@ export async function tryFormatJson(text: string): Promise<string> {
   }
 }
 
-export async function tryFormatXml(text: string): Promise<string> {
+export async function tryFormatXml(text: string, mode: FormatMode = 'pretty'): Promise<string> {
   if (text === '') return text;
 
   try {
-    const result = await invokeCmd<string>('cmd_format_xml', { text });
+    const result = await invokeCmd<string>('cmd_format_xml', { text, mode });
     return result;
   } catch (err) {
     console.warn('Failed to format XML', err);
@@ -0,0 +1,10 @@
+/**
+ * Parse the value of an optional number input, where an empty value means unset (`null`).
+ * Returns `undefined` for anything that isn't a whole number of at least `min`, so the caller
+ * can leave the stored value as it was.
+ */
+export function parseOptionalInt(value: string, min = 0): number | null | undefined {
+  if (value.trim() === '') return null;
+  const n = Number(value);
+  return Number.isInteger(n) && n >= min ? n : undefined;
+}
@@ -12,8 +12,11 @@ type TauriCmd =
   | 'cmd_delete_all_grpc_connections'
   | 'cmd_delete_all_http_responses'
   | 'cmd_delete_send_history'
+  | 'cmd_diff_http_responses'
   | 'cmd_dismiss_notification'
+  | 'cmd_encrypt_secret_variables'
   | 'cmd_export_data'
+  | 'cmd_export_har'
   | 'cmd_format_json'
   | 'cmd_format_xml'
   | 'cmd_get_http_authentication_config'
@@ -28,14 +31,18 @@ type TauriCmd =
   | 'cmd_http_response_body'
   | 'cmd_import_data'
   | 'cmd_install_plugin'
+  | 'cmd_lint_workspace'
+  | 'cmd_list_variable_usages'
   | 'cmd_metadata'
   | 'cmd_restart'
   | 'cmd_new_child_window'
   | 'cmd_new_main_window'
   | 'cmd_plugin_info'
   | 'cmd_reload_plugins'
+  | 'cmd_rename_variable'
   | 'cmd_render_template'
   | 'cmd_save_response'
+  | 'cmd_search_http_responses'
   | 'cmd_secure_template'
   | 'cmd_send_ephemeral_request'
   | 'cmd_send_folder'
@@ -43,7 +50,8 @@ type TauriCmd =
   | 'cmd_show_workspace_key'
   | 'cmd_template_function_summaries'
   | 'cmd_template_function_config'
-  | 'cmd_template_tokens_to_string';
+  | 'cmd_template_tokens_to_string'
+  | 'cmd_watch_dotenv';
 
 export async function invokeCmd<T>(cmd: TauriCmd, args?: InvokeArgs): Promise<T> {
   // console.log('RUN COMMAND', cmd, args);
@@ -5,6 +5,7 @@ import { type } from '@tauri-apps/plugin-os';
 import { changeModelStoreWorkspace, initModelStore } from '@yaakapp-internal/models';
 import { StrictMode } from 'react';
 import { createRoot } from 'react-dom/client';
+import { initDotenv } from './init/dotenv';
 import { initSync } from './init/sync';
 import { initGlobalListeners } from './lib/initGlobalListeners';
 import { jotaiStore } from './lib/jotai';
@@ -37,6 +38,7 @@ window.addEventListener('keydown', (e) => {
 
 // Initialize a bunch of watchers
 initSync();
+initDotenv();
 initModelStore(jotaiStore);
 initGlobalListeners();
 await changeModelStoreWorkspace(null); // Load global models